@@ -18,13 +18,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     std::env::set_var("PROTOC", protoc_build::PROTOC);
     std::env::set_var("PROTOC_INCLUDE", protoc_build::PROTOC_INCLUDE);
 
-    tonic_build::configure().compile(
-        &[
-            "engula/v1/engula.proto",
-            "engula/server/v1/node.proto",
-            "engula/server/v1/root.proto",
-        ],
-        &["."],
-    )?;
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("engula_descriptor.bin"))
+        .compile(
+            &[
+                "engula/v1/engula.proto",
+                "engula/server/v1/node.proto",
+                "engula/server/v1/root.proto",
+            ],
+            &["."],
+        )?;
     Ok(())
 }
@@ -16,6 +16,9 @@ mod error;
 mod migration;
 pub mod shard;
 
+/// The encoded file descriptor set of all Engula protos, used by gRPC server reflection.
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("engula_descriptor");
+
 pub mod v1 {
     #![allow(clippy::all)]
     tonic::include_proto!("engula.v1");
@@ -347,6 +347,7 @@ impl Collection {
 
     async fn delete_inner(&self, key: &[u8], timeout: Option<Duration>) -> crate::Result<()> {
         let router = self.client.inner.router.clone();
+        self.check_writable(&router)?;
         let (group, shard) = router.find_shard(self.co_desc.clone(), key)?;
         let mut client = GroupClient::new(
             group,
@@ -373,6 +374,7 @@ impl Collection {
         timeout: Option<Duration>,
     ) -> crate::Result<()> {
         let router = self.client.inner.router.clone();
+        self.check_writable(&router)?;
         let (group, shard) = router.find_shard(self.co_desc.clone(), key)?;
         let mut client = GroupClient::new(
             group,
@@ -422,6 +424,16 @@ impl Collection {
         }
     }
 
+    fn check_writable(&self, router: &Router) -> crate::Result<()> {
+        if router.is_read_only(&self.co_desc) {
+            return Err(crate::Error::InvalidArgument(format!(
+                "collection {} is read only",
+                self.co_desc.name
+            )));
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     fn name(&self) -> String {
         self.co_desc.name.to_owned()
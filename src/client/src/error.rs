@@ -84,6 +84,10 @@ pub enum Error {
     #[error("connect {0}")]
     Connect(tonic::Status),
 
+    /// The watched root belongs to another cluster.
+    #[error("cluster not match")]
+    ClusterNotMatch,
+
     #[error("rpc {0}")]
     Rpc(tonic::Status),
 
@@ -152,6 +156,7 @@ impl From<Error> for AppError {
             Error::NotFound(v) => AppError::NotFound(v),
             Error::AlreadyExists(v) => AppError::AlreadyExists(v),
             Error::Internal(v) => AppError::Internal(v),
+            err @ Error::ClusterNotMatch => AppError::Internal(Box::new(err)),
 
             Error::Transport(status) => AppError::Network(status),
             Error::Connect(status) => panic!("do not expose connect error {status:?} to user"),
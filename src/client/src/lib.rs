@@ -35,7 +35,9 @@ pub use group_client::{GroupClient, RetryableShardChunkStreaming};
 pub use migrate_client::MigrateClient;
 pub use node_client::{Client as NodeClient, RequestBatchBuilder, RpcTimeout};
 pub use retry::RetryState;
-pub use root_client::{AdminRequestBuilder, AdminResponseExtractor, Client as RootClient};
+pub use root_client::{
+    AdminRequestBuilder, AdminResponseExtractor, Client as RootClient, ResumableWatch,
+};
 pub use router::{Router, RouterGroupState};
 pub use shard_client::ShardClient;
 use tonic::async_trait;
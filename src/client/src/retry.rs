@@ -68,6 +68,7 @@ impl RetryState {
             | Error::AlreadyExists(_)
             | Error::Rpc(_)
             | Error::Transport(_)
+            | Error::ClusterNotMatch
             | Error::Internal(_) => Err(err),
         }
     }
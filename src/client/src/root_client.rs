@@ -21,14 +21,18 @@ use engula_api::{
 };
 use prost::Message;
 use tokio::sync::Mutex;
+use tokio_stream::StreamExt;
 use tonic::{transport::Channel, Code, Status, Streaming};
-use tracing::trace;
+use tracing::{trace, warn};
 
 use crate::{
     conn_manager::ConnManager, discovery::ServiceDiscovery, error::retryable_rpc_err, NodeClient,
     Result,
 };
 
+/// The max number of consecutive reconnections of a watch stream following the leader hints.
+const MAX_WATCH_LEADER_HINT_RETRIES: usize = 3;
+
 #[derive(thiserror::Error, Debug)]
 enum RootError {
     #[error("not root")]
@@ -39,6 +43,17 @@ enum RootError {
     Rpc(#[from] Status),
 }
 
+/// A watch stream follows the root leadership. Once the stream is terminated by a
+/// `NotRootLeader` error, it reconnects to the hinted leader and resumes with the group epochs
+/// already received, instead of surfacing the error.
+pub struct ResumableWatch {
+    client: Client,
+    group_epochs: HashMap<u64, u64>,
+    events: Streaming<WatchResponse>,
+    /// The number of consecutive reconnections without receiving any response.
+    retries: usize,
+}
+
 pub struct AdminRequestBuilder;
 pub struct AdminResponseExtractor;
 
@@ -114,11 +129,54 @@ impl Client {
         Ok(res.into_inner())
     }
 
+    pub async fn join_nodes(&self, req: JoinNodesRequest) -> Result<JoinNodesResponse> {
+        let res = self
+            .invoke(|mut client| {
+                let req = req.clone();
+                async move { client.join_nodes(req).await }
+            })
+            .await?;
+        Ok(res.into_inner())
+    }
+
     pub async fn watch(
         &self,
         cur_group_epochs: HashMap<u64, u64>,
     ) -> Result<Streaming<WatchResponse>> {
-        let req = WatchRequest { cur_group_epochs };
+        let req = WatchRequest {
+            cur_group_epochs,
+            group_id: None,
+        };
+        let res = self
+            .invoke(|mut client| {
+                let req = req.clone();
+                async move { client.watch(req).await }
+            })
+            .await?;
+        Ok(res.into_inner())
+    }
+
+    /// Watch the events like `watch`, but the stream is resumed transparently after the root
+    /// leadership is changed.
+    pub async fn watch_resumable(
+        &self,
+        cur_group_epochs: HashMap<u64, u64>,
+    ) -> Result<ResumableWatch> {
+        let events = self.watch(cur_group_epochs.clone()).await?;
+        Ok(ResumableWatch {
+            client: self.clone(),
+            group_epochs: cur_group_epochs,
+            events,
+            retries: 0,
+        })
+    }
+
+    /// Watch the events of a single group, and the nodes hosting its replicas.
+    pub async fn watch_group(&self, group_id: u64) -> Result<Streaming<WatchResponse>> {
+        let req = WatchRequest {
+            cur_group_epochs: HashMap::default(),
+            group_id: Some(group_id),
+        };
         let res = self
             .invoke(|mut client| {
                 let req = req.clone();
@@ -228,6 +286,22 @@ impl Client {
         }
     }
 
+    /// Follow the leader hint carried by a `NotRootLeader` error, the root nodes are iterated by
+    /// the next invoking if there is no hint.
+    async fn apply_leader_hint(&self, root: RootDesc, term: u64, leader: Option<ReplicaDesc>) {
+        let mut core = self.core().await;
+        if core.root.epoch < root.epoch {
+            core.root = Arc::new(root);
+        }
+        core.leader = None;
+        if let Some(leader) = leader {
+            if core.term <= term {
+                core.apply_leader(leader, term);
+            }
+        }
+        self.apply_core(core).await;
+    }
+
     #[inline]
     async fn core(&self) -> ClientCore {
         self.shared.core.lock().await.clone()
@@ -286,6 +360,52 @@ impl Client {
     }
 }
 
+impl ResumableWatch {
+    pub async fn next(&mut self) -> Option<Result<WatchResponse>> {
+        loop {
+            let status = match self.events.next().await? {
+                Ok(resp) => {
+                    self.retries = 0;
+                    self.track_group_epochs(&resp);
+                    return Some(Ok(resp));
+                }
+                Err(status) => status,
+            };
+            let (root, term, leader) = match extract_root_descriptor(&status) {
+                Some(hint) if self.retries < MAX_WATCH_LEADER_HINT_RETRIES => hint,
+                _ => return Some(Err(status.into())),
+            };
+            self.retries += 1;
+            warn!(
+                term,
+                leader = ?leader,
+                retries = self.retries,
+                "watch stream is terminated by root leader change, resume watching"
+            );
+            self.client.apply_leader_hint(root, term, leader).await;
+            match self.client.watch(self.group_epochs.clone()).await {
+                Ok(events) => self.events = events,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    fn track_group_epochs(&mut self, resp: &WatchResponse) {
+        use watch_response::{delete_event, update_event};
+
+        for update in &resp.updates {
+            if let Some(update_event::Event::Group(group)) = &update.event {
+                self.group_epochs.insert(group.id, group.epoch);
+            }
+        }
+        for delete in &resp.deletes {
+            if let Some(delete_event::Event::Group(group_id)) = &delete.event {
+                self.group_epochs.remove(group_id);
+            }
+        }
+    }
+}
+
 impl ClientCore {
     fn apply_leader(&mut self, leader: ReplicaDesc, term: u64) {
         for (idx, node) in self.root.root_nodes.iter().enumerate() {
@@ -351,6 +471,7 @@ impl AdminRequestBuilder {
                         name: co_name,
                         database: Some(database),
                         partition,
+                        ..Default::default()
                     },
                 )),
             }),
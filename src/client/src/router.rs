@@ -19,11 +19,9 @@ use std::{
 };
 
 use engula_api::{server::v1::*, v1::*};
-use tokio_stream::StreamExt;
-use tonic::Streaming;
-use tracing::{info, trace, warn};
+use tracing::{error, info, trace, warn};
 
-use crate::RootClient;
+use crate::{ResumableWatch, RootClient};
 
 #[derive(Debug, Clone)]
 pub struct Router {
@@ -40,6 +38,8 @@ pub struct State {
     co_shards_lookup: HashMap<u64 /* co */, Vec<ShardDesc>>,
     shard_group_lookup: HashMap<u64 /* shard */, u64 /* group */>,
     group_id_lookup: HashMap<u64 /* group */, RouterGroupState>,
+    /// The watch stream came from another cluster, routing with its metadata isn't allowed.
+    cluster_not_match: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -60,6 +60,23 @@ impl Router {
         Self { state }
     }
 
+    /// Whether the writes of the collection are rejected, by the latest descriptor watched from
+    /// root if any.
+    pub fn is_read_only(&self, desc: &CollectionDesc) -> bool {
+        self.is_collection_read_only(desc.id)
+            .unwrap_or(desc.read_only)
+    }
+
+    /// Whether the writes of the collection are rejected by the latest watched descriptor, or
+    /// `None` if the collection is unknown.
+    pub fn is_collection_read_only(&self, collection_id: u64) -> Option<bool> {
+        let state = self.state.lock().unwrap();
+        state
+            .co_id_lookup
+            .get(&collection_id)
+            .map(|desc| desc.read_only)
+    }
+
     pub fn find_shard(
         &self,
         desc: CollectionDesc,
@@ -73,6 +90,7 @@ impl Router {
             let slot = crc % (slots as u32);
 
             let state = self.state.lock().unwrap();
+            state.check_cluster()?;
 
             let shards = state
                 .co_shards_lookup
@@ -111,6 +129,7 @@ impl Router {
         }
 
         let state = self.state.lock().unwrap();
+        state.check_cluster()?;
         let shards = state
             .co_shards_lookup
             .get(&desc.id)
@@ -151,6 +170,7 @@ impl Router {
 
     pub fn find_group_by_shard(&self, shard: u64) -> Result<RouterGroupState, crate::Error> {
         let state = self.state.lock().unwrap();
+        state.check_cluster()?;
         let group = state
             .shard_group_lookup
             .get(&shard)
@@ -161,12 +181,14 @@ impl Router {
 
     pub fn find_group(&self, id: u64) -> Result<RouterGroupState, crate::Error> {
         let state = self.state.lock().unwrap();
+        state.check_cluster()?;
         let group = state.group_id_lookup.get(&id).cloned();
         group.ok_or_else(|| crate::Error::NotFound(format!("group (id={:?})", id)))
     }
 
     pub fn find_node_addr(&self, id: u64) -> Result<String, crate::Error> {
         let state = self.state.lock().unwrap();
+        state.check_cluster()?;
         let addr = state.node_id_lookup.get(&id).cloned();
         addr.ok_or_else(|| crate::Error::NotFound(format!("node_addr (node_id={:?})", id)))
     }
@@ -176,12 +198,28 @@ impl Router {
     }
 }
 
+impl State {
+    fn check_cluster(&self) -> Result<(), crate::Error> {
+        if self.cluster_not_match {
+            return Err(crate::Error::ClusterNotMatch);
+        }
+        Ok(())
+    }
+}
+
 async fn state_main(state: Arc<Mutex<State>>, root_client: RootClient) {
     info!("start watching events...");
 
+    // The cluster id of the first watch stream, which is used to detect connecting to other
+    // clusters.
+    let mut cluster_id = None;
     let mut interval = 1;
+    let mut resync = false;
     loop {
-        let cur_group_epochs = {
+        // All groups are sent again if the states miss some events.
+        let cur_group_epochs = if resync {
+            HashMap::default()
+        } else {
             let state = state.lock().unwrap();
             state
                 .group_id_lookup
@@ -189,7 +227,7 @@ async fn state_main(state: Arc<Mutex<State>>, root_client: RootClient) {
                 .map(|(id, s)| (*id, s.epoch))
                 .collect()
         };
-        let events = match root_client.watch(cur_group_epochs).await {
+        let events = match root_client.watch_resumable(cur_group_epochs).await {
             Ok(events) => events,
             Err(e) => {
                 warn!(err = ?e, "watch events");
@@ -200,19 +238,45 @@ async fn state_main(state: Arc<Mutex<State>>, root_client: RootClient) {
         };
 
         interval = 1;
-        watch_events(state.as_ref(), events).await;
+        match watch_events(state.as_ref(), events, &mut cluster_id).await {
+            Ok(need_resync) => resync = need_resync,
+            Err(err) => {
+                // Retrying doesn't help, the requests routed by now are rejected.
+                error!(err = ?err, "stop watching events");
+                state.lock().unwrap().cluster_not_match = true;
+                return;
+            }
+        }
     }
 }
 
-async fn watch_events(state: &Mutex<State>, mut events: Streaming<WatchResponse>) {
+/// Apply the watched events to the states until the stream is terminated. Return whether the
+/// states miss some events and need to be resynced from scratch.
+async fn watch_events(
+    state: &Mutex<State>,
+    mut events: ResumableWatch,
+    cluster_id: &mut Option<Vec<u8>>,
+) -> Result<bool, crate::Error> {
     use watch_response::{delete_event::Event as DeleteEvent, update_event::Event as UpdateEvent};
 
     let mut cached_group_states: HashMap<u64, GroupState> = HashMap::default();
     while let Some(event) = events.next().await {
         let (updates, deletes) = match event {
+            Ok(resp) if !is_same_cluster(cluster_id, &resp.cluster_id) => {
+                error!(
+                    expect = ?cluster_id.as_ref().map(|id| String::from_utf8_lossy(id).to_string()),
+                    actual = ?String::from_utf8_lossy(&resp.cluster_id),
+                    "watch events from other cluster, bail out",
+                );
+                return Err(crate::Error::ClusterNotMatch);
+            }
+            Ok(resp) if resp.resync => {
+                warn!("watch events are dropped by root, resync states");
+                return Ok(true);
+            }
             Ok(resp) => (resp.updates, resp.deletes),
-            Err(status) => {
-                warn!("WatchEvent error: {}", status);
+            Err(err) => {
+                warn!("WatchEvent error: {}", err);
                 continue;
             }
         };
@@ -267,7 +331,12 @@ async fn watch_events(state: &Mutex<State>, mut events: Streaming<WatchResponse>
                     trace!("update event; group state {group_state:?}");
                     let id = group_state.group_id;
                     if let Some(group) = state.group_id_lookup.get_mut(&id) {
-                        group.leader_state = leader_state(&group_state);
+                        match (group.leader_state, leader_state(&group_state)) {
+                            (Some((_, cached_epoch)), Some((_, epoch))) if epoch < cached_epoch => {
+                                trace!("ignore stale group state; group {id} epoch {epoch}");
+                            }
+                            (_, leader) => group.leader_state = leader,
+                        }
                     } else {
                         cached_group_states.insert(id, group_state);
                     }
@@ -292,6 +361,23 @@ async fn watch_events(state: &Mutex<State>, mut events: Streaming<WatchResponse>
                     }
                     state.co_name_lookup.insert((db, name), id);
                 }
+                UpdateEvent::CollectionOptions(change) => {
+                    // The descriptor is applied by the accompanied collection update event.
+                    trace!(
+                        "update event; collection {} options {:?}",
+                        change.collection_id,
+                        change.fields
+                    );
+                }
+                UpdateEvent::ReplicaRole(change) => {
+                    // The leader is tracked by the accompanied group state update event.
+                    trace!("update event; replica role {change:?}");
+                }
+                UpdateEvent::Alias(alias) => {
+                    state
+                        .co_name_lookup
+                        .insert((alias.db, alias.name), alias.collection_id);
+                }
             }
         }
         for delete in deletes {
@@ -304,8 +390,17 @@ async fn watch_events(state: &Mutex<State>, mut events: Streaming<WatchResponse>
                 DeleteEvent::Node(node) => {
                     state.node_id_lookup.remove(&node);
                 }
-                DeleteEvent::Group(_) => todo!(),
-                DeleteEvent::GroupState(_) => todo!(),
+                DeleteEvent::Group(group) => {
+                    state.group_id_lookup.remove(&group);
+                    state.shard_group_lookup.retain(|_, id| *id != group);
+                    cached_group_states.remove(&group);
+                }
+                DeleteEvent::GroupState(group) => {
+                    if let Some(desc) = state.group_id_lookup.get_mut(&group) {
+                        desc.leader_state = None;
+                    }
+                    cached_group_states.remove(&group);
+                }
                 DeleteEvent::Database(db) => {
                     if let Some(desc) = state.db_id_lookup.remove(&db) {
                         state.db_name_lookup.remove(desc.name.as_str());
@@ -316,13 +411,39 @@ async fn watch_events(state: &Mutex<State>, mut events: Streaming<WatchResponse>
                         state.co_name_lookup.remove(&(desc.db, desc.name));
                     }
                 }
+                DeleteEvent::Alias(alias) => {
+                    state.co_name_lookup.remove(&(alias.db, alias.name));
+                }
             }
         }
     }
+    Ok(false)
+}
+
+/// Verify the cluster id carried by the watch response, the first seen id is remembered.
+fn is_same_cluster(expect: &mut Option<Vec<u8>>, actual: &[u8]) -> bool {
+    if actual.is_empty() {
+        // Only the first response of a stream carries the cluster id.
+        return true;
+    }
+    match expect {
+        Some(expect) => expect == actual,
+        None => {
+            *expect = Some(actual.to_owned());
+            true
+        }
+    }
 }
 
 #[inline]
 fn leader_state(group_state: &GroupState) -> Option<(u64, u64)> {
+    // The leader epoch is the term of the leader with the largest term.
+    if let Some(leader_id) = group_state
+        .leader_id
+        .filter(|_| group_state.leader_epoch > 0)
+    {
+        return Some((leader_id, group_state.leader_epoch));
+    }
     if let Some(_leader_id) = group_state.leader_id {
         // FIXME: This is a temporary solution to bypass issue #1014.
         // group_state
@@ -77,7 +77,7 @@ impl ShardClient {
             self.conn_manager.clone(),
         );
         match client.request(&req).await? {
-            Response::PrefixList(ShardPrefixListResponse { values }) => Ok(values),
+            Response::PrefixList(ShardPrefixListResponse { values, .. }) => Ok(values),
             _ => Err(Error::Internal(
                 "invalid response type, `SharedPrefixListResponse` is required".into(),
             )),
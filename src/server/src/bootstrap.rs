@@ -21,7 +21,7 @@ use tracing::{debug, info, warn};
 use crate::{
     discovery::RootDiscovery,
     node::{engine::StateEngine, resolver::AddressResolver, Node},
-    root::{Root, Schema},
+    root::{metrics, Root, Schema},
     runtime::{Executor, Shutdown},
     serverpb::v1::{raft_server::RaftServer, NodeIdent},
     service::ProxyServer,
@@ -45,6 +45,7 @@ lazy_static::lazy_static! {
 
 /// The main entrance of engula server.
 pub fn run(config: Config, executor: Executor, shutdown: Shutdown) -> Result<()> {
+    config.root.validate()?;
     executor.block_on(async {
         let provider = build_provider(&config, executor.clone()).await?;
         let node = Node::new(config.clone(), provider.clone())?;
@@ -63,6 +64,7 @@ pub fn run(config: Config, executor: Executor, shutdown: Shutdown) -> Result<()>
             node: Arc::new(node),
             root,
             address_resolver: provider.address_resolver.clone(),
+            config: Arc::new(config.clone()),
         };
 
         let proxy_server = if config.enable_proxy_service {
@@ -86,7 +88,9 @@ async fn bootstrap_services(
     use tokio_stream::wrappers::TcpListenerStream;
     use tonic::transport::Server;
 
-    use crate::service::admin::make_admin_service;
+    use crate::service::admin::{
+        make_admin_service, make_grpc_health_service, make_reflection_service,
+    };
 
     let listener = TcpListener::bind(addr).await?;
     let listener = TcpListenerStream::new(listener);
@@ -97,6 +101,8 @@ async fn bootstrap_services(
         .add_service(RaftServer::new(server.clone()))
         .add_service(RootServer::new(server.clone()))
         .add_service(make_admin_service(server.clone()))
+        .add_service(make_grpc_health_service().await)
+        .add_service(make_reflection_service())
         .add_optional_service(proxy_server.map(EngulaServer::new))
         .serve_with_incoming(listener);
 
@@ -221,7 +227,14 @@ pub(crate) async fn bootstrap_cluster(node: &Node, addr: &str) -> Result<NodeIde
     let state_engine = node.state_engine();
     let cluster_id = vec![];
 
+    let timer = metrics::BOOTSTRAP_STEP_DURATION_SECONDS
+        .save_ident
+        .start_timer();
     let ident = save_node_ident(state_engine, cluster_id.to_owned(), FIRST_NODE_ID).await?;
+    info!(
+        "bootstrap step save node ident takes {:.3}s",
+        timer.stop_and_record()
+    );
 
     info!("bootstrap cluster successfully");
 
@@ -245,6 +258,10 @@ async fn save_node_ident(
 }
 
 async fn write_initial_cluster_data(node: &Node, addr: &str) -> Result<()> {
+    let timer = metrics::BOOTSTRAP_STEP_DURATION_SECONDS
+        .create_groups
+        .start_timer();
+
     // Create the first raft group of cluster, this node is the only member of the raft group.
     let (shards, _) = Schema::init_shards();
 
@@ -273,6 +290,14 @@ async fn write_initial_cluster_data(node: &Node, addr: &str) -> Result<()> {
     };
     node.create_replica(INIT_USER_REPLICA_ID, init_group)
         .await?;
+    info!(
+        "bootstrap step create groups takes {:.3}s",
+        timer.stop_and_record()
+    );
+
+    let timer = metrics::BOOTSTRAP_STEP_DURATION_SECONDS
+        .update_root
+        .start_timer();
 
     let root_node = NodeDesc {
         id: FIRST_NODE_ID,
@@ -284,6 +309,10 @@ async fn write_initial_cluster_data(node: &Node, addr: &str) -> Result<()> {
         root_nodes: vec![root_node],
     };
     node.update_root(root_desc).await?;
+    info!(
+        "bootstrap step update root takes {:.3}s",
+        timer.stop_and_record()
+    );
 
     Ok(())
 }
@@ -291,7 +320,14 @@ async fn write_initial_cluster_data(node: &Node, addr: &str) -> Result<()> {
 pub(crate) async fn build_provider(config: &Config, executor: Executor) -> Result<Arc<Provider>> {
     let db_path = config.root_dir.join("db");
     let log_path = config.root_dir.join("log");
+    let timer = metrics::BOOTSTRAP_STEP_DURATION_SECONDS
+        .open_engine
+        .start_timer();
     let raw_db = Arc::new(open_engine(&db_path)?);
+    info!(
+        "bootstrap step open engine takes {:.3}s",
+        timer.stop_and_record()
+    );
 
     let root_list = if config.init {
         vec![config.addr.clone()]
@@ -22,18 +22,42 @@ pub enum Error {
     #[error("invalid argument {0}")]
     InvalidArgument(String),
 
+    #[error("invalid alias {0}")]
+    InvalidAlias(String),
+
+    #[error("invalid name {0}")]
+    InvalidName(String),
+
     #[error("deadline exceeded {0}")]
     DeadlineExceeded(String),
 
     #[error("database {0} not found")]
     DatabaseNotFound(String),
 
+    #[error("collection {0} is not in database {1}")]
+    CollectionNotInDatabase(String, String),
+
     #[error("no available group")]
     NoAvaliableGroup,
 
     #[error("{0} is exhausted")]
     ResourceExhausted(String),
 
+    #[error("{0} is overloaded")]
+    Overloaded(String),
+
+    #[error("cluster is frozen")]
+    ClusterFrozen,
+
+    #[error("watch snapshot of {0} events exceeds limit {1}, use a group filtered watch")]
+    WatchSnapshotTooLarge(usize, usize),
+
+    #[error("report of group {0} is based on a stale descriptor")]
+    StaleReport(u64),
+
+    #[error("cluster is not bootstrapped")]
+    NotBootstrapped,
+
     // internal errors
     #[error("shard {0} not found")]
     ShardNotFound(u64),
@@ -41,6 +65,9 @@ pub enum Error {
     #[error("invalid {0} data")]
     InvalidData(String),
 
+    #[error("corrupt metadata {0}")]
+    CorruptMetadata(String),
+
     #[error("request canceled")]
     Canceled,
 
@@ -105,10 +132,18 @@ impl From<Error> for tonic::Status {
 
         match e {
             Error::InvalidArgument(msg) => Status::invalid_argument(msg),
+            err @ Error::InvalidAlias(_) => Status::invalid_argument(err.to_string()),
+            err @ Error::InvalidName(_) => Status::invalid_argument(err.to_string()),
             Error::DeadlineExceeded(msg) => Status::deadline_exceeded(msg),
             err @ Error::DatabaseNotFound(_) => Status::not_found(err.to_string()),
+            err @ Error::CollectionNotInDatabase(..) => Status::invalid_argument(err.to_string()),
             err @ Error::AlreadyExists(_) => Status::already_exists(err.to_string()),
             Error::ResourceExhausted(msg) => Status::resource_exhausted(msg),
+            err @ Error::Overloaded(_) => Status::resource_exhausted(err.to_string()),
+            err @ Error::ClusterFrozen => Status::failed_precondition(err.to_string()),
+            err @ Error::WatchSnapshotTooLarge(..) => Status::resource_exhausted(err.to_string()),
+            err @ Error::StaleReport(_) => Status::aborted(err.to_string()),
+            err @ Error::NotBootstrapped => Status::unavailable(err.to_string()),
 
             Error::GroupNotFound(group_id) => Status::with_details(
                 Code::Unknown,
@@ -143,6 +178,7 @@ impl From<Error> for tonic::Status {
             | Error::AbortScheduleTask(_)
             | Error::ClusterNotMatch
             | Error::InvalidData(_)
+            | Error::CorruptMetadata(_)
             | Error::Transport(_)
             | Error::Io(_)
             | Error::RocksDb(_)
@@ -189,7 +225,29 @@ impl From<Error> for engula_api::server::v1::Error {
             Error::EpochNotMatch(desc) => v1::Error::not_match(desc),
 
             Error::InvalidArgument(msg) => v1::Error::status(Code::InvalidArgument.into(), msg),
+            err @ Error::InvalidAlias(_) => {
+                v1::Error::status(Code::InvalidArgument.into(), err.to_string())
+            }
+            err @ Error::InvalidName(_) => {
+                v1::Error::status(Code::InvalidArgument.into(), err.to_string())
+            }
             Error::DeadlineExceeded(msg) => v1::Error::status(Code::DeadlineExceeded.into(), msg),
+            err @ Error::CollectionNotInDatabase(..) => {
+                v1::Error::status(Code::InvalidArgument.into(), err.to_string())
+            }
+            err @ Error::Overloaded(_) => {
+                v1::Error::status(Code::ResourceExhausted.into(), err.to_string())
+            }
+            err @ Error::ClusterFrozen => {
+                v1::Error::status(Code::FailedPrecondition.into(), err.to_string())
+            }
+            err @ Error::WatchSnapshotTooLarge(..) => {
+                v1::Error::status(Code::ResourceExhausted.into(), err.to_string())
+            }
+            err @ Error::StaleReport(_) => v1::Error::status(Code::Aborted.into(), err.to_string()),
+            err @ Error::NotBootstrapped => {
+                v1::Error::status(Code::Unavailable.into(), err.to_string())
+            }
 
             Error::Forward(_) => panic!("Forward only used inside node"),
             Error::ServiceIsBusy(_) => panic!("ServiceIsBusy only used inside node"),
@@ -204,6 +262,7 @@ impl From<Error> for engula_api::server::v1::Error {
             | Error::RocksDb(_)
             | Error::Io(_)
             | Error::InvalidData(_)
+            | Error::CorruptMetadata(_)
             | Error::DatabaseNotFound(_)
             | Error::ShardNotFound(_)
             | Error::ClusterNotMatch
@@ -239,6 +298,7 @@ impl From<engula_client::Error> for Error {
                 Error::NotLeader(group, term, leader)
             }
             engula_client::Error::EpochNotMatch(v) => Error::EpochNotMatch(v),
+            engula_client::Error::ClusterNotMatch => Error::ClusterNotMatch,
 
             // NOTE: This is a fallback, for some scenarios where you don't need to deal with
             // `GroupNotAccessable` raised by `GroupClient`. (`GroupNotReady` only used inside
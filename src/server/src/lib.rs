@@ -41,8 +41,8 @@ pub use crate::{
     error::{Error, Result},
     node::NodeConfig,
     raftgroup::RaftConfig,
-    root::{diagnosis, RootConfig},
-    service::Server,
+    root::{diagnosis, RootConfig, RootTestingKnobs},
+    service::{admin::AdminConfig, Server},
 };
 
 #[derive(Default, Clone, Debug, Deserialize, Serialize)]
@@ -71,6 +71,9 @@ pub struct Config {
 
     #[serde(default)]
     pub executor: ExecutorConfig,
+
+    #[serde(default)]
+    pub admin: AdminConfig,
 }
 
 pub(crate) struct Provider {
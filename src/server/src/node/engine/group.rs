@@ -231,6 +231,46 @@ impl GroupEngine {
         internal::flushed_apply_state(&self.raw_db, &self.cf_handle())
     }
 
+    /// Return the estimated number of keys and live data bytes of the group, it is cheap since the
+    /// values are read from the properties of rocksdb instead of scanning.
+    pub fn approximate_size(&self) -> (u64, u64) {
+        let cf_handle = self.cf_handle();
+        let property = |name: &str| {
+            self.raw_db
+                .property_int_value_cf(&cf_handle, name)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        (
+            property("rocksdb.estimate-num-keys"),
+            property("rocksdb.estimate-live-data-size"),
+        )
+    }
+
+    /// Return the estimated size of obsolete data of the group, which is the difference between
+    /// the size of all sst files and the live data.
+    pub fn approximate_garbage_size(&self) -> u64 {
+        let cf_handle = self.cf_handle();
+        let property = |name: &str| {
+            self.raw_db
+                .property_int_value_cf(&cf_handle, name)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let total_bytes = property("rocksdb.total-sst-files-size");
+        total_bytes.saturating_sub(property("rocksdb.estimate-live-data-size"))
+    }
+
+    /// Compact the whole group to reclaim the space of obsolete data. It blocks until the
+    /// compaction is finished.
+    pub fn compact(&self) {
+        let cf_handle = self.cf_handle();
+        self.raw_db
+            .compact_range_cf(&cf_handle, None::<&[u8]>, None::<&[u8]>);
+    }
+
     /// Get key value from the corresponding shard.
     pub async fn get(&self, shard_id: u64, key: &[u8]) -> Result<Option<Vec<u8>>> {
         let snapshot_mode = SnapshotMode::Key { key };
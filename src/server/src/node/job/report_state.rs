@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
 use engula_api::server::v1::{
     report_request::GroupUpdates, GroupDesc, ReplicaState, ReportRequest, ScheduleState,
@@ -86,10 +86,41 @@ async fn wait_state_updates(
 ///
 /// If one day you find that reporting has become a bottleneck, you can consider optimizing this
 /// code.
-async fn report_state_updates(root_client: &RootClient, request: ReportRequest) {
+///
+/// The root might fail to apply some of the updates, only the failed updates are retried, except
+/// the permanent failures such as the stale updates.
+async fn report_state_updates(root_client: &RootClient, mut request: ReportRequest) {
     let mut interval = 1;
-    while let Err(e) = root_client.report(&request).await {
-        warn!("report state updates: {e}");
+    loop {
+        match root_client.report(&request).await {
+            Ok(resp) if resp.failures.is_empty() => return,
+            Ok(resp) => {
+                for failure in &resp.failures {
+                    warn!(
+                        "report state updates: update {} is not applied, permanent {}: {}",
+                        failure.index, failure.permanent, failure.reason
+                    );
+                }
+                let failed = resp
+                    .failures
+                    .iter()
+                    .filter(|f| !f.permanent)
+                    .map(|f| f.index as usize)
+                    .collect::<HashSet<_>>();
+                request.updates = std::mem::take(&mut request.updates)
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| failed.contains(index))
+                    .map(|(_, update)| update)
+                    .collect();
+                if request.updates.is_empty() {
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!("report state updates: {e}");
+            }
+        }
         crate::runtime::time::sleep(Duration::from_millis(interval)).await;
         interval = std::cmp::min(interval * 2, 120);
     }
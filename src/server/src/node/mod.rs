@@ -426,9 +426,42 @@ impl Node {
             }
         };
 
+        self.check_writable(&replica, &request)?;
         forwardable_execute(&self.migrate_ctrl, &replica, ExecCtx::default(), request).await
     }
 
+    /// Reject the writes to the shards of read only collections. The collections are known by the
+    /// descriptors watched from root, so the writes racing with setting the flag might be served.
+    fn check_writable(&self, replica: &Replica, request: &GroupRequest) -> Result<()> {
+        use engula_api::server::v1::group_request_union::Request;
+
+        let shard_ids = match request.request.as_ref().and_then(|r| r.request.as_ref()) {
+            Some(Request::Put(req)) => vec![req.shard_id],
+            Some(Request::Delete(req)) => vec![req.shard_id],
+            Some(Request::BatchWrite(req)) => req
+                .puts
+                .iter()
+                .map(|r| r.shard_id)
+                .chain(req.deletes.iter().map(|r| r.shard_id))
+                .collect(),
+            _ => return Ok(()),
+        };
+        let desc = replica.descriptor();
+        for shard in desc.shards.iter().filter(|s| shard_ids.contains(&s.id)) {
+            if let Some(true) = self
+                .provider
+                .router
+                .is_collection_read_only(shard.collection_id)
+            {
+                return Err(Error::InvalidArgument(format!(
+                    "collection {} is read only",
+                    shard.collection_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub async fn pull_shard_chunks(&self, request: PullRequest) -> Result<ShardChunkStream> {
         let replica = match self.replica_route_table.find(request.group_id) {
             Some(replica) => replica,
@@ -576,11 +609,16 @@ impl Node {
                 let replica_state = replica.replica_state();
                 if replica_state.role == RaftRole::Leader as i32 {
                     ns.leader_count += 1;
+                    let group_engine = replica.group_engine();
+                    let (approximate_keys, approximate_bytes) = group_engine.approximate_size();
                     let gs = GroupStats {
                         group_id: info.group_id,
                         shard_count: descriptor.shards.len() as u64,
                         read_qps: 0.,
                         write_qps: 0.,
+                        approximate_keys,
+                        approximate_bytes,
+                        approximate_garbage_bytes: group_engine.approximate_garbage_size(),
                     };
                     group_stats.push(gs);
                 }
@@ -618,10 +656,13 @@ impl Node {
                     continue;
                 }
 
-                let state = replica.replica_state();
+                let mut state = replica.replica_state();
                 if state.role == RaftRole::Leader as i32 {
                     descriptors.push(replica.descriptor());
                 }
+                if let Ok(apply_state) = replica.group_engine().flushed_apply_state() {
+                    state.applied_index = apply_state.index;
+                }
                 states.push(state);
             }
         }
@@ -686,6 +727,28 @@ impl Node {
         resp
     }
 
+    /// Compact the groups advised by root in background, only the leader replicas are compacted
+    /// since the advice is made from the stats reported by leaders.
+    pub async fn compact_groups(&self, req: &CompactGroupsRequest) -> CompactGroupsResponse {
+        for group_id in &req.groups {
+            if let Some(replica) = self.replica_route_table.find(*group_id) {
+                if replica.replica_info().is_terminated()
+                    || replica.replica_state().role != RaftRole::Leader as i32
+                {
+                    continue;
+                }
+                let group_engine = replica.group_engine();
+                let group_id = *group_id;
+                tokio::task::spawn_blocking(move || {
+                    info!("group {group_id} compaction is started by the advice of root");
+                    group_engine.compact();
+                    info!("group {group_id} compaction is finished");
+                });
+            }
+        }
+        CompactGroupsResponse {}
+    }
+
     #[inline]
     async fn serving_group_id_list(&self) -> Vec<u64> {
         let node_state = self.node_state.lock().await;
@@ -29,13 +29,14 @@ pub async fn prefix_list(
     let snapshot_mode = SnapshotMode::Prefix { key: prefix };
     let mut snapshot = engine.snapshot(req.shard_id, snapshot_mode)?;
     let mut values = Vec::new();
+    let mut keys = Vec::new();
     for mut mvcc_iter in snapshot.iter() {
-        if let Some(value) = mvcc_iter
-            .next()
-            .and_then(|e| e.value().map(ToOwned::to_owned))
-        {
-            values.push(value);
+        if let Some(entry) = mvcc_iter.next() {
+            if let Some(value) = entry.value() {
+                keys.push(entry.user_key().to_owned());
+                values.push(value.to_owned());
+            }
         }
     }
-    Ok(ShardPrefixListResponse { values })
+    Ok(ShardPrefixListResponse { values, keys })
 }
@@ -44,6 +44,7 @@ use crate::{
 pub struct ReplicaTestingKnobs {
     pub disable_scheduler_orphan_replica_detecting_intervals: bool,
     pub disable_scheduler_durable_task: bool,
+    pub disable_scheduler_promote_group_task: bool,
     pub disable_scheduler_remove_orphan_replica_task: bool,
 }
 
@@ -139,6 +140,20 @@ impl Replica {
         }
     }
 
+    /// Open another instance of the same replica, to simulate the replica re-registered in the
+    /// route table.
+    #[cfg(test)]
+    pub fn reopen(&self) -> Self {
+        Replica {
+            info: self.info.clone(),
+            group_engine: self.group_engine.clone(),
+            raft_node: self.raft_node.clone(),
+            lease_state: self.lease_state.clone(),
+            move_replicas_provider: self.move_replicas_provider.clone(),
+            meta_acl: self.meta_acl.clone(),
+        }
+    }
+
     /// Shutdown this replicas with the newer `GroupDesc`.
     pub async fn shutdown(&self, _actual_desc: &GroupDesc) -> Result<()> {
         // TODO(walter) check actual desc.
@@ -207,6 +222,13 @@ impl Replica {
         self.info.clone()
     }
 
+    /// The latest known leader of the group and the term, to redirect the requests.
+    #[inline]
+    pub fn leader_hint(&self) -> (u64, Option<ReplicaDesc>) {
+        let lease_state = self.lease_state.lock().unwrap();
+        (lease_state.applied_term, lease_state.leader_descriptor())
+    }
+
     #[inline]
     pub fn epoch(&self) -> u64 {
         self.lease_state.lock().unwrap().descriptor.epoch
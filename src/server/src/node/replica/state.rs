@@ -153,6 +153,7 @@ impl LeaseStateObserver {
             voted_for,
             role: role.into(),
             node_id: self.info.node_id,
+            applied_index: 0,
         };
         let mut lease_state = self.lease_state.lock().unwrap();
         let prev_role = lease_state.replica_state.role;
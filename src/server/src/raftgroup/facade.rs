@@ -12,13 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use engula_api::server::v1::ChangeReplicas;
 use futures::channel::{mpsc, oneshot};
 
 use super::{
     metrics::*,
+    snap::SnapshotStat,
     worker::{RaftGroupState, Request},
     ReadPolicy,
 };
@@ -96,6 +97,17 @@ impl RaftNodeFacade {
         receiver.await?
     }
 
+    /// Create a snapshot of the state machine, the latest snapshot is reused if it is taken within
+    /// `min_interval`.
+    pub async fn create_snapshot(&mut self, min_interval: Duration) -> Result<SnapshotStat> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Request::CreateSnapshot {
+            min_interval,
+            sender,
+        })?;
+        receiver.await?
+    }
+
     pub async fn raft_group_state(&mut self) -> Option<RaftGroupState> {
         let (sender, receiver) = oneshot::channel();
         let request = Request::State(sender);
@@ -14,11 +14,14 @@
 
 use std::path::Path;
 
-use futures::{channel::mpsc, SinkExt};
+use futures::{
+    channel::{mpsc, oneshot},
+    SinkExt,
+};
 use prost::Message;
 use tracing::{error, info};
 
-use super::{SnapManager, SNAP_DATA};
+use super::{SnapManager, SnapshotStat, SNAP_DATA};
 use crate::{
     raftgroup::{
         fsm::SnapshotBuilder,
@@ -30,26 +33,37 @@ use crate::{
     record_latency,
     runtime::{Executor, TaskPriority},
     serverpb::v1::{SnapshotFile, SnapshotMeta},
-    Result,
+    Error, Result,
 };
 
+/// Dispatch a task to create snapshot, the summary of the snapshot is sent to `callback` if it
+/// is specified.
 pub fn dispatch_creating_snap_task(
     executor: &Executor,
     replica_id: u64,
     mut sender: mpsc::Sender<Request>,
     state_machine: &impl StateMachine,
     snap_mgr: SnapManager,
+    callback: Option<oneshot::Sender<Result<SnapshotStat>>>,
 ) {
     let builder = state_machine.snapshot_builder();
     executor.spawn(None, TaskPriority::IoLow, async move {
-        match create_snapshot(replica_id, &snap_mgr, builder).await {
-            Ok(_) => {
+        let result = match create_snapshot(replica_id, &snap_mgr, builder).await {
+            Ok(snapshot_id) => {
                 info!("replica {replica_id} create snapshot success");
+                snap_mgr
+                    .lock_snap(replica_id, &snapshot_id)
+                    .map(|info| info.stat(true))
+                    .ok_or_else(|| Error::InvalidData("snapshot is recycled".into()))
             }
             Err(err) => {
                 error!("replica {replica_id} create snapshot: {err}");
+                Err(err)
             }
         };
+        if let Some(callback) = callback {
+            callback.send(result).unwrap_or_default();
+        }
 
         sender
             .send(Request::CreateSnapshotFinished)
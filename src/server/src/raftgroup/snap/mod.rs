@@ -59,6 +59,17 @@ pub struct SnapshotInfo {
     created_at: Instant,
 }
 
+/// The summary of a snapshot.
+#[derive(Clone, Debug)]
+pub struct SnapshotStat {
+    /// The applied index of the snapshot.
+    pub index: u64,
+    /// The total size of the snapshot files in bytes.
+    pub size: u64,
+    /// Whether the snapshot is just created, otherwise a recently taken snapshot is reused.
+    pub created: bool,
+}
+
 pub struct SnapshotGuard {
     replica_id: u64,
     info: SnapshotInfo,
@@ -329,6 +340,24 @@ impl ReplicaSnapManager {
 }
 
 impl SnapshotInfo {
+    #[inline]
+    pub fn created_at(&self) -> Instant {
+        self.created_at
+    }
+
+    pub fn stat(&self, created: bool) -> SnapshotStat {
+        SnapshotStat {
+            index: self
+                .meta
+                .apply_state
+                .as_ref()
+                .map(|s| s.index)
+                .unwrap_or_default(),
+            size: self.meta.files.iter().map(|f| f.size).sum(),
+            created,
+        }
+    }
+
     pub fn to_raft_snapshot(&self) -> Snapshot {
         let snap_meta = &self.meta;
         let apply_state = snap_meta.apply_state.clone().unwrap();
@@ -29,14 +29,14 @@ use super::{
     fsm::StateMachine,
     metrics::*,
     node::RaftNode,
-    snap::{apply::apply_snapshot, RecycleSnapMode, SnapManager},
+    snap::{apply::apply_snapshot, RecycleSnapMode, SnapManager, SnapshotStat},
     transport::{Channel, TransportManager},
     RaftManager, ReadPolicy,
 };
 use crate::{
     runtime::Executor,
     serverpb::v1::{EvalResult, RaftMessage},
-    Result,
+    Error, Result,
 };
 
 pub enum Request {
@@ -48,6 +48,11 @@ pub enum Request {
         eval_result: EvalResult,
         sender: oneshot::Sender<Result<()>>,
     },
+    CreateSnapshot {
+        /// The snapshot taken within the interval is reused.
+        min_interval: Duration,
+        sender: oneshot::Sender<Result<SnapshotStat>>,
+    },
     CreateSnapshotFinished,
     InstallSnapshot {
         msg: Message,
@@ -96,6 +101,10 @@ pub struct RaftGroupState {
     pub first_index: u64,
     /// The last index of log entries.
     pub last_index: u64,
+    /// The index of the latest config change, it is pending until the applied index reaches it.
+    pub pending_conf_index: u64,
+    /// The latest snapshot of the replica.
+    pub last_snapshot: Option<SnapshotStat>,
 
     pub peers: HashMap<u64, PeerState>,
 }
@@ -301,6 +310,7 @@ where
                     self.request_sender.clone(),
                     self.raft_node.mut_state_machine(),
                     self.snap_mgr.clone(),
+                    None,
                 );
             }
         }
@@ -321,6 +331,10 @@ where
             } => self.handle_proposal(eval_result, sender),
             Request::Read { policy, sender } => self.handle_read(policy, sender),
             Request::ChangeConfig { change, sender } => self.handle_conf_change(change, sender),
+            Request::CreateSnapshot {
+                min_interval,
+                sender,
+            } => self.handle_create_snapshot(min_interval, sender),
             Request::CreateSnapshotFinished => {
                 self.raft_node.mut_store().is_creating_snapshot.set(false);
             }
@@ -419,6 +433,36 @@ where
         }
     }
 
+    fn handle_create_snapshot(
+        &mut self,
+        min_interval: Duration,
+        sender: oneshot::Sender<Result<SnapshotStat>>,
+    ) {
+        if let Some(info) = self.snap_mgr.latest_snap(self.desc.id) {
+            if info.created_at().elapsed() < min_interval {
+                sender.send(Ok(info.stat(false))).unwrap_or_default();
+                return;
+            }
+        }
+
+        let store = self.raft_node.mut_store();
+        if store.is_creating_snapshot.get() {
+            sender
+                .send(Err(Error::AlreadyExists("snapshot creating task".into())))
+                .unwrap_or_default();
+            return;
+        }
+        store.is_creating_snapshot.set(true);
+        super::snap::dispatch_creating_snap_task(
+            &self.executor,
+            self.desc.id,
+            self.request_sender.clone(),
+            self.raft_node.mut_state_machine(),
+            self.snap_mgr.clone(),
+            Some(sender),
+        );
+    }
+
     fn compact_log(&mut self) {
         let mut to = self.raft_node.mut_state_machine().flushed_index();
 
@@ -465,6 +509,11 @@ where
             committed: self.raft_node.committed_index(),
             first_index,
             last_index,
+            pending_conf_index: self.raft_node.raft().pending_conf_index,
+            last_snapshot: self
+                .snap_mgr
+                .latest_snap(self.desc.id)
+                .map(|info| info.stat(false)),
             peers: peer_states,
         }
     }
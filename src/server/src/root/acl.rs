@@ -0,0 +1,97 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use engula_api::v1::Permission;
+
+use crate::{Error, Result};
+
+const MAX_PRINCIPAL_NAME_LEN: usize = 128;
+
+/// The kinds of principals which could be granted permissions.
+const PRINCIPAL_KINDS: &[&str] = &["user", "group", "service"];
+
+/// Validate a principal, which is in form of `<kind>:<name>`, eg. `user:alice`.
+pub fn validate_principal(principal: &str) -> Result<()> {
+    let invalid = |reason: &str| Error::InvalidArgument(format!("principal {principal} {reason}"));
+    let (kind, name) = principal
+        .split_once(':')
+        .ok_or_else(|| invalid("should be in form of <kind>:<name>"))?;
+    if !PRINCIPAL_KINDS.contains(&kind) {
+        return Err(invalid("has unknown kind"));
+    }
+    if name.is_empty() || name.len() > MAX_PRINCIPAL_NAME_LEN {
+        return Err(invalid("has illegal name length"));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '@'))
+    {
+        return Err(invalid("has illegal characters"));
+    }
+    Ok(())
+}
+
+/// Parse a comma separated permission set, eg. `read,write`. The result is sorted and
+/// deduplicated.
+pub fn parse_permissions(permissions: &str) -> Result<Vec<Permission>> {
+    let mut parsed = permissions
+        .split(',')
+        .map(|p| match p.trim() {
+            "read" => Ok(Permission::Read),
+            "write" => Ok(Permission::Write),
+            "admin" => Ok(Permission::Admin),
+            p => Err(Error::InvalidArgument(format!("unknown permission {p:?}"))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    parsed.sort_unstable();
+    parsed.dedup();
+    Ok(parsed)
+}
+
+pub fn permission_name(permission: Permission) -> &'static str {
+    match permission {
+        Permission::Read => "read",
+        Permission::Write => "write",
+        Permission::Admin => "admin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn principal_validation() {
+        validate_principal("user:alice").unwrap();
+        validate_principal("service:backup-job.v1@prod").unwrap();
+
+        for principal in ["", "alice", "user:", "robot:alice", "user:a b", "user:a/b"] {
+            assert!(
+                validate_principal(principal).is_err(),
+                "{principal} should be rejected"
+            );
+        }
+        assert!(validate_principal(&format!("user:{}", "a".repeat(129))).is_err());
+    }
+
+    #[test]
+    fn permissions_parsing() {
+        assert_eq!(
+            parse_permissions("write, read,write").unwrap(),
+            vec![Permission::Read, Permission::Write]
+        );
+        assert!(parse_permissions("").is_err());
+        assert!(parse_permissions("read,delete").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use engula_api::server::v1::NodeDesc;
+
+/// Pick the leaders of new groups by smooth weighted round-robin, so the groups created before
+/// the next heartbeat refreshes the leader counts don't pile their leaders on the same node.
+#[derive(Clone, Default)]
+pub struct LeaderRoundRobin {
+    current_weights: Arc<Mutex<HashMap<u64, f64>>>,
+}
+
+impl LeaderRoundRobin {
+    /// Pick a node from the `(node_id, weight)` candidates, or return `None` if there is no
+    /// candidate.
+    pub fn pick(&self, candidates: &[(u64, f64)]) -> Option<u64> {
+        let mut current_weights = self.current_weights.lock().unwrap();
+        let total = candidates.iter().map(|(_, w)| w).sum::<f64>();
+        let mut picked: Option<(u64, f64)> = None;
+        for (node_id, weight) in candidates {
+            let current = current_weights.entry(*node_id).or_default();
+            *current += weight;
+            if picked.map(|(_, w)| *current > w).unwrap_or(true) {
+                picked = Some((*node_id, *current));
+            }
+        }
+        let (node_id, _) = picked?;
+        *current_weights.get_mut(&node_id).unwrap() -= total;
+        Some(node_id)
+    }
+}
+
+/// The weight of a node to lead a new group, the nodes with more cpus and fewer leaders are
+/// preferred.
+pub fn leader_weight(node: &NodeDesc) -> f64 {
+    let (cpus, leaders) = node
+        .capacity
+        .as_ref()
+        .map(|c| (c.cpu_nums, c.leader_count))
+        .unwrap_or_default();
+    cpus.max(1.0) / (leaders + 1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use engula_api::server::v1::NodeCapacity;
+
+    use super::*;
+
+    fn picks(rr: &LeaderRoundRobin, candidates: &[(u64, f64)], n: usize) -> Vec<u64> {
+        (0..n).map(|_| rr.pick(candidates).unwrap()).collect()
+    }
+
+    #[test]
+    fn equal_weights_spread() {
+        let rr = LeaderRoundRobin::default();
+        let candidates = [(1, 1.0), (2, 1.0), (3, 1.0)];
+        assert_eq!(picks(&rr, &candidates, 6), vec![1, 2, 3, 1, 2, 3]);
+        assert_eq!(rr.pick(&[]), None);
+    }
+
+    #[test]
+    fn weighted_spread() {
+        let rr = LeaderRoundRobin::default();
+        let candidates = [(1, 2.0), (2, 1.0)];
+        let picked = picks(&rr, &candidates, 6);
+        assert_eq!(picked.iter().filter(|id| **id == 1).count(), 4);
+        assert_eq!(picked.iter().filter(|id| **id == 2).count(), 2);
+        // The picks are interleaved rather than consecutive.
+        assert_eq!(picked, vec![1, 2, 1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn weight_of_node() {
+        let node = |cpus: f64, leaders: u64| NodeDesc {
+            capacity: Some(NodeCapacity {
+                cpu_nums: cpus,
+                leader_count: leaders,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(leader_weight(&node(4.0, 0)), 4.0);
+        assert_eq!(leader_weight(&node(4.0, 3)), 1.0);
+        assert!(leader_weight(&node(2.0, 0)) > leader_weight(&node(2.0, 1)));
+        assert_eq!(leader_weight(&NodeDesc::default()), 1.0);
+    }
+}
@@ -12,26 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
 
 use engula_api::server::v1::{GroupDesc, NodeDesc};
 use serde::{Deserialize, Serialize};
 
 use self::{
-    policy_leader_cnt::LeaderCountPolicy, policy_replica_cnt::ReplicaCountPolicy,
-    policy_shard_cnt::ShardCountPolicy, source::NodeFilter,
+    leader_rr::{leader_weight, LeaderRoundRobin},
+    placement::PlacementStrategy,
+    policy_leader_cnt::LeaderCountPolicy,
+    policy_replica_cnt::ReplicaCountPolicy,
+    policy_shard_cnt::ShardCountPolicy,
+    source::NodeFilter,
 };
-use super::{metrics, OngoingStats, RootShared};
+use super::{metrics, watch::Backpressure, OngoingStats, RootShared};
 use crate::{bootstrap::REPLICA_PER_GROUP, Result};
 
 #[cfg(test)]
 mod sim_test;
 
+mod leader_rr;
+mod placement;
 mod policy_leader_cnt;
 mod policy_replica_cnt;
 mod policy_shard_cnt;
 mod source;
 
+pub use placement::PlacementStrategyKind;
 pub use source::{AllocSource, SysAllocSource};
 
 #[derive(Clone, Debug)]
@@ -94,6 +104,20 @@ enum BalanceStatus {
     Underfull,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct RootTestingKnobs {
+    pub inject_join_failure_after_add_node: bool,
+    pub inject_heartbeat_failure: bool,
+    /// The warmup reads after stepping leader fail while it is positive, each failure
+    /// decrements it.
+    pub inject_warmup_read_failures: Arc<AtomicU64>,
+    /// The leadership steps fail after the schema is exposed while it is positive, each failure
+    /// decrements it.
+    pub inject_lead_failures: Arc<AtomicU64>,
+    /// Each delivery of watch events to a watcher is delayed by it, to simulate slow watchers.
+    pub inject_watch_delivery_delay: Duration,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RootConfig {
     pub replicas_per_group: usize,
@@ -102,9 +126,64 @@ pub struct RootConfig {
     pub enable_shard_balance: bool,
     pub enable_leader_balance: bool,
     pub liveness_threshold_sec: u64,
+    /// The time a node is allowed to miss heartbeats after its liveness expired, before it is
+    /// marked unreachable.
+    pub unreachable_grace_period_sec: u64,
     pub heartbeat_timeout_sec: u64,
+    /// The heartbeats to a node are skipped for `heartbeat_breaker_cooldown_sec` after these
+    /// consecutive failures, then the node is probed again. Zero means never skip, which is the
+    /// default.
+    pub heartbeat_breaker_failure_threshold: u64,
+    pub heartbeat_breaker_cooldown_sec: u64,
     pub schedule_interval_sec: u64,
     pub max_create_group_retry_before_rollback: u64,
+    pub drain_max_concurrent_moves: u64,
+    /// The strategy to place the replicas of new groups and the replicas added to the existing
+    /// groups.
+    pub placement_strategy: PlacementStrategyKind,
+    /// The decommission of a node is aborted if its drain doesn't finish within it.
+    pub decommission_drain_timeout_sec: u64,
+    pub watch_notify_concurrency: usize,
+    pub watch_retention_window: u64,
+    /// The watchers which don't consume the delivered events within it are handled by
+    /// `watch_backpressure`. Zero means no deadline, which is the default.
+    pub watch_delivery_deadline_ms: u64,
+    pub watch_backpressure: Backpressure,
+    /// The max number of events in the initial snapshot of an unfiltered watch, above which the
+    /// watch is rejected with `Error::WatchSnapshotTooLarge`. Zero means unlimited.
+    pub watch_init_max_events: usize,
+    pub report_max_inflight: usize,
+    /// The ratio of obsolete data of a group, above which its leader is advised to compact.
+    pub compaction_garbage_ratio_threshold: f64,
+    /// The max number of groups advised to compact in each round of heartbeat.
+    pub compaction_max_advised_groups: usize,
+    /// The nodes which the root leadership prefers, in order of preference. The leadership is
+    /// transferred to the first healthy one of them.
+    pub root_leader_preferred_nodes: Vec<u64>,
+    /// The root steps down after the heartbeats of these consecutive rounds all fail, since the
+    /// node is probably isolated. Zero means never, which is the default.
+    pub heartbeat_failures_before_step_down: u64,
+    /// The root snapshot requested by operators is skipped if a snapshot was taken within it.
+    pub snapshot_min_interval_sec: u64,
+    /// Skip the metadata records which could not be decoded when loading schema, instead of
+    /// failing with `Error::CorruptMetadata`.
+    pub skip_corrupt_metadata: bool,
+    /// The failed leadership step of root is retried after `run_retry_initial_backoff_ms`, the
+    /// delay grows by `run_retry_backoff_multiplier` after each consecutive failure, up to
+    /// `run_retry_max_backoff_ms`.
+    pub run_retry_initial_backoff_ms: u64,
+    pub run_retry_backoff_multiplier: f64,
+    pub run_retry_max_backoff_ms: u64,
+    /// The max number of finished replica moves retained for `/admin/moves/history`. Zero
+    /// disables the history.
+    pub move_history_capacity: usize,
+    /// The names of the new databases, collections and aliases must start with one of these
+    /// prefixes, eg. the names of tenants. Empty means any name is allowed. The existing
+    /// databases, collections and aliases are unaffected.
+    pub name_prefixes: Vec<String>,
+
+    #[serde(skip)]
+    pub testing_knobs: RootTestingKnobs,
 }
 
 impl Default for RootConfig {
@@ -116,9 +195,33 @@ impl Default for RootConfig {
             enable_shard_balance: true,
             enable_leader_balance: true,
             liveness_threshold_sec: 30,
+            unreachable_grace_period_sec: 0,
             heartbeat_timeout_sec: 4,
+            heartbeat_breaker_failure_threshold: 0,
+            heartbeat_breaker_cooldown_sec: 60,
             schedule_interval_sec: 1,
             max_create_group_retry_before_rollback: 10,
+            drain_max_concurrent_moves: 8,
+            placement_strategy: PlacementStrategyKind::default(),
+            decommission_drain_timeout_sec: 600,
+            watch_notify_concurrency: 4,
+            watch_retention_window: 1024,
+            watch_delivery_deadline_ms: 0,
+            watch_backpressure: Backpressure::Disconnect,
+            watch_init_max_events: 100000,
+            report_max_inflight: 64,
+            compaction_garbage_ratio_threshold: 0.5,
+            compaction_max_advised_groups: 4,
+            root_leader_preferred_nodes: vec![],
+            heartbeat_failures_before_step_down: 0,
+            snapshot_min_interval_sec: 60,
+            skip_corrupt_metadata: false,
+            run_retry_initial_backoff_ms: 100,
+            run_retry_backoff_multiplier: 2.0,
+            run_retry_max_backoff_ms: 30000,
+            move_history_capacity: 1024,
+            name_prefixes: vec![],
+            testing_knobs: RootTestingKnobs::default(),
         }
     }
 }
@@ -127,12 +230,41 @@ impl RootConfig {
     pub fn heartbeat_interval(&self) -> Duration {
         Duration::from_secs(self.liveness_threshold_sec - self.heartbeat_timeout_sec)
     }
+
+    /// The delay before retrying the root run loop after `attempts` consecutive failures.
+    pub fn run_retry_backoff(&self, attempts: u32) -> Duration {
+        let backoff = self.run_retry_initial_backoff_ms as f64
+            * self.run_retry_backoff_multiplier.powi(attempts as i32);
+        Duration::from_millis(backoff.min(self.run_retry_max_backoff_ms as f64) as u64)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.run_retry_initial_backoff_ms == 0 {
+            return Err(crate::Error::InvalidArgument(
+                "root.run_retry_initial_backoff_ms should be greater than 0".into(),
+            ));
+        }
+        if self.run_retry_initial_backoff_ms > self.run_retry_max_backoff_ms {
+            return Err(crate::Error::InvalidArgument(
+                "root.run_retry_initial_backoff_ms exceeds root.run_retry_max_backoff_ms".into(),
+            ));
+        }
+        let multiplier = self.run_retry_backoff_multiplier;
+        if !multiplier.is_finite() || multiplier <= 1.0 {
+            return Err(crate::Error::InvalidArgument(
+                "root.run_retry_backoff_multiplier should be a finite number greater than 1".into(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
 pub struct Allocator<T: AllocSource> {
     alloc_source: Arc<T>,
     ongoing_stats: Arc<OngoingStats>,
+    placement: Arc<dyn PlacementStrategy>,
+    leader_rr: LeaderRoundRobin,
     config: RootConfig,
 }
 
@@ -140,8 +272,10 @@ impl<T: AllocSource> Allocator<T> {
     pub fn new(alloc_source: Arc<T>, ongoing_stats: Arc<OngoingStats>, config: RootConfig) -> Self {
         Self {
             alloc_source,
-            config,
             ongoing_stats,
+            placement: config.placement_strategy.build(),
+            leader_rr: LeaderRoundRobin::default(),
+            config,
         }
     }
 
@@ -186,18 +320,24 @@ impl<T: AllocSource> Allocator<T> {
 
     /// Compute replica change action.
     pub async fn compute_replica_action(&self) -> Result<Vec<ReplicaAction>> {
+        self.alloc_source.refresh_all().await?;
+        let policy =
+            ReplicaCountPolicy::with(self.alloc_source.to_owned(), self.ongoing_stats.to_owned());
+
+        // The decommissioning nodes are drained even if the balance is disabled.
+        let actions = policy.compute_drain()?;
+        if !actions.is_empty() {
+            return Ok(actions);
+        }
+
         if !self.config.enable_replica_balance {
             return Ok(vec![]);
         }
 
-        self.alloc_source.refresh_all().await?;
-
         // TODO: try qps rebalance.
 
         // try replica-count rebalance.
-        let actions =
-            ReplicaCountPolicy::with(self.alloc_source.to_owned(), self.ongoing_stats.to_owned())
-                .compute_balance()?;
+        let actions = policy.compute_balance()?;
         if !actions.is_empty() {
             return Ok(actions);
         }
@@ -236,7 +376,24 @@ impl<T: AllocSource> Allocator<T> {
         self.alloc_source.refresh_all().await?;
 
         ReplicaCountPolicy::with(self.alloc_source.to_owned(), self.ongoing_stats.to_owned())
-            .allocate_group_replica(existing_replica_nodes, wanted_count)
+            .allocate_group_replica(
+                self.placement.as_ref(),
+                existing_replica_nodes,
+                wanted_count,
+            )
+    }
+
+    /// Choose the node to lead a new group from the nodes hosting its replicas, by weighted
+    /// round-robin across the schedulable ones. Return `None` if none of them is schedulable.
+    pub fn preferred_group_leader(&self, replica_nodes: &[NodeDesc]) -> Option<u64> {
+        let candidates = self
+            .alloc_source
+            .nodes(NodeFilter::Schedulable)
+            .iter()
+            .filter(|n| replica_nodes.iter().any(|r| r.id == n.id))
+            .map(|n| (n.id, leader_weight(n)))
+            .collect::<Vec<_>>();
+        self.leader_rr.pick(&candidates)
     }
 
     /// Find a group to place shard.
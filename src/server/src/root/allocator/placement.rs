@@ -0,0 +1,223 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::HashSet, sync::Arc};
+
+use engula_api::server::v1::NodeDesc;
+use serde::{Deserialize, Serialize};
+
+/// The built-in placement strategies which could be selected by config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlacementStrategyKind {
+    /// Prefer the nodes with the fewest replicas.
+    #[default]
+    CountBalanced,
+    /// Prefer the nodes with the fewest replicas per cpu.
+    CapacityWeighted,
+    /// Prefer the hosts which don't hold the replicas of the group yet, then the nodes with the
+    /// fewest replicas.
+    LocalitySpread,
+}
+
+impl PlacementStrategyKind {
+    pub fn build(self) -> Arc<dyn PlacementStrategy> {
+        match self {
+            PlacementStrategyKind::CountBalanced => Arc::new(CountBalanced),
+            PlacementStrategyKind::CapacityWeighted => Arc::new(CapacityWeighted),
+            PlacementStrategyKind::LocalitySpread => Arc::new(LocalitySpread),
+        }
+    }
+}
+
+/// A node seen by the placement strategies.
+#[derive(Clone, Debug)]
+pub struct PlacementNode {
+    pub desc: NodeDesc,
+    /// The replicas of the node, including the ongoing moves.
+    pub replica_count: u64,
+    /// Whether new replicas could be placed on the node.
+    pub schedulable: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ClusterState {
+    pub nodes: Vec<PlacementNode>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PlacementRequest {
+    /// The nodes already hold the replicas of the group.
+    pub existing_replica_nodes: Vec<u64>,
+    pub wanted_count: usize,
+}
+
+/// Decide the target nodes of the new replicas of a group.
+pub trait PlacementStrategy: Send + Sync {
+    /// Return at most `wanted_count` schedulable nodes which don't hold the replicas of the
+    /// group, in order of preference.
+    fn place(&self, cluster: &ClusterState, request: &PlacementRequest) -> Vec<NodeDesc>;
+}
+
+pub struct CountBalanced;
+
+impl PlacementStrategy for CountBalanced {
+    fn place(&self, cluster: &ClusterState, request: &PlacementRequest) -> Vec<NodeDesc> {
+        let mut candidates = candidates(cluster, request);
+        candidates.sort_by_key(|n| n.replica_count);
+        take(candidates, request.wanted_count)
+    }
+}
+
+pub struct CapacityWeighted;
+
+impl PlacementStrategy for CapacityWeighted {
+    fn place(&self, cluster: &ClusterState, request: &PlacementRequest) -> Vec<NodeDesc> {
+        let load = |n: &PlacementNode| {
+            let cpus = n
+                .desc
+                .capacity
+                .as_ref()
+                .map(|c| c.cpu_nums)
+                .unwrap_or_default();
+            n.replica_count as f64 / cpus.max(1.0)
+        };
+        let mut candidates = candidates(cluster, request);
+        candidates.sort_by(|n1, n2| load(n1).partial_cmp(&load(n2)).unwrap());
+        take(candidates, request.wanted_count)
+    }
+}
+
+pub struct LocalitySpread;
+
+impl PlacementStrategy for LocalitySpread {
+    fn place(&self, cluster: &ClusterState, request: &PlacementRequest) -> Vec<NodeDesc> {
+        let mut used_hosts = cluster
+            .nodes
+            .iter()
+            .filter(|n| request.existing_replica_nodes.contains(&n.desc.id))
+            .map(|n| host(&n.desc))
+            .collect::<HashSet<_>>();
+        let mut candidates = candidates(cluster, request);
+        candidates.sort_by_key(|n| n.replica_count);
+
+        let mut targets = Vec::new();
+        while targets.len() < request.wanted_count && !candidates.is_empty() {
+            // Fallback to the least loaded node once every host is used.
+            let idx = candidates
+                .iter()
+                .position(|n| !used_hosts.contains(host(&n.desc)))
+                .unwrap_or_default();
+            let node = candidates.remove(idx);
+            used_hosts.insert(host(&node.desc));
+            targets.push(node.desc.to_owned());
+        }
+        targets
+    }
+}
+
+fn candidates<'a>(cluster: &'a ClusterState, request: &PlacementRequest) -> Vec<&'a PlacementNode> {
+    cluster
+        .nodes
+        .iter()
+        .filter(|n| n.schedulable && !request.existing_replica_nodes.contains(&n.desc.id))
+        .collect()
+}
+
+fn take(candidates: Vec<&PlacementNode>, wanted_count: usize) -> Vec<NodeDesc> {
+    candidates
+        .into_iter()
+        .take(wanted_count)
+        .map(|n| n.desc.to_owned())
+        .collect()
+}
+
+/// The host of a node, the nodes on the same host share the same failure domain.
+fn host(desc: &NodeDesc) -> &str {
+    desc.addr
+        .rsplit_once(':')
+        .map(|(host, _)| host)
+        .unwrap_or(&desc.addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use engula_api::server::v1::NodeCapacity;
+
+    use super::*;
+
+    /// Node 1 and 2 are on the same host, node 4 is not schedulable.
+    fn cluster() -> ClusterState {
+        let node =
+            |id: u64, addr: &str, cpus: f64, replicas: u64, schedulable: bool| PlacementNode {
+                desc: NodeDesc {
+                    id,
+                    addr: addr.into(),
+                    capacity: Some(NodeCapacity {
+                        cpu_nums: cpus,
+                        replica_count: replicas,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                replica_count: replicas,
+                schedulable,
+            };
+        ClusterState {
+            nodes: vec![
+                node(1, "10.0.0.1:21805", 2.0, 2, true),
+                node(2, "10.0.0.1:21806", 2.0, 3, true),
+                node(3, "10.0.0.2:21805", 8.0, 6, true),
+                node(4, "10.0.0.3:21805", 8.0, 0, false),
+                node(5, "10.0.0.4:21805", 2.0, 4, true),
+            ],
+        }
+    }
+
+    fn place(kind: PlacementStrategyKind, existing: Vec<u64>, wanted_count: usize) -> Vec<u64> {
+        let request = PlacementRequest {
+            existing_replica_nodes: existing,
+            wanted_count,
+        };
+        kind.build()
+            .place(&cluster(), &request)
+            .into_iter()
+            .map(|n| n.id)
+            .collect()
+    }
+
+    #[test]
+    fn count_balanced_placement() {
+        let kind = PlacementStrategyKind::CountBalanced;
+        assert_eq!(place(kind, vec![], 3), vec![1, 2, 5]);
+        assert_eq!(place(kind, vec![1], 2), vec![2, 5]);
+        assert_eq!(place(kind, vec![], 10), vec![1, 2, 5, 3]);
+    }
+
+    #[test]
+    fn capacity_weighted_placement() {
+        let kind = PlacementStrategyKind::CapacityWeighted;
+        assert_eq!(place(kind, vec![], 3), vec![3, 1, 2]);
+        assert_eq!(place(kind, vec![3], 2), vec![1, 2]);
+    }
+
+    #[test]
+    fn locality_spread_placement() {
+        let kind = PlacementStrategyKind::LocalitySpread;
+        assert_eq!(place(kind, vec![], 3), vec![1, 5, 3]);
+        assert_eq!(place(kind, vec![2], 2), vec![5, 3]);
+        // All hosts are used, fallback to the least loaded node.
+        assert_eq!(place(kind, vec![], 4), vec![1, 5, 3, 2]);
+    }
+}
@@ -18,10 +18,14 @@ use std::{
     sync::Arc,
 };
 
-use engula_api::server::v1::{NodeDesc, ReplicaDesc};
+use engula_api::server::v1::{NodeDesc, NodeStatus, ReplicaDesc};
 use tracing::trace;
 
-use super::{source::NodeFilter, *};
+use super::{
+    placement::{ClusterState, PlacementNode, PlacementRequest, PlacementStrategy},
+    source::NodeFilter,
+    *,
+};
 use crate::{bootstrap::ROOT_GROUP_ID, root::OngoingStats, Result};
 
 pub struct ReplicaCountPolicy<T: AllocSource> {
@@ -39,22 +43,33 @@ impl<T: AllocSource> ReplicaCountPolicy<T> {
 
     pub fn allocate_group_replica(
         &self,
+        placement: &dyn PlacementStrategy,
         existing_replica_nodes: Vec<u64>,
         wanted_count: usize,
     ) -> Result<Vec<NodeDesc>> {
-        let mut candidate_nodes = self.alloc_source.nodes(NodeFilter::Schedulable);
-
-        // skip the nodes already have group replicas.
-        candidate_nodes.retain(|n| !existing_replica_nodes.iter().any(|rn| *rn == n.id));
-
-        // sort by alloc score
-        candidate_nodes.sort_by(|n1, n2| {
-            self.node_alloc_score(n2)
-                .partial_cmp(&self.node_alloc_score(n1))
-                .unwrap()
-        });
-
-        Ok(candidate_nodes.into_iter().take(wanted_count).collect())
+        let schedulable = self
+            .alloc_source
+            .nodes(NodeFilter::Schedulable)
+            .into_iter()
+            .map(|n| n.id)
+            .collect::<HashSet<_>>();
+        let cluster = ClusterState {
+            nodes: self
+                .alloc_source
+                .nodes(NodeFilter::All)
+                .into_iter()
+                .map(|n| PlacementNode {
+                    replica_count: self.node_replica_count(&n),
+                    schedulable: schedulable.contains(&n.id),
+                    desc: n,
+                })
+                .collect(),
+        };
+        let request = PlacementRequest {
+            existing_replica_nodes,
+            wanted_count,
+        };
+        Ok(placement.place(&cluster, &request))
     }
 
     pub fn compute_balance(&self) -> Result<Vec<ReplicaAction>> {
@@ -79,12 +94,41 @@ impl<T: AllocSource> ReplicaCountPolicy<T> {
         Ok(Vec::new())
     }
 
-    fn rebalance_target(
-        &self,
-        src: &NodeDesc,
-        ranked_nodes: &[(NodeDesc, BalanceStatus)],
-        mean: f64,
-    ) -> Option<ReplicaAction> {
+    /// Move a replica out of the decommissioning nodes, to the schedulable node which hosts the
+    /// fewest replicas. The replicas of root group are not moved.
+    pub fn compute_drain(&self) -> Result<Vec<ReplicaAction>> {
+        let sources = self
+            .alloc_source
+            .nodes(NodeFilter::All)
+            .into_iter()
+            .filter(|n| n.status == NodeStatus::Decommissioning as i32)
+            .collect::<Vec<_>>();
+        if sources.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut targets = self.alloc_source.nodes(NodeFilter::Schedulable);
+        targets.sort_by_key(|n| self.node_replica_count(n));
+        let groups = self.group_nodes();
+        for src in &sources {
+            for target in &targets {
+                if let Some((source_replica, group)) =
+                    self.preferred_remove_replica(src, target, &groups)
+                {
+                    return Ok(vec![ReplicaAction::Migrate(ReallocateReplica {
+                        group,
+                        source_node: source_replica.node_id,
+                        source_replica: source_replica.id,
+                        target_node: target.to_owned(),
+                    })]);
+                }
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// The nodes hosting the replicas of each group, including the replicas being added.
+    fn group_nodes(&self) -> HashMap<u64, HashSet<u64>> {
         let mut groups = self
             .alloc_source
             .groups()
@@ -106,7 +150,16 @@ impl<T: AllocSource> ReplicaCountPolicy<T> {
                 g.insert(replica_state.node_id);
             }
         }
+        groups
+    }
 
+    fn rebalance_target(
+        &self,
+        src: &NodeDesc,
+        ranked_nodes: &[(NodeDesc, BalanceStatus)],
+        mean: f64,
+    ) -> Option<ReplicaAction> {
+        let groups = self.group_nodes();
         for (target, state) in ranked_nodes.iter().rev() {
             if *state != BalanceStatus::Underfull {
                 break;
@@ -198,11 +251,6 @@ impl<T: AllocSource> ReplicaCountPolicy<T> {
         BalanceStatus::Balanced
     }
 
-    fn node_alloc_score(&self, n: &NodeDesc) -> f64 {
-        // TODO: add more rule to calculate score.
-        -(self.node_replica_count(n) as f64)
-    }
-
     fn node_replica_count(&self, n: &NodeDesc) -> u64 {
         let mut cnt = n.capacity.as_ref().unwrap().replica_count as i64;
         let delta = self.ongoing_stats.get_node_delta(n.id);
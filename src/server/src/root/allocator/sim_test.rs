@@ -56,6 +56,7 @@ fn sim_boostrap_join_node_balance() {
                 leader_count: 1,
             }),
             status: NodeStatus::Active as i32,
+            ..Default::default()
         }]);
         p.set_replica_states(vec![ReplicaState {
             replica_id: 1,
@@ -64,6 +65,7 @@ fn sim_boostrap_join_node_balance() {
             voted_for: 0,
             role: RaftRole::Leader.into(),
             node_id: 1,
+            applied_index: 0,
         }]);
 
         let act = a.compute_group_action().await.unwrap();
@@ -82,6 +84,7 @@ fn sim_boostrap_join_node_balance() {
                     leader_count: 0,
                 }),
                 status: NodeStatus::Active as i32,
+                ..Default::default()
             },
             NodeDesc {
                 id: 3,
@@ -92,6 +95,7 @@ fn sim_boostrap_join_node_balance() {
                     leader_count: 0,
                 }),
                 status: NodeStatus::Active as i32,
+                ..Default::default()
             },
         ]);
         p.set_nodes(nodes);
@@ -128,6 +132,7 @@ fn sim_boostrap_join_node_balance() {
                 voted_for: 0,
                 role: RaftRole::Leader.into(),
                 node_id: 1,
+                applied_index: 0,
             },
             ReplicaState {
                 replica_id: 2,
@@ -136,6 +141,7 @@ fn sim_boostrap_join_node_balance() {
                 voted_for: 0,
                 role: RaftRole::Follower.into(),
                 node_id: 2,
+                applied_index: 0,
             },
             ReplicaState {
                 replica_id: 3,
@@ -144,6 +150,7 @@ fn sim_boostrap_join_node_balance() {
                 voted_for: 0,
                 role: RaftRole::Follower.into(),
                 node_id: 3,
+                applied_index: 0,
             },
         ]);
         p.display();
@@ -187,6 +194,7 @@ fn sim_boostrap_join_node_balance() {
                             voted_for: 0,
                             role,
                             node_id: n.id,
+                            applied_index: 0,
                         });
                         replica_id_gen += 1;
                     }
@@ -236,6 +244,7 @@ fn sim_boostrap_join_node_balance() {
                 leader_count: 0,
             }),
             status: NodeStatus::Active as i32,
+            ..Default::default()
         }]);
         p.set_nodes(nodes);
         p.display();
@@ -278,6 +287,7 @@ fn sim_boostrap_join_node_balance() {
                             voted_for: 0,
                             role,
                             node_id: n.id,
+                            applied_index: 0,
                         });
                         replica_id_gen += 1;
                     }
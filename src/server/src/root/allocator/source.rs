@@ -17,10 +17,16 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use engula_api::server::v1::*;
+use engula_api::server::v1::{
+    watch_response::{delete_event, update_event, DeleteEvent, UpdateEvent},
+    *,
+};
 
 use super::RootShared;
-use crate::{root::liveness::Liveness, Result};
+use crate::{
+    root::{liveness::Liveness, metrics, Schema, WatchHub},
+    Result,
+};
 
 pub enum NodeFilter {
     All,
@@ -53,6 +59,32 @@ pub struct SysAllocSource {
     nodes: Arc<Mutex<Vec<NodeDesc>>>,
     groups: Arc<Mutex<GroupInfo>>,
     replicas: Arc<Mutex<ReplicaInfo>>,
+    cursor: Arc<Mutex<Option<CatchUpCursor>>>,
+}
+
+/// The sequence of watch events which the cached groups and replica states have caught up. It is
+/// only valid in the leadership the schema belongs to, since the events emitted during other
+/// leaderships are not observed.
+struct CatchUpCursor {
+    schema: Arc<Schema>,
+    seq: u64,
+}
+
+/// How to catch up the cached groups and replica states.
+enum CatchUp {
+    /// Apply the events emitted since the cursor.
+    Incremental(Vec<UpdateEvent>, Vec<DeleteEvent>),
+    /// The events since the cursor are compacted or unknown, reload all of them.
+    FullReload,
+}
+
+impl CatchUp {
+    fn since(hub: &WatchHub, seq: Option<u64>) -> Self {
+        match seq.and_then(|seq| hub.events_since(seq)) {
+            Some((updates, deletes)) => CatchUp::Incremental(updates, deletes),
+            None => CatchUp::FullReload,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -74,6 +106,7 @@ impl SysAllocSource {
             nodes: Default::default(),
             groups: Default::default(),
             replicas: Default::default(),
+            cursor: Default::default(),
         }
     }
 }
@@ -82,8 +115,31 @@ impl SysAllocSource {
 impl AllocSource for SysAllocSource {
     async fn refresh_all(&self) -> Result<()> {
         self.reload_nodes().await?;
-        self.reload_groups().await?;
-        self.reload_replica_status().await
+
+        let schema = self.root.schema()?;
+        let hub = self.root.watcher_hub.clone();
+        // Read the sequence before loading, the events after it will be applied again next time.
+        let seq = hub.last_sequence();
+        let cursor = self
+            .cursor
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|c| Arc::ptr_eq(&c.schema, &schema))
+            .map(|c| c.seq);
+        match CatchUp::since(&hub, cursor) {
+            CatchUp::Incremental(updates, deletes) => {
+                metrics::ROOT_ALLOC_SOURCE_CATCH_UP_TOTAL.incremental.inc();
+                self.apply_events(updates, deletes);
+            }
+            CatchUp::FullReload => {
+                metrics::ROOT_ALLOC_SOURCE_CATCH_UP_TOTAL.full_reload.inc();
+                self.reload_groups().await?;
+                self.reload_replica_status().await?;
+            }
+        }
+        *self.cursor.lock().unwrap() = Some(CatchUpCursor { schema, seq });
+        Ok(())
     }
 
     fn nodes(&self, filter: NodeFilter) -> Vec<NodeDesc> {
@@ -181,6 +237,19 @@ impl SysAllocSource {
         );
     }
 
+    /// Apply the updates and deletes of groups and replica states to the cached ones.
+    fn apply_events(&self, updates: Vec<UpdateEvent>, deletes: Vec<DeleteEvent>) {
+        let mut groups = self.groups();
+        let mut replicas = self.replicas.lock().unwrap().replicas.clone();
+        let changed = apply_events(&mut groups, &mut replicas, updates, deletes);
+        if changed.groups {
+            self.set_groups(groups.into_values().collect());
+        }
+        if changed.replicas {
+            self.set_replica_states(replicas.into_values().collect());
+        }
+    }
+
     async fn reload_replica_status(&self) -> Result<()> {
         let schema = self.root.schema()?;
         let replicas = schema.list_replica_state().await?;
@@ -188,7 +257,6 @@ impl SysAllocSource {
         Ok(())
     }
 
-    #[allow(dead_code)]
     fn set_replica_states(&self, rs: Vec<ReplicaState>) {
         let mut replicas = self.replicas.lock().unwrap();
         let id_to_state = rs
@@ -203,3 +271,215 @@ impl SysAllocSource {
         );
     }
 }
+
+#[derive(Default)]
+struct Changed {
+    groups: bool,
+    replicas: bool,
+}
+
+/// Apply the deletes after the updates. The ids of groups are never reused, so a deleted group
+/// must not be brought back by an update emitted before it was deleted.
+fn apply_events(
+    groups: &mut HashMap<u64, GroupDesc>,
+    replicas: &mut HashMap<u64, ReplicaState>,
+    updates: Vec<UpdateEvent>,
+    deletes: Vec<DeleteEvent>,
+) -> Changed {
+    let mut changed = Changed::default();
+    for update in updates {
+        match update.event {
+            Some(update_event::Event::Group(desc)) => {
+                // The events might be applied again, so skip the stale ones.
+                if groups.get(&desc.id).map(|g| g.epoch).unwrap_or_default() <= desc.epoch {
+                    groups.insert(desc.id, desc);
+                    changed.groups = true;
+                }
+            }
+            Some(update_event::Event::GroupState(state)) => {
+                replicas.retain(|_, r| r.group_id != state.group_id);
+                replicas.extend(state.replicas.into_iter().map(|r| (r.replica_id, r)));
+                changed.replicas = true;
+            }
+            _ => {}
+        }
+    }
+    for delete in deletes {
+        match delete.event {
+            Some(delete_event::Event::Group(group_id)) => {
+                if groups.remove(&group_id).is_some() {
+                    changed.groups = true;
+                }
+            }
+            Some(delete_event::Event::GroupState(group_id)) => {
+                let before = replicas.len();
+                replicas.retain(|_, r| r.group_id != group_id);
+                if replicas.len() != before {
+                    changed.replicas = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{root::SystemClock, runtime::ExecutorOwner};
+
+    fn group_event(id: u64, epoch: u64) -> UpdateEvent {
+        UpdateEvent {
+            event: Some(update_event::Event::Group(GroupDesc {
+                id,
+                epoch,
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn group_state_event(group_id: u64, replica_ids: &[u64]) -> UpdateEvent {
+        UpdateEvent {
+            event: Some(update_event::Event::GroupState(GroupState {
+                group_id,
+                replicas: replica_ids
+                    .iter()
+                    .map(|id| ReplicaState {
+                        replica_id: *id,
+                        group_id,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            })),
+        }
+    }
+
+    #[test]
+    fn incremental_catch_up_within_retention() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            let mut groups = HashMap::from([(
+                1,
+                GroupDesc {
+                    id: 1,
+                    epoch: 1,
+                    ..Default::default()
+                },
+            )]);
+            let mut replicas = HashMap::new();
+            let cursor = hub.last_sequence();
+            hub.notify_updates(vec![group_event(1, 2), group_state_event(1, &[1, 2])])
+                .await;
+            hub.notify_updates(vec![group_event(2, 1), group_state_event(1, &[2, 3])])
+                .await;
+
+            let updates = match CatchUp::since(&hub, Some(cursor)) {
+                CatchUp::Incremental(updates, _) => updates,
+                CatchUp::FullReload => panic!("expect incremental catch up"),
+            };
+            let changed = apply_events(&mut groups, &mut replicas, updates.clone(), vec![]);
+            assert!(changed.groups && changed.replicas);
+            assert_eq!(groups[&1].epoch, 2);
+            assert_eq!(groups[&2].epoch, 1);
+            let mut replica_ids = replicas.keys().cloned().collect::<Vec<_>>();
+            replica_ids.sort_unstable();
+            assert_eq!(replica_ids, vec![2, 3]);
+
+            // Apply the stale events again doesn't rollback the newer groups.
+            groups.get_mut(&1).unwrap().epoch = 3;
+            apply_events(&mut groups, &mut replicas, updates, vec![]);
+            assert_eq!(groups[&1].epoch, 3);
+        });
+    }
+
+    #[test]
+    fn incremental_catch_up_deletes() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            let mut groups = HashMap::new();
+            let mut replicas = HashMap::new();
+            let cursor = hub.last_sequence();
+            hub.notify_updates(vec![
+                group_event(1, 1),
+                group_state_event(1, &[1, 2]),
+                group_event(2, 1),
+                group_state_event(2, &[3]),
+            ])
+            .await;
+            hub.notify_deletes(vec![
+                DeleteEvent {
+                    event: Some(delete_event::Event::Group(1)),
+                },
+                DeleteEvent {
+                    event: Some(delete_event::Event::GroupState(1)),
+                },
+            ])
+            .await;
+
+            let (updates, deletes) = match CatchUp::since(&hub, Some(cursor)) {
+                CatchUp::Incremental(updates, deletes) => (updates, deletes),
+                CatchUp::FullReload => panic!("expect incremental catch up"),
+            };
+            let changed = apply_events(&mut groups, &mut replicas, updates, deletes);
+            assert!(changed.groups && changed.replicas);
+            assert_eq!(groups.keys().cloned().collect::<Vec<_>>(), vec![2]);
+            assert_eq!(replicas.keys().cloned().collect::<Vec<_>>(), vec![3]);
+        });
+    }
+
+    #[test]
+    fn full_reload_beyond_retention() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            2,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            // The first catch up always reloads fully.
+            assert!(matches!(CatchUp::since(&hub, None), CatchUp::FullReload));
+
+            let cursor = hub.last_sequence();
+            for epoch in 1..=8 {
+                hub.notify_updates(vec![group_event(1, epoch)]).await;
+            }
+            // The events since cursor have been compacted.
+            assert!(matches!(
+                CatchUp::since(&hub, Some(cursor)),
+                CatchUp::FullReload
+            ));
+            // So does an unknown sequence.
+            assert!(matches!(
+                CatchUp::since(&hub, Some(hub.last_sequence() + 1)),
+                CatchUp::FullReload
+            ));
+            assert!(matches!(
+                CatchUp::since(&hub, Some(hub.last_sequence())),
+                CatchUp::Incremental(..)
+            ));
+        });
+    }
+}
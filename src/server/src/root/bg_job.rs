@@ -16,18 +16,23 @@ use std::{
     collections::HashSet,
     sync::{atomic, Arc, Mutex},
     task::{Poll, Waker},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use engula_api::server::v1::{GroupDesc, ReplicaDesc, ReplicaRole, RootDesc, ShardDesc};
+use engula_api::server::v1::{
+    watch_response::{delete_event, update_event, DeleteEvent, UpdateEvent},
+    GroupDesc, NodeStatus, ReplicaDesc, ReplicaRole, RootDesc, ShardDesc,
+};
 use engula_client::GroupClient;
 use futures::future::poll_fn;
 use prometheus::HistogramTimer;
-use tokio::time::Instant;
 use tracing::{error, info, warn};
 
-use super::{allocator::*, HeartbeatQueue, HeartbeatTask, RootShared, Schema};
+use super::{
+    allocator::*, lag::ReplicaLag, HeartbeatQueue, HeartbeatTask, OngoingStats, RootShared, Schema,
+};
 use crate::{
-    bootstrap::INITIAL_EPOCH,
+    bootstrap::{INITIAL_EPOCH, ROOT_GROUP_ID},
     root::metrics,
     serverpb::v1::{background_job::Job, *},
     Result,
@@ -42,12 +47,16 @@ impl Jobs {
         root_shared: Arc<RootShared>,
         alloc: Arc<Allocator<SysAllocSource>>,
         heartbeat_queue: Arc<HeartbeatQueue>,
+        ongoing_stats: Arc<OngoingStats>,
+        replica_lag: Arc<ReplicaLag>,
     ) -> Self {
         Self {
             core: JobCore {
                 root_shared,
                 alloc,
                 heartbeat_queue,
+                ongoing_stats,
+                replica_lag,
                 mem_jobs: Default::default(),
                 res_locks: Default::default(),
                 enable: Default::default(),
@@ -89,12 +98,16 @@ impl Jobs {
 
     async fn handle_job(&self, job: &BackgroundJob) -> Result<()> {
         info!("start background job: {job:?}");
+        // The job is stopped once the leadership is lost, the next leader resumes it.
+        let (_, generation) = self.core.root_shared.schema_with_generation()?;
         let r = match job.job.as_ref().unwrap() {
             background_job::Job::CreateCollection(create_collection) => {
-                self.handle_create_collection(job, create_collection).await
+                self.handle_create_collection(job, create_collection, generation)
+                    .await
             }
             background_job::Job::CreateOneGroup(create_group) => {
-                self.handle_create_one_group(job, create_group).await
+                self.handle_create_one_group(job, create_group, generation)
+                    .await
             }
             background_job::Job::PurgeCollection(purge_collection) => {
                 self.handle_purge_collection(job, purge_collection).await
@@ -102,6 +115,9 @@ impl Jobs {
             background_job::Job::PurgeDatabase(purge_database) => {
                 self.handle_purge_database(job, purge_database).await
             }
+            background_job::Job::DecommissionNode(decommission_node) => {
+                self.handle_decommission_node(job, decommission_node).await
+            }
         };
         info!("backgroud job: {job:?}, handle result: {r:?}");
         r
@@ -114,9 +130,11 @@ impl Jobs {
         &self,
         job: &BackgroundJob,
         create_collection: &CreateCollectionJob,
+        generation: u64,
     ) -> Result<()> {
         let mut create_collection = create_collection.to_owned();
         loop {
+            self.core.root_shared.check_generation(generation)?;
             let status = CreateCollectionJobStatus::from_i32(create_collection.status).unwrap();
             let _timer = Self::record_create_collection_step(&status);
             match status {
@@ -275,9 +293,11 @@ impl Jobs {
         &self,
         job: &BackgroundJob,
         create_group: &CreateOneGroupJob,
+        generation: u64,
     ) -> Result<()> {
         let mut create_group = create_group.to_owned();
         loop {
+            self.core.root_shared.check_generation(generation)?;
             let status = CreateOneGroupStatus::from_i32(create_group.status).unwrap();
             let _timer = Self::record_create_group_step(&status);
             match status {
@@ -353,6 +373,13 @@ impl Jobs {
                 role: ReplicaRole::Voter.into(),
             });
         }
+        let preferred_leader = self
+            .core
+            .alloc
+            .preferred_group_leader(&nodes)
+            .and_then(|node_id| replicas.iter().find(|r| r.node_id == node_id))
+            .map(|r| r.id)
+            .unwrap_or_default();
         let group_desc = GroupDesc {
             id: group_id,
             epoch: INITIAL_EPOCH,
@@ -360,6 +387,7 @@ impl Jobs {
             replicas,
         };
         create_group.group_desc = Some(group_desc);
+        create_group.preferred_leader = preferred_leader;
         create_group.wait_create = nodes;
         create_group.status = CreateOneGroupStatus::CreateOneGroupCreating as i32;
         self.save_create_group(job_id, create_group).await
@@ -469,9 +497,17 @@ impl Jobs {
                         .cloned()
                         .map(|node_id| HeartbeatTask { node_id })
                         .collect(),
-                    Instant::now(),
+                    self.core.root_shared.clock.now(),
                 )
                 .await;
+            if create_group.preferred_leader != 0 {
+                let group = create_group.group_desc.as_ref().unwrap().id;
+                let replica = create_group.preferred_leader;
+                if let Err(err) = self.try_transfer_leader(group, replica).await {
+                    // The leader balance will spread the leaders later.
+                    warn!(group, replica, err = ?err, "transfer leader of new group to preferred replica fail");
+                }
+            }
         }
         let mut job = job.to_owned();
         job.job = Some(background_job::Job::CreateOneGroup(create_group));
@@ -512,17 +548,20 @@ impl Jobs {
         job: &BackgroundJob,
         purge_collection: &PurgeCollectionJob,
     ) -> Result<()> {
-        let schema = self.core.root_shared.schema()?;
+        let (schema, generation) = self.core.root_shared.schema_with_generation()?;
         let mut group_shards = schema
             .get_collection_shards(purge_collection.collection_id)
             .await?;
         loop {
             if let Some((group, shard)) = group_shards.pop() {
+                self.core.root_shared.check_generation(generation)?;
                 self.try_remove_shard(group, shard.id).await?;
                 continue;
             }
             break;
         }
+        self.cleanup_empty_groups(&schema, generation, purge_collection.collection_id)
+            .await?;
         self.core.finish(job.to_owned()).await?;
         Ok(())
     }
@@ -532,19 +571,20 @@ impl Jobs {
         job: &BackgroundJob,
         purge_database: &PurgeDatabaseJob,
     ) -> Result<()> {
-        let schema = self.core.root_shared.schema()?;
+        let (schema, generation) = self.core.root_shared.schema_with_generation()?;
         let mut collections = schema
             .list_database_collections(purge_database.database_id)
             .await?;
         loop {
             if let Some(co) = collections.pop() {
+                self.core.root_shared.check_generation(generation)?;
                 let job = BackgroundJob {
                     job: Some(Job::PurgeCollection(PurgeCollectionJob {
                         database_id: co.db,
                         collection_id: co.id,
                         database_name: "".to_owned(),
                         collection_name: co.name.to_owned(),
-                        created_time: format!("{:?}", Instant::now()),
+                        created_time: format!("{:?}", self.core.root_shared.clock.now()),
                     })),
                     ..Default::default()
                 };
@@ -563,6 +603,83 @@ impl Jobs {
     }
 }
 
+impl Jobs {
+    /// Remove the decommissioning node once the scheduler moves all replicas out of it. The node
+    /// is rolled back to active if the replicas are not moved out before the deadline, or a
+    /// replica of root group is placed on it, which must be removed manually.
+    async fn handle_decommission_node(
+        &self,
+        job: &BackgroundJob,
+        decommission_node: &DecommissionNodeJob,
+    ) -> Result<()> {
+        let (schema, generation) = self.core.root_shared.schema_with_generation()?;
+        let node_id = decommission_node.node_id;
+        let mut node_desc = match schema.get_node(node_id).await? {
+            Some(desc) if desc.status == NodeStatus::Decommissioning as i32 => desc,
+            _ => {
+                // The node is removed or its status is changed by others, eg. uncordoned.
+                warn!(node = node_id, "decommission node cancelled");
+                self.core.finish(job.to_owned()).await?;
+                return Ok(());
+            }
+        };
+
+        let remaining = schema
+            .list_group()
+            .await?
+            .into_iter()
+            .filter(|g| g.replicas.iter().any(|r| r.node_id == node_id))
+            .map(|g| g.id)
+            .collect::<Vec<_>>();
+        self.core.root_shared.check_generation(generation)?;
+        if remaining.is_empty() {
+            schema.delete_node(node_id).await?;
+            self.core
+                .root_shared
+                .watcher_hub
+                .notify_deletes(vec![DeleteEvent {
+                    event: Some(delete_event::Event::Node(node_id)),
+                }])
+                .await;
+            info!(node = node_id, "decommission node, removed");
+            self.core.finish(job.to_owned()).await?;
+            return Ok(());
+        }
+
+        if remaining.contains(&ROOT_GROUP_ID) || unix_millis() >= decommission_node.deadline_ms {
+            warn!(node = node_id, groups = ?remaining, "decommission node aborted, rollback to active");
+            node_desc.status = NodeStatus::Active as i32;
+            schema.update_node(node_desc.to_owned()).await?; // TODO: cas
+            self.core
+                .root_shared
+                .watcher_hub
+                .notify_updates(vec![UpdateEvent {
+                    event: Some(update_event::Event::Node(node_desc)),
+                }])
+                .await;
+            self.core.finish(job.to_owned()).await?;
+            return Ok(());
+        }
+
+        // The replicas are being moved out by the scheduler, check again later.
+        self.core
+            .root_shared
+            .clock
+            .sleep(Duration::from_secs(1))
+            .await;
+        Ok(())
+    }
+}
+
+/// The milliseconds since unix epoch, it is persisted in jobs so that the deadlines survive
+/// the failover of root.
+pub(super) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
 impl Jobs {
     async fn try_create_shard(&self, group_id: u64, desc: &ShardDesc) -> Result<()> {
         let mut group_client = GroupClient::lazy(
@@ -574,6 +691,17 @@ impl Jobs {
         Ok(())
     }
 
+    async fn try_transfer_leader(&self, group_id: u64, target_replica: u64) -> Result<()> {
+        let mut group_client = GroupClient::lazy(
+            group_id,
+            self.core.root_shared.provider.router.clone(),
+            self.core.root_shared.provider.conn_manager.clone(),
+        );
+        group_client.set_timeout(Duration::from_secs(3));
+        group_client.transfer_leader(target_replica).await?;
+        Ok(())
+    }
+
     async fn try_create_replica(
         &self,
         addr: &str,
@@ -616,6 +744,31 @@ impl Jobs {
             )
             .await?;
         schema.remove_replica_state(group, replica).await?;
+        self.core.replica_lag.remove_replica(group, replica);
+        Ok(())
+    }
+
+    async fn try_remove_replica(
+        &self,
+        addr: &str,
+        group_id: u64,
+        replica_id: u64,
+    ) -> std::result::Result<(), engula_client::Error> {
+        let client = self
+            .core
+            .root_shared
+            .provider
+            .conn_manager
+            .get_node_client(addr.to_owned())?;
+        client
+            .remove_replica(
+                replica_id,
+                GroupDesc {
+                    id: group_id,
+                    ..Default::default()
+                },
+            )
+            .await?;
         Ok(())
     }
 
@@ -623,6 +776,114 @@ impl Jobs {
         // TODO: impl remove shard.
         Ok(())
     }
+
+    /// Remove the groups which only hosted the shards of deleted collections, including the one
+    /// being purged. The groups with ongoing replica changes are still draining data and are left
+    /// to the next purging, and at least one user group is kept to place the shards of new
+    /// collections.
+    async fn cleanup_empty_groups(
+        &self,
+        schema: &Schema,
+        generation: u64,
+        purged_collection: u64,
+    ) -> Result<()> {
+        let live_collections = schema
+            .list_collection()
+            .await?
+            .into_iter()
+            .map(|c| c.id)
+            .filter(|id| *id != purged_collection)
+            .collect::<HashSet<_>>();
+        let is_empty = |group: &GroupDesc| {
+            !group.shards.is_empty()
+                && group
+                    .shards
+                    .iter()
+                    .all(|s| !live_collections.contains(&s.collection_id))
+        };
+
+        let groups = schema
+            .list_group()
+            .await?
+            .into_iter()
+            .filter(|g| g.id != ROOT_GROUP_ID)
+            .collect::<Vec<_>>();
+        let mut user_groups = groups.len();
+        for group in groups {
+            if user_groups <= 1 {
+                break;
+            }
+            if !is_empty(&group) {
+                continue;
+            }
+            if self.core.ongoing_stats.is_group_busy(group.id) {
+                info!(
+                    group = group.id,
+                    "skip cleanup group, it has ongoing replica changes"
+                );
+                continue;
+            }
+            self.core.root_shared.check_generation(generation)?;
+            self.try_remove_group(schema, &group).await?;
+            user_groups -= 1;
+        }
+        Ok(())
+    }
+
+    /// Retire the empty group and then remove its replicas. The replicas on the unreachable
+    /// nodes are skipped, they are left to be cleaned by their nodes. The group is kept if some
+    /// shards are placed on it since `group` was read.
+    pub(super) async fn try_remove_group(&self, schema: &Schema, group: &GroupDesc) -> Result<()> {
+        // No shard could be placed on the group once it is retired, so its replicas are removed
+        // only after that.
+        if !schema.retire_empty_group(group).await? {
+            warn!(
+                group = group.id,
+                "skip removing group, new shards are placed on it"
+            );
+            return Ok(());
+        }
+        self.core
+            .root_shared
+            .watcher_hub
+            .notify_deletes(vec![DeleteEvent {
+                event: Some(delete_event::Event::Group(group.id)),
+            }])
+            .await;
+
+        for replica in &group.replicas {
+            if let Some(node) = schema.get_node(replica.node_id).await? {
+                if let Err(err) = self
+                    .try_remove_replica(&node.addr, group.id, replica.id)
+                    .await
+                {
+                    match err {
+                        engula_client::Error::Transport(_)
+                        | engula_client::Error::Connect(_)
+                        | engula_client::Error::DeadlineExceeded(_)
+                        | engula_client::Error::NotFound(_) => {
+                            warn!(group = group.id, replica = replica.id, node = node.id, err = ?err, "skip removing unreachable replica");
+                        }
+                        err => return Err(err.into()),
+                    }
+                }
+            }
+            schema.remove_replica_state(group.id, replica.id).await?;
+            self.core.replica_lag.remove_replica(group.id, replica.id);
+        }
+        // The states reported by the replicas already removed from the descriptor may linger.
+        schema.delete_group_state(group.id).await?;
+        self.core.replica_lag.remove_group(group.id);
+        self.core
+            .root_shared
+            .watcher_hub
+            .notify_deletes(vec![DeleteEvent {
+                event: Some(delete_event::Event::GroupState(group.id)),
+            }])
+            .await;
+        info!(group = group.id, "remove empty group");
+        Ok(())
+    }
 }
 
 struct JobCore {
@@ -631,6 +892,8 @@ struct JobCore {
     res_locks: Arc<Mutex<HashSet<Vec<u8>>>>,
     alloc: Arc<Allocator<SysAllocSource>>,
     heartbeat_queue: Arc<HeartbeatQueue>,
+    ongoing_stats: Arc<OngoingStats>,
+    replica_lag: Arc<ReplicaLag>,
     enable: atomic::AtomicBool,
 }
 
@@ -834,6 +1097,11 @@ fn res_key(job: &BackgroundJob) -> Option<Vec<u8>> {
             key.extend_from_slice(job.collection_name.as_bytes());
             Some(key)
         }
+        background_job::Job::DecommissionNode(job) => {
+            let mut key = b"decommission_node/".to_vec();
+            key.extend_from_slice(&job.node_id.to_le_bytes());
+            Some(key)
+        }
         background_job::Job::CreateOneGroup(_) | background_job::Job::PurgeDatabase(_) => None,
     }
 }
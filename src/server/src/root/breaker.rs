@@ -0,0 +1,152 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use super::{metrics, Clock};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakerState {
+    /// The heartbeats are sent as usual.
+    Closed,
+    /// The heartbeats are skipped until the cooldown is passed.
+    Open,
+    /// The cooldown is passed, the next heartbeat probes the node.
+    HalfOpen,
+}
+
+#[derive(Default)]
+struct NodeBreaker {
+    failures: u64,
+    open_until: Option<Instant>,
+}
+
+/// A per-node circuit breaker of heartbeats. The breaker of a node opens after
+/// `failure_threshold` consecutive failures, and the heartbeats to the node are skipped within
+/// the cooldown. Once the cooldown is passed, a probe is allowed: the breaker is closed if the
+/// probe succeeds, otherwise it opens again.
+#[derive(Clone)]
+pub struct HeartbeatBreaker {
+    /// Zero means the breaker never opens.
+    failure_threshold: u64,
+    cooldown: Duration,
+    clock: Arc<dyn Clock>,
+    nodes: Arc<Mutex<HashMap<u64, NodeBreaker>>>,
+}
+
+impl HeartbeatBreaker {
+    pub fn new(failure_threshold: u64, cooldown: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            clock,
+            nodes: Default::default(),
+        }
+    }
+
+    pub fn state(&self, node_id: u64) -> BreakerState {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(&node_id).and_then(|b| b.open_until) {
+            None => BreakerState::Closed,
+            Some(open_until) if self.clock.now() < open_until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether the heartbeat to the node should be sent.
+    pub fn allow(&self, node_id: u64) -> bool {
+        self.state(node_id) != BreakerState::Open
+    }
+
+    pub fn record_success(&self, node_id: u64) {
+        let mut nodes = self.nodes.lock().unwrap();
+        if nodes.remove(&node_id).is_some() {
+            Self::update_open_nodes(&nodes);
+        }
+    }
+
+    pub fn record_failure(&self, node_id: u64) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+        let mut nodes = self.nodes.lock().unwrap();
+        let breaker = nodes.entry(node_id).or_default();
+        breaker.failures += 1;
+        if breaker.failures >= self.failure_threshold {
+            breaker.open_until = Some(self.clock.now() + self.cooldown);
+            metrics::HEARTBEAT_BREAKER_OPEN_TOTAL.inc();
+        }
+        Self::update_open_nodes(&nodes);
+    }
+
+    pub fn reset(&self) {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.clear();
+        Self::update_open_nodes(&nodes);
+    }
+
+    fn update_open_nodes(nodes: &HashMap<u64, NodeBreaker>) {
+        let open_nodes = nodes.values().filter(|b| b.open_until.is_some()).count();
+        metrics::HEARTBEAT_BREAKER_OPEN_NODES.set(open_nodes as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::root::clock::ManualClock;
+
+    #[test]
+    fn open_after_consecutive_failures() {
+        let clock = Arc::new(ManualClock::new());
+        let breaker = HeartbeatBreaker::new(2, Duration::from_secs(10), clock.clone());
+
+        breaker.record_failure(1);
+        assert_eq!(breaker.state(1), BreakerState::Closed);
+        breaker.record_failure(1);
+        assert_eq!(breaker.state(1), BreakerState::Open);
+        assert!(!breaker.allow(1));
+        assert!(breaker.allow(2));
+
+        // The node is probed once the cooldown is passed, and the failed probe opens it again.
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(breaker.state(1), BreakerState::HalfOpen);
+        assert!(breaker.allow(1));
+        breaker.record_failure(1);
+        assert_eq!(breaker.state(1), BreakerState::Open);
+
+        // The succeeded probe closes it.
+        clock.advance(Duration::from_secs(10));
+        breaker.record_success(1);
+        assert_eq!(breaker.state(1), BreakerState::Closed);
+        breaker.record_failure(1);
+        assert_eq!(breaker.state(1), BreakerState::Closed);
+    }
+
+    #[test]
+    fn never_open_without_threshold() {
+        let breaker =
+            HeartbeatBreaker::new(0, Duration::from_secs(10), Arc::new(ManualClock::new()));
+        for _ in 0..10 {
+            breaker.record_failure(1);
+        }
+        assert_eq!(breaker.state(1), BreakerState::Closed);
+    }
+}
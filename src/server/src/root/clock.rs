@@ -0,0 +1,136 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// The source of time of the root, so that the timing-dependent logic, eg. heartbeat and watch
+/// delivery deadline, could be driven by a fake clock in tests.
+#[crate::async_trait]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    async fn sleep(&self, duration: Duration);
+}
+
+/// The clock of the real time.
+#[derive(Default)]
+pub struct SystemClock;
+
+#[crate::async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        crate::runtime::time::sleep(duration).await;
+    }
+}
+
+#[cfg(test)]
+pub use self::manual::ManualClock;
+
+#[cfg(test)]
+mod manual {
+    use std::sync::Mutex;
+
+    use futures::channel::oneshot;
+
+    use super::*;
+
+    /// A clock only moves forward when it is advanced, the sleepers are woken once their
+    /// deadlines are passed.
+    pub struct ManualClock {
+        inner: Mutex<ManualClockInner>,
+    }
+
+    struct ManualClockInner {
+        now: Instant,
+        sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+    }
+
+    impl ManualClock {
+        pub fn new() -> Self {
+            ManualClock {
+                inner: Mutex::new(ManualClockInner {
+                    now: Instant::now(),
+                    sleepers: Vec::default(),
+                }),
+            }
+        }
+
+        pub fn advance(&self, duration: Duration) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.now += duration;
+            let now = inner.now;
+            let (expired, pending) = std::mem::take(&mut inner.sleepers)
+                .into_iter()
+                .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= now);
+            inner.sleepers = pending;
+            for (_, sender) in expired {
+                let _ = sender.send(());
+            }
+        }
+    }
+
+    impl Default for ManualClock {
+        fn default() -> Self {
+            ManualClock::new()
+        }
+    }
+
+    #[crate::async_trait]
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.inner.lock().unwrap().now
+        }
+
+        async fn sleep(&self, duration: Duration) {
+            let receiver = {
+                let mut inner = self.inner.lock().unwrap();
+                if duration.is_zero() {
+                    return;
+                }
+                let (sender, receiver) = oneshot::channel();
+                let deadline = inner.now + duration;
+                inner.sleepers.push((deadline, sender));
+                receiver
+            };
+            let _ = receiver.await;
+        }
+    }
+
+    mod tests {
+        use futures::FutureExt;
+
+        use super::*;
+
+        #[test]
+        fn sleep_until_advanced() {
+            let clock = ManualClock::new();
+            let start = clock.now();
+            let mut sleep = Box::pin(clock.sleep(Duration::from_secs(2)));
+            assert!((&mut sleep).now_or_never().is_none());
+
+            clock.advance(Duration::from_secs(1));
+            assert!((&mut sleep).now_or_never().is_none());
+
+            clock.advance(Duration::from_secs(1));
+            assert!(sleep.now_or_never().is_some());
+            assert_eq!(clock.now() - start, Duration::from_secs(2));
+        }
+    }
+}
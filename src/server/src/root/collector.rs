@@ -48,6 +48,7 @@ struct RootCollectorShared {
     groups: IntGauge,
     replica_counts: IntGaugeVec,
     leader_counts: IntGaugeVec,
+    group_leader_counts: IntGaugeVec,
     shard_counts: IntGaugeVec,
 }
 
@@ -80,6 +81,15 @@ impl RootCollectorShared {
         )
         .unwrap();
         descs.extend(leader_counts.desc().into_iter().cloned());
+        let group_leader_counts = IntGaugeVec::new(
+            Opts::new(
+                "cluster_node_group_leader_total",
+                "group leader count for each node reported by heartbeat",
+            ),
+            &["node"],
+        )
+        .unwrap();
+        descs.extend(group_leader_counts.desc().into_iter().cloned());
         let shard_counts = IntGaugeVec::new(
             Opts::new("cluster_group_shard_total", "shard count for each group"),
             &["group"],
@@ -94,6 +104,7 @@ impl RootCollectorShared {
             groups,
             replica_counts,
             leader_counts,
+            group_leader_counts,
             shard_counts,
             is_root_leader,
         }
@@ -155,6 +166,17 @@ impl RootCollector {
                     .with_label_values(&[&n.id.to_string()])
                     .set(n.leaders.len() as i64);
             }
+
+            // group leader cnt reported by heartbeat, a heatmap could be rendered from it.
+            if let Ok(counts) = root.node_leader_counts().await {
+                self.shared.group_leader_counts.reset();
+                for (node, leaders) in counts {
+                    self.shared
+                        .group_leader_counts
+                        .with_label_values(&[&node.to_string()])
+                        .set(leaders as i64);
+                }
+            }
         } else {
             self.shared.is_root_leader.store(false, Ordering::Relaxed);
         }
@@ -175,6 +197,7 @@ impl core::Collector for RootCollector {
             mfs.extend(self.shared.groups.collect());
             mfs.extend(self.shared.replica_counts.collect());
             mfs.extend(self.shared.leader_counts.collect());
+            mfs.extend(self.shared.group_leader_counts.collect());
             mfs.extend(self.shared.shard_counts.collect());
         }
         mfs
@@ -0,0 +1,169 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MovePhase {
+    Pending,
+    Moving,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReplicaMove {
+    pub replica_id: u64,
+    pub group_id: u64,
+    pub phase: MovePhase,
+    pub target_node: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Tracks the moves of replicas out of the draining nodes, it is driven by the progress of the
+/// shed leader task.
+#[derive(Clone, Default)]
+pub struct DrainProgress {
+    nodes: Arc<Mutex<HashMap<u64 /* node */, BTreeMap<u64 /* replica */, ReplicaMove>>>>,
+}
+
+impl DrainProgress {
+    /// Start tracking the drain of a node, the records of the previous drain are cleared.
+    pub fn begin(&self, node_id: u64) {
+        self.nodes
+            .lock()
+            .unwrap()
+            .insert(node_id, BTreeMap::default());
+    }
+
+    /// Refresh the replicas which remain to move out of the node, the tracked replicas not in
+    /// `remaining` have been moved.
+    pub fn refresh(&self, node_id: u64, remaining: &[(u64 /* group */, u64 /* replica */)]) {
+        let mut nodes = self.nodes.lock().unwrap();
+        let replicas = nodes.entry(node_id).or_default();
+        for (replica_id, record) in replicas.iter_mut() {
+            if !remaining.iter().any(|(_, id)| id == replica_id) {
+                record.phase = MovePhase::Done;
+                record.error = None;
+            }
+        }
+        for (group_id, replica_id) in remaining {
+            let record = replicas.entry(*replica_id).or_insert_with(|| ReplicaMove {
+                replica_id: *replica_id,
+                group_id: *group_id,
+                phase: MovePhase::Pending,
+                target_node: None,
+                error: None,
+            });
+            if record.phase == MovePhase::Done {
+                // The replica is moved back, eg. by leader balancing.
+                record.phase = MovePhase::Pending;
+                record.target_node = None;
+            }
+        }
+    }
+
+    pub fn moving(&self, node_id: u64, replica_id: u64, target_node: u64) {
+        self.update(node_id, replica_id, |record| {
+            record.phase = MovePhase::Moving;
+            record.target_node = Some(target_node);
+            record.error = None;
+        });
+    }
+
+    pub fn failed(&self, node_id: u64, replica_id: u64, err: &Error) {
+        self.update(node_id, replica_id, |record| {
+            record.phase = MovePhase::Failed;
+            record.error = Some(err.to_string());
+        });
+    }
+
+    pub fn replicas(&self, node_id: u64) -> Vec<ReplicaMove> {
+        let nodes = self.nodes.lock().unwrap();
+        nodes
+            .get(&node_id)
+            .map(|replicas| replicas.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn reset(&self) {
+        self.nodes.lock().unwrap().clear();
+    }
+
+    fn update(&self, node_id: u64, replica_id: u64, f: impl FnOnce(&mut ReplicaMove)) {
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(record) = nodes
+            .get_mut(&node_id)
+            .and_then(|replicas| replicas.get_mut(&replica_id))
+        {
+            f(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phases(progress: &DrainProgress, node_id: u64) -> Vec<(u64, MovePhase)> {
+        progress
+            .replicas(node_id)
+            .into_iter()
+            .map(|r| (r.replica_id, r.phase))
+            .collect()
+    }
+
+    #[test]
+    fn replica_move_phases() {
+        let progress = DrainProgress::default();
+        progress.begin(1);
+        progress.refresh(1, &[(10, 100), (11, 110)]);
+        assert_eq!(
+            phases(&progress, 1),
+            vec![(100, MovePhase::Pending), (110, MovePhase::Pending)]
+        );
+
+        progress.moving(1, 100, 2);
+        progress.failed(1, 110, &Error::InvalidArgument("no leader".into()));
+        let replicas = progress.replicas(1);
+        assert_eq!(replicas[0].phase, MovePhase::Moving);
+        assert_eq!(replicas[0].target_node, Some(2));
+        assert_eq!(replicas[1].phase, MovePhase::Failed);
+        assert!(replicas[1].error.as_ref().unwrap().contains("no leader"));
+
+        // The moving replica is done once it is no longer remaining, the failed one is retried.
+        progress.refresh(1, &[(11, 110)]);
+        progress.moving(1, 110, 3);
+        assert_eq!(
+            phases(&progress, 1),
+            vec![(100, MovePhase::Done), (110, MovePhase::Moving)]
+        );
+        assert!(progress.replicas(1)[1].error.is_none());
+
+        progress.refresh(1, &[]);
+        assert_eq!(
+            phases(&progress, 1),
+            vec![(100, MovePhase::Done), (110, MovePhase::Done)]
+        );
+        assert!(progress.replicas(2).is_empty());
+    }
+}
@@ -12,21 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashSet, ops::Add, sync::Arc, time::Duration, vec};
+use std::{
+    collections::HashSet,
+    ops::Add,
+    sync::{atomic, Arc},
+    time::Duration,
+    vec,
+};
 
 use engula_api::server::v1::{
     watch_response::{update_event, UpdateEvent},
     *,
 };
 use futures::future::join_all;
-use tokio::time::Instant;
 use tracing::{info, trace, warn};
 
 use super::{HeartbeatTask, Root, Schema};
 use crate::{
     bootstrap::ROOT_GROUP_ID,
     root::{metrics, schema::ReplicaNodes},
-    Result,
+    Error, Result,
 };
 
 impl Root {
@@ -38,6 +43,18 @@ impl Root {
             .filter(|n| tasks.iter().any(|t| t.node_id == n.id))
             .collect::<Vec<_>>();
 
+        // The nodes whose breakers are open are skipped within the cooldown, their heartbeats
+        // are still rescheduled so that they are probed once the cooldown is passed.
+        let (nodes, skipped): (Vec<_>, Vec<_>) = nodes
+            .into_iter()
+            .partition(|n| self.heartbeat_breaker.allow(n.id));
+        for n in &skipped {
+            trace!(node = n.id, target = ?n.addr, "skip heartbeat since breaker is open");
+            metrics::HEARTBEAT_BREAKER_SKIP_TOTAL
+                .with_label_values(&[&n.id.to_string()])
+                .inc();
+        }
+
         info!("sending heartbeat to {:?}", &nodes);
 
         let mut piggybacks = Vec::new();
@@ -76,15 +93,32 @@ impl Root {
             })
         }
 
+        let node_ids = nodes.iter().map(|n| n.id).collect::<Vec<_>>();
+        let mut compaction_advice = self.compaction_advisor.advise(
+            &node_ids,
+            self.cfg.compaction_max_advised_groups,
+            self.shared.clock.now().into_std(),
+        );
+
         let resps = {
             let _timer = metrics::HEARTBEAT_NODES_RPC_DURATION_SECONDS.start_timer();
             metrics::HEARTBEAT_NODES_BATCH_SIZE.set(nodes.len() as i64);
             let mut futs = Vec::new();
             for n in &nodes {
                 trace!(node = n.id, target = ?n.addr, "attempt send heartbeat");
+                let mut piggybacks = piggybacks.clone();
+                if let Some(groups) = compaction_advice.remove(&n.id) {
+                    info!(node = n.id, groups = ?groups, "advise node to compact groups");
+                    metrics::ROOT_COMPACTION_ADVISED_GROUPS_TOTAL.inc_by(groups.len() as u64);
+                    piggybacks.push(PiggybackRequest {
+                        info: Some(piggyback_request::Info::CompactGroups(
+                            CompactGroupsRequest { groups },
+                        )),
+                    });
+                }
                 let fut = self.try_send_heartbeat(
                     n.addr.to_owned(),
-                    &piggybacks,
+                    piggybacks,
                     Duration::from_secs(self.cfg.heartbeat_timeout_sec),
                 );
                 futs.push(fut);
@@ -92,17 +126,37 @@ impl Root {
             join_all(futs).await
         };
 
-        let last_heartbeat = Instant::now();
+        // Only the heartbeats of the other nodes tell the connectivity, the local node is always
+        // reachable even if the root is isolated. A round in which no other node is contacted,
+        // eg. all skipped, tells nothing.
+        let peer_resps = nodes
+            .iter()
+            .zip(resps.iter())
+            .filter(|(n, _)| n.id != cur_node_id)
+            .map(|(_, resp)| resp)
+            .collect::<Vec<_>>();
+        if !peer_resps.is_empty() {
+            if peer_resps.iter().all(|resp| resp.is_err()) {
+                self.heartbeat_failures
+                    .fetch_add(1, atomic::Ordering::AcqRel);
+            } else {
+                self.heartbeat_failures.store(0, atomic::Ordering::Release);
+            }
+        }
+
+        let last_heartbeat = self.shared.clock.now();
         let mut heartbeat_tasks = Vec::new();
         for (i, resp) in resps.iter().enumerate() {
             let n = nodes.get(i).unwrap();
             match resp {
                 Ok(res) => {
                     self.liveness.renew(n.id);
+                    self.heartbeat_breaker.record_success(n.id);
                     for resp in &res.piggybacks {
                         match resp.info.as_ref().unwrap() {
                             piggyback_response::Info::SyncRoot(_)
-                            | piggyback_response::Info::CollectMigrationState(_) => {}
+                            | piggyback_response::Info::CollectMigrationState(_)
+                            | piggyback_response::Info::CompactGroups(_) => {}
                             piggyback_response::Info::CollectStats(ref resp) => {
                                 self.handle_collect_stats(&schema, resp, n.id).await?
                             }
@@ -120,11 +174,20 @@ impl Root {
                         .with_label_values(&[&n.id.to_string()])
                         .inc();
                     self.liveness.init_node_if_first_seen(n.id);
+                    self.heartbeat_breaker.record_failure(n.id);
                     warn!(node = n.id, target = ?n.addr, err = ?err, "send heartbeat error");
                 }
             }
             heartbeat_tasks.push(HeartbeatTask { node_id: n.id })
         }
+        for n in &skipped {
+            heartbeat_tasks.push(HeartbeatTask { node_id: n.id })
+        }
+        let node_ids = heartbeat_tasks
+            .iter()
+            .map(|t| t.node_id)
+            .collect::<Vec<_>>();
+        self.refresh_reachability(&schema, &node_ids).await?;
         self.heartbeat_queue
             .try_schedule(
                 heartbeat_tasks,
@@ -135,28 +198,72 @@ impl Root {
         Ok(())
     }
 
-    async fn try_send_heartbeat(
+    /// Mark the nodes which are dead by liveness as unreachable, and clear the mark once their
+    /// heartbeats resume. The changes are notified to watchers.
+    pub(super) async fn refresh_reachability(
+        &self,
+        schema: &Schema,
+        node_ids: &[u64],
+    ) -> Result<()> {
+        let mut update_events = Vec::new();
+        for node_id in node_ids {
+            let unreachable = self.liveness.get(node_id).is_dead();
+            let mut node = match schema.get_node(*node_id).await? {
+                Some(node) if node.unreachable != unreachable => node,
+                _ => continue,
+            };
+            node.unreachable = unreachable;
+            schema.update_node(node.to_owned()).await?;
+            if unreachable {
+                warn!(node = node.id, target = ?node.addr, "node is unreachable");
+            } else {
+                info!(node = node.id, target = ?node.addr, "node is reachable again");
+            }
+            update_events.push(UpdateEvent {
+                event: Some(update_event::Event::Node(node)),
+            });
+        }
+        if !update_events.is_empty() {
+            self.watcher_hub().notify_updates(update_events).await;
+        }
+        Ok(())
+    }
+
+    pub(super) async fn try_send_heartbeat(
         &self,
         addr: String,
-        piggybacks: &[PiggybackRequest],
+        piggybacks: Vec<PiggybackRequest>,
         _timeout: Duration,
     ) -> Result<HeartbeatResponse> {
+        if self.cfg.testing_knobs.inject_heartbeat_failure {
+            return Err(crate::Error::DeadlineExceeded(
+                "injected heartbeat failure".into(),
+            ));
+        }
         let client = self.get_node_client(addr).await?;
         let resp = client
             .root_heartbeat(HeartbeatRequest {
-                piggybacks: piggybacks.to_owned(),
+                piggybacks,
                 timestamp: 0, // TODO: use hlc
             })
             .await?;
         Ok(resp)
     }
 
-    async fn handle_collect_stats(
+    pub(super) async fn handle_collect_stats(
         &self,
         schema: &Schema,
         resp: &CollectStatsResponse,
         node_id: u64,
     ) -> Result<()> {
+        for gs in &resp.group_stats {
+            self.group_stats.update(gs);
+            self.compaction_advisor.update(
+                node_id,
+                gs,
+                self.cfg.compaction_garbage_ratio_threshold,
+            );
+        }
         if let Some(ns) = &resp.node_stats {
             if let Some(mut node) = schema.get_node(node_id).await? {
                 let _timer =
@@ -190,14 +297,20 @@ impl Root {
         let _timer = super::metrics::HEARTBEAT_HANDLE_GROUP_DETAIL_DURATION_SECONDS.start_timer();
         let mut update_events = Vec::new();
         for desc in &resp.group_descs {
-            if let Some(ex) = schema.get_group(desc.id).await? {
-                if desc.epoch <= ex.epoch {
-                    continue;
-                }
+            let ex = schema.get_group(desc.id).await?;
+            if matches!(&ex, Some(ex) if desc.epoch <= ex.epoch) {
+                continue;
             }
-            schema
-                .update_group_replica(Some(desc.to_owned()), None)
-                .await?;
+            match schema
+                .cas_group_replica(ex.map(|g| g.epoch), desc.to_owned(), None)
+                .await
+            {
+                Ok(()) => {}
+                // A concurrent update is applied, the desc is compared again in next heartbeat.
+                Err(Error::StaleReport(_)) => continue,
+                Err(err) => return Err(err),
+            }
+            self.replica_lag.retain_replicas(desc.id, &desc.replicas);
             metrics::ROOT_UPDATE_GROUP_DESC_TOTAL.heartbeat.inc();
             info!(
                 group = desc.id,
@@ -210,7 +323,7 @@ impl Root {
                         vec![HeartbeatTask {
                             node_id: self.current_node_id(),
                         }],
-                        Instant::now(),
+                        self.shared.clock.now(),
                     )
                     .await;
             }
@@ -221,6 +334,7 @@ impl Root {
 
         let mut changed_group_states = HashSet::new();
         for state in &resp.replica_states {
+            self.replica_lag.update(state);
             if let Some(pre_state) = schema
                 .get_replica_state(state.group_id, state.replica_id)
                 .await?
@@ -0,0 +1,193 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use engula_api::server::v1::{ReplicaDesc, ReplicaState};
+use serde::Serialize;
+
+#[derive(Clone, Copy)]
+struct ReplicaProgress {
+    node_id: u64,
+    applied_index: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct NodeLag {
+    pub max: u64,
+    pub avg: f64,
+}
+
+/// Tracks the applied index of replicas reported by heartbeat, the lag of a replica is the
+/// distance from the largest applied index in its group.
+#[derive(Clone, Default)]
+pub struct ReplicaLag {
+    groups: Arc<Mutex<HashMap<u64 /* group */, HashMap<u64 /* replica */, ReplicaProgress>>>>,
+}
+
+impl ReplicaLag {
+    pub fn update(&self, state: &ReplicaState) {
+        let mut groups = self.groups.lock().unwrap();
+        groups.entry(state.group_id).or_default().insert(
+            state.replica_id,
+            ReplicaProgress {
+                node_id: state.node_id,
+                applied_index: state.applied_index,
+            },
+        );
+    }
+
+    pub fn node_lag(&self, node_id: u64) -> NodeLag {
+        let groups = self.groups.lock().unwrap();
+        let mut lags = Vec::new();
+        for replicas in groups.values() {
+            let newest = replicas
+                .values()
+                .map(|p| p.applied_index)
+                .max()
+                .unwrap_or_default();
+            lags.extend(
+                replicas
+                    .values()
+                    .filter(|p| p.node_id == node_id)
+                    .map(|p| newest - p.applied_index),
+            );
+        }
+        if lags.is_empty() {
+            return NodeLag::default();
+        }
+        NodeLag {
+            max: lags.iter().cloned().max().unwrap_or_default(),
+            avg: lags.iter().sum::<u64>() as f64 / lags.len() as f64,
+        }
+    }
+
+    /// The max lag of the replicas of a group, zero if the group hasn't been reported.
+    pub fn group_lag(&self, group_id: u64) -> u64 {
+        let groups = self.groups.lock().unwrap();
+        let replicas = match groups.get(&group_id) {
+            Some(replicas) => replicas,
+            None => return 0,
+        };
+        let applied = replicas.values().map(|p| p.applied_index);
+        let newest = applied.clone().max().unwrap_or_default();
+        let oldest = applied.min().unwrap_or_default();
+        newest - oldest
+    }
+
+    /// Forget the replicas which are removed from the group.
+    pub fn retain_replicas(&self, group_id: u64, replicas: &[ReplicaDesc]) {
+        let mut groups = self.groups.lock().unwrap();
+        if let Some(progress) = groups.get_mut(&group_id) {
+            progress.retain(|id, _| replicas.iter().any(|r| r.id == *id));
+            if progress.is_empty() {
+                groups.remove(&group_id);
+            }
+        }
+    }
+
+    pub fn remove_replica(&self, group_id: u64, replica_id: u64) {
+        let mut groups = self.groups.lock().unwrap();
+        if let Some(progress) = groups.get_mut(&group_id) {
+            progress.remove(&replica_id);
+            if progress.is_empty() {
+                groups.remove(&group_id);
+            }
+        }
+    }
+
+    pub fn remove_group(&self, group_id: u64) {
+        self.groups.lock().unwrap().remove(&group_id);
+    }
+
+    pub fn reset(&self) {
+        self.groups.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(group_id: u64, replica_id: u64, node_id: u64, applied_index: u64) -> ReplicaState {
+        ReplicaState {
+            group_id,
+            replica_id,
+            node_id,
+            applied_index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn node_lag_max_and_avg() {
+        let lag = ReplicaLag::default();
+        // group 1: node 1 is up-to-date, node 2 lags 10.
+        lag.update(&state(1, 1, 1, 100));
+        lag.update(&state(1, 2, 2, 90));
+        // group 2: node 1 lags 6, node 2 lags 2.
+        lag.update(&state(2, 3, 1, 44));
+        lag.update(&state(2, 4, 2, 48));
+        lag.update(&state(2, 5, 3, 50));
+
+        assert_eq!(lag.node_lag(1), NodeLag { max: 6, avg: 3.0 });
+        assert_eq!(lag.node_lag(2), NodeLag { max: 10, avg: 6.0 });
+        assert_eq!(lag.node_lag(3), NodeLag { max: 0, avg: 0.0 });
+
+        // node without replicas.
+        assert_eq!(lag.node_lag(4), NodeLag::default());
+
+        // the newer report overrides the older one.
+        lag.update(&state(1, 2, 2, 100));
+        assert_eq!(lag.node_lag(2), NodeLag { max: 2, avg: 1.0 });
+    }
+
+    #[test]
+    fn group_max_lag() {
+        let lag = ReplicaLag::default();
+        lag.update(&state(1, 1, 1, 100));
+        lag.update(&state(1, 2, 2, 90));
+        lag.update(&state(1, 3, 3, 95));
+        assert_eq!(lag.group_lag(1), 10);
+        assert_eq!(lag.group_lag(2), 0);
+    }
+
+    #[test]
+    fn evict_removed_replicas() {
+        let replica = |id| ReplicaDesc {
+            id,
+            ..Default::default()
+        };
+        let lag = ReplicaLag::default();
+        lag.update(&state(1, 1, 1, 100));
+        lag.update(&state(1, 2, 2, 90));
+        lag.update(&state(1, 3, 3, 95));
+        lag.update(&state(2, 4, 1, 10));
+
+        // The lagging replica is moved out of group 1.
+        lag.retain_replicas(1, &[replica(1), replica(3)]);
+        assert_eq!(lag.group_lag(1), 5);
+        assert_eq!(lag.node_lag(2), NodeLag::default());
+
+        lag.remove_replica(1, 3);
+        assert_eq!(lag.group_lag(1), 0);
+
+        lag.remove_group(2);
+        lag.remove_replica(1, 1);
+        assert!(lag.groups.lock().unwrap().is_empty());
+    }
+}
@@ -18,41 +18,68 @@ use std::{
     time::Duration,
 };
 
+use tokio::time::Instant;
+
+use super::Clock;
+
 #[derive(Clone)]
 pub struct NodeLiveness {
-    expiration: u128,
+    /// `None` if the node is marked dead.
+    expiration: Option<Instant>,
+    grace_period: Duration,
+    now: Instant,
 }
 
 impl NodeLiveness {
+    /// The node is dead once its liveness has expired for longer than the grace period, so a
+    /// brief pause of heartbeats doesn't make it unreachable.
     pub fn is_dead(&self) -> bool {
-        self.expiration < current_timestamp()
+        match self.expiration {
+            Some(expiration) => expiration + self.grace_period < self.now,
+            None => true,
+        }
     }
 
     #[allow(dead_code)]
     pub fn is_alive(&self) -> bool {
-        self.expiration > current_timestamp()
+        matches!(self.expiration, Some(expiration) if expiration > self.now)
     }
 }
 
 #[derive(Clone)]
 pub struct Liveness {
     liveness_threshold: Duration,
-    nodes: Arc<Mutex<HashMap<u64, NodeLiveness>>>,
+    grace_period: Duration,
+    clock: Arc<dyn Clock>,
+    nodes: Arc<Mutex<HashMap<u64, Option<Instant>>>>,
 }
 
 impl Liveness {
-    pub fn new(liveness_threshold: Duration) -> Self {
+    pub fn new(
+        liveness_threshold: Duration,
+        grace_period: Duration,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         Self {
             liveness_threshold,
+            grace_period,
+            clock,
             nodes: Default::default(),
         }
     }
 
     pub fn get(&self, node: &u64) -> NodeLiveness {
         let nodes = self.nodes.lock().unwrap();
-        nodes.get(node).cloned().unwrap_or_else(|| NodeLiveness {
-            expiration: self.new_expiration(),
-        })
+        let now = self.clock.now();
+        let expiration = nodes
+            .get(node)
+            .cloned()
+            .unwrap_or_else(|| Some(now + self.liveness_threshold));
+        NodeLiveness {
+            expiration,
+            grace_period: self.grace_period,
+            now,
+        }
     }
 
     pub fn renew(&self, node_id: u64) {
@@ -62,25 +89,33 @@ impl Liveness {
             hash_map::Entry::Occupied(mut ent) => {
                 let renew = self.new_expiration();
                 let ent = ent.get_mut();
-                if ent.expiration < renew {
-                    ent.expiration = renew
+                if ent.map(|expiration| expiration < renew).unwrap_or(true) {
+                    *ent = Some(renew);
                 }
             }
             hash_map::Entry::Vacant(ent) => {
-                ent.insert(NodeLiveness {
-                    expiration: self.new_expiration(),
-                });
+                ent.insert(Some(self.new_expiration()));
             }
         }
     }
 
+    /// Force the node back to alive, whatever the previous expiration is.
+    pub fn mark_alive(&self, node_id: u64) {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.insert(node_id, Some(self.new_expiration()));
+    }
+
+    #[cfg(test)]
+    pub fn mark_dead(&self, node_id: u64) {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.insert(node_id, None);
+    }
+
     pub fn init_node_if_first_seen(&self, node_id: u64) {
         // Give `liveness_threshold` time window to retry before mark as offline.
         let mut nodes = self.nodes.lock().unwrap();
         if let hash_map::Entry::Vacant(ent) = nodes.entry(node_id) {
-            ent.insert(NodeLiveness {
-                expiration: self.new_expiration(),
-            });
+            ent.insert(Some(self.new_expiration()));
         }
     }
 
@@ -88,14 +123,53 @@ impl Liveness {
         self.nodes.lock().unwrap().clear();
     }
 
-    fn new_expiration(&self) -> u128 {
-        current_timestamp() + self.liveness_threshold.as_millis()
+    fn new_expiration(&self) -> Instant {
+        self.clock.now() + self.liveness_threshold
     }
 }
 
-fn current_timestamp() -> u128 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let start = SystemTime::now();
-    let since_the_epoch = start.duration_since(UNIX_EPOCH).unwrap();
-    since_the_epoch.as_millis()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::root::clock::ManualClock;
+
+    fn liveness(clock: Arc<ManualClock>) -> Liveness {
+        Liveness::new(Duration::from_secs(10), Duration::from_secs(20), clock)
+    }
+
+    #[test]
+    fn blip_shorter_than_grace_period() {
+        let clock = Arc::new(ManualClock::new());
+        let liveness = liveness(clock.clone());
+        liveness.renew(1);
+
+        // The liveness expires, but the node is not dead within the grace period.
+        clock.advance(Duration::from_secs(25));
+        assert!(!liveness.get(&1).is_alive());
+        assert!(!liveness.get(&1).is_dead());
+
+        // The heartbeats resume.
+        liveness.renew(1);
+        assert!(liveness.get(&1).is_alive());
+        clock.advance(Duration::from_secs(25));
+        assert!(!liveness.get(&1).is_dead());
+    }
+
+    #[test]
+    fn outage_longer_than_grace_period() {
+        let clock = Arc::new(ManualClock::new());
+        let liveness = liveness(clock.clone());
+        liveness.init_node_if_first_seen(1);
+
+        clock.advance(Duration::from_secs(31));
+        assert!(liveness.get(&1).is_dead());
+
+        // Recover automatically once the heartbeats resume.
+        liveness.renew(1);
+        assert!(liveness.get(&1).is_alive());
+        assert!(!liveness.get(&1).is_dead());
+
+        liveness.mark_dead(1);
+        assert!(liveness.get(&1).is_dead());
+    }
 }
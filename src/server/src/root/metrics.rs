@@ -23,10 +23,32 @@ lazy_static! {
         "the node as root leader count"
     )
     .unwrap();
+    pub static ref DEGRADED_MODE_INFO: IntGauge = register_int_gauge!(
+        "root_service_degraded_mode_info",
+        "whether the cluster has fewer nodes than the replicas of a group"
+    )
+    .unwrap();
+    pub static ref CORRUPT_METADATA_TOTAL: IntCounter = register_int_counter!(
+        "root_service_corrupt_metadata_total",
+        "the total of corrupt metadata records skipped by root"
+    )
+    .unwrap();
 }
 
 // bootstrap root.
 
+make_static_metric! {
+    pub struct BootstrapStepDuration: Histogram {
+        "type" => {
+            open_engine,
+            create_groups,
+            update_root,
+            save_ident,
+            init_root_meta,
+        }
+    }
+}
+
 lazy_static! {
     pub static ref BOOTSTRAP_DURATION_SECONDS: Histogram = register_histogram!(
         "root_bootstrap_duration_seconds",
@@ -39,6 +61,15 @@ lazy_static! {
         "the count of boostrap root fail"
     )
     .unwrap();
+    pub static ref BOOTSTRAP_STEP_DURATION_SECONDS_VEC: HistogramVec = register_histogram_vec!(
+        "root_bootstrap_step_duration_seconds",
+        "the duration of each step of bootstrap cluster",
+        &["type"],
+        exponential_buckets(0.00005, 1.8, 26).unwrap(),
+    )
+    .unwrap();
+    pub static ref BOOTSTRAP_STEP_DURATION_SECONDS: BootstrapStepDuration =
+        BootstrapStepDuration::from(&BOOTSTRAP_STEP_DURATION_SECONDS_VEC);
 }
 
 // reconcile.
@@ -186,6 +217,26 @@ lazy_static! {
         ReconcileScheduleHandleTaskTotal::from(&RECONCILE_RETRY_TASK_TOTAL_VEC);
 }
 
+make_static_metric! {
+    pub struct RootAllocSourceCatchUpTotal: IntCounter {
+        "type" => {
+            incremental,
+            full_reload,
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref ROOT_ALLOC_SOURCE_CATCH_UP_TOTAL_VEC: IntCounterVec = register_int_counter_vec!(
+        "root_alloc_source_catch_up_total",
+        "the count of catching up the cached groups and replica states of allocator",
+        &["type"]
+    )
+    .unwrap();
+    pub static ref ROOT_ALLOC_SOURCE_CATCH_UP_TOTAL: RootAllocSourceCatchUpTotal =
+        RootAllocSourceCatchUpTotal::from(&ROOT_ALLOC_SOURCE_CATCH_UP_TOTAL_VEC);
+}
+
 // hearbeat & report
 
 make_static_metric! {
@@ -249,6 +300,27 @@ lazy_static! {
         exponential_buckets(0.00005, 1.8, 26).unwrap(),
     )
     .unwrap();
+    pub static ref HEARTBEAT_STEP_DOWN_TOTAL: IntCounter = register_int_counter!(
+        "root_heartbeat_step_down_total",
+        "the count of root leader step down since all heartbeats fail",
+    )
+    .unwrap();
+    pub static ref HEARTBEAT_BREAKER_OPEN_TOTAL: IntCounter = register_int_counter!(
+        "root_heartbeat_breaker_open_total",
+        "the count of heartbeat breakers opened after consecutive failures",
+    )
+    .unwrap();
+    pub static ref HEARTBEAT_BREAKER_OPEN_NODES: IntGauge = register_int_gauge!(
+        "root_heartbeat_breaker_open_nodes",
+        "the number of nodes whose heartbeat breaker is open or half open"
+    )
+    .unwrap();
+    pub static ref HEARTBEAT_BREAKER_SKIP_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "root_heartbeat_breaker_skip_total",
+        "the count of heartbeats skipped by the open breaker",
+        &["node"]
+    )
+    .unwrap();
     pub static ref HEARTBEAT_UPDATE_NODE_STATS_TOTAL: IntCounter = register_int_counter!(
         "root_heartbeat_update_node_stats_total",
         "the count of real update node stats after receive heartbeat response",
@@ -270,6 +342,57 @@ lazy_static! {
     .unwrap();
     pub static ref ROOT_UPDATE_REPLICA_STATE_TOTAL: UpdateReplicaState =
         UpdateReplicaState::from(&ROOT_UPDATE_REPLICA_STATE_TOTAL_VEC);
+    pub static ref REPORT_SHED_TOTAL: IntCounter = register_int_counter!(
+        "root_report_shed_total",
+        "The count of report requests rejected since the in-flight limit is exceeded"
+    )
+    .unwrap();
+    pub static ref ROOT_COMPACTION_ADVISED_GROUPS_TOTAL: IntCounter = register_int_counter!(
+        "root_compaction_advised_groups_total",
+        "The count of groups advised to compact"
+    )
+    .unwrap();
+}
+
+// store
+
+make_static_metric! {
+    pub struct RootStoreOperationTotal: IntCounter {
+        "type" => {
+            get,
+            put,
+            delete,
+            list,
+            batch_write,
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref ROOT_STORE_ATTEMPT_TOTAL_VEC: IntCounterVec = register_int_counter_vec!(
+        "root_store_attempt_total",
+        "The count of root store operation attempts",
+        &["type"]
+    )
+    .unwrap();
+    pub static ref ROOT_STORE_ATTEMPT_TOTAL: RootStoreOperationTotal =
+        RootStoreOperationTotal::from(&ROOT_STORE_ATTEMPT_TOTAL_VEC);
+    pub static ref ROOT_STORE_RETRY_TOTAL_VEC: IntCounterVec = register_int_counter_vec!(
+        "root_store_retry_total",
+        "The count of root store operation retries caused by not leader",
+        &["type"]
+    )
+    .unwrap();
+    pub static ref ROOT_STORE_RETRY_TOTAL: RootStoreOperationTotal =
+        RootStoreOperationTotal::from(&ROOT_STORE_RETRY_TOTAL_VEC);
+    pub static ref ROOT_STORE_RETRY_EXHAUSTED_TOTAL_VEC: IntCounterVec = register_int_counter_vec!(
+        "root_store_retry_exhausted_total",
+        "The count of root store operation failures after retry budget exhausted",
+        &["type"]
+    )
+    .unwrap();
+    pub static ref ROOT_STORE_RETRY_EXHAUSTED_TOTAL: RootStoreOperationTotal =
+        RootStoreOperationTotal::from(&ROOT_STORE_RETRY_EXHAUSTED_TOTAL_VEC);
 }
 
 // watch
@@ -282,4 +405,14 @@ lazy_static! {
         exponential_buckets(0.00005, 1.8, 26).unwrap(),
     )
     .unwrap();
+    pub static ref WATCH_SEQUENCE_GAP_TOTAL: IntCounter = register_int_counter!(
+        "root_watch_sequence_gap_total",
+        "the count of non-contiguous watch event sequence detected on emit"
+    )
+    .unwrap();
+    pub static ref WATCH_DELIVERY_DEADLINE_EXCEEDED_TOTAL: IntCounter = register_int_counter!(
+        "root_watch_delivery_deadline_exceeded_total",
+        "the count of watch deliveries to the watchers which stall beyond the deadline"
+    )
+    .unwrap();
 }
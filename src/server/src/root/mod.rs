@@ -12,14 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod acl;
 mod allocator;
 mod bg_job;
+mod breaker;
+mod clock;
 mod collector;
+mod drain;
 mod heartbeat;
+mod lag;
 mod liveness;
-mod metrics;
+pub(crate) mod metrics;
+mod move_history;
+mod options;
+mod page;
 mod schedule;
 mod schema;
+mod stats;
 mod store;
 mod watch;
 
@@ -28,20 +37,28 @@ use std::{collections::*, sync::*, task::Poll, time::Duration};
 use engula_api::{
     server::v1::{report_request::GroupUpdates, watch_response::*, *},
     v1::{
-        collection_desc as co_desc, create_collection_request as co_req, CollectionDesc,
-        DatabaseDesc,
+        collection_desc as co_desc, collection_options as co_opts,
+        create_collection_request as co_req, AclEntry, CollectionDesc, CollectionOptions,
+        DatabaseDesc, Permission,
     },
 };
-use engula_client::NodeClient;
+use engula_client::{GroupClient, NodeClient};
 use tokio::time::Instant;
-use tokio_util::time::delay_queue;
 use tracing::{error, info, trace, warn};
 
 pub(crate) use self::schema::*;
 pub use self::{
-    allocator::RootConfig,
+    acl::{parse_permissions, permission_name},
+    allocator::{RootConfig, RootTestingKnobs},
+    clock::{Clock, SystemClock},
     collector::RootCollector,
-    watch::{WatchHub, Watcher, WatcherInitializer},
+    drain::ReplicaMove,
+    lag::NodeLag,
+    move_history::{MoveOutcome, MoveReason, MoveRecord},
+    options::CreateCollectionOptions,
+    page::{PageObject, PageToken},
+    stats::CollectionStats,
+    watch::{EventType, RetainedEvent, WatchHub, Watcher, WatcherInitializer},
 };
 use self::{
     allocator::SysAllocSource, bg_job::Jobs, diagnosis::Metadata, schedule::ReconcileScheduler,
@@ -50,11 +67,45 @@ use self::{
 use crate::{
     bootstrap::{ROOT_GROUP_ID, SHARD_MAX, SHARD_MIN},
     node::{Node, Replica, ReplicaRouteTable},
-    runtime::{self, TaskPriority},
+    raftgroup::snap::SnapshotStat,
+    runtime::TaskPriority,
     serverpb::v1::{background_job::Job, reconcile_task, *},
     Config, Error, Provider, Result,
 };
 
+/// The max number of hops to follow when resolving an alias chain.
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// The max duration to wait for a manual replica change to be committed and reported.
+const GROUP_CHANGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The result of a report, the updates are identified by their indexes in the request.
+#[derive(Debug, Default)]
+pub struct ReportSummary {
+    pub applied: Vec<usize>,
+    pub failed: Vec<(usize, Error)>,
+}
+
+/// The result of joining a batch of nodes, each address is reported on its own.
+#[derive(Debug, Default)]
+pub struct JoinNodesSummary {
+    /// The nodes registered by this batch.
+    pub joined: Vec<NodeDesc>,
+    /// The nodes which had already joined, they are left untouched.
+    pub existing: Vec<NodeDesc>,
+    pub failed: Vec<(String, Error)>,
+}
+
+/// The nodes registered with the same address, the routing to the address is ambiguous.
+#[derive(Debug, Default)]
+pub struct DuplicateNodeAddr {
+    pub addr: String,
+    /// The node which should be kept, it hosts the most replicas.
+    pub keep: u64,
+    /// The other nodes of the address, and the number of replicas they host.
+    pub stale: Vec<(u64, usize)>,
+}
+
 #[derive(Clone)]
 pub struct Root {
     cfg: RootConfig,
@@ -64,6 +115,21 @@ pub struct Root {
     scheduler: Arc<ReconcileScheduler>,
     heartbeat_queue: Arc<HeartbeatQueue>,
     ongoing_stats: Arc<OngoingStats>,
+    replica_lag: Arc<lag::ReplicaLag>,
+    drain_progress: Arc<drain::DrainProgress>,
+    move_history: Arc<move_history::MoveHistory>,
+    group_stats: Arc<stats::GroupStatsCache>,
+    compaction_advisor: Arc<stats::CompactionAdvisor>,
+    /// Limits the in-flight report requests, the excess requests are shed.
+    report_limiter: Arc<tokio::sync::Semaphore>,
+    /// Serializes the deletions of databases and collections, so that only the first of the
+    /// concurrent deletions of the same object takes effect.
+    delete_lock: Arc<tokio::sync::Mutex<()>>,
+    /// The number of consecutive heartbeat rounds in which no node responds.
+    heartbeat_failures: Arc<atomic::AtomicU64>,
+    /// Whether the cluster runs in degraded mode, as of the last check of the leader.
+    degraded: Arc<atomic::AtomicBool>,
+    heartbeat_breaker: Arc<breaker::HeartbeatBreaker>,
     jobs: Arc<Jobs>,
 }
 
@@ -73,38 +139,98 @@ pub struct RootShared {
     local_addr: String,
     cfg_cpu_nums: u32,
     core: Mutex<Option<RootCore>>,
+    /// Advanced whenever the core is exposed or torn down.
+    generation: atomic::AtomicU64,
     watcher_hub: Arc<WatchHub>,
+    clock: Arc<dyn Clock>,
 }
 
 impl RootShared {
+    /// Return the schema if this node is the root leader, otherwise `NotRootLeader` is returned
+    /// to redirect the caller. All metadata reads go through it, so they are always served by the
+    /// leader and there is no follower read mode to disable.
     pub fn schema(&self) -> Result<Arc<Schema>> {
         let core = self.core.lock().unwrap();
         core.as_ref()
             .map(|c| c.schema.clone())
             .ok_or_else(|| Error::NotRootLeader(RootDesc::default(), 0, None))
     }
+
+    /// Return the schema with the generation of the core serving it, see `check_generation`.
+    pub fn schema_with_generation(&self) -> Result<(Arc<Schema>, u64)> {
+        let core = self.core.lock().unwrap();
+        core.as_ref()
+            .map(|c| (c.schema.clone(), c.generation))
+            .ok_or_else(|| Error::NotRootLeader(RootDesc::default(), 0, None))
+    }
+
+    /// Return `NotRootLeader` if the core of `generation` has been torn down, even if this node
+    /// becomes leader again since then. The long running operations holding a schema check it
+    /// to stop acting on behalf of a stale leader.
+    pub fn check_generation(&self, generation: u64) -> Result<()> {
+        if self.generation.load(atomic::Ordering::Acquire) != generation {
+            return Err(Error::NotRootLeader(RootDesc::default(), 0, None));
+        }
+        Ok(())
+    }
+
+    /// Expose the schema, or tear down the core if it is `None`.
+    fn set_core(&self, schema: Option<Arc<Schema>>) {
+        let mut core = self.core.lock().unwrap();
+        let generation = self.generation.fetch_add(1, atomic::Ordering::AcqRel) + 1;
+        *core = schema.map(|schema| RootCore { schema, generation });
+    }
 }
 
 struct RootCore {
     schema: Arc<Schema>,
+    generation: u64,
 }
 
 impl Root {
     pub(crate) fn new(provider: Arc<Provider>, node_ident: &NodeIdent, cfg: Config) -> Self {
+        Self::with_clock(provider, node_ident, cfg, Arc::new(SystemClock))
+    }
+
+    pub(crate) fn with_clock(
+        provider: Arc<Provider>,
+        node_ident: &NodeIdent,
+        cfg: Config,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         let local_addr = cfg.addr.clone();
         let cfg_cpu_nums = cfg.cpu_nums;
         let ongoing_stats = Arc::new(OngoingStats::default());
+        let watcher_hub = Arc::new(
+            WatchHub::new(
+                provider.executor.clone(),
+                cfg.root.watch_notify_concurrency,
+                cfg.root.watch_retention_window,
+                Duration::from_millis(cfg.root.watch_delivery_deadline_ms),
+                clock.clone(),
+            )
+            .with_injected_delivery_delay(cfg.root.testing_knobs.inject_watch_delivery_delay),
+        );
         let shared = Arc::new(RootShared {
             provider,
             local_addr,
             cfg_cpu_nums,
             core: Mutex::new(None),
+            generation: Default::default(),
             node_ident: node_ident.to_owned(),
-            watcher_hub: Default::default(),
+            watcher_hub,
+            clock,
         });
-        let liveness = Arc::new(liveness::Liveness::new(Duration::from_secs(
-            cfg.root.liveness_threshold_sec,
-        )));
+        let liveness = Arc::new(liveness::Liveness::new(
+            Duration::from_secs(cfg.root.liveness_threshold_sec),
+            Duration::from_secs(cfg.root.unreachable_grace_period_sec),
+            shared.clock.clone(),
+        ));
+        let heartbeat_breaker = Arc::new(breaker::HeartbeatBreaker::new(
+            cfg.root.heartbeat_breaker_failure_threshold,
+            Duration::from_secs(cfg.root.heartbeat_breaker_cooldown_sec),
+            shared.clock.clone(),
+        ));
         let info = Arc::new(SysAllocSource::new(shared.clone(), liveness.to_owned()));
         let alloc = Arc::new(allocator::Allocator::new(
             info,
@@ -112,20 +238,30 @@ impl Root {
             cfg.root.to_owned(),
         ));
         let heartbeat_queue = Arc::new(HeartbeatQueue::default());
+        let replica_lag = Arc::new(lag::ReplicaLag::default());
         let jobs = Arc::new(Jobs::new(
             shared.to_owned(),
             alloc.to_owned(),
             heartbeat_queue.to_owned(),
+            ongoing_stats.to_owned(),
+            replica_lag.to_owned(),
+        ));
+        let drain_progress = Arc::new(drain::DrainProgress::default());
+        let move_history = Arc::new(move_history::MoveHistory::new(
+            cfg.root.move_history_capacity,
         ));
         let sched_ctx = schedule::ScheduleContext::new(
             shared.clone(),
             alloc.clone(),
             heartbeat_queue.clone(),
             ongoing_stats.clone(),
+            drain_progress.clone(),
+            move_history.clone(),
             jobs.to_owned(),
             cfg.root.to_owned(),
         );
         let scheduler = Arc::new(schedule::ReconcileScheduler::new(sched_ctx));
+        let report_limiter = Arc::new(tokio::sync::Semaphore::new(cfg.root.report_max_inflight));
         Self {
             cfg: cfg.root,
             alloc,
@@ -134,6 +270,16 @@ impl Root {
             scheduler,
             heartbeat_queue,
             ongoing_stats,
+            replica_lag,
+            drain_progress,
+            move_history,
+            group_stats: Default::default(),
+            compaction_advisor: Default::default(),
+            report_limiter,
+            delete_lock: Default::default(),
+            heartbeat_failures: Default::default(),
+            degraded: Default::default(),
+            heartbeat_breaker,
             jobs,
         }
     }
@@ -182,6 +328,14 @@ impl Root {
         self.shared.schema()
     }
 
+    pub fn schema_with_generation(&self) -> Result<(Arc<Schema>, u64)> {
+        self.shared.schema_with_generation()
+    }
+
+    pub fn check_generation(&self, generation: u64) -> Result<()> {
+        self.shared.check_generation(generation)
+    }
+
     pub fn watcher_hub(&self) -> Arc<WatchHub> {
         self.shared.watcher_hub.clone()
     }
@@ -192,6 +346,7 @@ impl Root {
     // - schedule heartbeat sending
     async fn run_schedule(&self, replica_table: ReplicaRouteTable) -> ! {
         let mut bootstrapped = false;
+        let mut failures: u32 = 0;
         loop {
             let root_replica = fetch_root_replica(&replica_table).await;
 
@@ -201,6 +356,7 @@ impl Root {
                     .step_leader(
                         &self.shared.local_addr,
                         self.shared.cfg_cpu_nums,
+                        &replica_table,
                         root_replica,
                         &mut bootstrapped,
                     )
@@ -208,10 +364,14 @@ impl Root {
                 {
                     Ok(()) | Err(Error::NotLeader(..)) => {
                         // Step follower
+                        failures = 0;
                         continue;
                     }
                     Err(err) => {
-                        todo!("handle error: {}", err)
+                        let backoff = self.cfg.run_retry_backoff(failures);
+                        failures = failures.saturating_add(1);
+                        warn!(err = ?err, failures, "step root leader, retry after {backoff:?}");
+                        self.shared.clock.sleep(backoff).await;
                     }
                 }
             }
@@ -224,7 +384,7 @@ impl Root {
         loop {
             if let Ok(schema) = self.schema() {
                 let _timer = metrics::HEARTBEAT_STEP_DURATION_SECONDS.start_timer();
-                let nodes = self.heartbeat_queue.try_poll().await;
+                let nodes = self.heartbeat_queue.try_poll(self.shared.clock.now()).await;
                 if !nodes.is_empty() {
                     metrics::HEARTBEAT_TASK_QUEUE_SIZE.set(nodes.len() as i64);
                     if let Err(err) = self.send_heartbeat(schema.to_owned(), &nodes).await {
@@ -232,7 +392,7 @@ impl Root {
                     }
                 }
             }
-            runtime::time::sleep(Duration::from_secs(1)).await;
+            self.shared.clock.sleep(Duration::from_secs(1)).await;
         }
     }
 
@@ -241,25 +401,43 @@ impl Root {
             if self.schema().is_ok() {
                 if let Err(err) = self.jobs.advance_jobs().await {
                     warn!(err=?err, "run background job meet err");
-                    runtime::time::sleep(Duration::from_secs(3)).await;
+                    self.shared.clock.sleep(Duration::from_secs(3)).await;
                     continue;
                 }
                 self.jobs.wait_more_jobs().await;
             } else {
-                runtime::time::sleep(Duration::from_secs(20)).await;
+                self.shared.clock.sleep(Duration::from_secs(20)).await;
             };
         }
     }
 
+    /// Read the cluster id and the databases through the schema, so that it is known to be
+    /// readable before it is exposed by `schema()`.
+    async fn warmup_schema(&self, schema: &Schema) -> Result<()> {
+        let injected = &self.cfg.testing_knobs.inject_warmup_read_failures;
+        if injected
+            .fetch_update(atomic::Ordering::AcqRel, atomic::Ordering::Acquire, |v| {
+                v.checked_sub(1)
+            })
+            .is_ok()
+        {
+            return Err(Error::NotBootstrapped);
+        }
+        schema.cluster_id().await?;
+        schema.list_database().await?;
+        Ok(())
+    }
+
     async fn step_leader(
         &self,
         local_addr: &str,
         cfg_cpu_nums: u32,
+        replica_table: &ReplicaRouteTable,
         root_replica: Arc<Replica>,
         bootstrapped: &mut bool,
     ) -> Result<()> {
         let store = Arc::new(RootStore::new(root_replica.to_owned()));
-        let mut schema = Schema::new(store.clone());
+        let mut schema = Schema::new(store.clone(), self.cfg.skip_corrupt_metadata);
 
         // Only when the program is initialized is it checked for bootstrap, after which the
         // leadership change does not need to check for whether bootstrap or not.
@@ -279,16 +457,73 @@ impl Root {
             *bootstrapped = true;
         }
 
+        // The store might not serve the schema yet, eg. the entries committed by the previous
+        // leader are still applying. Step again later instead of exposing a half-ready view.
+        self.warmup_schema(&schema).await?;
+        self.shared.set_core(Some(Arc::new(schema.to_owned())));
+        self::metrics::LEADER_STATE_INFO.set(1);
+
+        // The exposed core must be withdrawn on any exit, otherwise a failed step leaves a zombie
+        // leader serving requests and heartbeats until the next step.
+        let result = self
+            .lead(replica_table, root_replica.to_owned(), &schema)
+            .await;
+        let node_id = self.shared.node_ident.node_id;
+        info!("node {node_id} current root node drop leader");
+
+        // Terminate the watchers with the leader hint, so that the clients could resume watching
+        // on the new leader at once.
+        let (term, leader) = root_replica.leader_hint();
+        self.watcher_hub()
+            .disconnect_all(|| Error::NotRootLeader(RootDesc::default(), term, leader.clone()))
+            .await;
+
+        // After that, RootCore needs to be set to None before returning.
+        self.heartbeat_queue.enable(false).await;
+        self.jobs.on_drop_leader();
+        self.ongoing_stats.reset();
         {
-            let mut core = self.shared.core.lock().unwrap();
-            *core = Some(RootCore {
-                schema: Arc::new(schema.to_owned()),
-            });
+            self.liveness.reset();
+            self.heartbeat_breaker.reset();
+            self.replica_lag.reset();
+            self.drain_progress.reset();
+            self.degraded.store(false, atomic::Ordering::Release);
+            self.group_stats.reset();
+            self.compaction_advisor.reset();
+            self.shared.set_core(None);
         }
-        self::metrics::LEADER_STATE_INFO.set(1);
 
+        self::metrics::LEADER_STATE_INFO.set(0);
+        self::metrics::DEGRADED_MODE_INFO.set(0);
+
+        if result? {
+            // Give the other nodes a chance to elect a new leader before stepping leader again.
+            self.shared.clock.sleep(self.cfg.heartbeat_interval()).await;
+        }
+
+        Ok(())
+    }
+
+    /// Serve as the root leader with the exposed schema until the leadership is lost, return
+    /// whether it steps down voluntarily.
+    async fn lead(
+        &self,
+        replica_table: &ReplicaRouteTable,
+        root_replica: Arc<Replica>,
+        schema: &Schema,
+    ) -> Result<bool> {
         self.ongoing_stats.reset();
+        self.heartbeat_failures.store(0, atomic::Ordering::Release);
         self.heartbeat_queue.enable(true).await;
+        let injected = &self.cfg.testing_knobs.inject_lead_failures;
+        if injected
+            .fetch_update(atomic::Ordering::AcqRel, atomic::Ordering::Acquire, |v| {
+                v.checked_sub(1)
+            })
+            .is_ok()
+        {
+            return Err(Error::NotBootstrapped);
+        }
         self.jobs.on_step_leader().await?;
 
         let node_id = self.shared.node_ident.node_id;
@@ -306,33 +541,63 @@ impl Root {
                     .iter()
                     .map(|n| HeartbeatTask { node_id: n.id })
                     .collect::<Vec<_>>(),
-                Instant::now(),
+                self.shared.clock.now(),
             )
             .await;
+        if let Err(err) = self.refresh_degraded().await {
+            warn!(err = ?err, "check degraded mode");
+        }
+        // Generate the key of page tokens in advance, so that the listings don't write the schema.
+        if let Err(err) = schema.page_token_key().await {
+            warn!(err = ?err, "generate page token key");
+        }
 
+        let mut last_preference_check = self.shared.clock.now();
+        let mut step_down = false;
         while let Ok(Some(_)) = root_replica.to_owned().on_leader("root", true).await {
             let next_interval = self.scheduler.step_one().await;
-            crate::runtime::time::sleep(next_interval).await;
-        }
-        info!("node {node_id} current root node drop leader");
+            self.shared.clock.sleep(next_interval).await;
+
+            // The node is probably isolated if no node responds to heartbeat, step down to avoid
+            // acting as a zombie leader.
+            let max_failures = self.cfg.heartbeat_failures_before_step_down;
+            let failures = self.heartbeat_failures.load(atomic::Ordering::Acquire);
+            if max_failures > 0 && failures >= max_failures {
+                warn!(
+                    "node {node_id} step down root leader after {failures} heartbeat rounds fail"
+                );
+                metrics::HEARTBEAT_STEP_DOWN_TOTAL.inc();
+                if let Err(err) = self.transfer_root_leader_away().await {
+                    warn!(err = ?err, "transfer root leader before stepping down");
+                }
+                step_down = true;
+                break;
+            }
 
-        // After that, RootCore needs to be set to None before returning.
-        self.heartbeat_queue.enable(false).await;
-        self.jobs.on_drop_leader();
-        self.ongoing_stats.reset();
-        {
-            self.liveness.reset();
+            // Check the preference after a round of heartbeat, so that the liveness of the
+            // preferred nodes is known.
+            let now = self.shared.clock.now();
+            if now.duration_since(last_preference_check) >= self.cfg.heartbeat_interval() {
+                last_preference_check = now;
+                if let Err(err) = self.try_transfer_root_leader_to_preferred().await {
+                    warn!(err = ?err, "transfer root leader to preferred node");
+                }
+                if let Err(err) = self.refresh_degraded().await {
+                    warn!(err = ?err, "check degraded mode");
+                }
+            }
 
-            let mut core = self.shared.core.lock().unwrap();
-            *core = None;
+            // The route table might be rebuilt, restart leadership steps with the new root replica.
+            if is_root_replica_stale(replica_table, &root_replica) {
+                warn!("node {node_id} root replica is changed in route table, restart root leader");
+                break;
+            }
         }
-
-        self::metrics::LEADER_STATE_INFO.set(0);
-
-        Ok(())
+        Ok(step_down)
     }
 
     pub async fn cordon_node(&self, node_id: u64) -> Result<()> {
+        self.check_not_frozen().await?;
         let schema = self.schema()?;
         let mut node_desc = schema
             .get_node(node_id)
@@ -351,6 +616,7 @@ impl Root {
     }
 
     pub async fn uncordon_node(&self, node_id: u64) -> Result<()> {
+        self.check_not_frozen().await?;
         let schema = self.schema()?;
         let mut node_desc = schema
             .get_node(node_id)
@@ -360,7 +626,10 @@ impl Root {
         let current_status = NodeStatus::from_i32(node_desc.status).unwrap();
         if !matches!(
             current_status,
-            NodeStatus::Cordoned | NodeStatus::Drained | NodeStatus::Decommissioned
+            NodeStatus::Cordoned
+                | NodeStatus::Drained
+                | NodeStatus::Decommissioning
+                | NodeStatus::Decommissioned
         ) {
             return Err(crate::Error::InvalidArgument(
                 "node status unsupport uncordon".into(),
@@ -372,7 +641,383 @@ impl Root {
         Ok(())
     }
 
-    pub async fn begin_drain(&self, node_id: u64) -> Result<()> {
+    /// Reset the liveness of a node which is falsely considered unreachable. It is only allowed if
+    /// the node responds to a probe.
+    pub async fn mark_node_alive(&self, node_id: u64) -> Result<()> {
+        let schema = self.schema()?;
+        let mut node_desc = schema
+            .get_node(node_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("node not found".into()))?;
+
+        // The node serving root is alive obviously.
+        if node_id != self.current_node_id() {
+            if let Err(err) = self
+                .try_send_heartbeat(
+                    node_desc.addr.to_owned(),
+                    vec![],
+                    Duration::from_secs(self.cfg.heartbeat_timeout_sec),
+                )
+                .await
+            {
+                warn!(node = node_id, target = ?node_desc.addr, err = ?err, "probe node before mark alive");
+                return Err(crate::Error::InvalidArgument(format!(
+                    "node {node_id} does not respond to probe"
+                )));
+            }
+        }
+
+        self.liveness.mark_alive(node_id);
+        if node_desc.unreachable {
+            node_desc.unreachable = false;
+            schema.update_node(node_desc.to_owned()).await?;
+        }
+        self.watcher_hub()
+            .notify_updates(vec![UpdateEvent {
+                event: Some(update_event::Event::Node(node_desc)),
+            }])
+            .await;
+        info!(node = node_id, "node is marked alive manually");
+        Ok(())
+    }
+
+    /// Resolve the route of group, return the group descriptor and the state which carries the
+    /// current leader and leader epoch.
+    pub async fn group_route(&self, group_id: u64) -> Result<(GroupDesc, Option<GroupState>)> {
+        let schema = self.schema()?;
+        let group = schema
+            .get_group(group_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("group not found".into()))?;
+        let state = schema.get_group_state(group_id).await?;
+        Ok((group, state))
+    }
+
+    /// Add a voter replica of group in the target node, it is used to recover group manually.
+    /// Return the group descriptor after the replica is added and the incoming replica.
+    pub async fn add_group_replica(
+        &self,
+        group_id: u64,
+        node_id: u64,
+    ) -> Result<(GroupDesc, ReplicaDesc)> {
+        self.check_not_frozen().await?;
+        let schema = self.schema()?;
+        let group = schema
+            .get_group(group_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("group not found".into()))?;
+        let node = schema
+            .get_node(node_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("node not found".into()))?;
+        if self.liveness.get(&node.id).is_dead() {
+            return Err(crate::Error::InvalidArgument(format!(
+                "node {node_id} is not alive"
+            )));
+        }
+        if group.replicas.iter().any(|r| r.node_id == node_id) {
+            return Err(crate::Error::InvalidArgument(format!(
+                "node {node_id} is already a member of group {group_id}"
+            )));
+        }
+        if self.ongoing_stats.is_group_busy(group_id) {
+            return Err(crate::Error::InvalidArgument(format!(
+                "group {group_id} is busy"
+            )));
+        }
+
+        let incoming = ReplicaDesc {
+            id: schema.next_replica_id().await?,
+            node_id,
+            role: ReplicaRole::Voter as i32,
+        };
+        let mut group_client = GroupClient::lazy(
+            group_id,
+            self.shared.provider.router.clone(),
+            self.shared.provider.conn_manager.clone(),
+        );
+        let schedule_state = group_client
+            .move_replicas(vec![incoming.to_owned()], vec![])
+            .await?;
+        self.ongoing_stats.handle_update(&[schedule_state], None);
+        info!(
+            group = group_id,
+            node = node_id,
+            replica = incoming.id,
+            "add group replica manually"
+        );
+        let group = self
+            .wait_group_changed(&schema, &group, |desc| {
+                desc.replicas
+                    .iter()
+                    .any(|r| r.id == incoming.id && r.role == ReplicaRole::Voter as i32)
+            })
+            .await?;
+        Ok((group, incoming))
+    }
+
+    /// Remove a replica from group manually. It refuses to leave the group below the configured
+    /// replicas unless `force` is set, and transfers the leadership before removing the leader.
+    /// Return the group descriptor after the replica is removed and the outgoing replica.
+    pub async fn remove_group_replica(
+        &self,
+        group_id: u64,
+        replica_id: u64,
+        force: bool,
+    ) -> Result<(GroupDesc, ReplicaDesc)> {
+        self.check_not_frozen().await?;
+        let schema = self.schema()?;
+        let group = schema
+            .get_group(group_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("group not found".into()))?;
+        let outgoing = group
+            .replicas
+            .iter()
+            .find(|r| r.id == replica_id)
+            .cloned()
+            .ok_or_else(|| crate::Error::InvalidArgument("replica not found".into()))?;
+        let voters = group
+            .replicas
+            .iter()
+            .filter(|r| r.role == ReplicaRole::Voter as i32)
+            .count();
+        if outgoing.role == ReplicaRole::Voter as i32
+            && voters <= self.cfg.replicas_per_group
+            && !force
+        {
+            return Err(crate::Error::InvalidArgument(format!(
+                "removing replica {replica_id} leaves group {group_id} below {} voters",
+                self.cfg.replicas_per_group
+            )));
+        }
+        if self.ongoing_stats.is_group_busy(group_id) {
+            return Err(crate::Error::InvalidArgument(format!(
+                "group {group_id} is busy"
+            )));
+        }
+
+        let mut group_client = GroupClient::lazy(
+            group_id,
+            self.shared.provider.router.clone(),
+            self.shared.provider.conn_manager.clone(),
+        );
+        let is_leader = schema
+            .get_replica_state(group_id, replica_id)
+            .await?
+            .map(|s| s.role == RaftRole::Leader as i32)
+            .unwrap_or_default();
+        if is_leader {
+            let target = group
+                .replicas
+                .iter()
+                .find(|r| r.id != replica_id && r.role == ReplicaRole::Voter as i32)
+                .ok_or_else(|| {
+                    crate::Error::InvalidArgument(format!(
+                        "replica {replica_id} is leader and no other voter to take over"
+                    ))
+                })?;
+            info!(
+                group = group_id,
+                replica = replica_id,
+                "remove leader replica manually, so transfer leader to {}",
+                target.id
+            );
+            group_client.transfer_leader(target.id).await?;
+        }
+
+        let schedule_state = group_client
+            .move_replicas(vec![], vec![outgoing.to_owned()])
+            .await?;
+        self.ongoing_stats.handle_update(&[schedule_state], None);
+        info!(
+            group = group_id,
+            replica = replica_id,
+            node = outgoing.node_id,
+            "remove group replica manually"
+        );
+        let group = self
+            .wait_group_changed(&schema, &group, |desc| {
+                !desc.replicas.iter().any(|r| r.id == replica_id)
+            })
+            .await?;
+        Ok((group, outgoing))
+    }
+
+    /// Wait until the replica change of the group is committed and reported, return the
+    /// descriptor after the change. The members are heartbeated at once to report it.
+    async fn wait_group_changed(
+        &self,
+        schema: &Schema,
+        group: &GroupDesc,
+        changed: impl Fn(&GroupDesc) -> bool,
+    ) -> Result<GroupDesc> {
+        let group_id = group.id;
+        let deadline = self.shared.clock.now() + GROUP_CHANGE_TIMEOUT;
+        self.heartbeat_queue
+            .try_schedule(
+                group
+                    .replicas
+                    .iter()
+                    .map(|r| HeartbeatTask { node_id: r.node_id })
+                    .collect(),
+                self.shared.clock.now(),
+            )
+            .await;
+        loop {
+            let desc = schema
+                .get_group(group_id)
+                .await?
+                .ok_or(Error::GroupNotFound(group_id))?;
+            if changed(&desc) {
+                return Ok(desc);
+            }
+            if self.shared.clock.now() >= deadline {
+                return Err(Error::DeadlineExceeded(format!(
+                    "the replica change of group {group_id} is not reported"
+                )));
+            }
+            self.shared.clock.sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Add a voter replica of the root group in the target node to grow the root replica set.
+    /// Return the root group descriptor after the replica is added and the incoming replica.
+    pub async fn add_root_replica(&self, node_id: u64) -> Result<(GroupDesc, ReplicaDesc)> {
+        self.add_group_replica(ROOT_GROUP_ID, node_id).await
+    }
+
+    /// Transfer the root leadership to the first healthy node in `root_leader_preferred_nodes`.
+    /// Nothing is done if the current node is preferred, or none of the preferred nodes is healthy,
+    /// so the availability of root does not depend on the preferred nodes.
+    async fn try_transfer_root_leader_to_preferred(&self) -> Result<()> {
+        let preferred_nodes = &self.cfg.root_leader_preferred_nodes;
+        if preferred_nodes.is_empty() || preferred_nodes.contains(&self.current_node_id()) {
+            return Ok(());
+        }
+
+        let schema = self.schema()?;
+        let root_group = schema
+            .get_group(ROOT_GROUP_ID)
+            .await?
+            .ok_or(Error::GroupNotFound(ROOT_GROUP_ID))?;
+        for node_id in preferred_nodes {
+            let replica = match root_group
+                .replicas
+                .iter()
+                .find(|r| r.node_id == *node_id && r.role == ReplicaRole::Voter as i32)
+            {
+                Some(replica) => replica,
+                None => continue,
+            };
+            let is_active = schema
+                .get_node(*node_id)
+                .await?
+                .map(|n| n.status == NodeStatus::Active as i32)
+                .unwrap_or_default();
+            let is_follower = schema
+                .get_replica_state(ROOT_GROUP_ID, replica.id)
+                .await?
+                .map(|s| s.role == RaftRole::Follower as i32)
+                .unwrap_or_default();
+            if !is_active || !is_follower || !self.liveness.get(node_id).is_alive() {
+                continue;
+            }
+
+            info!(
+                "transfer root leader to replica {} of preferred node {node_id}",
+                replica.id
+            );
+            let mut group_client = GroupClient::lazy(
+                ROOT_GROUP_ID,
+                self.shared.provider.router.clone(),
+                self.shared.provider.conn_manager.clone(),
+            );
+            group_client.transfer_leader(replica.id).await?;
+            break;
+        }
+        Ok(())
+    }
+
+    /// Transfer the raft leadership of the root group to a voter on another node, so that the
+    /// root leader steps down in raft too, rather than being elected as root leader again.
+    async fn transfer_root_leader_away(&self) -> Result<()> {
+        let schema = self.schema()?;
+        let root_group = schema
+            .get_group(ROOT_GROUP_ID)
+            .await?
+            .ok_or(Error::GroupNotFound(ROOT_GROUP_ID))?;
+        let current_node = self.current_node_id();
+        let mut target = None;
+        for replica in &root_group.replicas {
+            if replica.node_id == current_node || replica.role != ReplicaRole::Voter as i32 {
+                continue;
+            }
+            let is_follower = schema
+                .get_replica_state(ROOT_GROUP_ID, replica.id)
+                .await?
+                .map(|s| s.role == RaftRole::Follower as i32)
+                .unwrap_or_default();
+            // Prefer the replicas reported as followers.
+            if is_follower || target.is_none() {
+                target = Some(replica.id);
+            }
+            if is_follower {
+                break;
+            }
+        }
+        let target = match target {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        info!("transfer root leader to replica {target} before stepping down");
+        let mut group_client = GroupClient::lazy(
+            ROOT_GROUP_ID,
+            self.shared.provider.router.clone(),
+            self.shared.provider.conn_manager.clone(),
+        );
+        // The root is probably isolated, don't wait the transferring forever.
+        group_client.set_timeout(Duration::from_secs(self.cfg.heartbeat_timeout_sec));
+        group_client.transfer_leader(target).await?;
+        Ok(())
+    }
+
+    /// Remove a replica of the root group. It is rejected if the remaining voters couldn't form
+    /// a quorum with the alive ones. Return the root group descriptor after the replica is
+    /// removed and the outgoing replica.
+    pub async fn remove_root_replica(&self, replica_id: u64) -> Result<(GroupDesc, ReplicaDesc)> {
+        self.check_not_frozen().await?;
+        let schema = self.schema()?;
+        let group = schema
+            .get_group(ROOT_GROUP_ID)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("root group not found".into()))?;
+        if !group.replicas.iter().any(|r| r.id == replica_id) {
+            return Err(crate::Error::InvalidArgument("replica not found".into()));
+        }
+        let remaining = group
+            .replicas
+            .iter()
+            .filter(|r| r.id != replica_id && r.role == ReplicaRole::Voter as i32)
+            .collect::<Vec<_>>();
+        let alive = remaining
+            .iter()
+            .filter(|r| {
+                r.node_id == self.current_node_id() || self.liveness.get(&r.node_id).is_alive()
+            })
+            .count();
+        if alive * 2 <= remaining.len() || remaining.is_empty() {
+            return Err(crate::Error::InvalidArgument(format!(
+                "removing root replica {replica_id} breaks quorum, {alive} of {} remaining voters are alive",
+                remaining.len()
+            )));
+        }
+        self.remove_group_replica(ROOT_GROUP_ID, replica_id, true)
+            .await
+    }
+
+    pub async fn begin_drain(&self, node_id: u64, max_concurrent_moves: Option<u64>) -> Result<()> {
+        self.check_not_frozen().await?;
         let schema = self.schema()?;
 
         if self.current_node_id() == node_id {
@@ -403,48 +1048,233 @@ impl Root {
 
         node_desc.status = NodeStatus::Draining as i32;
         schema.update_node(node_desc).await?; // TODO: cas
+        self.drain_progress.begin(node_id);
 
         self.scheduler
             .setup_task(ReconcileTask {
-                task: Some(reconcile_task::Task::ShedLeader(ShedLeaderTask { node_id })),
+                task: Some(reconcile_task::Task::ShedLeader(ShedLeaderTask {
+                    node_id,
+                    max_concurrent_moves: max_concurrent_moves.unwrap_or_default(),
+                })),
             })
             .await;
 
         Ok(())
     }
 
-    pub async fn node_status(&self, node_id: u64) -> Result<NodeStatus> {
+    /// Cordon a node and decommission it in background, the scheduler moves the replicas out of
+    /// it, then it is removed by the job. The node status changes of each phase are notified to
+    /// the watchers. The node is rolled back to active if the decommission is aborted, eg. the
+    /// replicas are not moved out within `decommission_drain_timeout_sec`.
+    pub async fn decommission_node(&self, node_id: u64) -> Result<()> {
+        self.check_not_frozen().await?;
+        if self.current_node_id() == node_id {
+            return Err(crate::Error::InvalidArgument(
+                "node is root leader, transfer root leadership first".into(),
+            ));
+        }
+        self.cordon_node(node_id).await?;
+        self.notify_node_status(node_id).await?;
+        info!(node = node_id, "decommission node, cordoned");
+
+        if let Err(err) = self.submit_decommission(node_id).await {
+            warn!(node = node_id, err = ?err, "decommission node aborted, rollback cordon");
+            self.rollback_decommission(node_id).await?;
+            return Err(err);
+        }
+        info!(node = node_id, "decommission node, decommissioning");
+        Ok(())
+    }
+
+    async fn submit_decommission(&self, node_id: u64) -> Result<()> {
         let schema = self.schema()?;
-        let node_desc = schema
+        let mut node_desc = schema
             .get_node(node_id)
             .await?
             .ok_or_else(|| crate::Error::InvalidArgument("node not found".into()))?;
+        node_desc.status = NodeStatus::Decommissioning as i32;
+        schema.update_node(node_desc).await?; // TODO: cas
+        self.notify_node_status(node_id).await?;
 
-        let current_status = NodeStatus::from_i32(node_desc.status).unwrap();
-
-        Ok(current_status)
+        let deadline_ms =
+            bg_job::unix_millis() + self.cfg.decommission_drain_timeout_sec.saturating_mul(1000);
+        self.jobs
+            .submit(
+                BackgroundJob {
+                    job: Some(Job::DecommissionNode(DecommissionNodeJob {
+                        node_id,
+                        deadline_ms,
+                        created_time: format!("{:?}", self.shared.clock.now()),
+                    })),
+                    ..Default::default()
+                },
+                false,
+            )
+            .await
     }
 
-    pub async fn nodes(&self) -> Option<u64> {
-        if let Ok(schema) = self.shared.schema() {
-            if let Ok(nodes) = schema.list_node().await {
-                return Some(nodes.len() as u64);
+    async fn rollback_decommission(&self, node_id: u64) -> Result<()> {
+        let schema = self.schema()?;
+        if let Some(mut node_desc) = schema.get_node(node_id).await? {
+            if node_desc.status != NodeStatus::Active as i32 {
+                node_desc.status = NodeStatus::Active as i32;
+                schema.update_node(node_desc).await?; // TODO: cas
             }
+            self.notify_node_status(node_id).await?;
         }
-        None
+        Ok(())
     }
 
-    pub async fn job_state(&self) -> Result<String> {
-        use serde_json::json;
-        fn to_json(j: &BackgroundJob) -> serde_json::Value {
-            match j.job.as_ref().unwrap() {
-                Job::CreateCollection(c) => {
-                    let state = format!(
-                        "{:?}",
-                        CreateCollectionJobStatus::from_i32(c.status).unwrap()
-                    );
-                    let wait_create = c.wait_create.len();
-                    let wait_cleanup = c.wait_cleanup.len();
+    async fn notify_node_status(&self, node_id: u64) -> Result<()> {
+        if let Some(node_desc) = self.schema()?.get_node(node_id).await? {
+            self.watcher_hub()
+                .notify_updates(vec![UpdateEvent {
+                    event: Some(update_event::Event::Node(node_desc)),
+                }])
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Find the nodes registered with the same address, eg. by a rejoin which failed to roll
+    /// back its node.
+    pub async fn duplicate_node_addrs(&self) -> Result<Vec<DuplicateNodeAddr>> {
+        let schema = self.schema()?;
+        let groups = schema.list_group().await?;
+        let num_replicas = |node_id: u64| {
+            groups
+                .iter()
+                .flat_map(|g| g.replicas.iter())
+                .filter(|r| r.node_id == node_id)
+                .count()
+        };
+        let mut nodes_by_addr = BTreeMap::<String, Vec<(u64, usize)>>::new();
+        for node in schema.list_node().await? {
+            nodes_by_addr
+                .entry(node.addr)
+                .or_default()
+                .push((node.id, num_replicas(node.id)));
+        }
+        let duplicates = nodes_by_addr
+            .into_iter()
+            .filter(|(_, nodes)| nodes.len() > 1)
+            .map(|(addr, mut nodes)| {
+                // The later one is kept if neither hosts replicas, since the node saves the ident
+                // of its last join.
+                nodes.sort_by_key(|(id, replicas)| (*replicas, *id));
+                let (keep, _) = nodes.pop().unwrap();
+                DuplicateNodeAddr {
+                    addr,
+                    keep,
+                    stale: nodes,
+                }
+            })
+            .collect();
+        Ok(duplicates)
+    }
+
+    /// Remove the stale nodes of the duplicated addresses, and return the removed nodes. A stale
+    /// node hosting replicas is left to be decommissioned, otherwise its replicas are stranded.
+    pub async fn repair_duplicate_node_addrs(&self) -> Result<Vec<u64>> {
+        self.check_not_frozen().await?;
+        let schema = self.schema()?;
+        let mut removed = Vec::new();
+        for duplicate in self.duplicate_node_addrs().await? {
+            for (node_id, replicas) in duplicate.stale {
+                if replicas != 0 {
+                    warn!(node = node_id, addr = ?duplicate.addr, replicas, "stale node of duplicated address still has replicas, skip removing");
+                    continue;
+                }
+                schema.delete_node(node_id).await?;
+                info!(node = node_id, keep = duplicate.keep, addr = ?duplicate.addr, "remove stale node of duplicated address");
+                removed.push(node_id);
+            }
+        }
+        if !removed.is_empty() {
+            let deletes = removed
+                .iter()
+                .map(|node_id| DeleteEvent {
+                    event: Some(delete_event::Event::Node(*node_id)),
+                })
+                .collect();
+            self.watcher_hub().notify_deletes(deletes).await;
+        }
+        Ok(removed)
+    }
+
+    /// Take a snapshot of the root state machine to bound the growth of raft logs, the recent
+    /// snapshot is returned instead if it was taken within `snapshot_min_interval_sec`.
+    pub async fn snapshot_root(&self) -> Result<SnapshotStat> {
+        let replica = self.schema()?.root_replica();
+        let stat = replica
+            .raft_node()
+            .create_snapshot(Duration::from_secs(self.cfg.snapshot_min_interval_sec))
+            .await?;
+        info!(
+            index = stat.index,
+            size = stat.size,
+            created = stat.created,
+            "snapshot root state machine"
+        );
+        Ok(stat)
+    }
+
+    /// Return the id of the cluster, it is the same one carried by the join and watch responses.
+    pub async fn cluster_id(&self) -> Result<Vec<u8>> {
+        self.schema()?.cluster_id().await
+    }
+
+    pub async fn node_status(&self, node_id: u64) -> Result<NodeStatus> {
+        let schema = self.schema()?;
+        let node_desc = schema
+            .get_node(node_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("node not found".into()))?;
+
+        let current_status = NodeStatus::from_i32(node_desc.status).unwrap();
+
+        Ok(current_status)
+    }
+
+    /// Return the move status of the replicas of a draining node.
+    pub async fn drain_status(&self, node_id: u64) -> Result<Vec<ReplicaMove>> {
+        let schema = self.schema()?;
+        schema
+            .get_node(node_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("node not found".into()))?;
+        Ok(self.drain_progress.replicas(node_id))
+    }
+
+    pub async fn node_lag(&self, node_id: u64) -> Result<NodeLag> {
+        let schema = self.schema()?;
+        schema
+            .get_node(node_id)
+            .await?
+            .ok_or_else(|| crate::Error::InvalidArgument("node not found".into()))?;
+        Ok(self.replica_lag.node_lag(node_id))
+    }
+
+    pub async fn nodes(&self) -> Option<u64> {
+        if let Ok(schema) = self.shared.schema() {
+            if let Ok(nodes) = schema.list_node().await {
+                return Some(nodes.len() as u64);
+            }
+        }
+        None
+    }
+
+    pub async fn job_state(&self) -> Result<String> {
+        use serde_json::json;
+        fn to_json(j: &BackgroundJob) -> serde_json::Value {
+            match j.job.as_ref().unwrap() {
+                Job::CreateCollection(c) => {
+                    let state = format!(
+                        "{:?}",
+                        CreateCollectionJobStatus::from_i32(c.status).unwrap()
+                    );
+                    let wait_create = c.wait_create.len();
+                    let wait_cleanup = c.wait_cleanup.len();
                     json!({
                         "type": "create collection",
                         "name": c.collection_name,
@@ -483,6 +1313,13 @@ impl Root {
                         "database": p.database_id,
                     })
                 }
+                Job::DecommissionNode(d) => {
+                    json!({
+                        "type": "decommission node",
+                        "node": d.node_id,
+                        "deadline_ms": d.deadline_ms,
+                    })
+                }
             }
         }
 
@@ -494,6 +1331,19 @@ impl Root {
         Ok(json!({"ongoing": ongoing, "history": history}).to_string())
     }
 
+    /// Return the number of group leaderships held by each node, as reported by the latest
+    /// heartbeats of the nodes.
+    pub async fn node_leader_counts(&self) -> Result<Vec<(u64 /* node */, u64 /* leaders */)>> {
+        let nodes = self.schema()?.list_node().await?;
+        Ok(nodes
+            .iter()
+            .map(|n| {
+                let leaders = n.capacity.as_ref().map(|c| c.leader_count);
+                (n.id, leaders.unwrap_or_default())
+            })
+            .collect())
+    }
+
     pub async fn info(&self) -> Result<Metadata> {
         let schema = self.schema()?;
         let nodes = schema.list_node().await?;
@@ -511,6 +1361,7 @@ impl Root {
         use diagnosis::*;
 
         Ok(Metadata {
+            version: METADATA_VERSION,
             nodes: nodes
                 .iter()
                 .map(|n| {
@@ -625,7 +1476,67 @@ impl Root {
 }
 
 impl Root {
-    pub async fn create_database(&self, name: String) -> Result<DatabaseDesc> {
+    /// Freeze or unfreeze the metadata mutations cluster-wide, the reads and watches are still
+    /// served while frozen. The flag is persisted so it survives the root leader changes.
+    pub async fn set_frozen(&self, frozen: bool) -> Result<()> {
+        self.schema()?.set_frozen(frozen).await?;
+        info!("cluster is {}", if frozen { "frozen" } else { "unfrozen" });
+        Ok(())
+    }
+
+    pub async fn is_frozen(&self) -> Result<bool> {
+        self.schema()?.is_frozen().await
+    }
+
+    /// Return false if the leader skipped any corrupt metadata record, see
+    /// `RootConfig::skip_corrupt_metadata`.
+    pub fn is_metadata_consistent(&self) -> Result<bool> {
+        Ok(self.schema()?.is_consistent())
+    }
+
+    /// Return whether the cluster runs in degraded mode, which has fewer nodes than the replicas
+    /// of a group. The state is cached by the leader and refreshed every heartbeat interval, it is
+    /// always false on the other nodes.
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(atomic::Ordering::Acquire)
+    }
+
+    /// Check whether the cluster runs in degraded mode, and update the cached state and the
+    /// metric. It clears once enough nodes join.
+    pub async fn refresh_degraded(&self) -> Result<bool> {
+        let degraded = self
+            .schema()?
+            .is_degraded(self.cfg.replicas_per_group)
+            .await?;
+        self.degraded.store(degraded, atomic::Ordering::Release);
+        metrics::DEGRADED_MODE_INFO.set(degraded as i64);
+        Ok(degraded)
+    }
+
+    async fn check_not_frozen(&self) -> Result<()> {
+        if self.schema()?.is_frozen().await? {
+            return Err(Error::ClusterFrozen);
+        }
+        Ok(())
+    }
+
+    /// Reject the name of a new database, collection or alias if it doesn't carry any of the
+    /// prefixes required by `name_prefixes`.
+    fn check_name_policy(&self, name: &str) -> Result<()> {
+        let prefixes = &self.cfg.name_prefixes;
+        if prefixes.is_empty() || prefixes.iter().any(|p| name.starts_with(p.as_str())) {
+            return Ok(());
+        }
+        Err(Error::InvalidName(format!(
+            "{name}, it should start with one of {prefixes:?}"
+        )))
+    }
+
+    /// Create a database, and return it with the ids of the system groups allocated for it. No
+    /// system group is provisioned for a database yet, so the ids are always empty.
+    pub async fn create_database(&self, name: String) -> Result<(DatabaseDesc, Vec<u64>)> {
+        self.check_not_frozen().await?;
+        self.check_name_policy(&name)?;
         let desc = self
             .schema()?
             .create_database(DatabaseDesc {
@@ -639,15 +1550,49 @@ impl Root {
             }])
             .await;
         trace!(database_id = desc.id, database = ?name, "create database");
+        Ok((desc, vec![]))
+    }
+
+    /// Update the default options of collections in the database, the existing collections are
+    /// not affected.
+    pub async fn update_database_defaults(
+        &self,
+        name: &str,
+        options: Option<CollectionOptions>,
+    ) -> Result<DatabaseDesc> {
+        let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        let mut desc = schema
+            .get_database(name)
+            .await?
+            .ok_or_else(|| Error::DatabaseNotFound(name.to_owned()))?;
+        if desc.id == SYSTEM_DATABASE_ID {
+            return Err(Error::InvalidArgument(
+                "unsupport update system database".into(),
+            ));
+        }
+        desc.default_collection_options = options;
+        schema.update_database(desc.to_owned()).await?;
+        self.watcher_hub()
+            .notify_updates(vec![UpdateEvent {
+                event: Some(update_event::Event::Database(desc.to_owned())),
+            }])
+            .await;
+        trace!(database = ?name, options = ?desc.default_collection_options, "update database defaults");
         Ok(desc)
     }
 
+    /// Delete the database, it is a no-op if the database has already been deleted.
     pub async fn delete_database(&self, name: &str) -> Result<()> {
-        let db = self.get_database(name).await?;
-        if db.is_none() {
-            return Err(Error::DatabaseNotFound(name.to_owned()));
-        }
-        let db = db.unwrap();
+        self.check_not_frozen().await?;
+        let _guard = self.delete_lock.lock().await;
+        let db = match self.get_database(name).await? {
+            Some(db) => db,
+            None => {
+                trace!(database = ?name, "database has already been deleted");
+                return Ok(());
+            }
+        };
         if db.id == SYSTEM_DATABASE_ID {
             return Err(Error::InvalidArgument(
                 "unsupport delete system database".into(),
@@ -659,7 +1604,7 @@ impl Root {
                     job: Some(Job::PurgeDatabase(PurgeDatabaseJob {
                         database_id: db.id,
                         database_name: db.name.to_owned(),
-                        created_time: format!("{:?}", Instant::now()),
+                        created_time: format!("{:?}", self.shared.clock.now()),
                     })),
                     ..Default::default()
                 },
@@ -681,9 +1626,11 @@ impl Root {
         &self,
         name: String,
         database: String,
-        partition: Option<co_req::Partition>,
+        options: CreateCollectionOptions,
     ) -> Result<CollectionDesc> {
         let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        self.check_name_policy(&name)?;
         let db = schema
             .get_database(&database)
             .await?
@@ -693,14 +1640,9 @@ impl Root {
             .prepare_create_collection(CollectionDesc {
                 name: name.to_owned(),
                 db: db.id,
-                partition: partition.map(|p| match p {
-                    co_req::Partition::Hash(hash) => {
-                        co_desc::Partition::Hash(co_desc::HashPartition { slots: hash.slots })
-                    }
-                    co_req::Partition::Range(_) => {
-                        co_desc::Partition::Range(co_desc::RangePartition {})
-                    }
-                }),
+                partition: resolve_collection_partition(options.partition, &db),
+                acl: options.acl,
+                read_only: options.read_only,
                 ..Default::default()
             })
             .await?;
@@ -718,6 +1660,47 @@ impl Root {
         Ok(collection)
     }
 
+    /// Create the `target` collection with the options of the `source` collection, the shards of
+    /// the clone are created on their own and the data of the source is not copied.
+    pub async fn clone_collection(
+        &self,
+        database: String,
+        source: String,
+        target: String,
+    ) -> Result<CollectionDesc> {
+        let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        let db = schema
+            .get_database(&database)
+            .await?
+            .ok_or_else(|| Error::DatabaseNotFound(database.to_owned()))?;
+        let source = schema
+            .get_collection(db.id, &source)
+            .await?
+            .ok_or_else(|| Error::InvalidArgument(format!("collection {source} not found")))?;
+
+        let collection = schema
+            .prepare_create_collection(CollectionDesc {
+                name: target.to_owned(),
+                db: db.id,
+                partition: source.partition.to_owned(),
+                ..Default::default()
+            })
+            .await?;
+        trace!(database = ?database, source = source.id, collection = ?collection, "prepare clone collection");
+
+        self.do_create_collection(schema.to_owned(), collection.to_owned())
+            .await?;
+
+        self.watcher_hub()
+            .notify_updates(vec![UpdateEvent {
+                event: Some(update_event::Event::Collection(collection.to_owned())),
+            }])
+            .await;
+
+        Ok(collection)
+    }
+
     async fn do_create_collection(
         &self,
         schema: Arc<Schema>,
@@ -783,14 +1766,16 @@ impl Root {
         Ok(())
     }
 
+    /// Delete the collection, it is a no-op if the collection has already been deleted.
     pub async fn delete_collection(&self, name: &str, database: &DatabaseDesc) -> Result<()> {
+        self.check_not_frozen().await?;
+        let _guard = self.delete_lock.lock().await;
         let schema = self.schema()?;
         let db = self
             .get_database(&database.name)
             .await?
             .ok_or_else(|| Error::DatabaseNotFound(database.name.clone()))?;
-        let collection = schema.get_collection(db.id, name).await?;
-        if let Some(collection) = collection {
+        if let Some(collection) = schema.get_collection_exact(db.id, name).await? {
             if collection.id < USER_COLLECTION_INIT_ID {
                 return Err(Error::InvalidArgument(
                     "unsupported delete system collection".into(),
@@ -807,19 +1792,27 @@ impl Root {
                             collection_id,
                             database_name,
                             collection_name,
-                            created_time: format!("{:?}", Instant::now()),
+                            created_time: format!("{:?}", self.shared.clock.now()),
                         })),
                         ..Default::default()
                     },
                     false,
                 )
                 .await?;
-            schema.delete_collection(collection).await?;
-            self.watcher_hub()
-                .notify_deletes(vec![DeleteEvent {
-                    event: Some(delete_event::Event::Collection(collection_id)),
-                }])
-                .await;
+            let aliases = schema.delete_collection(collection).await?;
+            let mut deletes = aliases
+                .into_iter()
+                .map(|alias| DeleteEvent {
+                    event: Some(delete_event::Event::Alias(alias)),
+                })
+                .collect::<Vec<_>>();
+            deletes.push(DeleteEvent {
+                event: Some(delete_event::Event::Collection(collection_id)),
+            });
+            self.watcher_hub().notify_deletes(deletes).await;
+        } else {
+            // A collection deleted already is a no-op, but one of another database is a mistake.
+            Self::ensure_collection_not_in_other_database(&schema, &db, name).await?;
         }
         trace!(
             collection = name,
@@ -829,10 +1822,284 @@ impl Root {
         Ok(())
     }
 
+    pub async fn create_alias(
+        &self,
+        name: String,
+        database: String,
+        collection: String,
+    ) -> Result<CollectionAlias> {
+        let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        // The aliases share the namespace of collections, so they follow the same policy.
+        self.check_name_policy(&name)?;
+        let (db, target, via) = self
+            .resolve_alias_target(&schema, &database, &name, &collection)
+            .await?;
+        let alias = schema
+            .create_alias(CollectionAlias {
+                name: name.to_owned(),
+                db: db.id,
+                collection_id: target.id,
+                target: via,
+            })
+            .await?;
+        self.watcher_hub()
+            .notify_updates(vec![UpdateEvent {
+                event: Some(update_event::Event::Alias(alias.to_owned())),
+            }])
+            .await;
+        trace!(database = ?database, alias = ?name, collection_id = target.id, "create alias");
+        Ok(alias)
+    }
+
+    pub async fn repoint_alias(
+        &self,
+        name: String,
+        database: String,
+        collection: String,
+    ) -> Result<CollectionAlias> {
+        let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        let (db, target, via) = self
+            .resolve_alias_target(&schema, &database, &name, &collection)
+            .await?;
+        let alias = CollectionAlias {
+            name: name.to_owned(),
+            db: db.id,
+            collection_id: target.id,
+            target: via,
+        };
+
+        // The aliases chained to this alias follow it to the new collection, the whole chain is
+        // collected before any write.
+        let aliases = schema
+            .list_alias()
+            .await?
+            .into_iter()
+            .filter(|a| a.db == db.id && a.name != name)
+            .collect::<Vec<_>>();
+        let mut updates = vec![alias.to_owned()];
+        let mut visited = HashSet::from([name.to_owned()]);
+        let mut chained = vec![name.to_owned()];
+        let mut depth = 0;
+        loop {
+            let next = aliases
+                .iter()
+                .filter(|dependent| chained.contains(&dependent.target))
+                .collect::<Vec<_>>();
+            if next.is_empty() {
+                break;
+            }
+            depth += 1;
+            if depth > MAX_ALIAS_DEPTH {
+                return Err(Error::InvalidAlias(format!(
+                    "the aliases chained to {name} exceed the max chain depth {MAX_ALIAS_DEPTH}"
+                )));
+            }
+            chained.clear();
+            for dependent in next {
+                if !visited.insert(dependent.name.to_owned()) {
+                    return Err(Error::InvalidAlias(format!(
+                        "the aliases chained to {name} form a cycle through {}",
+                        dependent.name
+                    )));
+                }
+                chained.push(dependent.name.to_owned());
+                updates.push(CollectionAlias {
+                    collection_id: target.id,
+                    ..dependent.to_owned()
+                });
+            }
+        }
+        schema.repoint_aliases(&updates).await?;
+
+        self.watcher_hub()
+            .notify_updates(
+                updates
+                    .into_iter()
+                    .map(|alias| UpdateEvent {
+                        event: Some(update_event::Event::Alias(alias)),
+                    })
+                    .collect(),
+            )
+            .await;
+        trace!(database = ?database, alias = ?name, collection_id = target.id, "repoint alias");
+        Ok(alias)
+    }
+
+    pub async fn delete_alias(&self, name: &str, database: &str) -> Result<()> {
+        let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        let db = schema
+            .get_database(database)
+            .await?
+            .ok_or_else(|| Error::DatabaseNotFound(database.to_owned()))?;
+        if let Some(dependent) = schema
+            .list_alias()
+            .await?
+            .into_iter()
+            .find(|alias| alias.db == db.id && alias.target == name)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "alias {name} is referenced by alias {}",
+                dependent.name
+            )));
+        }
+        if let Some(alias) = schema.delete_alias(db.id, name).await? {
+            self.watcher_hub()
+                .notify_deletes(vec![DeleteEvent {
+                    event: Some(delete_event::Event::Alias(alias)),
+                }])
+                .await;
+        }
+        trace!(database = ?database, alias = ?name, "delete alias");
+        Ok(())
+    }
+
+    /// Resolve the target of alias `name` to a real collection. The target could be another
+    /// alias, in which case the alias chain is followed and the name of the first hop is returned,
+    /// so that the alias could follow it on repointing.
+    async fn resolve_alias_target(
+        &self,
+        schema: &Schema,
+        database: &str,
+        name: &str,
+        collection: &str,
+    ) -> Result<(DatabaseDesc, CollectionDesc, String)> {
+        let db = schema
+            .get_database(database)
+            .await?
+            .ok_or_else(|| Error::DatabaseNotFound(database.to_owned()))?;
+        let mut via = String::new();
+        let mut next = collection.to_owned();
+        for _ in 0..MAX_ALIAS_DEPTH {
+            if next == name {
+                return Err(Error::InvalidAlias(if via.is_empty() {
+                    format!("{name} references itself")
+                } else {
+                    format!("{name} forms a cycle through {via}")
+                }));
+            }
+            if let Some(target) = schema.get_collection_exact(db.id, &next).await? {
+                return Ok((db, target, via));
+            }
+            let alias = schema
+                .get_alias(db.id, &next)
+                .await?
+                .ok_or_else(|| Error::InvalidArgument(format!("collection {next} not found")))?;
+            if via.is_empty() {
+                via = alias.name.to_owned();
+            }
+            if alias.target.is_empty() {
+                let target = schema
+                    .get_collection_by_id(db.id, alias.collection_id)
+                    .await?
+                    .ok_or_else(|| {
+                        Error::InvalidArgument(format!(
+                            "alias {} target collection {} not found",
+                            alias.name, alias.collection_id
+                        ))
+                    })?;
+                return Ok((db, target, via));
+            }
+            next = alias.target;
+        }
+        Err(Error::InvalidAlias(format!(
+            "{name} exceeds the max chain depth {MAX_ALIAS_DEPTH}"
+        )))
+    }
+
     pub async fn list_database(&self) -> Result<Vec<DatabaseDesc>> {
         self.schema()?.list_database().await
     }
 
+    /// List a page of databases ordered by id, the token of the next page is returned if there
+    /// are more databases.
+    pub async fn list_database_page(
+        &self,
+        page_token: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<DatabaseDesc>, Option<String>)> {
+        let mut databases = self.list_database().await?;
+        databases.sort_unstable_by_key(|db| db.id);
+        self.paginate(
+            databases,
+            |db| db.id,
+            page_token,
+            PageObject::Database,
+            limit,
+        )
+        .await
+    }
+
+    /// List a page of groups ordered by id, the token of the next page is returned if there are
+    /// more groups.
+    pub async fn list_group_page(
+        &self,
+        page_token: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<GroupDesc>, Option<String>)> {
+        let mut groups = self.schema()?.list_group().await?;
+        groups.sort_unstable_by_key(|g| g.id);
+        self.paginate(groups, |g| g.id, page_token, PageObject::Group, limit)
+            .await
+    }
+
+    /// List a page of the finished replica moves ordered by id, the token of the next page is
+    /// returned if there are more moves. The history is kept in memory of the current root
+    /// leader.
+    pub async fn list_move_history_page(
+        &self,
+        page_token: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<MoveRecord>, Option<String>)> {
+        self.paginate(
+            self.move_history.records(),
+            |r| r.id,
+            page_token,
+            PageObject::Move,
+            limit,
+        )
+        .await
+    }
+
+    /// Take a page of at most `limit` items sorted by id after the position of `page_token`,
+    /// and return the encoded token of the next page. The tokens are signed by the key kept in
+    /// the schema, and their fences are allocated from the schema in batch, so the tokens survive
+    /// the changes of the root leader while the listings rarely write the schema.
+    async fn paginate<T>(
+        &self,
+        items: Vec<T>,
+        id: impl Fn(&T) -> u64,
+        page_token: Option<&str>,
+        object: PageObject,
+        limit: usize,
+    ) -> Result<(Vec<T>, Option<String>)> {
+        if limit == 0 {
+            return Err(Error::InvalidArgument(
+                "page limit must be greater than zero".into(),
+            ));
+        }
+        let schema = self.schema()?;
+        let key = schema.page_token_key().await?;
+        let (token, fence) = match page_token {
+            Some(token) => {
+                let token = PageToken::decode(token, object, &key)?;
+                if token.fence >= schema.page_fence().await? {
+                    // The fences never go back, so the token is not issued by this cluster.
+                    return Err(Error::InvalidArgument(format!(
+                        "page token fence {} is not allocated",
+                        token.fence
+                    )));
+                }
+                (Some(token), token.fence)
+            }
+            None => (None, schema.next_page_fence().await?),
+        };
+        let (page, next) = page::paginate(items, id, token, object, fence, limit);
+        Ok((page, next.map(|token| token.encode(&key))))
+    }
+
     pub async fn get_database(&self, name: &str) -> Result<Option<DatabaseDesc>> {
         self.schema()?.get_database(name).await
     }
@@ -861,16 +2128,236 @@ impl Root {
             .get_database(&database.name)
             .await?
             .ok_or_else(|| Error::DatabaseNotFound(database.name.clone()))?;
-        self.schema()?.get_collection(db.id, name).await
+        let schema = self.schema()?;
+        let collection = schema.get_collection(db.id, name).await?;
+        if collection.is_none() {
+            Self::ensure_collection_not_in_other_database(&schema, &db, name).await?;
+        }
+        Ok(collection)
     }
 
-    pub async fn watch(&self, cur_groups: HashMap<u64, u64>) -> Result<Watcher> {
-        let schema = self.schema()?;
+    /// Return `CollectionNotInDatabase` if the collection is missing in the named database but
+    /// belongs to another one, which usually means the caller passes a wrong database.
+    async fn ensure_collection_not_in_other_database(
+        schema: &Schema,
+        db: &DatabaseDesc,
+        name: &str,
+    ) -> Result<()> {
+        if schema
+            .find_collection_database(name, db.id)
+            .await?
+            .is_some()
+        {
+            return Err(Error::CollectionNotInDatabase(
+                name.to_owned(),
+                db.name.to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn get_collection_acl(
+        &self,
+        name: &str,
+        database: &str,
+    ) -> Result<HashMap<String, Vec<Permission>>> {
+        let schema = self.schema()?;
+        let collection = Self::get_exact_collection(&schema, name, database).await?;
+        Ok(collection
+            .acl
+            .iter()
+            .map(|(principal, entry)| (principal.to_owned(), entry.permissions().collect()))
+            .collect())
+    }
+
+    /// Grant the permissions to the principal, the former permissions of it are replaced.
+    pub async fn set_collection_acl(
+        &self,
+        name: &str,
+        database: &str,
+        principal: String,
+        permissions: Vec<Permission>,
+    ) -> Result<CollectionDesc> {
+        acl::validate_principal(&principal)?;
+        if permissions.is_empty() {
+            return Err(Error::InvalidArgument(
+                "the permissions of acl entry is empty".into(),
+            ));
+        }
+        self.update_collection_desc(name, database, |desc| {
+            let entry = AclEntry {
+                permissions: permissions.iter().map(|p| *p as i32).collect(),
+            };
+            desc.acl.insert(principal.to_owned(), entry);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Clear the entry of the principal, or all entries if `principal` is `None`.
+    pub async fn clear_collection_acl(
+        &self,
+        name: &str,
+        database: &str,
+        principal: Option<&str>,
+    ) -> Result<CollectionDesc> {
+        if let Some(principal) = principal {
+            acl::validate_principal(principal)?;
+        }
+        self.update_collection_desc(name, database, |desc| {
+            match principal {
+                Some(principal) => {
+                    desc.acl.remove(principal);
+                }
+                None => desc.acl.clear(),
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reject the writes of the collection if `read_only`, or restore them otherwise. The change
+    /// is delivered to clients by watch, which block the writes of a read only collection.
+    pub async fn set_collection_read_only(
+        &self,
+        name: &str,
+        database: &str,
+        read_only: bool,
+    ) -> Result<CollectionDesc> {
+        self.update_collection_desc(name, database, |desc| {
+            desc.read_only = read_only;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_collection_desc(
+        &self,
+        name: &str,
+        database: &str,
+        f: impl FnOnce(&mut CollectionDesc) -> Result<()>,
+    ) -> Result<CollectionDesc> {
+        let schema = self.schema()?;
+        self.check_not_frozen().await?;
+        let previous = Self::get_exact_collection(&schema, name, database).await?;
+        let mut collection = previous.clone();
+        f(&mut collection)?;
+        schema.update_collection(collection.to_owned()).await?;
+        let mut updates = vec![UpdateEvent {
+            event: Some(update_event::Event::Collection(collection.to_owned())),
+        }];
+        if let Some(change) = options::diff_collection_options(&previous, &collection) {
+            updates.push(UpdateEvent {
+                event: Some(update_event::Event::CollectionOptions(change)),
+            });
+        }
+        self.watcher_hub().notify_updates(updates).await;
+        info!(
+            collection = collection.id,
+            acl = ?collection.acl,
+            read_only = collection.read_only,
+            "update collection"
+        );
+        Ok(collection)
+    }
+
+    /// Get the collection by name, the aliases are not resolved.
+    async fn get_exact_collection(
+        schema: &Schema,
+        name: &str,
+        database: &str,
+    ) -> Result<CollectionDesc> {
+        let db = schema
+            .get_database(database)
+            .await?
+            .ok_or_else(|| Error::DatabaseNotFound(database.to_owned()))?;
+        schema
+            .get_collection_exact(db.id, name)
+            .await?
+            .ok_or_else(|| Error::InvalidArgument(format!("collection {name} not found")))
+    }
+
+    /// Estimate the size of a collection by the group stats reported by heartbeat.
+    pub async fn collection_stats(&self, name: &str, database: &str) -> Result<CollectionStats> {
+        let schema = self.schema()?;
+        let db = schema
+            .get_database(database)
+            .await?
+            .ok_or_else(|| Error::DatabaseNotFound(database.to_owned()))?;
+        let collection = match schema.get_collection(db.id, name).await? {
+            Some(collection) => collection,
+            None => {
+                Self::ensure_collection_not_in_other_database(&schema, &db, name).await?;
+                return Err(Error::InvalidArgument(format!(
+                    "collection {name} not found"
+                )));
+            }
+        };
+        let shards = schema.get_collection_shards(collection.id).await?;
+        Ok(self.group_stats.collection_stats(&shards))
+    }
+
+    /// Summarize the replication health of a collection.
+    pub async fn collection_health(&self, name: &str, database: &str) -> Result<CollectionHealth> {
+        let schema = self.schema()?;
+        let collection = Self::get_exact_collection(&schema, name, database).await?;
+        schema
+            .collection_health(
+                collection.id,
+                self.cfg.replicas_per_group,
+                &self.liveness,
+                &self.replica_lag,
+            )
+            .await
+    }
+
+    /// Return the latest `limit` retained watch events of `types` with their sequences.
+    pub fn tail_events(
+        &self,
+        limit: usize,
+        types: &[EventType],
+    ) -> Result<Vec<(u64, RetainedEvent)>> {
+        self.schema()?;
+        Ok(self.watcher_hub().tail(limit, types))
+    }
+
+    /// Watch the changes of metadata, the watcher receives only the events of the group and
+    /// the nodes hosting its replicas if `group_id` is specified, and only the events of
+    /// `event_types` if it is not empty.
+    pub async fn watch(
+        &self,
+        cur_groups: HashMap<u64, u64>,
+        group_id: Option<u64>,
+        event_types: &[EventType],
+    ) -> Result<Watcher> {
+        let schema = self.schema()?;
+        let cluster_id = schema.cluster_id().await?;
 
         let watcher = {
             let hub = self.watcher_hub();
-            let (watcher, mut initializer) = hub.create_watcher().await;
-            let (updates, deletes) = schema.list_all_events(cur_groups).await?;
+            let (watcher, mut initializer) = hub.create_watcher(event_types).await;
+            let max_events = if let Some(group_id) = group_id {
+                let group = schema
+                    .get_group(group_id)
+                    .await?
+                    .ok_or_else(|| Error::InvalidArgument(format!("group {group_id} not found")))?;
+                initializer.set_group_scope(&group);
+                None
+            } else {
+                // An unfiltered snapshot of enormous metadata would blow up the memory of leader,
+                // so it is limited while being built.
+                Some(self.cfg.watch_init_max_events).filter(|max_events| *max_events != 0)
+            };
+            let (updates, deletes) = match schema.list_all_events(cur_groups, max_events).await {
+                Ok(events) => events,
+                Err(err @ Error::WatchSnapshotTooLarge(..)) => {
+                    warn!(err = ?err, "unfiltered snapshot too large");
+                    return Err(err);
+                }
+                Err(err) => return Err(err),
+            };
+            initializer.set_cluster_id(cluster_id);
+            initializer.set_backpressure(self.cfg.watch_backpressure);
             initializer.set_init_resp(updates, deletes);
             watcher
         };
@@ -890,99 +2377,160 @@ impl Root {
                 ..Default::default()
             })
             .await?;
+
+        // The node descriptor is already persisted, so any failure while assembling the response
+        // must roll it back, otherwise a zombie node without any caller would be left behind.
+        let (cluster_id, root) = match self.assemble_join_response(&schema, &node).await {
+            Ok(resp) => resp,
+            Err(err) => {
+                warn!(node = node.id, addr = ?node.addr, err = ?err, "join cluster fail, rollback new node");
+                if let Err(rollback_err) = schema.delete_node(node.id).await {
+                    error!(node = node.id, err = ?rollback_err, "rollback new node fail");
+                }
+                return Err(err);
+            }
+        };
+
         self.watcher_hub()
             .notify_updates(vec![UpdateEvent {
                 event: Some(update_event::Event::Node(node.to_owned())),
             }])
             .await;
+        self.heartbeat_queue
+            .try_schedule(
+                vec![HeartbeatTask { node_id: node.id }],
+                self.shared.clock.now(),
+            )
+            .await;
+        info!(node = node.id, addr = ?node.addr, "new node join cluster");
+        Ok((cluster_id, node, root))
+    }
+
+    /// Register a batch of nodes with their capacities in one operation, eg. to expand the
+    /// cluster. An address which has already joined is reported with its existing node, and a
+    /// failed address doesn't prevent the others from joining. The joined nodes are notified in
+    /// a single batch.
+    pub async fn join_nodes(&self, nodes: Vec<(String, NodeCapacity)>) -> Result<JoinNodesSummary> {
+        self.check_not_frozen().await?;
+        let schema = self.schema()?;
+
+        let mut summary = JoinNodesSummary::default();
+        let mut descs = Vec::with_capacity(nodes.len());
+        for (addr, capacity) in nodes {
+            if addr.is_empty() {
+                let err = Error::InvalidArgument("node address is empty".into());
+                summary.failed.push((addr, err));
+                continue;
+            }
+            descs.push(NodeDesc {
+                addr,
+                capacity: Some(capacity),
+                ..Default::default()
+            });
+        }
+        let addrs = descs.iter().map(|n| n.addr.to_owned()).collect::<Vec<_>>();
+        let results = schema.add_nodes_if_absent(descs).await?;
+        for (addr, result) in addrs.into_iter().zip(results) {
+            match result {
+                Ok((node, true)) => summary.joined.push(node),
+                Ok((node, false)) => summary.existing.push(node),
+                Err(err) => {
+                    warn!(addr = ?addr, err = ?err, "join node fail");
+                    summary.failed.push((addr, err));
+                }
+            }
+        }
+
+        if !summary.joined.is_empty() {
+            self.watcher_hub()
+                .notify_updates(
+                    summary
+                        .joined
+                        .iter()
+                        .map(|n| UpdateEvent {
+                            event: Some(update_event::Event::Node(n.to_owned())),
+                        })
+                        .collect(),
+                )
+                .await;
+            self.heartbeat_queue
+                .try_schedule(
+                    summary
+                        .joined
+                        .iter()
+                        .map(|n| HeartbeatTask { node_id: n.id })
+                        .collect(),
+                    self.shared.clock.now(),
+                )
+                .await;
+        }
+        info!(
+            joined = ?summary.joined.iter().map(|n| n.id).collect::<Vec<_>>(),
+            existing = summary.existing.len(),
+            failed = summary.failed.len(),
+            "join nodes in batch"
+        );
+        Ok(summary)
+    }
 
-        let cluster_id = schema.cluster_id().await?.unwrap();
+    async fn assemble_join_response(
+        &self,
+        schema: &Schema,
+        node: &NodeDesc,
+    ) -> Result<(Vec<u8>, RootDesc)> {
+        if self.cfg.testing_knobs.inject_join_failure_after_add_node {
+            return Err(Error::InvalidData("injected join failure".into()));
+        }
+        let cluster_id = schema.cluster_id().await?;
         let mut root = schema.get_root_desc().await?;
         root.root_nodes = {
             let mut nodes = ReplicaNodes(root.root_nodes);
             nodes.move_first(node.id);
             nodes.0
         };
-        self.heartbeat_queue
-            .try_schedule(vec![HeartbeatTask { node_id: node.id }], Instant::now())
-            .await;
-        info!(node = node.id, addr = ?node.addr, "new node join cluster");
-        Ok((cluster_id, node, root))
+        Ok((cluster_id, root))
     }
 
-    pub async fn report(&self, updates: Vec<GroupUpdates>) -> Result<()> {
+    /// Apply the reported updates, a failed update doesn't prevent the others from being applied.
+    /// The indexes of the failed updates are returned, so the reporter could retry them only.
+    pub async fn report(&self, updates: Vec<GroupUpdates>) -> Result<ReportSummary> {
         // mock report doesn't work.
         // return Ok(());
 
-        let ongoing_stats = self.ongoing_stats.clone();
+        let _permit = self.report_limiter.try_acquire().map_err(|_| {
+            metrics::REPORT_SHED_TOTAL.inc();
+            Error::Overloaded("report".into())
+        })?;
+
         let schema = self.schema()?;
+        let mut summary = ReportSummary::default();
         let mut update_events = Vec::new();
         let mut changed_group_states = Vec::new();
-        for u in updates {
-            let group_desc = if let Some(update_group) = &u.group_desc {
-                match schema.get_group(u.group_id).await? {
-                    Some(pre_group) if pre_group.epoch >= update_group.epoch => None,
-                    _ => u.group_desc,
-                }
-            } else {
-                None
-            };
-
-            let replica_state = if let Some(update_replica_state) = &u.replica_state {
-                match schema
-                    .get_replica_state(u.group_id, update_replica_state.replica_id)
-                    .await?
-                {
-                    Some(pre_rs)
-                        if pre_rs.term > update_replica_state.term
-                            || (pre_rs.term == update_replica_state.term
-                                && pre_rs.role == update_replica_state.role) =>
-                    {
-                        None
+        for (index, u) in updates.into_iter().enumerate() {
+            match self.apply_group_updates(&schema, u).await {
+                Ok((group_desc, replica_state, role_changes)) => {
+                    if let Some(desc) = group_desc {
+                        update_events.push(UpdateEvent {
+                            event: Some(update_event::Event::Group(desc)),
+                        })
+                    }
+                    if let Some(state) = replica_state {
+                        changed_group_states.push(state.group_id);
                     }
-                    _ => u.replica_state,
+                    update_events.extend(role_changes.into_iter().map(|change| UpdateEvent {
+                        event: Some(update_event::Event::ReplicaRole(change)),
+                    }));
+                    summary.applied.push(index);
                 }
-            } else {
-                None
-            };
-            schema
-                .update_group_replica(group_desc.to_owned(), replica_state.to_owned())
-                .await?;
-
-            if let Some(sched_state) = u.schedule_state {
-                ongoing_stats.handle_update(&[sched_state], None);
-            }
-
-            if let Some(desc) = group_desc {
-                info!(
-                    group = desc.id,
-                    desc = ?desc,
-                    "update group_desc from node report"
-                );
-                if desc.id == ROOT_GROUP_ID {
-                    self.heartbeat_queue
-                        .try_schedule(
-                            vec![HeartbeatTask {
-                                node_id: self.current_node_id(),
-                            }],
-                            Instant::now(),
-                        )
-                        .await;
+                Err(err @ (Error::NotRootLeader(..) | Error::NotLeader(..))) => {
+                    // None of the updates could be applied by this root any more, fail the whole
+                    // report so that the reporter turns to the new leader.
+                    return Err(err);
+                }
+                Err(err) => {
+                    warn!(index, "apply reported group updates: {err}");
+                    summary.failed.push((index, err));
                 }
-                metrics::ROOT_UPDATE_GROUP_DESC_TOTAL.report.inc();
-                update_events.push(UpdateEvent {
-                    event: Some(update_event::Event::Group(desc)),
-                })
-            }
-            if let Some(state) = replica_state {
-                info!(
-                    group = state.group_id,
-                    replica = state.replica_id,
-                    state = ?state,
-                    "update replica_state from node report"
-                );
-                metrics::ROOT_UPDATE_REPLICA_STATE_TOTAL.report.inc();
-                changed_group_states.push(state.group_id);
             }
         }
 
@@ -996,7 +2544,131 @@ impl Root {
 
         self.watcher_hub().notify_updates(update_events).await;
 
-        Ok(())
+        Ok(summary)
+    }
+
+    /// Apply an update of the report, the group desc and replica state which are newer than the
+    /// persisted ones are returned, along with the changes of replica serving roles.
+    async fn apply_group_updates(
+        &self,
+        schema: &Schema,
+        u: GroupUpdates,
+    ) -> Result<(
+        Option<GroupDesc>,
+        Option<ReplicaState>,
+        Vec<ReplicaRoleChange>,
+    )> {
+        if u.group_desc.as_ref().map(|d| d.id != u.group_id) == Some(true)
+            || u.replica_state.as_ref().map(|s| s.group_id != u.group_id) == Some(true)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "the updates of group {} contain the state of other groups",
+                u.group_id
+            )));
+        }
+
+        let pre_group = schema.get_group(u.group_id).await?;
+        let group_desc = if let Some(update_group) = &u.group_desc {
+            match &pre_group {
+                // The report is based on an outdated desc, the node should refetch and retry.
+                Some(pre_group) if pre_group.epoch > update_group.epoch => {
+                    return Err(Error::StaleReport(u.group_id));
+                }
+                Some(pre_group) if pre_group.epoch == update_group.epoch => None,
+                _ => u.group_desc,
+            }
+        } else {
+            None
+        };
+
+        let replica_state = if let Some(update_replica_state) = &u.replica_state {
+            match schema
+                .get_replica_state(u.group_id, update_replica_state.replica_id)
+                .await?
+            {
+                Some(pre_rs)
+                    if pre_rs.term > update_replica_state.term
+                        || (pre_rs.term == update_replica_state.term
+                            && pre_rs.role == update_replica_state.role) =>
+                {
+                    None
+                }
+                _ => u.replica_state,
+            }
+        } else {
+            None
+        };
+        let role_changes = if group_desc.is_some() || replica_state.is_some() {
+            diff_replica_roles(
+                schema,
+                pre_group.as_ref(),
+                group_desc.as_ref(),
+                replica_state.as_ref(),
+            )
+            .await?
+        } else {
+            vec![]
+        };
+        match &group_desc {
+            // The desc is only applied if no other update is applied since it was compared.
+            Some(desc) => {
+                schema
+                    .cas_group_replica(
+                        pre_group.as_ref().map(|g| g.epoch),
+                        desc.to_owned(),
+                        replica_state.to_owned(),
+                    )
+                    .await?
+            }
+            None => {
+                schema
+                    .update_group_replica(None, replica_state.to_owned())
+                    .await?
+            }
+        }
+
+        if let Some(sched_state) = u.schedule_state {
+            self.ongoing_stats.handle_update(&[sched_state], None);
+        }
+
+        if let Some(desc) = &group_desc {
+            self.replica_lag.retain_replicas(desc.id, &desc.replicas);
+            info!(
+                group = desc.id,
+                desc = ?desc,
+                "update group_desc from node report"
+            );
+            if desc.id == ROOT_GROUP_ID {
+                self.heartbeat_queue
+                    .try_schedule(
+                        vec![HeartbeatTask {
+                            node_id: self.current_node_id(),
+                        }],
+                        self.shared.clock.now(),
+                    )
+                    .await;
+            }
+            metrics::ROOT_UPDATE_GROUP_DESC_TOTAL.report.inc();
+        }
+        if let Some(state) = &replica_state {
+            info!(
+                group = state.group_id,
+                replica = state.replica_id,
+                state = ?state,
+                "update replica_state from node report"
+            );
+            metrics::ROOT_UPDATE_REPLICA_STATE_TOTAL.report.inc();
+        }
+        for change in &role_changes {
+            info!(
+                group = change.group_id,
+                replica = change.replica_id,
+                previous = ?change.previous(),
+                current = ?change.current(),
+                "replica serving role changed"
+            );
+        }
+        Ok((group_desc, replica_state, role_changes))
     }
 
     pub async fn alloc_replica(
@@ -1057,6 +2729,107 @@ impl Root {
     }
 }
 
+/// The partition specified at create time overrides the default options of database.
+fn resolve_collection_partition(
+    partition: Option<co_req::Partition>,
+    db: &DatabaseDesc,
+) -> Option<co_desc::Partition> {
+    match partition {
+        Some(co_req::Partition::Hash(hash)) => {
+            Some(co_desc::Partition::Hash(co_desc::HashPartition {
+                slots: hash.slots,
+            }))
+        }
+        Some(co_req::Partition::Range(_)) => {
+            Some(co_desc::Partition::Range(co_desc::RangePartition {}))
+        }
+        None => db
+            .default_collection_options
+            .as_ref()
+            .and_then(|opts| opts.partition.as_ref())
+            .map(|p| match p {
+                co_opts::Partition::Hash(hash) => co_desc::Partition::Hash(hash.to_owned()),
+                co_opts::Partition::Range(range) => co_desc::Partition::Range(range.to_owned()),
+            }),
+    }
+}
+
+/// Compare the serving roles of the replicas before and after applying the updates of a group.
+/// Only the replica whose state is updated, or all replicas if the descriptor is updated, are
+/// compared.
+async fn diff_replica_roles(
+    schema: &Schema,
+    pre_group: Option<&GroupDesc>,
+    group_desc: Option<&GroupDesc>,
+    replica_state: Option<&ReplicaState>,
+) -> Result<Vec<ReplicaRoleChange>> {
+    let group = group_desc.or(pre_group);
+    let replica_ids = match (group_desc, replica_state) {
+        (Some(desc), _) => desc.replicas.iter().map(|r| r.id).collect::<Vec<_>>(),
+        (None, Some(state)) => vec![state.replica_id],
+        (None, None) => return Ok(vec![]),
+    };
+    let group_id = group.map(|g| g.id).or(replica_state.map(|s| s.group_id));
+    let pre_states = match group_id {
+        Some(group_id) => schema.group_replica_states(group_id).await?,
+        None => vec![],
+    };
+
+    let mut changes = Vec::new();
+    for replica_id in replica_ids {
+        let pre_state = pre_states.iter().find(|s| s.replica_id == replica_id);
+        let state = match replica_state {
+            Some(state) if state.replica_id == replica_id => Some(state),
+            _ => pre_state,
+        };
+        let previous = replica_serving_role(pre_group, pre_state, replica_id);
+        let current = replica_serving_role(group, state, replica_id);
+        if previous == current || current == ReplicaServingRole::Unknown {
+            continue;
+        }
+        let node_id = group
+            .and_then(|g| g.replicas.iter().find(|r| r.id == replica_id))
+            .map(|r| r.node_id)
+            .or(state.map(|s| s.node_id))
+            .unwrap_or_default();
+        changes.push(ReplicaRoleChange {
+            group_id: group_id.unwrap_or_default(),
+            replica_id,
+            node_id,
+            previous: previous.into(),
+            current: current.into(),
+        });
+    }
+    Ok(changes)
+}
+
+/// The serving role of a replica, a learner never serves as leader or follower.
+fn replica_serving_role(
+    group: Option<&GroupDesc>,
+    state: Option<&ReplicaState>,
+    replica_id: u64,
+) -> ReplicaServingRole {
+    let membership = group
+        .and_then(|g| g.replicas.iter().find(|r| r.id == replica_id))
+        .map(|r| r.role());
+    if membership == Some(ReplicaRole::Learner) {
+        return ReplicaServingRole::Learner;
+    }
+    match state.map(|s| s.role()) {
+        Some(RaftRole::Leader) => ReplicaServingRole::Leader,
+        Some(_) => ReplicaServingRole::Follower,
+        None if membership.is_some() => ReplicaServingRole::Follower,
+        None => ReplicaServingRole::Unknown,
+    }
+}
+
+fn is_root_replica_stale(replica_table: &ReplicaRouteTable, root_replica: &Arc<Replica>) -> bool {
+    match replica_table.current_root_replica(None) {
+        Some(current) => !Arc::ptr_eq(&current, root_replica),
+        None => true,
+    }
+}
+
 pub async fn fetch_root_replica(replica_table: &ReplicaRouteTable) -> Arc<Replica> {
     use futures::future::poll_fn;
     poll_fn(
@@ -1068,12 +2841,6 @@ pub async fn fetch_root_replica(replica_table: &ReplicaRouteTable) -> Arc<Replic
     .await
 }
 
-#[derive(Debug)]
-pub enum QueueTask {
-    Heartbeat(HeartbeatTask),
-    Sentinel(Sentinel),
-}
-
 #[derive(Debug)]
 pub struct HeartbeatTask {
     pub node_id: u64,
@@ -1084,6 +2851,8 @@ pub struct Sentinel {
     sender: futures::channel::oneshot::Sender<()>,
 }
 
+/// The queue of heartbeats to send, the heartbeats are scheduled by the root clock and are sent
+/// once their time is passed.
 #[derive(Default)]
 pub struct HeartbeatQueue {
     core: Arc<futures::lock::Mutex<HeartbeatQueueCore>>,
@@ -1092,8 +2861,8 @@ pub struct HeartbeatQueue {
 #[derive(Default)]
 struct HeartbeatQueueCore {
     enable: bool,
-    delay: delay_queue::DelayQueue<QueueTask>,
-    node_scheduled: HashMap<u64, (delay_queue::Key, Instant)>,
+    node_scheduled: HashMap<u64, Instant>,
+    sentinels: Vec<Sentinel>,
 }
 
 impl HeartbeatQueue {
@@ -1104,24 +2873,23 @@ impl HeartbeatQueue {
         }
         for task in tasks {
             let node = task.node_id;
-            if let Some((scheduled_key, old_when)) =
-                core.node_scheduled.get(&node).map(ToOwned::to_owned)
-            {
-                if when < old_when {
+            match core.node_scheduled.get(&node).cloned() {
+                Some(old_when) if when < old_when => {
                     metrics::HEARTBEAT_RESCHEDULE_EARLY_INTERVAL_SECONDS
                         .observe(old_when.saturating_duration_since(when).as_secs_f64());
-                    core.delay.reset_at(&scheduled_key, when);
-                    core.node_scheduled.insert(node, (scheduled_key, when));
+                    core.node_scheduled.insert(node, when);
                     trace!(node=node, when=?when, "update next heartbeat");
                 }
-            } else {
-                let key = core.delay.insert_at(QueueTask::Heartbeat(task), when);
-                core.node_scheduled.insert(node, (key, when));
-                trace!(node=node, when=?when, "schedule next heartbeat");
+                Some(_) => {}
+                None => {
+                    core.node_scheduled.insert(node, when);
+                    trace!(node=node, when=?when, "schedule next heartbeat");
+                }
             }
         }
     }
 
+    /// Wait until the next poll of the queue, which is a tick of heartbeat.
     pub async fn wait_one_heartbeat_tick(&self) {
         let (sender, receiver) = futures::channel::oneshot::channel::<()>();
         let sentinel = Sentinel { sender };
@@ -1130,40 +2898,30 @@ impl HeartbeatQueue {
             if !core.enable {
                 return;
             }
-            core.delay
-                .insert(QueueTask::Sentinel(sentinel), Duration::from_millis(0));
+            core.sentinels.push(sentinel);
         }
         let _ = receiver.await;
     }
 
-    async fn try_poll(&self) -> Vec<HeartbeatTask> {
+    async fn try_poll(&self, now: Instant) -> Vec<HeartbeatTask> {
         let mut core = self.core.lock().await;
         if !core.enable {
             return vec![];
         }
-        let tasks = futures::future::poll_fn(|cx| {
-            let mut tasks = Vec::new();
-            while let Poll::Ready(Some(task)) = core.delay.poll_expired(cx) {
-                tasks.push(task);
-            }
-            Poll::Ready(tasks)
-        })
-        .await;
-        let tasks = tasks
-            .into_iter()
-            .map(|e| e.into_inner())
+        let mut expired = core
+            .node_scheduled
+            .iter()
+            .filter(|(_, when)| **when <= now)
+            .map(|(node, when)| (*when, *node))
             .collect::<Vec<_>>();
-        let mut heartbeats = Vec::new();
-        for task in tasks {
-            match task {
-                QueueTask::Heartbeat(task) => {
-                    core.node_scheduled.remove(&task.node_id);
-                    heartbeats.push(task);
-                }
-                QueueTask::Sentinel(sential) => {
-                    let _ = sential.sender.send(());
-                }
-            }
+        expired.sort_unstable();
+        let mut heartbeats = Vec::with_capacity(expired.len());
+        for (_, node_id) in expired {
+            core.node_scheduled.remove(&node_id);
+            heartbeats.push(HeartbeatTask { node_id });
+        }
+        for sentinel in std::mem::take(&mut core.sentinels) {
+            let _ = sentinel.sender.send(());
         }
         heartbeats
     }
@@ -1172,7 +2930,7 @@ impl HeartbeatQueue {
         let mut core = self.core.lock().await;
         if core.enable != enable {
             core.node_scheduled.clear();
-            core.delay.clear();
+            core.sentinels.clear();
             core.enable = enable;
         }
     }
@@ -1242,6 +3000,26 @@ impl OngoingStats {
         rs
     }
 
+    /// A group is busy if it has ongoing replica changes.
+    pub fn is_group_busy(&self, group: u64) -> bool {
+        let inner = self.sched_stats.lock().unwrap();
+        inner
+            .raw_group_delta
+            .get(&group)
+            .map(|delta| !delta.incoming.is_empty() || !delta.outgoing.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// The number of the ongoing replica moves out of the node.
+    pub fn moves_out_of(&self, node: u64) -> u64 {
+        let inner = self.sched_stats.lock().unwrap();
+        inner
+            .raw_group_delta
+            .values()
+            .filter(|delta| delta.outgoing.iter().any(|r| r.node_id == node))
+            .count() as u64
+    }
+
     pub fn reset(&self) {
         {
             let mut inner = self.sched_stats.lock().unwrap();
@@ -1310,55 +3088,136 @@ impl SchedStats {
 
 #[cfg(test)]
 mod root_test {
+    use std::{
+        collections::HashSet,
+        sync::{atomic::Ordering, Arc},
+        time::Duration,
+    };
+
     use engula_api::{
         server::v1::{
-            watch_response::{update_event, UpdateEvent},
-            GroupDesc,
+            report_request::GroupUpdates,
+            watch_response::{delete_event, update_event, DeleteEvent, UpdateEvent},
+            CollectStatsResponse, CollectionAlias, GroupDesc, NodeCapacity, NodeDesc, NodeStats,
+            NodeStatus, RaftRole, ReplicaDesc, ReplicaRole, ReplicaServingRole, ReplicaState,
+            ScheduleState, ShardDesc,
         },
-        v1::DatabaseDesc,
+        v1::{CollectionDesc, DatabaseDesc, Permission},
     };
     use futures::StreamExt;
     use tempdir::TempDir;
+    use tokio::time::Instant;
 
-    use super::Config;
+    use super::{Config, RootConfig};
     use crate::{
-        bootstrap::{bootstrap_cluster, INITIAL_EPOCH, ROOT_GROUP_ID},
+        bootstrap::{
+            bootstrap_cluster, FIRST_NODE_ID, FIRST_REPLICA_ID, INITIAL_EPOCH, INIT_USER_GROUP_ID,
+            INIT_USER_REPLICA_ID, ROOT_GROUP_ID,
+        },
         node::Node,
-        root::Root,
-        runtime::{Executor, ExecutorOwner},
+        root::{
+            breaker::BreakerState,
+            clock::{Clock, ManualClock, SystemClock},
+            drain::MovePhase,
+            CollectionHealth, EventType, HeartbeatTask, OngoingStats, PageObject, PageToken,
+            RetainedEvent, Root, Schema, Watcher, MAX_ALIAS_DEPTH,
+        },
+        runtime::{Executor, ExecutorOwner, TaskPriority},
         serverpb::v1::NodeIdent,
+        Error,
     };
 
     fn create_root_and_node(
         config: &Config,
         executor: Executor,
         node_ident: &NodeIdent,
+    ) -> (Root, Node) {
+        create_root_and_node_with_clock(config, executor, node_ident, Arc::new(SystemClock))
+    }
+
+    fn create_root_and_node_with_clock(
+        config: &Config,
+        executor: Executor,
+        node_ident: &NodeIdent,
+        clock: Arc<dyn Clock>,
     ) -> (Root, Node) {
         use crate::bootstrap::build_provider;
 
         let provider =
             executor.block_on(async { build_provider(config, executor.clone()).await.unwrap() });
-        let root = Root::new(provider.clone(), node_ident, config.clone());
+        let root = Root::with_clock(provider.clone(), node_ident, config.clone(), clock);
         let node = Node::new(config.clone(), provider).unwrap();
         (root, node)
     }
 
-    #[test]
-    fn boostrap_root() {
-        let executor_owner = ExecutorOwner::new(1);
-        let executor = executor_owner.executor();
-        let tmp_dir = TempDir::new("bootstrap_root").unwrap();
-        let config = Config {
-            root_dir: tmp_dir.path().to_owned(),
-            ..Default::default()
-        };
+    /// Bootstrap a single node cluster and wait until the root of it becomes leader.
+    fn bootstrap_root_leader(config: &Config, executor: Executor) -> (Root, Node) {
+        bootstrap_cluster_root_leader(config, executor, vec![])
+    }
 
-        let ident = NodeIdent {
-            cluster_id: vec![],
-            node_id: 1,
-        };
+    fn bootstrap_cluster_root_leader(
+        config: &Config,
+        executor: Executor,
+        cluster_id: Vec<u8>,
+    ) -> (Root, Node) {
+        bootstrap_root_leader_with_clock(config, executor, cluster_id, Arc::new(SystemClock))
+    }
 
-        let (root, node) = create_root_and_node(&config, executor.to_owned(), &ident);
+    fn bootstrap_root_leader_with_clock(
+        config: &Config,
+        executor: Executor,
+        cluster_id: Vec<u8>,
+        clock: Arc<dyn Clock>,
+    ) -> (Root, Node) {
+        let ident = NodeIdent {
+            cluster_id,
+            node_id: FIRST_NODE_ID,
+        };
+        let (root, node) =
+            create_root_and_node_with_clock(config, executor.to_owned(), &ident, clock);
+        executor.block_on(async {
+            bootstrap_cluster(&node, &config.addr).await.unwrap();
+            node.bootstrap(&ident).await.unwrap();
+            root.bootstrap(&node).await.unwrap();
+            wait_root_leader(&root).await;
+        });
+        (root, node)
+    }
+
+    async fn wait_root_leader(root: &Root) -> Arc<Schema> {
+        loop {
+            if let Ok(schema) = root.schema() {
+                return schema;
+            }
+            crate::runtime::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    fn leader_config(tmp_dir: &TempDir) -> Config {
+        Config {
+            root_dir: tmp_dir.path().to_owned(),
+            addr: "127.0.0.1:0".into(),
+            cpu_nums: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn boostrap_root() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("bootstrap_root").unwrap();
+        let config = Config {
+            root_dir: tmp_dir.path().to_owned(),
+            ..Default::default()
+        };
+
+        let ident = NodeIdent {
+            cluster_id: vec![],
+            node_id: 1,
+        };
+
+        let (root, node) = create_root_and_node(&config, executor.to_owned(), &ident);
         executor.block_on(async {
             bootstrap_cluster(&node, "0.0.0.0:8888").await.unwrap();
             node.bootstrap(&ident).await.unwrap();
@@ -1421,9 +3280,10 @@ mod root_test {
             let _create_db1_event = Some(update_event::Event::Database(DatabaseDesc {
                 id: 1,
                 name: "db1".into(),
+                ..Default::default()
             }));
             let mut w = {
-                let (w, mut initializer) = hub.create_watcher().await;
+                let (w, mut initializer) = hub.create_watcher(&[]).await;
                 initializer.set_init_resp(
                     vec![UpdateEvent {
                         event: _create_db1_event,
@@ -1436,13 +3296,14 @@ mod root_test {
             assert!(matches!(&resp1.updates[0].event, _create_db1_event));
 
             let mut w2 = {
-                let (w, _) = hub.create_watcher().await;
+                let (w, _) = hub.create_watcher(&[]).await;
                 w
             };
 
             let _create_db2_event = Some(update_event::Event::Database(DatabaseDesc {
                 id: 2,
                 name: "db2".into(),
+                ..Default::default()
             }));
             hub.notify_updates(vec![UpdateEvent {
                 event: _create_db2_event,
@@ -1455,13 +3316,2831 @@ mod root_test {
             // hub.notify_error(Error::NotRootLeader(vec![])).await;
         });
     }
-}
 
-pub mod diagnosis {
-    use serde::{Deserialize, Serialize};
+    #[test]
+    fn join_rollback_node_on_failure() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("join_rollback_node_on_failure").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.testing_knobs.inject_join_failure_after_add_node = true;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let num_nodes = schema.list_node().await.unwrap().len();
+            let capacity = NodeCapacity {
+                cpu_nums: 1.0,
+                ..Default::default()
+            };
+            assert!(root.join("127.0.0.1:1".into(), capacity).await.is_err());
+
+            let nodes = schema.list_node().await.unwrap();
+            assert_eq!(nodes.len(), num_nodes);
+            assert!(nodes.iter().all(|n| n.addr != "127.0.0.1:1"));
+        });
+    }
+
+    /// Skip the unrelated events (eg. reported by heartbeat) and return the next alias event.
+    async fn next_alias_event(w: &mut Watcher) -> CollectionAlias {
+        loop {
+            let resp = w.next().await.unwrap().unwrap();
+            for update in resp.updates {
+                if let Some(update_event::Event::Alias(alias)) = update.event {
+                    return alias;
+                }
+            }
+            for delete in resp.deletes {
+                if let Some(delete_event::Event::Alias(alias)) = delete.event {
+                    return alias;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn alias_name_policy() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("alias_name_policy").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.name_prefixes = vec!["tenant_a_".into()];
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let db = schema
+                .create_database(DatabaseDesc {
+                    name: "tenant_a_db".into(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "tenant_a_co".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            schema.create_collection(desc).await.unwrap();
+
+            assert!(matches!(
+                root.create_alias("co".into(), "tenant_a_db".into(), "tenant_a_co".into())
+                    .await,
+                Err(Error::InvalidName(_))
+            ));
+            assert!(schema.get_collection(db.id, "co").await.unwrap().is_none());
+            root.create_alias(
+                "tenant_a_alias".into(),
+                "tenant_a_db".into(),
+                "tenant_a_co".into(),
+            )
+            .await
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn collection_alias() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("collection_alias").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let db = schema
+                .create_database(DatabaseDesc {
+                    name: "db".into(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let mut collections = vec![];
+            for name in ["c1", "c2"] {
+                let desc = schema
+                    .prepare_create_collection(CollectionDesc {
+                        name: name.into(),
+                        db: db.id,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                collections.push(schema.create_collection(desc).await.unwrap());
+            }
+            let (c1, c2) = (collections[0].id, collections[1].id);
+
+            let mut w = {
+                let (w, _) = root.watcher_hub().create_watcher(&[]).await;
+                w
+            };
+
+            // create and resolve.
+            root.create_alias("a".into(), "db".into(), "c1".into())
+                .await
+                .unwrap();
+            let resolved = schema.get_collection(db.id, "a").await.unwrap().unwrap();
+            assert_eq!(resolved.id, c1);
+            assert!(schema
+                .get_collection_exact(db.id, "a")
+                .await
+                .unwrap()
+                .is_none());
+            assert_eq!(next_alias_event(&mut w).await.collection_id, c1);
+
+            // name collision.
+            assert!(matches!(
+                root.create_alias("c2".into(), "db".into(), "c1".into())
+                    .await,
+                Err(Error::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                root.create_alias("a".into(), "db".into(), "c2".into())
+                    .await,
+                Err(Error::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                schema
+                    .prepare_create_collection(CollectionDesc {
+                        name: "a".into(),
+                        db: db.id,
+                        ..Default::default()
+                    })
+                    .await,
+                Err(Error::AlreadyExists(_))
+            ));
+
+            // repoint.
+            root.repoint_alias("a".into(), "db".into(), "c2".into())
+                .await
+                .unwrap();
+            let resolved = schema.get_collection(db.id, "a").await.unwrap().unwrap();
+            assert_eq!(resolved.id, c2);
+            assert_eq!(next_alias_event(&mut w).await.collection_id, c2);
+
+            // delete.
+            root.delete_alias("a", "db").await.unwrap();
+            assert!(schema.get_collection(db.id, "a").await.unwrap().is_none());
+            assert_eq!(next_alias_event(&mut w).await.name, "a");
+            assert!(root
+                .repoint_alias("a".into(), "db".into(), "c1".into())
+                .await
+                .is_err());
+
+            // an alias created after the collection is prepared.
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "b".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            root.create_alias("b".into(), "db".into(), "c1".into())
+                .await
+                .unwrap();
+            assert!(matches!(
+                schema.create_collection(desc).await,
+                Err(Error::AlreadyExists(_))
+            ));
+
+            // the aliases are deleted with the collection.
+            root.delete_collection("c1", &db).await.unwrap();
+            assert!(schema.get_alias(db.id, "b").await.unwrap().is_none());
+            assert!(schema.get_collection(db.id, "b").await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn alias_chain_and_cycle() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("alias_chain_and_cycle").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let db = schema
+                .create_database(DatabaseDesc {
+                    name: "db".into(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let mut collections = vec![];
+            for name in ["c1", "c2"] {
+                let desc = schema
+                    .prepare_create_collection(CollectionDesc {
+                        name: name.into(),
+                        db: db.id,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                collections.push(schema.create_collection(desc).await.unwrap());
+            }
+            let (c1, c2) = (collections[0].id, collections[1].id);
+
+            // direct self-reference.
+            assert!(matches!(
+                root.create_alias("a".into(), "db".into(), "a".into()).await,
+                Err(Error::InvalidAlias(_))
+            ));
+            root.create_alias("a".into(), "db".into(), "c1".into())
+                .await
+                .unwrap();
+            assert!(matches!(
+                root.repoint_alias("a".into(), "db".into(), "a".into())
+                    .await,
+                Err(Error::InvalidAlias(_))
+            ));
+
+            // a valid chain resolves to the real collection.
+            let b = root
+                .create_alias("b".into(), "db".into(), "a".into())
+                .await
+                .unwrap();
+            assert_eq!(b.collection_id, c1);
+            assert_eq!(b.target, "a");
+            root.create_alias("c".into(), "db".into(), "b".into())
+                .await
+                .unwrap();
+            let resolved = schema.get_collection(db.id, "c").await.unwrap().unwrap();
+            assert_eq!(resolved.id, c1);
+
+            // two-hop cycle: a -> b -> a.
+            assert!(matches!(
+                root.repoint_alias("a".into(), "db".into(), "b".into())
+                    .await,
+                Err(Error::InvalidAlias(_))
+            ));
+            assert!(matches!(
+                root.repoint_alias("a".into(), "db".into(), "c".into())
+                    .await,
+                Err(Error::InvalidAlias(_))
+            ));
+
+            // the chained aliases follow the repointing.
+            root.repoint_alias("a".into(), "db".into(), "c2".into())
+                .await
+                .unwrap();
+            for name in ["a", "b", "c"] {
+                let resolved = schema.get_collection(db.id, name).await.unwrap().unwrap();
+                assert_eq!(resolved.id, c2);
+            }
+
+            // a cascade deeper than the max chain depth is rejected without any write.
+            let deep = (0..=MAX_ALIAS_DEPTH)
+                .map(|i| format!("d{i}"))
+                .collect::<Vec<_>>();
+            for (i, name) in deep.iter().enumerate() {
+                let target = if i == 0 { "a" } else { deep[i - 1].as_str() };
+                schema
+                    .create_alias(CollectionAlias {
+                        name: name.to_owned(),
+                        db: db.id,
+                        collection_id: c2,
+                        target: target.to_owned(),
+                    })
+                    .await
+                    .unwrap();
+            }
+            assert!(matches!(
+                root.repoint_alias("a".into(), "db".into(), "c1".into())
+                    .await,
+                Err(Error::InvalidAlias(_))
+            ));
+            for name in ["a", "b", "c", "d0", deep[MAX_ALIAS_DEPTH].as_str()] {
+                let resolved = schema.get_collection(db.id, name).await.unwrap().unwrap();
+                assert_eq!(resolved.id, c2);
+            }
+            for name in deep.iter().rev() {
+                root.delete_alias(name, "db").await.unwrap();
+            }
+
+            // a referenced alias is not allowed to delete.
+            assert!(root.delete_alias("a", "db").await.is_err());
+            root.delete_alias("c", "db").await.unwrap();
+            root.delete_alias("b", "db").await.unwrap();
+            root.delete_alias("a", "db").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn collection_acl() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("collection_acl").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let (db, _) = root.create_database("db".into()).await.unwrap();
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            schema.create_collection(desc).await.unwrap();
+            assert!(root.get_collection_acl("c", "db").await.unwrap().is_empty());
+
+            root.set_collection_acl(
+                "c",
+                "db",
+                "user:alice".into(),
+                vec![Permission::Read, Permission::Write],
+            )
+            .await
+            .unwrap();
+            let desc = root
+                .set_collection_acl("c", "db", "group:ops".into(), vec![Permission::Admin])
+                .await
+                .unwrap();
+            assert_eq!(desc.acl.len(), 2);
+            let acl = root.get_collection_acl("c", "db").await.unwrap();
+            assert_eq!(acl["user:alice"], vec![Permission::Read, Permission::Write]);
+            assert_eq!(acl["group:ops"], vec![Permission::Admin]);
+
+            // The update is notified to watchers.
+            let notified = root.tail_events(1, &[EventType::Collection]).unwrap();
+            assert!(matches!(
+                &notified[0].1,
+                RetainedEvent::Update(UpdateEvent {
+                    event: Some(update_event::Event::Collection(c)),
+                }) if c.acl.len() == 2
+            ));
+
+            // The malformed entries are rejected.
+            for principal in ["alice", "robot:alice", "user:"] {
+                assert!(matches!(
+                    root.set_collection_acl("c", "db", principal.into(), vec![Permission::Read])
+                        .await,
+                    Err(Error::InvalidArgument(_))
+                ));
+            }
+            assert!(matches!(
+                root.set_collection_acl("c", "db", "user:bob".into(), vec![])
+                    .await,
+                Err(Error::InvalidArgument(_))
+            ));
+            assert!(root
+                .set_collection_acl("missing", "db", "user:bob".into(), vec![Permission::Read])
+                .await
+                .is_err());
+
+            root.clear_collection_acl("c", "db", Some("user:alice"))
+                .await
+                .unwrap();
+            let acl = root.get_collection_acl("c", "db").await.unwrap();
+            assert_eq!(acl.keys().collect::<Vec<_>>(), vec!["group:ops"]);
+            root.clear_collection_acl("c", "db", None).await.unwrap();
+            assert!(root.get_collection_acl("c", "db").await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn collection_health_under_replicated() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("collection_health_under_replicated").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let (db, _) = root.create_database("db".into()).await.unwrap();
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let collection = schema.create_collection(desc).await.unwrap();
+
+            // Group 100 is fully replicated, group 101 has a replica on the dead node 13.
+            for (group_id, node_ids) in [(100, [10, 11, 12]), (101, [10, 11, 13])] {
+                let replicas = node_ids
+                    .iter()
+                    .map(|&node_id| ReplicaDesc {
+                        id: group_id * 10 + node_id,
+                        node_id,
+                        role: ReplicaRole::Voter as i32,
+                    })
+                    .collect();
+                let group = GroupDesc {
+                    id: group_id,
+                    shards: vec![ShardDesc {
+                        id: group_id,
+                        collection_id: collection.id,
+                        ..Default::default()
+                    }],
+                    replicas,
+                    ..Default::default()
+                };
+                schema
+                    .update_group_replica(Some(group), None)
+                    .await
+                    .unwrap();
+            }
+            root.liveness.mark_dead(13);
+            for (replica_id, applied_index) in [(1010, 100), (1011, 93)] {
+                root.replica_lag.update(&ReplicaState {
+                    group_id: 101,
+                    replica_id,
+                    applied_index,
+                    ..Default::default()
+                });
+            }
+
+            let health = root.collection_health("c", "db").await.unwrap();
+            assert_eq!(
+                health,
+                CollectionHealth {
+                    groups: 2,
+                    desired_replicas: 6,
+                    healthy_replicas: 5,
+                    under_replicated_groups: 1,
+                    max_replica_lag: 7,
+                }
+            );
+            assert!(root.collection_health("missing", "db").await.is_err());
+        });
+    }
+
+    #[test]
+    fn collection_read_only() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("collection_read_only").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let (db, _) = root.create_database("db".into()).await.unwrap();
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            schema.create_collection(desc).await.unwrap();
+
+            async fn set_and_notified(root: &Root, read_only: bool) {
+                let desc = root
+                    .set_collection_read_only("c", "db", read_only)
+                    .await
+                    .unwrap();
+                assert_eq!(desc.read_only, read_only);
+
+                // The clients block the writes by the notified descriptor.
+                let notified = root
+                    .tail_events(1, &[EventType::CollectionOptions])
+                    .unwrap();
+                assert!(matches!(
+                    &notified[0].1,
+                    RetainedEvent::Update(UpdateEvent {
+                        event: Some(update_event::Event::CollectionOptions(change)),
+                    }) if change.fields == vec!["read_only".to_owned()]
+                        && change.current.as_ref().unwrap().read_only == read_only
+                ));
+            }
+
+            set_and_notified(&root, true).await;
+            let desc = root.get_collection("c", &db).await.unwrap().unwrap();
+            assert!(desc.read_only);
+
+            set_and_notified(&root, false).await;
+            let desc = root.get_collection("c", &db).await.unwrap().unwrap();
+            assert!(!desc.read_only);
+
+            assert!(root
+                .set_collection_read_only("missing", "db", true)
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn collection_options_change_event() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("collection_options_change_event").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let (db, _) = root.create_database("db".into()).await.unwrap();
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            schema.create_collection(desc.to_owned()).await.unwrap();
+
+            let mut watcher = root.watch(Default::default(), None, &[]).await.unwrap();
+            watcher.next().await.unwrap().unwrap();
+
+            root.set_collection_acl("c", "db", "user:alice".into(), vec![Permission::Read])
+                .await
+                .unwrap();
+            let resp = watcher.next().await.unwrap().unwrap();
+            let change = resp
+                .updates
+                .into_iter()
+                .find_map(|u| match u.event {
+                    Some(update_event::Event::CollectionOptions(change)) => Some(change),
+                    _ => None,
+                })
+                .expect("the option change event is delivered");
+            assert_eq!(change.collection_id, desc.id);
+            assert_eq!(change.fields, vec!["acl".to_owned()]);
+            assert!(change.previous.unwrap().acl.is_empty());
+            assert!(change.current.unwrap().acl.contains_key("user:alice"));
+        });
+    }
+
+    #[test]
+    fn corrupt_metadata_fails_fast() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("corrupt_metadata_fails_fast").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            schema.inject_corrupt_node(100).await.unwrap();
+            let err = schema.list_node().await.unwrap_err();
+            assert!(
+                matches!(&err, Error::CorruptMetadata(key) if key == "node/6400000000000000"),
+                "{err:?}"
+            );
+            assert!(schema.is_consistent());
+        });
+    }
+
+    #[test]
+    fn corrupt_metadata_skipped() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("corrupt_metadata_skipped").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.skip_corrupt_metadata = true;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            schema.inject_corrupt_node(100).await.unwrap();
+            let skipped = super::metrics::CORRUPT_METADATA_TOTAL.get();
+            let nodes = schema.list_node().await.unwrap();
+            assert_eq!(
+                nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+                vec![FIRST_NODE_ID]
+            );
+            assert!(super::metrics::CORRUPT_METADATA_TOTAL.get() > skipped);
+            assert!(!root.is_metadata_consistent().unwrap());
+        });
+    }
+
+    #[test]
+    fn join_nodes_in_batch() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("join_nodes_in_batch").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let mut watcher = root.watch(Default::default(), None, &[]).await.unwrap();
+            watcher.next().await.unwrap().unwrap();
+
+            let addrs = ["127.0.0.1:1", "127.0.0.1:2", "127.0.0.1:3"];
+            let summary = root.join_nodes(with_capacity(&addrs)).await.unwrap();
+            assert!(summary.existing.is_empty() && summary.failed.is_empty());
+            assert_eq!(
+                summary
+                    .joined
+                    .iter()
+                    .map(|n| n.addr.as_str())
+                    .collect::<Vec<_>>(),
+                addrs
+            );
+            let ids = summary.joined.iter().map(|n| n.id).collect::<HashSet<_>>();
+            assert_eq!(ids.len(), addrs.len());
+
+            // All joined nodes are notified in a single batch.
+            let resp = watcher.next().await.unwrap().unwrap();
+            let notified = resp
+                .updates
+                .iter()
+                .filter_map(|u| match &u.event {
+                    Some(update_event::Event::Node(n)) => Some(n.id),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>();
+            assert_eq!(notified, ids);
+        });
+    }
+
+    #[test]
+    fn join_nodes_mixed_with_joined() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("join_nodes_mixed_with_joined").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let summary = root
+                .join_nodes(with_capacity(&["127.0.0.1:1", "127.0.0.1:2"]))
+                .await
+                .unwrap();
+            let joined = summary.joined[1].to_owned();
+
+            let summary = root
+                .join_nodes(with_capacity(&[
+                    "127.0.0.1:2",
+                    "127.0.0.1:3",
+                    "127.0.0.1:3",
+                    "",
+                    config.addr.as_str(),
+                ]))
+                .await
+                .unwrap();
+            assert_eq!(summary.joined.len(), 1);
+            assert_eq!(summary.joined[0].addr, "127.0.0.1:3");
+            assert_eq!(
+                summary.existing.iter().map(|n| n.id).collect::<Vec<_>>(),
+                vec![joined.id, summary.joined[0].id, FIRST_NODE_ID]
+            );
+            assert_eq!(summary.failed.len(), 1);
+            assert!(matches!(summary.failed[0], (ref addr, Error::InvalidArgument(_)) if addr.is_empty()));
+
+            // No duplicated nodes are registered.
+            let nodes = root.schema().unwrap().list_node().await.unwrap();
+            assert_eq!(nodes.len(), 4);
+        });
+    }
+
+    fn with_capacity(addrs: &[&str]) -> Vec<(String, NodeCapacity)> {
+        addrs
+            .iter()
+            .map(|addr| {
+                let capacity = NodeCapacity {
+                    cpu_nums: 4.0,
+                    ..Default::default()
+                };
+                (addr.to_string(), capacity)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn join_nodes_concurrently() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("join_nodes_concurrently").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let addrs = ["127.0.0.1:1", "127.0.0.1:2"];
+            let (first, second) = futures::join!(
+                root.join_nodes(with_capacity(&addrs)),
+                root.join_nodes(with_capacity(&addrs)),
+            );
+            let (first, second) = (first.unwrap(), second.unwrap());
+            assert_eq!(first.joined.len() + second.joined.len(), addrs.len());
+            assert_eq!(first.existing.len() + second.existing.len(), addrs.len());
+
+            // Each address is registered once, with the requested capacity.
+            let nodes = root.schema().unwrap().list_node().await.unwrap();
+            assert_eq!(nodes.len(), addrs.len() + 1);
+            for node in nodes.iter().filter(|n| n.id != FIRST_NODE_ID) {
+                assert_eq!(node.capacity.as_ref().unwrap().cpu_nums, 4.0);
+            }
+        });
+    }
+
+    #[test]
+    fn frozen_blocks_mutations() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("frozen_blocks_mutations").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let (db, _) = root.create_database("db".into()).await.unwrap();
+            root.set_frozen(true).await.unwrap();
+            assert!(root.is_frozen().await.unwrap());
+
+            assert!(matches!(
+                root.create_database("db2".into()).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.create_collection("c".into(), "db".into(), Default::default())
+                    .await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.delete_database("db").await,
+                Err(Error::ClusterFrozen)
+            ));
+
+            // So are the mutations of nodes and replicas.
+            let node_id = root.current_node_id();
+            assert!(matches!(
+                root.cordon_node(node_id).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.uncordon_node(node_id).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.begin_drain(node_id, None).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.decommission_node(node_id).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.add_group_replica(INIT_USER_GROUP_ID, node_id).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.remove_group_replica(INIT_USER_GROUP_ID, INIT_USER_REPLICA_ID, false)
+                    .await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.add_root_replica(node_id).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.remove_root_replica(FIRST_REPLICA_ID).await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.repair_duplicate_node_addrs().await,
+                Err(Error::ClusterFrozen)
+            ));
+            assert!(matches!(
+                root.join_nodes(with_capacity(&["127.0.0.1:1"])).await,
+                Err(Error::ClusterFrozen)
+            ));
+
+            // Reads and watches are still served.
+            assert!(root.get_database("db").await.unwrap().is_some());
+            assert!(root.list_collection(&db).await.unwrap().is_empty());
+            root.watch(Default::default(), None, &[]).await.unwrap();
+
+            root.set_frozen(false).await.unwrap();
+            root.create_database("db2".into()).await.unwrap();
+            root.delete_database("db2").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn create_database_system_groups() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("create_database_system_groups").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let (db, system_group_ids) = root.create_database("db".into()).await.unwrap();
+            assert_eq!(db.name, "db");
+            // No system group is provisioned for a database.
+            assert!(system_group_ids.is_empty());
+
+            // The database is still notified to watchers.
+            let notified = root.tail_events(1, &[EventType::Database]).unwrap();
+            assert!(matches!(
+                &notified[0].1,
+                RetainedEvent::Update(UpdateEvent {
+                    event: Some(update_event::Event::Database(d)),
+                }) if d.id == db.id
+            ));
+        });
+    }
+
+    #[test]
+    fn collection_not_in_database() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("collection_not_in_database").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let mut dbs = vec![];
+            for name in ["a", "b"] {
+                let db = schema
+                    .create_database(DatabaseDesc {
+                        name: name.into(),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                dbs.push(db);
+            }
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: dbs[0].id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            schema.create_collection(desc).await.unwrap();
+
+            assert!(root.get_collection("c", &dbs[0]).await.unwrap().is_some());
+
+            // The collection belongs to another database.
+            assert!(matches!(
+                root.get_collection("c", &dbs[1]).await,
+                Err(Error::CollectionNotInDatabase(..))
+            ));
+            assert!(matches!(
+                root.collection_stats("c", "b").await,
+                Err(Error::CollectionNotInDatabase(..))
+            ));
+            assert!(matches!(
+                root.delete_collection("c", &dbs[1]).await,
+                Err(Error::CollectionNotInDatabase(..))
+            ));
+            assert!(root.get_collection("c", &dbs[0]).await.unwrap().is_some());
+            assert!(root
+                .get_collection("unknown", &dbs[1])
+                .await
+                .unwrap()
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn revalidate_root_replica_after_route_table_swap() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("revalidate_root_replica").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let replica_table = node.replica_table().clone();
+            let root_replica = replica_table.find(ROOT_GROUP_ID).unwrap();
+
+            assert!(Arc::ptr_eq(
+                &root.schema().unwrap().root_replica(),
+                &root_replica
+            ));
+
+            // The root task should drop the stale root replica.
+            replica_table.remove(ROOT_GROUP_ID);
+            while root.schema().is_ok() {
+                crate::runtime::time::sleep(Duration::from_millis(100)).await;
+            }
+
+            // And picks up the new one.
+            let new_replica = Arc::new(root_replica.reopen());
+            replica_table.update(new_replica.clone());
+            wait_root_leader(&root).await;
+            let replica = root.schema().unwrap().root_replica();
+            assert!(Arc::ptr_eq(&replica, &new_replica));
+            assert!(!Arc::ptr_eq(&replica, &root_replica));
+
+            // A replica swapped in place is picked up as well.
+            let swapped = Arc::new(new_replica.reopen());
+            replica_table.update(swapped.clone());
+            loop {
+                match root.schema() {
+                    Ok(schema) if Arc::ptr_eq(&schema.root_replica(), &swapped) => break,
+                    _ => crate::runtime::time::sleep(Duration::from_millis(100)).await,
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn database_default_collection_options() {
+        use engula_api::v1::{
+            collection_desc as co_desc, collection_options as co_opts,
+            create_collection_request as co_req, CollectionOptions,
+        };
+
+        use super::resolve_collection_partition;
+
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("database_default_collection_options").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            root.create_database("db".into()).await.unwrap();
+            let db = root
+                .update_database_defaults(
+                    "db",
+                    Some(CollectionOptions {
+                        partition: Some(co_opts::Partition::Hash(co_desc::HashPartition {
+                            slots: 8,
+                        })),
+                    }),
+                )
+                .await
+                .unwrap();
+            assert_eq!(root.get_database("db").await.unwrap(), Some(db.clone()));
+
+            // Inherit the default options of database.
+            let inherited = resolve_collection_partition(None, &db);
+            assert_eq!(
+                inherited,
+                Some(co_desc::Partition::Hash(co_desc::HashPartition {
+                    slots: 8
+                }))
+            );
+
+            // Override at create time.
+            let overridden = resolve_collection_partition(
+                Some(co_req::Partition::Range(co_req::RangePartition {})),
+                &db,
+            );
+            assert_eq!(
+                overridden,
+                Some(co_desc::Partition::Range(co_desc::RangePartition {}))
+            );
+
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: db.id,
+                    partition: inherited.clone(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            schema.create_collection(desc).await.unwrap();
+
+            // Changing the defaults doesn't alter the existing collections.
+            let db = root.update_database_defaults("db", None).await.unwrap();
+            assert_eq!(resolve_collection_partition(None, &db), None);
+            let collection = root.get_collection("c", &db).await.unwrap().unwrap();
+            assert_eq!(collection.partition, inherited);
+        });
+    }
+
+    #[test]
+    fn shed_report_exceeding_inflight_limit() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("shed_report_exceeding_inflight_limit").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.report_max_inflight = 2;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            // Saturate the report path.
+            let permits = root.report_limiter.try_acquire_many(2).unwrap();
+            let shed = super::metrics::REPORT_SHED_TOTAL.get();
+            for _ in 0..4 {
+                assert!(matches!(
+                    root.report(vec![]).await,
+                    Err(Error::Overloaded(_))
+                ));
+            }
+            assert!(super::metrics::REPORT_SHED_TOTAL.get() >= shed + 4);
+
+            drop(permits);
+            root.report(vec![]).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn report_replica_role_changes() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("report_replica_role_changes").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let mut group = schema.get_group(INIT_USER_GROUP_ID).await.unwrap().unwrap();
+            let mut watcher = root.watch(Default::default(), None, &[]).await.unwrap();
+            watcher.next().await.unwrap().unwrap();
+
+            // A learner is added, then promoted after catching up.
+            let learner = ReplicaDesc {
+                id: 100,
+                node_id: FIRST_NODE_ID,
+                role: ReplicaRole::Learner as i32,
+            };
+            group.epoch += 1;
+            group.replicas.push(learner.clone());
+            let changes = report_and_watch(
+                &root,
+                &mut watcher,
+                GroupUpdates {
+                    group_id: INIT_USER_GROUP_ID,
+                    group_desc: Some(group.clone()),
+                    ..Default::default()
+                },
+            )
+            .await;
+            assert_eq!(
+                changes,
+                vec![(
+                    100,
+                    ReplicaServingRole::Unknown,
+                    ReplicaServingRole::Learner
+                )]
+            );
+
+            group.epoch += 1;
+            group.replicas.last_mut().unwrap().role = ReplicaRole::Voter as i32;
+            let changes = report_and_watch(
+                &root,
+                &mut watcher,
+                GroupUpdates {
+                    group_id: INIT_USER_GROUP_ID,
+                    group_desc: Some(group.clone()),
+                    ..Default::default()
+                },
+            )
+            .await;
+            assert_eq!(
+                changes,
+                vec![(
+                    100,
+                    ReplicaServingRole::Learner,
+                    ReplicaServingRole::Follower
+                )]
+            );
+
+            // The follower is elected as leader.
+            let changes = report_and_watch(
+                &root,
+                &mut watcher,
+                GroupUpdates {
+                    group_id: INIT_USER_GROUP_ID,
+                    replica_state: Some(ReplicaState {
+                        replica_id: 100,
+                        group_id: INIT_USER_GROUP_ID,
+                        term: 100,
+                        voted_for: 100,
+                        role: RaftRole::Leader as i32,
+                        node_id: FIRST_NODE_ID,
+                        applied_index: 0,
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await;
+            assert_eq!(
+                changes,
+                vec![(
+                    100,
+                    ReplicaServingRole::Follower,
+                    ReplicaServingRole::Leader
+                )]
+            );
+        });
+
+        async fn report_and_watch(
+            root: &Root,
+            watcher: &mut Watcher,
+            updates: GroupUpdates,
+        ) -> Vec<(u64, ReplicaServingRole, ReplicaServingRole)> {
+            root.report(vec![updates]).await.unwrap();
+            let resp = watcher.next().await.unwrap().unwrap();
+            resp.updates
+                .into_iter()
+                .filter_map(|u| match u.event {
+                    Some(update_event::Event::ReplicaRole(change)) => {
+                        Some((change.replica_id, change.previous(), change.current()))
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn report_surfaces_partial_failures() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("report_surfaces_partial_failures").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let group = schema.get_group(INIT_USER_GROUP_ID).await.unwrap().unwrap();
+            let new_group = GroupDesc {
+                epoch: group.epoch + 1,
+                ..group.clone()
+            };
+            let updates = vec![
+                GroupUpdates {
+                    group_id: INIT_USER_GROUP_ID,
+                    group_desc: Some(new_group.clone()),
+                    ..Default::default()
+                },
+                // The desc doesn't belong to the reported group.
+                GroupUpdates {
+                    group_id: INIT_USER_GROUP_ID,
+                    group_desc: Some(GroupDesc {
+                        id: 12345,
+                        epoch: group.epoch + 1,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                GroupUpdates {
+                    group_id: INIT_USER_GROUP_ID,
+                    replica_state: Some(ReplicaState {
+                        replica_id: INIT_USER_REPLICA_ID,
+                        group_id: INIT_USER_GROUP_ID,
+                        term: 100,
+                        voted_for: INIT_USER_REPLICA_ID,
+                        role: RaftRole::Leader as i32,
+                        node_id: FIRST_NODE_ID,
+                        applied_index: 0,
+                    }),
+                    ..Default::default()
+                },
+            ];
+            let summary = root.report(updates).await.unwrap();
+            assert_eq!(summary.applied, vec![0, 2]);
+            assert_eq!(summary.failed.len(), 1);
+            assert!(matches!(summary.failed[0], (1, Error::InvalidArgument(_))));
+
+            // The other updates are applied.
+            let desc = schema.get_group(INIT_USER_GROUP_ID).await.unwrap().unwrap();
+            assert_eq!(desc.epoch, new_group.epoch);
+            assert!(schema.get_group(12345).await.unwrap().is_none());
+            let state = schema
+                .get_replica_state(INIT_USER_GROUP_ID, INIT_USER_REPLICA_ID)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(state.term, 100);
+
+            // Only the applied updates are notified.
+            let groups = root
+                .tail_events(100, &[])
+                .unwrap()
+                .into_iter()
+                .filter_map(|(_, event)| match event {
+                    RetainedEvent::Update(UpdateEvent {
+                        event: Some(update_event::Event::Group(desc)),
+                    }) => Some(desc.id),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert!(groups.contains(&INIT_USER_GROUP_ID));
+            assert!(!groups.contains(&12345));
+        });
+    }
+
+    #[test]
+    fn snapshot_root_on_demand() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("snapshot_root_on_demand").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.snapshot_min_interval_sec = 0;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let first = root.snapshot_root().await.unwrap();
+            assert!(first.created);
+            assert!(first.size > 0);
+
+            root.create_database("db".into()).await.unwrap();
+            let second = root.snapshot_root().await.unwrap();
+            assert!(second.created);
+            assert!(second.index > first.index);
+        });
+    }
+
+    #[test]
+    fn degraded_until_enough_nodes() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("degraded_until_enough_nodes").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            assert!(root.refresh_degraded().await.unwrap());
+            assert!(root.is_degraded());
+
+            for port in 1..config.root.replicas_per_group {
+                assert!(root.refresh_degraded().await.unwrap());
+                schema
+                    .add_node(NodeDesc {
+                        addr: format!("127.0.0.1:{port}"),
+                        capacity: Some(NodeCapacity::default()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+            }
+            assert!(!root.refresh_degraded().await.unwrap());
+            assert!(!root.is_degraded());
+        });
+    }
+
+    #[test]
+    fn reject_adding_group_replica() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("reject_adding_group_replica").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+
+            // The target node is already a member.
+            let err = root.add_group_replica(ROOT_GROUP_ID, FIRST_NODE_ID).await;
+            assert!(matches!(err, Err(Error::InvalidArgument(msg)) if msg.contains("member")));
+
+            let mut nodes = vec![];
+            for port in [1, 2] {
+                let node = schema
+                    .add_node(NodeDesc {
+                        addr: format!("127.0.0.1:{port}"),
+                        capacity: Some(NodeCapacity::default()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                nodes.push(node.id);
+            }
+
+            // The target node is dead.
+            root.liveness.mark_dead(nodes[0]);
+            let err = root.add_group_replica(ROOT_GROUP_ID, nodes[0]).await;
+            assert!(matches!(err, Err(Error::InvalidArgument(msg)) if msg.contains("alive")));
+
+            // The group is busy.
+            root.ongoing_stats.handle_update(
+                &[ScheduleState {
+                    group_id: ROOT_GROUP_ID,
+                    epoch: u64::MAX,
+                    incoming_replicas: vec![ReplicaDesc {
+                        id: 100,
+                        node_id: nodes[0],
+                        ..Default::default()
+                    }],
+                    outgoing_replicas: vec![],
+                }],
+                None,
+            );
+            let err = root.add_group_replica(ROOT_GROUP_ID, nodes[1]).await;
+            assert!(matches!(err, Err(Error::InvalidArgument(msg)) if msg.contains("busy")));
+        });
+    }
+
+    #[test]
+    fn wait_group_replica_changed() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("wait_group_replica_changed").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let group = schema.get_group(ROOT_GROUP_ID).await.unwrap().unwrap();
+            let incoming = ReplicaDesc {
+                id: 100,
+                node_id: 100,
+                role: ReplicaRole::Voter as i32,
+            };
+            let mut changed = group.clone();
+            changed.epoch += 1;
+            changed.replicas.push(incoming.clone());
+
+            // The descriptor reported after the wait begins is returned.
+            let (desc, _) = futures::join!(
+                root.wait_group_changed(&schema, &group, |desc| {
+                    desc.replicas.iter().any(|r| r.id == incoming.id)
+                }),
+                async {
+                    crate::runtime::time::sleep(Duration::from_millis(200)).await;
+                    schema
+                        .update_group_replica(Some(changed.clone()), None)
+                        .await
+                        .unwrap();
+                }
+            );
+            let desc = desc.unwrap();
+            assert_eq!(desc.epoch, changed.epoch);
+            assert!(desc.replicas.contains(&incoming));
+        });
+    }
+
+    #[test]
+    fn run_retry_backoff_capped() {
+        let cfg = RootConfig {
+            run_retry_initial_backoff_ms: 100,
+            run_retry_backoff_multiplier: 3.0,
+            run_retry_max_backoff_ms: 2000,
+            ..Default::default()
+        };
+        cfg.validate().unwrap();
+        let backoffs = (0..5)
+            .map(|attempts| cfg.run_retry_backoff(attempts).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(backoffs, vec![100, 300, 900, 2000, 2000]);
+        assert_eq!(cfg.run_retry_backoff(1000), Duration::from_secs(2));
+
+        let invalid = RootConfig {
+            run_retry_initial_backoff_ms: 3000,
+            ..cfg.to_owned()
+        };
+        assert!(matches!(invalid.validate(), Err(Error::InvalidArgument(_))));
+        // A zero backoff retries in a hot loop.
+        let invalid = RootConfig {
+            run_retry_initial_backoff_ms: 0,
+            ..cfg.to_owned()
+        };
+        assert!(matches!(invalid.validate(), Err(Error::InvalidArgument(_))));
+        for multiplier in [0.5, f64::NAN, f64::INFINITY] {
+            let invalid = RootConfig {
+                run_retry_backoff_multiplier: multiplier,
+                ..cfg.to_owned()
+            };
+            assert!(matches!(invalid.validate(), Err(Error::InvalidArgument(_))));
+        }
+
+        // A constant backoff is rejected, the multiplier must grow the backoff.
+        let constant = RootConfig {
+            run_retry_backoff_multiplier: 1.0,
+            ..cfg
+        };
+        assert!(matches!(
+            constant.validate(),
+            Err(Error::InvalidArgument(_))
+        ));
+        RootConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn reject_stale_group_report() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("reject_stale_group_report").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let group = |epoch: u64| GroupDesc {
+                id: 100,
+                epoch,
+                ..Default::default()
+            };
+            schema
+                .cas_group_replica(None, group(1), None)
+                .await
+                .unwrap();
+
+            // Both reports are based on epoch 1, the later one is applied after the desc changed.
+            schema
+                .cas_group_replica(Some(1), group(3), None)
+                .await
+                .unwrap();
+            let err = schema.cas_group_replica(Some(1), group(2), None).await;
+            assert!(matches!(err, Err(Error::StaleReport(100))));
+            assert_eq!(schema.get_group(100).await.unwrap().unwrap().epoch, 3);
+
+            // The group is created concurrently.
+            let err = schema.cas_group_replica(None, group(4), None).await;
+            assert!(matches!(err, Err(Error::StaleReport(100))));
+
+            // A report based on an outdated desc is rejected.
+            let summary = root
+                .report(vec![GroupUpdates {
+                    group_id: 100,
+                    group_desc: Some(group(2)),
+                    ..Default::default()
+                }])
+                .await
+                .unwrap();
+            assert!(summary.applied.is_empty());
+            assert_eq!(summary.failed.len(), 1);
+            assert!(matches!(summary.failed[0], (0, Error::StaleReport(100))));
+            assert_eq!(schema.get_group(100).await.unwrap().unwrap().epoch, 3);
+            let events = root.tail_events(100, &[EventType::Group]).unwrap();
+            assert!(events.iter().all(|(_, event)| !matches!(
+                event,
+                RetainedEvent::Update(UpdateEvent {
+                    event: Some(update_event::Event::Group(desc)),
+                }) if desc.id == 100
+            )));
+
+            // The same desc is reported again, it is a no-op.
+            let summary = root
+                .report(vec![GroupUpdates {
+                    group_id: 100,
+                    group_desc: Some(group(3)),
+                    ..Default::default()
+                }])
+                .await
+                .unwrap();
+            assert_eq!(summary.applied, vec![0]);
+        });
+    }
+
+    #[test]
+    fn remove_group_deletes_group_state() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("remove_group_deletes_group_state").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+
+            // The replicas are placed on an unknown node, so no replica is removed remotely. The
+            // state of replica 101 lingers after it was removed from the descriptor.
+            let group = GroupDesc {
+                id: 100,
+                replicas: vec![ReplicaDesc {
+                    id: 100,
+                    node_id: 100,
+                    role: ReplicaRole::Voter as i32,
+                }],
+                ..Default::default()
+            };
+            for replica_id in [100, 101] {
+                schema
+                    .update_group_replica(
+                        Some(group.to_owned()),
+                        Some(ReplicaState {
+                            group_id: group.id,
+                            replica_id,
+                            node_id: 100,
+                            ..Default::default()
+                        }),
+                    )
+                    .await
+                    .unwrap();
+            }
+            assert!(schema.get_group_state(group.id).await.unwrap().is_some());
+
+            root.jobs.try_remove_group(&schema, &group).await.unwrap();
+            assert!(schema.get_group(group.id).await.unwrap().is_none());
+            assert!(schema.get_group_state(group.id).await.unwrap().is_none());
+
+            let events = root
+                .tail_events(2, &[EventType::Group, EventType::GroupState])
+                .unwrap();
+            assert!(matches!(
+                &events[0].1,
+                RetainedEvent::Delete(DeleteEvent {
+                    event: Some(delete_event::Event::Group(100)),
+                })
+            ));
+            assert!(matches!(
+                &events[1].1,
+                RetainedEvent::Delete(DeleteEvent {
+                    event: Some(delete_event::Event::GroupState(100)),
+                })
+            ));
+        });
+    }
+
+    #[test]
+    fn remove_group_keeps_new_shards() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("remove_group_keeps_new_shards").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+
+            let group = GroupDesc {
+                id: 100,
+                ..Default::default()
+            };
+            schema
+                .update_group_replica(Some(group.to_owned()), None)
+                .await
+                .unwrap();
+
+            // A shard is placed on the group after it was found empty.
+            let placed = GroupDesc {
+                shards: vec![ShardDesc {
+                    id: 100,
+                    ..Default::default()
+                }],
+                ..group.to_owned()
+            };
+            schema
+                .update_group_replica(Some(placed), None)
+                .await
+                .unwrap();
+
+            root.jobs.try_remove_group(&schema, &group).await.unwrap();
+            assert!(!schema.retire_empty_group(&group).await.unwrap());
+            let current = schema.get_group(group.id).await.unwrap().unwrap();
+            assert_eq!(current.shards.len(), 1);
+        });
+    }
+
+    #[test]
+    fn remove_group_rejects_new_shards() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("remove_group_rejects_new_shards").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+
+            let group = GroupDesc {
+                id: 100,
+                epoch: 1,
+                ..Default::default()
+            };
+            schema
+                .update_group_replica(Some(group.to_owned()), None)
+                .await
+                .unwrap();
+
+            // The group is found empty and retired, then a shard is placed on it before its
+            // replicas are removed.
+            assert!(schema.retire_empty_group(&group).await.unwrap());
+            let placed = GroupDesc {
+                epoch: 2,
+                shards: vec![ShardDesc {
+                    id: 100,
+                    ..Default::default()
+                }],
+                ..group.to_owned()
+            };
+            assert!(matches!(
+                schema
+                    .cas_group_replica(Some(1), placed.to_owned(), None)
+                    .await,
+                Err(Error::StaleReport(100))
+            ));
+            assert!(matches!(
+                schema
+                    .cas_group_replica(None, placed.to_owned(), None)
+                    .await,
+                Err(Error::StaleReport(100))
+            ));
+            assert!(matches!(
+                schema.update_group_replica(Some(placed), None).await,
+                Err(Error::StaleReport(100))
+            ));
+            assert!(schema.get_group(group.id).await.unwrap().is_none());
+
+            // The removing goes on, the group is never resurrected.
+            root.jobs.try_remove_group(&schema, &group).await.unwrap();
+            assert!(schema.get_group(group.id).await.unwrap().is_none());
+            let groups = root.alloc.place_group_for_shard(16).await.unwrap();
+            assert!(groups.iter().all(|g| g.id != group.id));
+        });
+    }
+
+    #[test]
+    fn allocate_after_group_deleted() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("allocate_after_group_deleted").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            // The first allocation reloads fully, the following ones catch up incrementally.
+            root.alloc.place_group_for_shard(16).await.unwrap();
+
+            let group = GroupDesc {
+                id: 100,
+                ..Default::default()
+            };
+            schema
+                .update_group_replica(Some(group.to_owned()), None)
+                .await
+                .unwrap();
+            root.watcher_hub()
+                .notify_updates(vec![UpdateEvent {
+                    event: Some(update_event::Event::Group(group.to_owned())),
+                }])
+                .await;
+            let groups = root.alloc.place_group_for_shard(16).await.unwrap();
+            assert!(groups.iter().any(|g| g.id == group.id));
+
+            root.jobs.try_remove_group(&schema, &group).await.unwrap();
+            let groups = root.alloc.place_group_for_shard(16).await.unwrap();
+            assert!(!groups.is_empty());
+            assert!(groups.iter().all(|g| g.id != group.id));
+        });
+    }
+
+    #[test]
+    fn node_leader_counts_by_heartbeat() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("node_leader_counts_by_heartbeat").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        let stats = |leaders| CollectStatsResponse {
+            node_stats: Some(NodeStats {
+                group_count: 4,
+                leader_count: leaders,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let mut nodes = Vec::new();
+            for (port, leaders) in [(1, 3), (2, 1)] {
+                let node = schema
+                    .add_node(NodeDesc {
+                        addr: format!("127.0.0.1:{port}"),
+                        capacity: Some(NodeCapacity::default()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                root.handle_collect_stats(&schema, &stats(leaders), node.id)
+                    .await
+                    .unwrap();
+                nodes.push((node.id, leaders as u64));
+            }
+
+            let counts = root.node_leader_counts().await.unwrap();
+            assert!(counts.iter().any(|(id, _)| *id == FIRST_NODE_ID));
+            for node in &nodes {
+                assert!(counts.contains(node), "{counts:?}");
+            }
+
+            // A leadership is transferred from the first node to the second one.
+            root.handle_collect_stats(&schema, &stats(2), nodes[0].0)
+                .await
+                .unwrap();
+            root.handle_collect_stats(&schema, &stats(2), nodes[1].0)
+                .await
+                .unwrap();
+            let counts = root.node_leader_counts().await.unwrap();
+            assert!(counts.contains(&(nodes[0].0, 2)), "{counts:?}");
+            assert!(counts.contains(&(nodes[1].0, 2)), "{counts:?}");
+        });
+    }
+
+    /// Wait until the background jobs are all finished.
+    async fn wait_jobs_finished(root: &Root) {
+        for _ in 0..100 {
+            if root.schema().unwrap().list_job().await.unwrap().is_empty() {
+                return;
+            }
+            crate::runtime::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("background jobs are not finished");
+    }
+
+    #[test]
+    fn decommission_node_removed() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("decommission_node_removed").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let node = schema
+                .add_node(NodeDesc {
+                    addr: "127.0.0.1:1".into(),
+                    capacity: Some(NodeCapacity::default()),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+
+            // It returns once the decommission is started, and is removed in background.
+            root.decommission_node(node.id).await.unwrap();
+            wait_jobs_finished(&root).await;
+            assert!(schema.get_node(node.id).await.unwrap().is_none());
+            assert!(schema
+                .list_group()
+                .await
+                .unwrap()
+                .iter()
+                .all(|g| g.replicas.iter().all(|r| r.node_id != node.id)));
+
+            let statuses = root
+                .tail_events(8, &[EventType::Node])
+                .unwrap()
+                .into_iter()
+                .filter_map(|(_, e)| match e {
+                    RetainedEvent::Update(UpdateEvent {
+                        event: Some(update_event::Event::Node(n)),
+                    }) if n.id == node.id => NodeStatus::from_i32(n.status),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                statuses,
+                vec![NodeStatus::Cordoned, NodeStatus::Decommissioning]
+            );
+            let removed = root.tail_events(1, &[EventType::Node]).unwrap();
+            assert!(matches!(
+                &removed[0].1,
+                RetainedEvent::Delete(DeleteEvent {
+                    event: Some(delete_event::Event::Node(id)),
+                }) if *id == node.id
+            ));
+
+            // The root leader itself couldn't be decommissioned.
+            assert!(root.decommission_node(FIRST_NODE_ID).await.is_err());
+        });
+    }
+
+    #[test]
+    fn decommission_node_rollback_cordon() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("decommission_node_rollback_cordon").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.decommission_drain_timeout_sec = 1;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let node = schema
+                .add_node(NodeDesc {
+                    addr: "127.0.0.1:1".into(),
+                    capacity: Some(NodeCapacity::default()),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+
+            // The replica couldn't be moved out since nobody serves the group.
+            let group = GroupDesc {
+                id: 100,
+                replicas: vec![ReplicaDesc {
+                    id: 100,
+                    node_id: node.id,
+                    role: ReplicaRole::Voter as i32,
+                }],
+                ..Default::default()
+            };
+            schema
+                .update_group_replica(Some(group), None)
+                .await
+                .unwrap();
+            root.decommission_node(node.id).await.unwrap();
+            assert_eq!(
+                root.node_status(node.id).await.unwrap(),
+                NodeStatus::Decommissioning
+            );
+            // The node is being decommissioned.
+            assert!(matches!(
+                root.decommission_node(node.id).await,
+                Err(Error::InvalidArgument(_))
+            ));
+
+            wait_jobs_finished(&root).await;
+            assert_eq!(root.node_status(node.id).await.unwrap(), NodeStatus::Active);
+        });
+    }
+
+    #[test]
+    fn repair_duplicate_node_addrs() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("repair_duplicate_node_addrs").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            assert!(root.duplicate_node_addrs().await.unwrap().is_empty());
+
+            let mut nodes = vec![];
+            for addr in ["127.0.0.1:1", "127.0.0.1:1", "127.0.0.1:1", "127.0.0.1:2"] {
+                let node = schema
+                    .add_node(NodeDesc {
+                        addr: addr.into(),
+                        capacity: Some(NodeCapacity::default()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                nodes.push(node.id);
+            }
+
+            // The first node hosts a replica, so it is kept.
+            let group = GroupDesc {
+                id: 100,
+                replicas: vec![ReplicaDesc {
+                    id: 100,
+                    node_id: nodes[0],
+                    role: ReplicaRole::Voter as i32,
+                }],
+                ..Default::default()
+            };
+            schema
+                .update_group_replica(Some(group), None)
+                .await
+                .unwrap();
+
+            let duplicates = root.duplicate_node_addrs().await.unwrap();
+            assert_eq!(duplicates.len(), 1);
+            assert_eq!(duplicates[0].addr, "127.0.0.1:1");
+            assert_eq!(duplicates[0].keep, nodes[0]);
+            assert_eq!(duplicates[0].stale, vec![(nodes[1], 0), (nodes[2], 0)]);
+
+            let removed = root.repair_duplicate_node_addrs().await.unwrap();
+            assert_eq!(removed, vec![nodes[1], nodes[2]]);
+            assert!(root.duplicate_node_addrs().await.unwrap().is_empty());
+            assert!(schema.get_node(nodes[0]).await.unwrap().is_some());
+            assert!(schema.get_node(nodes[1]).await.unwrap().is_none());
+            assert!(schema.get_node(nodes[3]).await.unwrap().is_some());
+            let events = root.tail_events(2, &[EventType::Node]).unwrap();
+            assert!(matches!(&events[1].1, RetainedEvent::Delete(DeleteEvent {
+                event: Some(delete_event::Event::Node(id)),
+            }) if *id == nodes[2]));
+        });
+    }
+
+    #[test]
+    fn reject_removing_group_replica() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("reject_removing_group_replica").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let group = schema.get_group(ROOT_GROUP_ID).await.unwrap().unwrap();
+            let replica = group.replicas[0].id;
+
+            // The group has only one voter.
+            let err = root
+                .remove_group_replica(ROOT_GROUP_ID, replica, false)
+                .await;
+            assert!(matches!(err, Err(Error::InvalidArgument(msg)) if msg.contains("below")));
+
+            // Even forced, the leader couldn't be removed without other voters.
+            while !matches!(
+                schema.get_replica_state(ROOT_GROUP_ID, replica).await.unwrap(),
+                Some(state) if state.role == RaftRole::Leader as i32
+            ) {
+                crate::runtime::time::sleep(Duration::from_millis(100)).await;
+            }
+            let err = root
+                .remove_group_replica(ROOT_GROUP_ID, replica, true)
+                .await;
+            assert!(matches!(err, Err(Error::InvalidArgument(msg)) if msg.contains("leader")));
+
+            let err = root
+                .remove_group_replica(ROOT_GROUP_ID, replica + 100, true)
+                .await;
+            assert!(matches!(err, Err(Error::InvalidArgument(msg)) if msg.contains("not found")));
+        });
+    }
+
+    #[test]
+    fn mark_unreachable_node_alive() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("mark_unreachable_node_alive").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let mut watcher = root.watch(Default::default(), None, &[]).await.unwrap();
+            watcher.next().await.unwrap().unwrap();
+
+            root.liveness.mark_dead(FIRST_NODE_ID);
+            assert!(root.liveness.get(&FIRST_NODE_ID).is_dead());
+            root.mark_node_alive(FIRST_NODE_ID).await.unwrap();
+            assert!(!root.liveness.get(&FIRST_NODE_ID).is_dead());
+
+            let resp = watcher.next().await.unwrap().unwrap();
+            assert!(resp.updates.iter().any(|u| matches!(
+                &u.event,
+                Some(update_event::Event::Node(n)) if n.id == FIRST_NODE_ID
+            )));
+
+            // A node which doesn't respond to probe is not allowed to mark alive.
+            let schema = root.schema().unwrap();
+            let node = schema
+                .add_node(NodeDesc {
+                    addr: "127.0.0.1:1".into(),
+                    capacity: Some(NodeCapacity::default()),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            root.liveness.mark_dead(node.id);
+            assert!(root.mark_node_alive(node.id).await.is_err());
+            assert!(root.liveness.get(&node.id).is_dead());
+        });
+    }
+
+    #[test]
+    fn watch_init_carries_cluster_id() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("watch_init_carries_cluster_id").unwrap();
+        let config = leader_config(&tmp_dir);
+        let cluster_id = b"watch-cluster".to_vec();
+
+        let (root, _node) =
+            bootstrap_cluster_root_leader(&config, executor.to_owned(), cluster_id.clone());
+        executor.block_on(async {
+            let mut watcher = root.watch(Default::default(), None, &[]).await.unwrap();
+            let resp = watcher.next().await.unwrap().unwrap();
+            assert_eq!(resp.cluster_id, cluster_id);
+            assert!(!resp.updates.is_empty());
+
+            // The following responses don't carry cluster id.
+            root.create_database("db".into()).await.unwrap();
+            let resp = watcher.next().await.unwrap().unwrap();
+            assert!(resp.cluster_id.is_empty());
+        });
+    }
+
+    #[test]
+    fn consistent_cluster_id() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("consistent_cluster_id").unwrap();
+        let config = leader_config(&tmp_dir);
+        let cluster_id = b"consistent-cluster".to_vec();
+        let ident = NodeIdent {
+            cluster_id: cluster_id.clone(),
+            node_id: FIRST_NODE_ID,
+        };
+
+        let (root, node) = create_root_and_node(&config, executor.to_owned(), &ident);
+        executor.block_on(async {
+            bootstrap_cluster(&node, &config.addr).await.unwrap();
+            node.bootstrap(&ident).await.unwrap();
+            let root_replica = super::fetch_root_replica(node.replica_table()).await;
+            root_replica.on_leader("test", false).await.unwrap();
+
+            // Serve a schema which is not bootstrapped yet.
+            let store = Arc::new(super::store::RootStore::new(root_replica));
+            let mut schema = Schema::new(store, false);
+            root.shared.set_core(Some(Arc::new(schema.clone())));
+            let capacity = NodeCapacity {
+                cpu_nums: 1.0,
+                ..Default::default()
+            };
+            assert!(matches!(
+                root.cluster_id().await,
+                Err(Error::NotBootstrapped)
+            ));
+            assert!(matches!(
+                root.join("127.0.0.1:1".into(), capacity.clone()).await,
+                Err(Error::NotBootstrapped)
+            ));
+            assert!(matches!(
+                root.watch(Default::default(), None, &[]).await,
+                Err(Error::NotBootstrapped)
+            ));
+
+            schema
+                .try_bootstrap_root(&config.addr, 1, cluster_id.clone())
+                .await
+                .unwrap();
+            for port in 1..3 {
+                assert_eq!(root.cluster_id().await.unwrap(), cluster_id);
+                let (joined_cluster_id, _, _) = root
+                    .join(format!("127.0.0.1:{port}"), capacity.clone())
+                    .await
+                    .unwrap();
+                assert_eq!(joined_cluster_id, cluster_id);
+                let mut watcher = root.watch(Default::default(), None, &[]).await.unwrap();
+                let resp = watcher.next().await.unwrap().unwrap();
+                assert_eq!(resp.cluster_id, cluster_id);
+            }
+        });
+    }
+
+    #[test]
+    fn watch_snapshot_exceeds_limit() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("watch_snapshot_exceeds_limit").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.watch_init_max_events = 2;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            // The bootstrapped metadata has more than two events, eg. nodes, groups and states.
+            let err = root.watch(Default::default(), None, &[]).await.unwrap_err();
+            assert!(
+                matches!(err, Error::WatchSnapshotTooLarge(num_events, 2) if num_events > 2),
+                "{err:?}"
+            );
+            // The snapshot is aborted before all metadata is listed, eg. groups and states.
+            let (updates, deletes) = root
+                .schema()
+                .unwrap()
+                .list_all_events(Default::default(), None)
+                .await
+                .unwrap();
+            let total = updates.len() + deletes.len();
+            assert!(
+                matches!(err, Error::WatchSnapshotTooLarge(num_events, _) if num_events < total),
+                "{err:?}"
+            );
+
+            // The group filtered watch isn't limited.
+            let mut watcher = root
+                .watch(Default::default(), Some(INIT_USER_GROUP_ID), &[])
+                .await
+                .unwrap();
+            let resp = watcher.next().await.unwrap().unwrap();
+            assert!(!resp.updates.is_empty());
+        });
+    }
+
+    #[test]
+    fn group_scoped_watch() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("group_scoped_watch").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let group = schema.get_group(INIT_USER_GROUP_ID).await.unwrap().unwrap();
+            let mut watcher = root
+                .watch(Default::default(), Some(INIT_USER_GROUP_ID), &[])
+                .await
+                .unwrap();
+
+            // The initial snapshot contains only the group and the nodes hosting its replicas.
+            let is_scoped = |update: &UpdateEvent| match update.event.as_ref().unwrap() {
+                update_event::Event::Group(desc) => desc.id == INIT_USER_GROUP_ID,
+                update_event::Event::GroupState(state) => state.group_id == INIT_USER_GROUP_ID,
+                update_event::Event::Node(desc) => desc.id == FIRST_NODE_ID,
+                _ => false,
+            };
+            let resp = watcher.next().await.unwrap().unwrap();
+            assert!(!resp.updates.is_empty());
+            assert!(resp.updates.iter().all(is_scoped));
+
+            // The events of the unrelated group and objects are not delivered.
+            let hub = root.watcher_hub();
+            hub.notify_updates(vec![
+                UpdateEvent {
+                    event: Some(update_event::Event::Group(GroupDesc {
+                        id: ROOT_GROUP_ID,
+                        ..Default::default()
+                    })),
+                },
+                UpdateEvent {
+                    event: Some(update_event::Event::Node(NodeDesc {
+                        id: FIRST_NODE_ID + 100,
+                        ..Default::default()
+                    })),
+                },
+            ])
+            .await;
+            root.create_database("db".into()).await.unwrap();
+            hub.notify_updates(vec![UpdateEvent {
+                event: Some(update_event::Event::Group(GroupDesc {
+                    epoch: group.epoch + 1,
+                    ..group.clone()
+                })),
+            }])
+            .await;
+            loop {
+                let resp = watcher.next().await.unwrap().unwrap();
+                assert!(resp.updates.iter().all(is_scoped));
+                if resp.updates.iter().any(|u| {
+                    matches!(&u.event, Some(update_event::Event::Group(desc)) if desc.epoch > group.epoch)
+                }) {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Add a node which is not served by anyone, and schedule its heartbeat at `when`.
+    async fn add_peer_node(root: &Root, when: Instant) -> u64 {
+        let schema = root.schema().unwrap();
+        let node = schema
+            .add_node(NodeDesc {
+                addr: "127.0.0.1:1".into(),
+                capacity: Some(NodeCapacity::default()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        root.heartbeat_queue
+            .try_schedule(vec![HeartbeatTask { node_id: node.id }], when)
+            .await;
+        node.id
+    }
+
+    #[test]
+    fn step_down_on_consecutive_heartbeat_failures() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("step_down_on_consecutive_heartbeat_failures").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        // Send heartbeat every second.
+        config.root.liveness_threshold_sec = 5;
+        config.root.heartbeat_timeout_sec = 4;
+        config.root.heartbeat_failures_before_step_down = 2;
+        config.root.testing_knobs.inject_heartbeat_failure = true;
+
+        let before = super::metrics::HEARTBEAT_STEP_DOWN_TOTAL.get();
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            // The failures of the local node alone don't step down the root.
+            crate::runtime::time::sleep(Duration::from_secs(3)).await;
+            assert!(root.schema().is_ok());
+
+            let now = root.shared.clock.now();
+            add_peer_node(&root, now).await;
+            for _ in 0..100 {
+                if root.schema().is_err() {
+                    assert!(super::metrics::HEARTBEAT_STEP_DOWN_TOTAL.get() > before);
+                    return;
+                }
+                crate::runtime::time::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("root doesn't step down after heartbeats fail");
+        });
+    }
+
+    #[test]
+    fn core_exposed_after_warmup_read() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("core_exposed_after_warmup_read").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.run_retry_initial_backoff_ms = 50;
+        let injected = config
+            .root
+            .testing_knobs
+            .inject_warmup_read_failures
+            .clone();
+        injected.store(3, Ordering::Release);
+
+        let ident = NodeIdent {
+            cluster_id: vec![],
+            node_id: FIRST_NODE_ID,
+        };
+        let (root, node) = create_root_and_node(&config, executor.to_owned(), &ident);
+        executor.block_on(async {
+            bootstrap_cluster(&node, &config.addr).await.unwrap();
+            node.bootstrap(&ident).await.unwrap();
+            root.bootstrap(&node).await.unwrap();
+            for _ in 0..500 {
+                if let Ok(schema) = root.schema() {
+                    // The core is only exposed once the warmup read succeeds.
+                    assert_eq!(injected.load(Ordering::Acquire), 0);
+                    schema.cluster_id().await.unwrap();
+                    return;
+                }
+                crate::runtime::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("root doesn't step leader after the warmup read succeeds");
+        });
+    }
+
+    #[test]
+    fn teardown_after_failed_step() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("teardown_after_failed_step").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.run_retry_initial_backoff_ms = 3000;
+        config.root.run_retry_max_backoff_ms = 3000;
+        let injected = config.root.testing_knobs.inject_lead_failures.clone();
+        injected.store(1, Ordering::Release);
+
+        let ident = NodeIdent {
+            cluster_id: vec![],
+            node_id: FIRST_NODE_ID,
+        };
+        let (root, node) = create_root_and_node(&config, executor.to_owned(), &ident);
+        executor.block_on(async {
+            bootstrap_cluster(&node, &config.addr).await.unwrap();
+            node.bootstrap(&ident).await.unwrap();
+            root.bootstrap(&node).await.unwrap();
+            while injected.load(Ordering::Acquire) > 0 {
+                crate::runtime::time::sleep(Duration::from_millis(10)).await;
+            }
+            crate::runtime::time::sleep(Duration::from_millis(500)).await;
+
+            // The failed step is torn down during the backoff instead of leaving a zombie leader.
+            assert!(root.schema().is_err());
+            assert!(!root.heartbeat_queue.core.lock().await.enable);
+
+            for _ in 0..500 {
+                if root.schema().is_ok() {
+                    assert!(root.heartbeat_queue.core.lock().await.enable);
+                    return;
+                }
+                crate::runtime::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("root doesn't step leader after the backoff");
+        });
+    }
+
+    #[test]
+    fn stale_generation_after_teardown() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("stale_generation_after_teardown").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let (schema, generation) = root.schema_with_generation().unwrap();
+            root.check_generation(generation).unwrap();
+
+            // Tear down the core while the operations are holding the schema.
+            let barrier = Arc::new(tokio::sync::Barrier::new(5));
+            let mut handles = Vec::new();
+            for _ in 0..4 {
+                let root = root.clone();
+                let barrier = barrier.clone();
+                handles.push(executor.spawn(None, TaskPriority::Middle, async move {
+                    let (schema, generation) = root.schema_with_generation().unwrap();
+                    barrier.wait().await;
+                    while root.check_generation(generation).is_ok() {
+                        crate::runtime::time::sleep(Duration::from_millis(1)).await;
+                    }
+                    // The captured schema is still readable, only the generation tells it is
+                    // stale.
+                    schema.list_database().await.unwrap();
+                    root.check_generation(generation).unwrap_err()
+                }));
+            }
+            barrier.wait().await;
+            root.shared.set_core(None);
+            for err in futures::future::join_all(handles).await {
+                assert!(matches!(err, Error::NotRootLeader(..)));
+            }
+            assert!(root.schema_with_generation().is_err());
+
+            // The generation of the previous leadership is stale even after stepping leader
+            // again.
+            root.shared.set_core(Some(schema));
+            let (_, new_generation) = root.schema_with_generation().unwrap();
+            assert!(new_generation > generation);
+            root.check_generation(new_generation).unwrap();
+            assert!(matches!(
+                root.check_generation(generation),
+                Err(Error::NotRootLeader(..))
+            ));
+        });
+    }
+
+    #[test]
+    fn heartbeat_ticks_by_clock() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("heartbeat_ticks_by_clock").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        // Every tick of heartbeat is observed as a failure.
+        config.root.heartbeat_failures_before_step_down = 0;
+        config.root.testing_knobs.inject_heartbeat_failure = true;
+
+        let clock = Arc::new(ManualClock::new());
+        let (root, _node) =
+            bootstrap_root_leader_with_clock(&config, executor.to_owned(), vec![], clock.clone());
+        executor.block_on(async {
+            let failures = || root.heartbeat_failures.load(Ordering::Acquire);
+            add_peer_node(&root, clock.now() + Duration::from_secs(1)).await;
+
+            // The heartbeats are scheduled once the root becomes leader, but no heartbeat is
+            // sent before the clock is advanced.
+            crate::runtime::time::sleep(Duration::from_millis(300)).await;
+            assert_eq!(failures(), 0);
+
+            clock.advance(Duration::from_secs(1));
+            for _ in 0..100 {
+                if failures() > 0 {
+                    return;
+                }
+                crate::runtime::time::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("no heartbeat is sent after the clock is advanced");
+        });
+    }
+
+    #[test]
+    fn heartbeat_breaker_skips_failed_node() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("heartbeat_breaker_skips_failed_node").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.heartbeat_failures_before_step_down = 0;
+        config.root.heartbeat_breaker_failure_threshold = 2;
+        config.root.heartbeat_breaker_cooldown_sec = 10;
+        config.root.testing_knobs.inject_heartbeat_failure = true;
+
+        // The clock is only advanced after the breaker opens, so the heartbeats are sent by the
+        // test before that.
+        let clock = Arc::new(ManualClock::new());
+        let (root, _node) =
+            bootstrap_root_leader_with_clock(&config, executor.to_owned(), vec![], clock.clone());
+        executor.block_on(async {
+            let schema = Arc::new(root.schema().unwrap());
+            let failures = || root.heartbeat_failures.load(Ordering::Acquire);
+
+            // The failures of the local node are not counted.
+            let tasks = [HeartbeatTask {
+                node_id: FIRST_NODE_ID,
+            }];
+            root.send_heartbeat(schema.clone(), &tasks).await.unwrap();
+            assert_eq!(failures(), 0);
+
+            // The node is scheduled after the cooldown, so it is only sent by the test.
+            let peer = add_peer_node(&root, clock.now() + Duration::from_secs(10)).await;
+            let tasks = [HeartbeatTask { node_id: peer }];
+
+            for _ in 0..2 {
+                root.send_heartbeat(schema.clone(), &tasks).await.unwrap();
+            }
+            assert_eq!(failures(), 2);
+            assert_eq!(root.heartbeat_breaker.state(peer), BreakerState::Open);
+
+            // The node is skipped during the cooldown.
+            root.send_heartbeat(schema.clone(), &tasks).await.unwrap();
+            assert_eq!(failures(), 2);
+
+            // And it is probed again once the cooldown is passed.
+            clock.advance(Duration::from_secs(10));
+            root.send_heartbeat(schema.clone(), &tasks).await.unwrap();
+            assert!(failures() > 2);
+            assert_eq!(root.heartbeat_breaker.state(peer), BreakerState::Open);
+        });
+    }
+
+    #[test]
+    fn unreachable_node_notified() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("unreachable_node_notified").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.unreachable_grace_period_sec = 20;
+
+        // The clock is never advanced, so the heartbeats are not sent in background.
+        let clock = Arc::new(ManualClock::new());
+        let (root, _node) =
+            bootstrap_root_leader_with_clock(&config, executor.to_owned(), vec![], clock);
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let node = schema
+                .add_node(NodeDesc {
+                    addr: "127.0.0.1:1".into(),
+                    capacity: Some(NodeCapacity::default()),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+
+            root.liveness.mark_dead(node.id);
+            root.refresh_reachability(&schema, &[node.id])
+                .await
+                .unwrap();
+            assert!(is_unreachable_notified(&root, &schema, node.id).await);
+
+            root.liveness.renew(node.id);
+            root.refresh_reachability(&schema, &[node.id])
+                .await
+                .unwrap();
+            assert!(!is_unreachable_notified(&root, &schema, node.id).await);
+        });
+
+        async fn is_unreachable_notified(root: &Root, schema: &Schema, node_id: u64) -> bool {
+            let node = schema.get_node(node_id).await.unwrap().unwrap();
+            let notified = root.tail_events(1, &[EventType::Node]).unwrap();
+            assert!(matches!(
+                &notified[0].1,
+                RetainedEvent::Update(UpdateEvent {
+                    event: Some(update_event::Event::Node(n)),
+                }) if n.id == node.id && n.unreachable == node.unreachable
+            ));
+            node.unreachable
+        }
+    }
+
+    #[test]
+    fn bootstrap_step_timers_recorded() {
+        use super::metrics::BOOTSTRAP_STEP_DURATION_SECONDS as STEPS;
+
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("bootstrap_step_timers_recorded").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (_root, _node) = bootstrap_root_leader(&config, executor);
+        for (step, histogram) in [
+            ("open_engine", &STEPS.open_engine),
+            ("create_groups", &STEPS.create_groups),
+            ("update_root", &STEPS.update_root),
+            ("save_ident", &STEPS.save_ident),
+            ("init_root_meta", &STEPS.init_root_meta),
+        ] {
+            assert!(histogram.get_sample_count() > 0, "step {step}");
+            assert!(histogram.get_sample_sum() > 0.0, "step {step}");
+        }
+    }
+
+    #[test]
+    fn group_route_reflects_leader_epoch() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("group_route_reflects_leader_epoch").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let group_id = INIT_USER_GROUP_ID;
+            let leader_state = |replica_id: u64, term: u64| ReplicaState {
+                replica_id,
+                group_id,
+                term,
+                voted_for: replica_id,
+                role: RaftRole::Leader as i32,
+                node_id: FIRST_NODE_ID,
+                applied_index: 0,
+            };
+
+            schema
+                .update_group_replica(None, Some(leader_state(100, 5)))
+                .await
+                .unwrap();
+            schema
+                .update_group_replica(None, Some(leader_state(INIT_USER_REPLICA_ID, 4)))
+                .await
+                .unwrap();
+            // The deposed leader with smaller term is ignored.
+            let (group, state) = root.group_route(group_id).await.unwrap();
+            assert_eq!(group.id, group_id);
+            let state = state.unwrap();
+            assert_eq!(state.leader_id, Some(100));
+            assert_eq!(state.leader_epoch, 5);
+
+            // The leadership changes, the epoch is bumped.
+            schema
+                .update_group_replica(None, Some(leader_state(INIT_USER_REPLICA_ID, 6)))
+                .await
+                .unwrap();
+            let (_, state) = root.group_route(group_id).await.unwrap();
+            let state = state.unwrap();
+            assert_eq!(state.leader_id, Some(INIT_USER_REPLICA_ID));
+            assert_eq!(state.leader_epoch, 6);
+            let states = schema.list_group_state().await.unwrap();
+            let listed = states.iter().find(|s| s.group_id == group_id).unwrap();
+            assert_eq!(listed.leader_epoch, 6);
+
+            assert!(root.group_route(12345).await.is_err());
+        });
+    }
+
+    #[test]
+    fn reject_removing_root_replica_breaks_quorum() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("reject_removing_root_replica_breaks_quorum").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            // The only voter can't be removed.
+            assert!(root.remove_root_replica(FIRST_REPLICA_ID).await.is_err());
+            assert!(root.remove_root_replica(12345).await.is_err());
+
+            // Pretend the root group has two more voters which are dead.
+            let schema = root.schema().unwrap();
+            let mut group = schema.get_group(ROOT_GROUP_ID).await.unwrap().unwrap();
+            for (replica_id, node_id) in [(100, 10), (101, 11)] {
+                group.replicas.push(ReplicaDesc {
+                    id: replica_id,
+                    node_id,
+                    role: ReplicaRole::Voter as i32,
+                });
+                root.liveness.mark_dead(node_id);
+            }
+            schema
+                .update_group_replica(Some(group), None)
+                .await
+                .unwrap();
+
+            // Only one of the remaining voters is alive.
+            let err = root.remove_root_replica(100).await.unwrap_err();
+            assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("quorum")));
+            // None of the remaining voters is alive.
+            let err = root
+                .remove_root_replica(FIRST_REPLICA_ID)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("quorum")));
+        });
+    }
+
+    #[test]
+    fn concurrent_delete_collection() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("concurrent_delete_collection").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let (db, _) = root.create_database("db".into()).await.unwrap();
+            let desc = schema
+                .prepare_create_collection(CollectionDesc {
+                    name: "c".into(),
+                    db: db.id,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let collection = schema.create_collection(desc).await.unwrap();
+
+            let hub = root.watcher_hub();
+            let seq = hub.last_sequence();
+            let (first, second) = futures::join!(
+                root.delete_collection("c", &db),
+                root.delete_collection("c", &db)
+            );
+            assert!(first.is_ok());
+            assert!(second.is_ok());
+            assert!(schema
+                .get_collection_exact(db.id, "c")
+                .await
+                .unwrap()
+                .is_none());
+
+            let (_, deletes) = hub.events_since(seq).unwrap();
+            let num_deletes = deletes
+                .iter()
+                .filter(|e| {
+                    matches!(e.event, Some(delete_event::Event::Collection(id)) if id == collection.id)
+                })
+                .count();
+            assert_eq!(num_deletes, 1);
+
+            // Delete the deleted database twice.
+            root.delete_database("db").await.unwrap();
+            root.delete_database("db").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn list_pages_with_signed_tokens() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("list_pages_with_signed_tokens").unwrap();
+        let config = leader_config(&tmp_dir);
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            for name in ["a", "b", "c"] {
+                root.create_database(name.into()).await.unwrap();
+            }
+            let num_databases = root.list_database().await.unwrap().len();
+
+            let mut listed = vec![];
+            let mut page_token = None;
+            loop {
+                let (page, next) = root
+                    .list_database_page(page_token.as_deref(), 1)
+                    .await
+                    .unwrap();
+                listed.extend(page.into_iter().map(|db| db.id));
+                page_token = match next {
+                    Some(next) => Some(next),
+                    None => break,
+                };
+            }
+            assert_eq!(listed.len(), num_databases);
+
+            // The fences are allocated in batch, the following listings don't write the schema.
+            let schema = root.schema().unwrap();
+            let fence = schema.page_fence().await.unwrap();
+            for _ in 0..16 {
+                root.list_database_page(None, 1).await.unwrap();
+            }
+            assert_eq!(schema.page_fence().await.unwrap(), fence);
+
+            assert!(matches!(
+                root.list_database_page(None, 0).await,
+                Err(Error::InvalidArgument(msg)) if msg.contains("zero")
+            ));
+
+            // The tokens signed by other keys or with unallocated fences are rejected.
+            let forged = PageToken::new(PageObject::Database, 1, 1).encode(b"forged key");
+            assert!(root.list_database_page(Some(&forged), 1).await.is_err());
+            let key = root.schema().unwrap().page_token_key().await.unwrap();
+            let ahead = PageToken::new(PageObject::Database, 1, u64::MAX).encode(&key);
+            assert!(matches!(
+                root.list_database_page(Some(&ahead), 1).await,
+                Err(Error::InvalidArgument(msg)) if msg.contains("fence")
+            ));
+        });
+    }
+
+    #[test]
+    fn drain_status_follows_leader_moves() {
+        let executor_owner = ExecutorOwner::new(1);
+        let executor = executor_owner.executor();
+        let tmp_dir = TempDir::new("drain_status_follows_leader_moves").unwrap();
+        let mut config = leader_config(&tmp_dir);
+        config.root.enable_group_balance = false;
+        config.root.enable_replica_balance = false;
+        config.root.enable_shard_balance = false;
+        config.root.enable_leader_balance = false;
+        config.root.heartbeat_failures_before_step_down = 0;
+
+        let (root, _node) = bootstrap_root_leader(&config, executor.to_owned());
+        executor.block_on(async {
+            let schema = root.schema().unwrap();
+            let mut nodes = vec![];
+            for port in [1, 2] {
+                let node = schema
+                    .add_node(NodeDesc {
+                        addr: format!("127.0.0.1:{port}"),
+                        capacity: Some(NodeCapacity::default()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                nodes.push(node.id);
+            }
+            let (draining, target) = (nodes[0], nodes[1]);
+
+            // The leader of a group is on the draining node, and the other voter is on the active
+            // node.
+            let replica = |id, node_id| ReplicaDesc {
+                id,
+                node_id,
+                role: ReplicaRole::Voter as i32,
+            };
+            let state = |replica_id, node_id, term, role: RaftRole| ReplicaState {
+                group_id: 100,
+                replica_id,
+                node_id,
+                term,
+                role: role as i32,
+                ..Default::default()
+            };
+            schema
+                .update_group_replica(
+                    Some(GroupDesc {
+                        id: 100,
+                        epoch: 1,
+                        replicas: vec![replica(101, draining), replica(102, target)],
+                        ..Default::default()
+                    }),
+                    Some(state(101, draining, 1, RaftRole::Leader)),
+                )
+                .await
+                .unwrap();
+
+            root.cordon_node(draining).await.unwrap();
+            root.begin_drain(draining, None).await.unwrap();
+
+            // The group is not served by any node, so the move fails and it is retried.
+            let failed = loop {
+                let replicas = root.drain_status(draining).await.unwrap();
+                match replicas.iter().find(|r| r.replica_id == 101) {
+                    Some(r) if r.phase == MovePhase::Failed => break r.clone(),
+                    _ => crate::runtime::time::sleep(Duration::from_millis(100)).await,
+                }
+            };
+            assert_eq!(failed.group_id, 100);
+            assert_eq!(failed.target_node, Some(target));
+            assert!(failed.error.is_some());
+
+            // The leadership is moved out.
+            schema
+                .update_group_replica(None, Some(state(101, draining, 2, RaftRole::Follower)))
+                .await
+                .unwrap();
+            schema
+                .update_group_replica(None, Some(state(102, target, 2, RaftRole::Leader)))
+                .await
+                .unwrap();
+            loop {
+                let replicas = root.drain_status(draining).await.unwrap();
+                if replicas
+                    .iter()
+                    .any(|r| r.replica_id == 101 && r.phase == MovePhase::Done)
+                {
+                    break;
+                }
+                crate::runtime::time::sleep(Duration::from_millis(100)).await;
+            }
+            let replicas = root.drain_status(draining).await.unwrap();
+            assert_eq!(replicas.len(), 1);
+            assert_eq!(replicas[0].error, None);
+            while schema.get_node(draining).await.unwrap().unwrap().status
+                != NodeStatus::Drained as i32
+            {
+                crate::runtime::time::sleep(Duration::from_millis(100)).await;
+            }
+        });
+    }
+
+    #[test]
+    fn replica_moves_in_flight() {
+        let replica = |id, node_id| ReplicaDesc {
+            id,
+            node_id,
+            role: ReplicaRole::Voter as i32,
+        };
+        let stats = OngoingStats::default();
+        stats.handle_update(
+            &(0..4)
+                .map(|group_id| ScheduleState {
+                    group_id,
+                    epoch: 1,
+                    incoming_replicas: vec![replica(group_id * 10 + 1, 2)],
+                    outgoing_replicas: vec![replica(group_id * 10, 1)],
+                })
+                .collect::<Vec<_>>(),
+            None,
+        );
+        assert_eq!(stats.moves_out_of(1), 4);
+        assert_eq!(stats.moves_out_of(2), 0);
+
+        // The finished moves are no longer counted.
+        stats.handle_update(
+            &[ScheduleState {
+                group_id: 0,
+                epoch: 2,
+                ..Default::default()
+            }],
+            None,
+        );
+        assert_eq!(stats.moves_out_of(1), 3);
+    }
+}
+
+pub mod diagnosis {
+    use serde::{Deserialize, Serialize};
+
+    /// The version of the metadata format, it is bumped once the format is changed incompatibly.
+    pub const METADATA_VERSION: u32 = 1;
 
     #[derive(Serialize, Deserialize)]
     pub struct Metadata {
+        /// Zero if the metadata is dumped before it is versioned.
+        #[serde(default)]
+        pub version: u32,
         pub databases: Vec<Database>,
         pub nodes: Vec<Node>,
         pub groups: Vec<Group>,
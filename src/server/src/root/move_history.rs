@@ -0,0 +1,135 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveReason {
+    /// Balance the replica count of nodes.
+    Rebalance,
+    /// Move out of a draining or decommissioning node.
+    Drain,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveOutcome {
+    Succeeded,
+    /// The group is changed by others during the move, eg. the replica is already moved.
+    Aborted,
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MoveRecord {
+    pub id: u64,
+    pub group_id: u64,
+    pub replica_id: u64,
+    pub from_node: u64,
+    pub to_node: u64,
+    pub reason: MoveReason,
+    pub outcome: MoveOutcome,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// The history of the replica moves executed by the reconcile scheduler, only the latest
+/// `capacity` records are retained.
+#[derive(Clone)]
+pub struct MoveHistory {
+    inner: Arc<Mutex<MoveHistoryInner>>,
+}
+
+struct MoveHistoryInner {
+    capacity: usize,
+    next_id: u64,
+    records: VecDeque<MoveRecord>,
+}
+
+impl MoveHistory {
+    pub fn new(capacity: usize) -> Self {
+        MoveHistory {
+            inner: Arc::new(Mutex::new(MoveHistoryInner {
+                capacity,
+                next_id: 1,
+                records: VecDeque::default(),
+            })),
+        }
+    }
+
+    /// Record a finished move, the id of the record is reassigned in order of finishing.
+    pub fn record(&self, mut record: MoveRecord) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.capacity == 0 {
+            return;
+        }
+        record.id = inner.next_id;
+        inner.next_id += 1;
+        while inner.records.len() >= inner.capacity {
+            inner.records.pop_front();
+        }
+        inner.records.push_back(record);
+    }
+
+    /// Return the retained records, ordered by id.
+    pub fn records(&self) -> Vec<MoveRecord> {
+        let inner = self.inner.lock().unwrap();
+        inner.records.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(group_id: u64, outcome: MoveOutcome, error: Option<&str>) -> MoveRecord {
+        MoveRecord {
+            id: 0,
+            group_id,
+            replica_id: group_id * 10,
+            from_node: 1,
+            to_node: 2,
+            reason: MoveReason::Rebalance,
+            outcome,
+            error: error.map(ToOwned::to_owned),
+            duration_ms: 5,
+        }
+    }
+
+    #[test]
+    fn bounded_history() {
+        let history = MoveHistory::new(2);
+        for group_id in 1..=3 {
+            history.record(record(group_id, MoveOutcome::Succeeded, None));
+        }
+        let records = history.records();
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| (r.id, r.group_id))
+                .collect::<Vec<_>>(),
+            vec![(2, 2), (3, 3)]
+        );
+
+        let disabled = MoveHistory::new(0);
+        disabled.record(record(1, MoveOutcome::Failed, Some("timeout")));
+        assert!(disabled.records().is_empty());
+    }
+}
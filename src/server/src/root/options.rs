@@ -0,0 +1,279 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use engula_api::{
+    server::v1::CollectionOptionsChange,
+    v1::{create_collection_request as co_req, AclEntry, CollectionDesc, Permission},
+};
+
+use super::acl;
+use crate::{Error, Result};
+
+/// The validated options to create a collection, see [`CreateCollectionOptionsBuilder`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CreateCollectionOptions {
+    /// The partition of collection, the default options of database is used if it is `None`.
+    pub(crate) partition: Option<co_req::Partition>,
+    /// The access control entries, from principal to the permissions granted to it.
+    pub(crate) acl: HashMap<String, AclEntry>,
+    pub(crate) read_only: bool,
+}
+
+impl CreateCollectionOptions {
+    pub fn builder() -> CreateCollectionOptionsBuilder {
+        CreateCollectionOptionsBuilder::default()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CreateCollectionOptionsBuilder {
+    hash_slots: Option<u32>,
+    range: bool,
+    acl: HashMap<String, AclEntry>,
+    read_only: bool,
+}
+
+impl CreateCollectionOptionsBuilder {
+    /// Partition the collection by hash of keys into `slots` shards.
+    pub fn hash(&mut self, slots: u32) -> &mut Self {
+        self.hash_slots = Some(slots);
+        self
+    }
+
+    /// Partition the collection by the range of keys.
+    pub fn range(&mut self) -> &mut Self {
+        self.range = true;
+        self
+    }
+
+    pub fn partition(&mut self, partition: Option<co_req::Partition>) -> &mut Self {
+        match partition {
+            Some(co_req::Partition::Hash(hash)) => self.hash(hash.slots),
+            Some(co_req::Partition::Range(_)) => self.range(),
+            None => self,
+        }
+    }
+
+    /// Grant the permissions to the principal, the former permissions of it are replaced.
+    pub fn grant(&mut self, principal: impl Into<String>, permissions: &[Permission]) -> &mut Self {
+        let entry = AclEntry {
+            permissions: permissions.iter().map(|p| *p as i32).collect(),
+        };
+        self.acl.insert(principal.into(), entry);
+        self
+    }
+
+    pub fn acl(&mut self, acl: HashMap<String, AclEntry>) -> &mut Self {
+        self.acl.extend(acl);
+        self
+    }
+
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn build(&self) -> Result<CreateCollectionOptions> {
+        let partition = match (self.hash_slots, self.range) {
+            (Some(_), true) => {
+                return Err(Error::InvalidArgument(
+                    "hash and range partition are exclusive".into(),
+                ))
+            }
+            (Some(0), false) => {
+                return Err(Error::InvalidArgument(
+                    "the slots of hash partition must be positive".into(),
+                ))
+            }
+            (Some(slots), false) => Some(co_req::Partition::Hash(co_req::HashPartition { slots })),
+            (None, true) => Some(co_req::Partition::Range(co_req::RangePartition {})),
+            (None, false) => None,
+        };
+        let mut entries = HashMap::with_capacity(self.acl.len());
+        for (principal, entry) in &self.acl {
+            acl::validate_principal(principal)?;
+            let mut permissions = entry.permissions.clone();
+            if permissions.is_empty() {
+                return Err(Error::InvalidArgument(format!(
+                    "the permissions of principal {principal} is empty"
+                )));
+            }
+            if let Some(p) = permissions
+                .iter()
+                .find(|p| Permission::from_i32(**p).is_none())
+            {
+                return Err(Error::InvalidArgument(format!("unknown permission {p}")));
+            }
+            permissions.sort_unstable();
+            permissions.dedup();
+            entries.insert(principal.to_owned(), AclEntry { permissions });
+        }
+        Ok(CreateCollectionOptions {
+            partition,
+            acl: entries,
+            read_only: self.read_only,
+        })
+    }
+}
+
+/// Compare the options of a collection before and after an update, returns `None` if nothing
+/// but the name is changed.
+pub(crate) fn diff_collection_options(
+    previous: &CollectionDesc,
+    current: &CollectionDesc,
+) -> Option<CollectionOptionsChange> {
+    let mut fields = vec![];
+    if previous.partition != current.partition {
+        fields.push("partition".to_owned());
+    }
+    if previous.acl != current.acl {
+        fields.push("acl".to_owned());
+    }
+    if previous.read_only != current.read_only {
+        fields.push("read_only".to_owned());
+    }
+    if fields.is_empty() {
+        return None;
+    }
+    Some(CollectionOptionsChange {
+        collection_id: current.id,
+        fields,
+        previous: Some(previous.clone()),
+        current: Some(current.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_valid_options() {
+        let opts = CreateCollectionOptions::builder().build().unwrap();
+        assert_eq!(opts.partition, None);
+
+        let opts = CreateCollectionOptions::builder().hash(8).build().unwrap();
+        assert_eq!(
+            opts.partition,
+            Some(co_req::Partition::Hash(co_req::HashPartition { slots: 8 }))
+        );
+
+        let opts = CreateCollectionOptions::builder().range().build().unwrap();
+        assert_eq!(
+            opts.partition,
+            Some(co_req::Partition::Range(co_req::RangePartition {}))
+        );
+
+        // Construct from the partition of request.
+        let partition = Some(co_req::Partition::Hash(co_req::HashPartition { slots: 2 }));
+        let opts = CreateCollectionOptions::builder()
+            .partition(partition.clone())
+            .build()
+            .unwrap();
+        assert_eq!(opts.partition, partition);
+
+        let opts = CreateCollectionOptions::builder()
+            .range()
+            .grant("user:alice", &[Permission::Write, Permission::Read])
+            .read_only(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            opts.acl["user:alice"].permissions().collect::<Vec<_>>(),
+            vec![Permission::Read, Permission::Write]
+        );
+        assert!(opts.read_only);
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        let err = CreateCollectionOptions::builder()
+            .hash(0)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(_)));
+
+        let err = CreateCollectionOptions::builder()
+            .hash(8)
+            .range()
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(_)));
+
+        let err = CreateCollectionOptions::builder()
+            .range()
+            .partition(Some(co_req::Partition::Hash(co_req::HashPartition {
+                slots: 1,
+            })))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(_)));
+
+        let err = CreateCollectionOptions::builder()
+            .grant("alice", &[Permission::Read])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("principal")));
+
+        let err = CreateCollectionOptions::builder()
+            .grant("user:alice", &[])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("empty")));
+
+        let err = CreateCollectionOptions::builder()
+            .acl(HashMap::from([(
+                "user:alice".to_owned(),
+                AclEntry {
+                    permissions: vec![42],
+                },
+            )]))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("permission")));
+    }
+
+    #[test]
+    fn diff_options() {
+        let previous = CollectionDesc {
+            id: 1,
+            name: "co".to_owned(),
+            ..Default::default()
+        };
+        let renamed = CollectionDesc {
+            name: "co2".to_owned(),
+            ..previous.clone()
+        };
+        assert!(diff_collection_options(&previous, &renamed).is_none());
+
+        let mut current = previous.clone();
+        current
+            .acl
+            .insert("user:alice".to_owned(), Default::default());
+        let change = diff_collection_options(&previous, &current).unwrap();
+        assert_eq!(change.collection_id, 1);
+        assert_eq!(change.fields, vec!["acl".to_owned()]);
+        assert_eq!(change.previous, Some(previous.clone()));
+        assert_eq!(change.current, Some(current.clone()));
+
+        current.read_only = true;
+        let change = diff_collection_options(&previous, &current).unwrap();
+        assert_eq!(
+            change.fields,
+            vec!["acl".to_owned(), "read_only".to_owned()]
+        );
+    }
+}
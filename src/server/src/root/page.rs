@@ -0,0 +1,215 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{Error, Result};
+
+const PAGE_TOKEN_VERSION: u8 = 2;
+
+/// The length of the truncated signature of a token.
+const PAGE_TOKEN_TAG_LEN: usize = 16;
+
+/// The length of an encoded token: version, object, last id, fence and signature.
+const PAGE_TOKEN_LEN: usize = 1 + 1 + 8 + 8 + PAGE_TOKEN_TAG_LEN;
+
+/// The kind of objects listed by an endpoint, a token is only accepted by the endpoint listing
+/// the same kind of objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageObject {
+    Database = 1,
+    Collection = 2,
+    Group = 3,
+    Watcher = 4,
+    Move = 5,
+}
+
+impl PageObject {
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(PageObject::Database),
+            2 => Some(PageObject::Collection),
+            3 => Some(PageObject::Group),
+            4 => Some(PageObject::Watcher),
+            5 => Some(PageObject::Move),
+            _ => None,
+        }
+    }
+}
+
+/// The position of a paginated listing, the next page starts after `last_id`. The `fence` is
+/// allocated from the schema when the listing began, it is carried by all tokens of the same
+/// listing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageToken {
+    pub object: PageObject,
+    pub last_id: u64,
+    pub fence: u64,
+}
+
+impl PageToken {
+    pub fn new(object: PageObject, last_id: u64, fence: u64) -> Self {
+        PageToken {
+            object,
+            last_id,
+            fence,
+        }
+    }
+
+    /// Encode the token as a hex string, it is signed by `key` so the modified or forged tokens
+    /// are rejected.
+    pub fn encode(&self, key: &[u8]) -> String {
+        let mut buf = Vec::with_capacity(PAGE_TOKEN_LEN);
+        buf.push(PAGE_TOKEN_VERSION);
+        buf.push(self.object as u8);
+        buf.extend_from_slice(&self.last_id.to_be_bytes());
+        buf.extend_from_slice(&self.fence.to_be_bytes());
+        let tag = new_mac(key, &buf).finalize().into_bytes();
+        buf.extend_from_slice(&tag[..PAGE_TOKEN_TAG_LEN]);
+        buf.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Decode a token signed by `key`, and verify that it is issued by the endpoint listing
+    /// `object`.
+    pub fn decode(token: &str, object: PageObject, key: &[u8]) -> Result<Self> {
+        let invalid = || Error::InvalidArgument(format!("invalid page token {token}"));
+        if token.len() != PAGE_TOKEN_LEN * 2 || !token.is_ascii() {
+            return Err(invalid());
+        }
+        let buf = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&token[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+
+        let (payload, tag) = buf.split_at(PAGE_TOKEN_LEN - PAGE_TOKEN_TAG_LEN);
+        if payload[0] != PAGE_TOKEN_VERSION
+            || new_mac(key, payload).verify_truncated_left(tag).is_err()
+        {
+            return Err(invalid());
+        }
+        let token_object = PageObject::from_u8(payload[1]).ok_or_else(invalid)?;
+        if token_object != object {
+            return Err(Error::InvalidArgument(format!(
+                "page token of {token_object:?} is used to list {object:?}"
+            )));
+        }
+        Ok(PageToken {
+            object,
+            last_id: u64::from_be_bytes(payload[2..10].try_into().unwrap()),
+            fence: u64::from_be_bytes(payload[10..18].try_into().unwrap()),
+        })
+    }
+}
+
+fn new_mac(key: &[u8], payload: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac
+}
+
+/// Take a page of at most `limit` items after the position of `token`, the items must be sorted
+/// by id. The token of the next page is returned if there are more items.
+pub fn paginate<T>(
+    items: Vec<T>,
+    id: impl Fn(&T) -> u64,
+    token: Option<PageToken>,
+    object: PageObject,
+    fence: u64,
+    limit: usize,
+) -> (Vec<T>, Option<PageToken>) {
+    let (last_id, fence) = match token {
+        Some(token) => (Some(token.last_id), token.fence),
+        None => (None, fence),
+    };
+    let mut page = items
+        .into_iter()
+        .filter(|item| last_id.map(|last_id| id(item) > last_id).unwrap_or(true))
+        .take(limit.saturating_add(1))
+        .collect::<Vec<_>>();
+    let next = if page.len() > limit {
+        page.truncate(limit);
+        page.last()
+            .map(|item| PageToken::new(object, id(item), fence))
+    } else {
+        None
+    };
+    (page, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"page token key";
+
+    #[test]
+    fn page_token_round_trip() {
+        let token = PageToken::new(PageObject::Collection, 42, u64::MAX);
+        let encoded = token.encode(KEY);
+        assert_eq!(
+            PageToken::decode(&encoded, PageObject::Collection, KEY).unwrap(),
+            token
+        );
+
+        // A modified token is rejected.
+        let mut tampered = encoded.clone().into_bytes();
+        tampered[10] = if tampered[10] == b'0' { b'1' } else { b'0' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(PageToken::decode(&tampered, PageObject::Collection, KEY).is_err());
+        assert!(PageToken::decode("", PageObject::Collection, KEY).is_err());
+        assert!(PageToken::decode("not a token", PageObject::Collection, KEY).is_err());
+
+        // A token signed by other keys is rejected, even the checksum is recomputed.
+        assert!(PageToken::decode(&encoded, PageObject::Collection, b"other key").is_err());
+        let forged = PageToken::new(PageObject::Collection, 1, 1).encode(b"other key");
+        assert!(PageToken::decode(&forged, PageObject::Collection, KEY).is_err());
+    }
+
+    #[test]
+    fn reject_cross_endpoint_token() {
+        let encoded = PageToken::new(PageObject::Database, 1, 1).encode(KEY);
+        assert!(matches!(
+            PageToken::decode(&encoded, PageObject::Group, KEY),
+            Err(Error::InvalidArgument(msg)) if msg.contains("Database")
+        ));
+        assert!(PageToken::decode(&encoded, PageObject::Database, KEY).is_ok());
+    }
+
+    #[test]
+    fn paginate_by_token() {
+        let items = (1..=5).collect::<Vec<u64>>();
+        let (page, next) = paginate(items.clone(), |v| *v, None, PageObject::Database, 7, 2);
+        assert_eq!(page, vec![1, 2]);
+        let next = next.unwrap();
+        assert_eq!((next.last_id, next.fence), (2, 7));
+
+        // The fence of the first page is carried by the following tokens.
+        let (page, next) = paginate(
+            items.clone(),
+            |v| *v,
+            Some(next),
+            PageObject::Database,
+            9,
+            2,
+        );
+        assert_eq!(page, vec![3, 4]);
+        assert_eq!(next.unwrap().fence, 7);
+
+        let (page, next) = paginate(items, |v| *v, next, PageObject::Database, 9, 2);
+        assert_eq!(page, vec![5]);
+        assert!(next.is_none());
+    }
+}
@@ -12,12 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::LinkedList, sync::Arc};
+use std::{collections::LinkedList, future::Future, sync::Arc};
 
 use engula_api::server::v1::*;
 use engula_client::GroupClient;
+use futures::{stream, StreamExt};
 use prometheus::HistogramTimer;
-use tokio::{sync::Mutex, time::Instant};
+use tokio::sync::Mutex;
 use tracing::{error, info, warn};
 
 use super::{allocator::*, metrics, *};
@@ -37,6 +38,8 @@ pub struct ScheduleContext {
     alloc: Arc<Allocator<SysAllocSource>>,
     heartbeat_queue: Arc<HeartbeatQueue>,
     ongoing_stats: Arc<OngoingStats>,
+    drain_progress: Arc<drain::DrainProgress>,
+    move_history: Arc<move_history::MoveHistory>,
     jobs: Arc<Jobs>,
     cfg: RootConfig,
 }
@@ -94,7 +97,10 @@ impl ReconcileScheduler {
 
     pub async fn check(&self, max_try_per_tick: u64) -> Result<bool> {
         let _timer = super::metrics::RECONCILE_CHECK_DURATION_SECONDS.start_timer();
+        // The actions computed by a stale leader are dropped, the next leader computes them again.
+        let (_, generation) = self.ctx.shared.schema_with_generation()?;
         let group_action = self.ctx.alloc.compute_group_action().await?;
+        self.ctx.shared.check_generation(generation)?;
         if let GroupAction::Add(cnt) = group_action {
             metrics::RECONCILE_ALREADY_BALANCED_INFO
                 .cluster_groups
@@ -127,6 +133,7 @@ impl ReconcileScheduler {
             if ractions.is_empty() && sactions.is_empty() {
                 break;
             }
+            self.ctx.shared.check_generation(generation)?;
 
             for action in ractions {
                 match action {
@@ -301,11 +308,14 @@ impl ReconcileScheduler {
 }
 
 impl ScheduleContext {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         shared: Arc<RootShared>,
         alloc: Arc<Allocator<SysAllocSource>>,
         heartbeat_queue: Arc<HeartbeatQueue>,
         ongoing_stats: Arc<OngoingStats>,
+        drain_progress: Arc<drain::DrainProgress>,
+        move_history: Arc<move_history::MoveHistory>,
         jobs: Arc<Jobs>,
         cfg: RootConfig,
     ) -> Self {
@@ -314,6 +324,8 @@ impl ScheduleContext {
             alloc,
             heartbeat_queue,
             ongoing_stats,
+            drain_progress,
+            move_history,
             jobs,
             cfg,
         }
@@ -347,11 +359,13 @@ impl ScheduleContext {
         bool, /* ack current */
         bool, /* immediately step next tick */
     )> {
-        let schema = self.shared.schema()?;
+        let (schema, generation) = self.shared.schema_with_generation()?;
 
         let group = task.group;
         let replica = task.src_replica;
-        let r = self.try_shed_leader_before_remove(group, replica).await;
+        let r = self
+            .try_shed_leader_before_remove(&schema, group, replica)
+            .await;
         match r {
             Ok(_) => {}
             Err(crate::Error::AbortScheduleTask(_)) => return Ok((true, false)),
@@ -397,8 +411,32 @@ impl ScheduleContext {
             dest_node = task.dest_node.as_ref().unwrap().id,
             "start move replica"
         );
+        let reason = match schema.get_node(task.src_node).await? {
+            Some(n)
+                if n.status == NodeStatus::Draining as i32
+                    || n.status == NodeStatus::Decommissioning as i32 =>
+            {
+                MoveReason::Drain
+            }
+            _ => MoveReason::Rebalance,
+        };
+        // The replica moves share the limit with the leader moves, the catch-up traffic floods the
+        // target nodes otherwise. The deferred move is computed again by the next tick.
+        let in_flight = self.ongoing_stats.moves_out_of(task.src_node);
+        if in_flight >= self.max_concurrent_moves(0) {
+            info!(
+                group = group,
+                src_node = task.src_node,
+                in_flight = in_flight,
+                "too many replica moves in flight, defer the move"
+            );
+            return Ok((true, false));
+        }
         let next_replica = schema.next_replica_id().await?;
-        match self
+        // The replica is moved on behalf of the leader which computed the task.
+        self.shared.check_generation(generation)?;
+        let started_at = self.shared.clock.now();
+        let result = self
             .try_move_replica(
                 group,
                 ReplicaDesc {
@@ -408,8 +446,32 @@ impl ScheduleContext {
                 },
                 src_replica.unwrap().to_owned(),
             )
-            .await
-        {
+            .await;
+        let (outcome, error) = match &result {
+            Ok(_) => (MoveOutcome::Succeeded, None),
+            Err(err @ crate::Error::AlreadyExists(_))
+            | Err(err @ crate::Error::EpochNotMatch(_)) => {
+                (MoveOutcome::Aborted, Some(err.to_string()))
+            }
+            Err(err) => (MoveOutcome::Failed, Some(err.to_string())),
+        };
+        self.move_history.record(MoveRecord {
+            id: 0,
+            group_id: group,
+            replica_id: task.src_replica,
+            from_node: task.src_node,
+            to_node: task.dest_node.as_ref().unwrap().id,
+            reason,
+            outcome,
+            error,
+            duration_ms: self
+                .shared
+                .clock
+                .now()
+                .saturating_duration_since(started_at)
+                .as_millis() as u64,
+        });
+        match result {
             Ok(schedule_state) => {
                 self.ongoing_stats.handle_update(&[schedule_state], None);
                 Ok((true, false))
@@ -503,7 +565,7 @@ impl ScheduleContext {
                         node_id: task.src_node,
                     },
                 ],
-                Instant::now(),
+                self.shared.clock.now(),
             )
             .await;
         Ok((true, true))
@@ -517,8 +579,11 @@ impl ScheduleContext {
         bool, /* immediately step next tick */
     )> {
         let node = shed.node_id;
+        let max_concurrent_moves = self.max_concurrent_moves(shed.max_concurrent_moves);
+        // The moves are stopped once the leadership is lost, the next leader resumes them.
+        let (schema, generation) = self.shared.schema_with_generation()?;
         loop {
-            let schema = self.shared.schema()?;
+            self.shared.check_generation(generation)?;
 
             if let Some(desc) = schema.get_node(node).await? {
                 if desc.status != NodeStatus::Draining as i32 {
@@ -533,6 +598,13 @@ impl ScheduleContext {
                 .into_iter()
                 .filter(|r| r.node_id == node && r.role == RaftRole::Leader as i32)
                 .collect::<Vec<_>>();
+            self.drain_progress.refresh(
+                node,
+                &leader_replicas
+                    .iter()
+                    .map(|r| (r.group_id, r.replica_id))
+                    .collect::<Vec<_>>(),
+            );
 
             // exit when all leader move-out
             // also change node status to Drained
@@ -546,6 +618,7 @@ impl ScheduleContext {
                 break;
             }
 
+            let mut moves = Vec::new();
             for replica in &leader_replicas {
                 let group_id = replica.group_id;
                 if let Some(group) = schema.get_group(group_id).await? {
@@ -564,8 +637,7 @@ impl ScheduleContext {
                         target_replica = Some(r.to_owned())
                     }
                     if let Some(target_replica) = target_replica {
-                        self.try_transfer_leader(group_id, target_replica.id)
-                            .await?;
+                        moves.push((group_id, replica.replica_id, target_replica));
                     } else {
                         warn!(
                             node = node,
@@ -577,6 +649,23 @@ impl ScheduleContext {
                     }
                 }
             }
+
+            self.shared.check_generation(generation)?;
+            // Limit the in-flight moves to avoid flooding the target nodes.
+            run_concurrently(
+                moves,
+                max_concurrent_moves,
+                |(group, replica, target)| async move {
+                    self.drain_progress.moving(node, replica, target.node_id);
+                    let result = self.try_transfer_leader(group, target.id).await;
+                    if let Err(err) = &result {
+                        self.drain_progress.failed(node, replica, err);
+                    }
+                    result
+                },
+            )
+            .await?;
+            self.heartbeat_queue.wait_one_heartbeat_tick().await;
         }
 
         Ok((true, true))
@@ -590,7 +679,7 @@ impl ScheduleContext {
         bool, /* immediately step next tick */
     )> {
         let node = task.node_id;
-        let schema = self.shared.schema()?;
+        let (schema, generation) = self.shared.schema_with_generation()?;
         let root_group = schema.get_group(ROOT_GROUP_ID).await?.unwrap();
         let mut target = None;
         for r in &root_group.replicas {
@@ -607,6 +696,7 @@ impl ScheduleContext {
             target = Some(r.to_owned())
         }
         if let Some(r) = target {
+            self.shared.check_generation(generation)?;
             self.try_transfer_leader(root_group.id, r.id).await?
         }
         Ok((true, false))
@@ -614,19 +704,22 @@ impl ScheduleContext {
 }
 
 impl ScheduleContext {
-    async fn get_group_leader(&self, group_id: u64) -> Result<Option<GroupDesc>> {
-        let schema = self.shared.schema()?;
-        let group = schema.get_group(group_id).await?;
-        Ok(group)
+    /// The limit of the in-flight moves out of a node, `requested` overrides the config if it is
+    /// not zero.
+    fn max_concurrent_moves(&self, requested: u64) -> u64 {
+        if requested > 0 {
+            requested
+        } else {
+            self.cfg.drain_max_concurrent_moves.max(1)
+        }
     }
 
     async fn try_shed_leader_before_remove(
         &self,
+        schema: &Schema,
         group_id: u64,
         remove_replica: u64,
     ) -> Result<()> {
-        let schema = self.shared.schema()?;
-
         let replica_state = schema
             .get_replica_state(group_id, remove_replica)
             .await?
@@ -638,12 +731,12 @@ impl ScheduleContext {
             return Ok(());
         }
 
-        let group =
-            self.get_group_leader(group_id)
-                .await?
-                .ok_or(crate::Error::AbortScheduleTask(
-                    "shed leader group has be destroyed",
-                ))?;
+        let group = schema
+            .get_group(group_id)
+            .await?
+            .ok_or(crate::Error::AbortScheduleTask(
+                "shed leader group has be destroyed",
+            ))?;
         if let Some(target_replica) = group.replicas.iter().find(|e| e.id != remove_replica) {
             // TODO: find least-leader node.
             info!(
@@ -687,8 +780,10 @@ impl ScheduleContext {
     }
 
     async fn try_migrate_shard(&self, src_group: u64, target_group: u64, shard: u64) -> Result<()> {
+        let (schema, generation) = self.shared.schema_with_generation()?;
         let src_group =
-            self.get_group_leader(src_group)
+            schema
+                .get_group(src_group)
                 .await?
                 .ok_or(crate::Error::AbortScheduleTask(
                     "migrate source group has be destroyed",
@@ -697,6 +792,7 @@ impl ScheduleContext {
             crate::Error::AbortScheduleTask("migrate shard has be moved out"),
         )?;
 
+        self.shared.check_generation(generation)?;
         let mut group_client = GroupClient::lazy(
             target_group,
             self.shared.provider.router.clone(),
@@ -709,3 +805,49 @@ impl ScheduleContext {
         Ok(())
     }
 }
+
+/// Run `f` over `items`, no more than `limit` futures are in flight at the same time.
+async fn run_concurrently<T, F, Fut>(items: Vec<T>, limit: u64, f: F) -> Result<()>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let results = stream::iter(items.into_iter().map(f))
+        .buffer_unordered(limit.max(1) as usize)
+        .collect::<Vec<_>>()
+        .await;
+    results.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::runtime::ExecutorOwner;
+
+    #[test]
+    fn drain_moves_in_flight_limit() {
+        let owner = ExecutorOwner::new(1);
+        owner.executor().block_on(async {
+            let in_flight = AtomicU64::new(0);
+            let max_in_flight = AtomicU64::new(0);
+            let finished = AtomicU64::new(0);
+            let moves = (0..64).collect::<Vec<u64>>();
+            run_concurrently(moves, 4, |_| async {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                crate::runtime::time::sleep(Duration::from_millis(1)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await
+            .unwrap();
+            assert_eq!(finished.load(Ordering::SeqCst), 64);
+            let max_in_flight = max_in_flight.load(Ordering::SeqCst);
+            assert!(max_in_flight <= 4, "{max_in_flight} moves in flight");
+            assert!(max_in_flight > 1);
+        });
+    }
+}
@@ -13,8 +13,12 @@
 // limitations under the License.
 
 use std::{
-    collections::{hash_map::Entry, BTreeMap, HashMap},
-    sync::Arc,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
 };
 
 use engula_api::{
@@ -28,14 +32,15 @@ use engula_api::{
 use engula_client::ShardClient;
 use futures::lock::Mutex;
 use prost::Message;
-use tracing::{info, warn};
+use serde::Serialize;
+use tracing::{error, info, warn};
 
-use super::store::RootStore;
+use super::{lag::ReplicaLag, liveness::Liveness, metrics, store::RootStore};
 use crate::{
     bootstrap::*,
     node::{
         engine::{SnapshotMode, LOCAL_COLLECTION_ID},
-        GroupEngine,
+        GroupEngine, Replica,
     },
     serverpb::v1::BackgroundJob,
     Error, Provider, Result,
@@ -78,6 +83,14 @@ const META_NODE_ID_KEY: &str = "node_id";
 const META_REPLICA_ID_KEY: &str = "replica_id";
 const META_SHARD_ID_KEY: &str = "shard_id";
 const META_JOB_ID_KEY: &str = "job_id";
+const META_FROZEN_KEY: &str = "frozen";
+const META_PAGE_FENCE_KEY: &str = "page_fence";
+const META_PAGE_TOKEN_KEY: &str = "page_token_key";
+
+/// The number of page fences allocated from the schema at once.
+const PAGE_FENCE_BATCH: u64 = 1024;
+
+const META_ALIAS_KEY_PREFIX: &str = "alias/";
 
 lazy_static::lazy_static! {
     pub static ref SYSTEM_COLLECTION_SHARD: BTreeMap<u64, u64> = BTreeMap::from([
@@ -99,26 +112,151 @@ lazy_static::lazy_static! {
         (META_REPLICA_ID_KEY.to_owned(),  Mutex::new(())),
         (META_SHARD_ID_KEY.to_owned(),  Mutex::new(())),
         (META_JOB_ID_KEY.to_owned(), Mutex::new(())),
+        (META_PAGE_FENCE_KEY.to_owned(), Mutex::new(())),
+        (META_PAGE_TOKEN_KEY.to_owned(), Mutex::new(())),
     ]);
 }
 
+/// The replication health of a collection, aggregated across the groups hosting its shards.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct CollectionHealth {
+    pub groups: u64,
+    pub desired_replicas: u64,
+    /// The number of voters on the nodes which are not dead.
+    pub healthy_replicas: u64,
+    pub under_replicated_groups: u64,
+    /// The max lag of the replicas in any of the groups.
+    pub max_replica_lag: u64,
+}
+
 #[derive(Clone)]
 pub struct Schema {
     store: Arc<RootStore>,
+    /// Skip the records which could not be decoded instead of failing the listing.
+    skip_corrupt: bool,
+    /// Whether any corrupt record was skipped, the listed metadata is incomplete if so.
+    corrupted: Arc<AtomicBool>,
+    /// Serialize the writes of group descs, so a compare-and-set is not interleaved.
+    group_lock: Arc<Mutex<()>>,
+    /// The groups being removed, their descs are deleted and must not be written again, so that
+    /// no shard is placed on a group whose replicas are being removed.
+    retired_groups: Arc<RwLock<HashSet<u64>>>,
+    /// Serialize the registering of nodes, so an address joined in batch is deduplicated.
+    node_lock: Arc<Mutex<()>>,
+    /// The cluster id is set once at bootstrap and never changes, so it is read only once.
+    cluster_id: Arc<RwLock<Option<Vec<u8>>>>,
+    /// The page fences allocated in batch but not used yet.
+    page_fences: Arc<std::sync::Mutex<Range<u64>>>,
 }
 
 // public interface.
 impl Schema {
-    pub fn new(store: Arc<RootStore>) -> Self {
-        Self { store }
+    pub fn new(store: Arc<RootStore>, skip_corrupt: bool) -> Self {
+        Self {
+            store,
+            skip_corrupt,
+            corrupted: Arc::default(),
+            group_lock: Arc::default(),
+            retired_groups: Arc::default(),
+            node_lock: Arc::default(),
+            cluster_id: Arc::default(),
+            page_fences: Arc::new(std::sync::Mutex::new(0..0)),
+        }
+    }
+
+    /// Return false if any corrupt record was skipped, so the metadata is not consistent.
+    pub fn is_consistent(&self) -> bool {
+        !self.corrupted.load(Ordering::Acquire)
     }
 
-    pub async fn cluster_id(&self) -> Result<Option<Vec<u8>>> {
-        let id = self.get_meta(META_CLUSTER_ID_KEY.as_bytes()).await?;
-        if let Some(id) = id {
-            return Ok(Some(id));
+    /// The replica of root group which serves the schema.
+    pub fn root_replica(&self) -> Arc<Replica> {
+        self.store.replica()
+    }
+
+    /// Return the id of the cluster, or `Error::NotBootstrapped` if the cluster is not
+    /// bootstrapped yet.
+    pub async fn cluster_id(&self) -> Result<Vec<u8>> {
+        if let Some(id) = self.cluster_id.read().unwrap().as_ref() {
+            return Ok(id.to_owned());
         }
-        Ok(None)
+        let id = self
+            .load_cluster_id()
+            .await?
+            .ok_or(Error::NotBootstrapped)?;
+        self.set_cluster_id(id.to_owned());
+        Ok(id)
+    }
+
+    /// Whether the metadata mutations are frozen cluster-wide.
+    pub async fn is_frozen(&self) -> Result<bool> {
+        let val = self.get_meta(META_FROZEN_KEY.as_bytes()).await?;
+        Ok(matches!(val.as_deref(), Some([1])))
+    }
+
+    pub async fn set_frozen(&self, frozen: bool) -> Result<()> {
+        self.batch_write(
+            PutBatchBuilder::default()
+                .put_meta(META_FROZEN_KEY.as_bytes().to_vec(), vec![frozen as u8])
+                .build(),
+        )
+        .await
+    }
+
+    /// The end of the allocated fences, the fences of the issued page tokens are below it.
+    pub async fn page_fence(&self) -> Result<u64> {
+        let val = self.get_meta(META_PAGE_FENCE_KEY.as_bytes()).await?;
+        match val {
+            Some(val) => Ok(u64::from_le_bytes(val.try_into().map_err(|_| {
+                Error::InvalidData(format!("{} id", META_PAGE_FENCE_KEY))
+            })?)),
+            None => Ok(1),
+        }
+    }
+
+    /// Allocate the fence of a paginated listing. The fences are persisted in batch, so that
+    /// most listings don't write the schema, and the tokens issued by the previous root leaders
+    /// are still accepted. The unused fences of a batch are skipped by the next root leader.
+    pub async fn next_page_fence(&self) -> Result<u64> {
+        if let Some(fence) = self.page_fences.lock().unwrap().next() {
+            return Ok(fence);
+        }
+        let _mutex = ID_GEN_LOCKS[META_PAGE_FENCE_KEY].lock().await;
+        if let Some(fence) = self.page_fences.lock().unwrap().next() {
+            return Ok(fence);
+        }
+        let start = self.page_fence().await?;
+        let end = start + PAGE_FENCE_BATCH;
+        self.batch_write(
+            PutBatchBuilder::default()
+                .put_meta(
+                    META_PAGE_FENCE_KEY.as_bytes().to_vec(),
+                    end.to_le_bytes().to_vec(),
+                )
+                .build(),
+        )
+        .await?;
+        *self.page_fences.lock().unwrap() = start + 1..end;
+        Ok(start)
+    }
+
+    /// The key to sign the page tokens, it is generated once and shared by all root leaders.
+    pub async fn page_token_key(&self) -> Result<Vec<u8>> {
+        if let Some(key) = self.get_meta(META_PAGE_TOKEN_KEY.as_bytes()).await? {
+            return Ok(key);
+        }
+        let _mutex = ID_GEN_LOCKS[META_PAGE_TOKEN_KEY].lock().await;
+        if let Some(key) = self.get_meta(META_PAGE_TOKEN_KEY.as_bytes()).await? {
+            return Ok(key);
+        }
+        let key = rand::random::<[u8; 32]>().to_vec();
+        self.batch_write(
+            PutBatchBuilder::default()
+                .put_meta(META_PAGE_TOKEN_KEY.as_bytes().to_vec(), key.clone())
+                .build(),
+        )
+        .await?;
+        Ok(key)
     }
 
     pub async fn create_database(&self, desc: DatabaseDesc) -> Result<DatabaseDesc> {
@@ -152,8 +290,14 @@ impl Schema {
         Ok(Some(desc))
     }
 
-    pub async fn update_database(&self, _desc: DatabaseDesc) -> Result<()> {
-        todo!()
+    pub async fn update_database(&self, desc: DatabaseDesc) -> Result<()> {
+        match self.get_database(&desc.name).await? {
+            Some(exist) if exist.id == desc.id => {}
+            _ => return Err(Error::DatabaseNotFound(desc.name.to_owned())),
+        }
+        self.batch_write(PutBatchBuilder::default().put_database(desc).build())
+            .await?;
+        Ok(())
     }
 
     pub async fn delete_database(&self, db: &DatabaseDesc) -> Result<u64> {
@@ -163,15 +307,7 @@ impl Schema {
     }
 
     pub async fn list_database(&self) -> Result<Vec<DatabaseDesc>> {
-        let vals = self.list(SYSTEM_DATABASE_COLLECTION_ID).await?;
-        let mut databases = Vec::new();
-        for val in vals {
-            databases.push(
-                DatabaseDesc::decode(&*val)
-                    .map_err(|_| Error::InvalidData("database desc".into()))?,
-            );
-        }
-        Ok(databases)
+        self.list(SYSTEM_DATABASE_COLLECTION_ID).await
     }
 
     pub async fn prepare_create_collection(&self, desc: CollectionDesc) -> Result<CollectionDesc> {
@@ -186,8 +322,19 @@ impl Schema {
         Ok(desc)
     }
 
+    /// Write the collection desc. It is a no-op if the same collection has already been written,
+    /// but a different collection or an alias with the same name, which might be created
+    /// concurrently, fails with `Error::AlreadyExists`.
     pub async fn create_collection(&self, desc: CollectionDesc) -> Result<CollectionDesc> {
-        assert!(self.get_collection(desc.db, &desc.name).await?.is_none());
+        if let Some(exist) = self.get_collection_exact(desc.db, &desc.name).await? {
+            if exist.id == desc.id {
+                return Ok(exist);
+            }
+            return Err(Error::AlreadyExists(format!("collection {}", desc.name)));
+        }
+        if self.get_alias(desc.db, &desc.name).await?.is_some() {
+            return Err(Error::AlreadyExists(format!("alias {}", desc.name)));
+        }
         self.batch_write(
             PutBatchBuilder::default()
                 .put_collection(desc.to_owned())
@@ -197,10 +344,29 @@ impl Schema {
         Ok(desc)
     }
 
+    /// Get collection by name, the name will be resolved as an alias if there is no such
+    /// collection.
     pub async fn get_collection(
         &self,
         database: u64,
         collection: &str,
+    ) -> Result<Option<CollectionDesc>> {
+        if let Some(desc) = self.get_collection_exact(database, collection).await? {
+            return Ok(Some(desc));
+        }
+        if let Some(alias) = self.get_alias(database, collection).await? {
+            return self
+                .get_collection_by_id(database, alias.collection_id)
+                .await;
+        }
+        Ok(None)
+    }
+
+    /// Get collection by name without alias resolving.
+    pub async fn get_collection_exact(
+        &self,
+        database: u64,
+        collection: &str,
     ) -> Result<Option<CollectionDesc>> {
         let val = self
             .get(
@@ -217,6 +383,40 @@ impl Schema {
         Ok(Some(desc))
     }
 
+    /// Find a database other than `except` which has a collection named `collection`, it looks
+    /// up the collection in each database instead of scanning all collections.
+    pub async fn find_collection_database(
+        &self,
+        collection: &str,
+        except: u64,
+    ) -> Result<Option<DatabaseDesc>> {
+        for db in self.list_database().await? {
+            if db.id == except {
+                continue;
+            }
+            if self
+                .get_collection_exact(db.id, collection)
+                .await?
+                .is_some()
+            {
+                return Ok(Some(db));
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn get_collection_by_id(
+        &self,
+        database: u64,
+        collection_id: u64,
+    ) -> Result<Option<CollectionDesc>> {
+        Ok(self
+            .list_database_collections(database)
+            .await?
+            .into_iter()
+            .find(|c| c.id == collection_id))
+    }
+
     pub async fn get_collection_shards(&self, collection_id: u64) -> Result<Vec<(u64, ShardDesc)>> {
         let groups = self.list_group().await?;
         let group_shards = groups
@@ -231,27 +431,81 @@ impl Schema {
         Ok(group_shards)
     }
 
-    pub async fn update_collection(&self, _desc: CollectionDesc) -> Result<()> {
-        todo!()
+    /// Aggregate the replication health of the groups hosting the shards of a collection, each
+    /// of which desires `replicas_per_group` healthy replicas.
+    pub async fn collection_health(
+        &self,
+        collection_id: u64,
+        replicas_per_group: usize,
+        liveness: &Liveness,
+        replica_lag: &ReplicaLag,
+    ) -> Result<CollectionHealth> {
+        let mut health = CollectionHealth::default();
+        for group in self.list_group().await? {
+            if !group
+                .shards
+                .iter()
+                .any(|s| s.collection_id == collection_id)
+            {
+                continue;
+            }
+            let healthy = group
+                .replicas
+                .iter()
+                .filter(|r| r.role == ReplicaRole::Voter as i32)
+                .filter(|r| !liveness.get(&r.node_id).is_dead())
+                .count();
+            health.groups += 1;
+            health.desired_replicas += replicas_per_group as u64;
+            health.healthy_replicas += healthy as u64;
+            if healthy < replicas_per_group {
+                health.under_replicated_groups += 1;
+            }
+            health.max_replica_lag = health.max_replica_lag.max(replica_lag.group_lag(group.id));
+        }
+        Ok(health)
+    }
+
+    pub async fn update_collection(&self, desc: CollectionDesc) -> Result<()> {
+        match self.get_collection_exact(desc.db, &desc.name).await? {
+            Some(exist) if exist.id == desc.id => {}
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "collection {} not found",
+                    desc.name
+                )))
+            }
+        }
+        self.batch_write(PutBatchBuilder::default().put_collection(desc).build())
+            .await?;
+        Ok(())
     }
 
-    pub async fn delete_collection(&self, collection: CollectionDesc) -> Result<()> {
+    /// Delete the collection and the aliases pointing to it, the deleted aliases are returned.
+    pub async fn delete_collection(
+        &self,
+        collection: CollectionDesc,
+    ) -> Result<Vec<CollectionAlias>> {
+        let aliases = self
+            .list_alias()
+            .await?
+            .into_iter()
+            .filter(|alias| alias.db == collection.db && alias.collection_id == collection.id)
+            .collect::<Vec<_>>();
+        for alias in &aliases {
+            self.delete(SYSTEM_MATE_COLLECTION_ID, &alias_key(alias.db, &alias.name))
+                .await?;
+        }
         self.delete(
             SYSTEM_COLLECTION_COLLECTION_ID,
             &collection_key(collection.db, &collection.name),
         )
-        .await
+        .await?;
+        Ok(aliases)
     }
 
     pub async fn list_collection(&self) -> Result<Vec<CollectionDesc>> {
-        let vals = self.list(SYSTEM_COLLECTION_COLLECTION_ID).await?;
-        let mut collections = Vec::new();
-        for val in vals {
-            let c = CollectionDesc::decode(&*val)
-                .map_err(|_| Error::InvalidData("collection desc".into()))?;
-            collections.push(c);
-        }
-        Ok(collections)
+        self.list(SYSTEM_COLLECTION_COLLECTION_ID).await
     }
 
     pub async fn list_database_collections(&self, database: u64) -> Result<Vec<CollectionDesc>> {
@@ -262,7 +516,105 @@ impl Schema {
             .collect::<Vec<_>>())
     }
 
+    pub async fn create_alias(&self, alias: CollectionAlias) -> Result<CollectionAlias> {
+        if self
+            .get_collection_exact(alias.db, &alias.name)
+            .await?
+            .is_some()
+        {
+            return Err(Error::AlreadyExists(format!("collection {}", alias.name)));
+        }
+        if self.get_alias(alias.db, &alias.name).await?.is_some() {
+            return Err(Error::AlreadyExists(format!("alias {}", alias.name)));
+        }
+        self.check_alias_target(&alias).await?;
+        self.batch_write(
+            PutBatchBuilder::default()
+                .put_alias(alias.to_owned())
+                .build(),
+        )
+        .await?;
+        Ok(alias)
+    }
+
+    pub async fn get_alias(&self, database: u64, name: &str) -> Result<Option<CollectionAlias>> {
+        let val = self.get_meta(&alias_key(database, name)).await?;
+        if val.is_none() {
+            return Ok(None);
+        }
+        let alias = CollectionAlias::decode(&*val.unwrap())
+            .map_err(|_| Error::InvalidData(format!("collection alias: {}, {}", database, name)))?;
+        Ok(Some(alias))
+    }
+
+    /// Repoint the aliases in a batch, so that an alias chain is never left half-updated.
+    pub async fn repoint_aliases(&self, aliases: &[CollectionAlias]) -> Result<()> {
+        let mut builder = PutBatchBuilder::default();
+        for alias in aliases {
+            if self.get_alias(alias.db, &alias.name).await?.is_none() {
+                return Err(Error::InvalidArgument(format!(
+                    "alias {} not found",
+                    alias.name
+                )));
+            }
+            self.check_alias_target(alias).await?;
+            builder.put_alias(alias.to_owned());
+        }
+        self.batch_write(builder.build()).await
+    }
+
+    pub async fn delete_alias(&self, database: u64, name: &str) -> Result<Option<CollectionAlias>> {
+        let alias = self.get_alias(database, name).await?;
+        if alias.is_some() {
+            self.delete(SYSTEM_MATE_COLLECTION_ID, &alias_key(database, name))
+                .await?;
+        }
+        Ok(alias)
+    }
+
+    pub async fn list_alias(&self) -> Result<Vec<CollectionAlias>> {
+        self.list_prefix(SYSTEM_MATE_COLLECTION_ID, META_ALIAS_KEY_PREFIX.as_bytes())
+            .await
+    }
+
     pub async fn add_node(&self, desc: NodeDesc) -> Result<NodeDesc> {
+        let _guard = self.node_lock.lock().await;
+        self.put_new_node(desc).await
+    }
+
+    /// Register the nodes whose addresses haven't joined, under the same lock as `add_node`, so
+    /// an address is not registered twice by concurrent joins. Return each node with whether it
+    /// is registered by this call, or the error of registering it.
+    pub async fn add_nodes_if_absent(
+        &self,
+        descs: Vec<NodeDesc>,
+    ) -> Result<Vec<Result<(NodeDesc, bool)>>> {
+        let _guard = self.node_lock.lock().await;
+        let mut known = self
+            .list_node()
+            .await?
+            .into_iter()
+            .filter(|n| n.status != NodeStatus::Decommissioned as i32)
+            .map(|n| (n.addr.to_owned(), n))
+            .collect::<HashMap<_, _>>();
+        let mut results = Vec::with_capacity(descs.len());
+        for desc in descs {
+            if let Some(node) = known.get(&desc.addr) {
+                results.push(Ok((node.to_owned(), false)));
+                continue;
+            }
+            match self.put_new_node(desc).await {
+                Ok(node) => {
+                    known.insert(node.addr.to_owned(), node.to_owned());
+                    results.push(Ok((node, true)));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+        Ok(results)
+    }
+
+    async fn put_new_node(&self, desc: NodeDesc) -> Result<NodeDesc> {
         let mut desc = desc.to_owned();
         desc.id = self.next_id(META_NODE_ID_KEY).await?;
         self.batch_write(PutBatchBuilder::default().put_node(desc.to_owned()).build())
@@ -294,13 +646,28 @@ impl Schema {
     }
 
     pub async fn list_node(&self) -> Result<Vec<NodeDesc>> {
-        let vals = self.list(SYSTEM_NODE_COLLECTION_ID).await?;
-        let mut nodes = Vec::new();
-        for val in vals {
-            nodes
-                .push(NodeDesc::decode(&*val).map_err(|_| Error::InvalidData("node desc".into()))?);
-        }
-        Ok(nodes)
+        self.list(SYSTEM_NODE_COLLECTION_ID).await
+    }
+
+    /// The cluster runs in degraded mode if there are fewer nodes than the replicas of a group,
+    /// in which case the groups could not place all of their replicas and lose redundancy.
+    pub async fn is_degraded(&self, replicas_per_group: usize) -> Result<bool> {
+        let nodes = self
+            .list_node()
+            .await?
+            .into_iter()
+            .filter(|n| n.status != NodeStatus::Decommissioned as i32)
+            .count();
+        Ok(nodes < replicas_per_group)
+    }
+
+    /// Write an undecodable node record, to simulate a corruption of the store.
+    #[cfg(test)]
+    pub async fn inject_corrupt_node(&self, id: u64) -> Result<()> {
+        let shard_id = Self::system_shard_id(SYSTEM_NODE_COLLECTION_ID);
+        self.store
+            .put(shard_id, id.to_le_bytes().to_vec(), vec![0xff; 4])
+            .await
     }
 
     pub async fn list_node_raw(engine: GroupEngine) -> Result<Vec<NodeDesc>> {
@@ -335,6 +702,48 @@ impl Schema {
         &self,
         group: Option<GroupDesc>,
         replica: Option<ReplicaState>,
+    ) -> Result<()> {
+        let _guard = match &group {
+            Some(group) => {
+                let guard = self.group_lock.lock().await;
+                self.check_group_not_retired(group.id)?;
+                Some(guard)
+            }
+            None => None,
+        };
+        self.write_group_replica(group, replica).await
+    }
+
+    /// Update the group desc only if the persisted one still has `expect_epoch`, `None` expects
+    /// no persisted desc. Otherwise the update is based on a stale desc, and
+    /// `Error::StaleReport` is returned.
+    pub async fn cas_group_replica(
+        &self,
+        expect_epoch: Option<u64>,
+        group: GroupDesc,
+        replica: Option<ReplicaState>,
+    ) -> Result<()> {
+        let _guard = self.group_lock.lock().await;
+        self.check_group_not_retired(group.id)?;
+        let current_epoch = self.get_group(group.id).await?.map(|g| g.epoch);
+        if current_epoch != expect_epoch {
+            return Err(Error::StaleReport(group.id));
+        }
+        self.write_group_replica(Some(group), replica).await
+    }
+
+    /// The desc of a retired group is deleted, a write based on it is stale.
+    fn check_group_not_retired(&self, group_id: u64) -> Result<()> {
+        if self.retired_groups.read().unwrap().contains(&group_id) {
+            return Err(Error::StaleReport(group_id));
+        }
+        Ok(())
+    }
+
+    async fn write_group_replica(
+        &self,
+        group: Option<GroupDesc>,
+        replica: Option<ReplicaState>,
     ) -> Result<()> {
         let mut builder = PutBatchBuilder::default();
         if group.is_some() {
@@ -367,21 +776,29 @@ impl Schema {
         Ok(Some(desc))
     }
 
+    /// Delete the desc of the empty group and reject the following writes of it, unless some
+    /// shards are placed on it since `group` was read. The check is serialized with the writes of
+    /// group descs, so no shard is placed on the group once it returns true, and its replicas
+    /// could be removed safely.
+    pub async fn retire_empty_group(&self, group: &GroupDesc) -> Result<bool> {
+        let _guard = self.group_lock.lock().await;
+        if let Some(current) = self.get_group(group.id).await? {
+            if has_new_shards(group, &current) {
+                return Ok(false);
+            }
+        }
+        self.retired_groups.write().unwrap().insert(group.id);
+        self.delete_group(group.id).await?;
+        Ok(true)
+    }
+
     pub async fn delete_group(&self, id: u64) -> Result<()> {
-        // TODO: prefix delete replica_state
         self.delete(SYSTEM_GROUP_COLLECTION_ID, &id.to_le_bytes())
             .await
     }
 
     pub async fn list_group(&self) -> Result<Vec<GroupDesc>> {
-        let vals = self.list(SYSTEM_GROUP_COLLECTION_ID).await?;
-        let mut groups = Vec::new();
-        for val in vals {
-            groups.push(
-                GroupDesc::decode(&*val).map_err(|_| Error::InvalidData("group desc".into()))?,
-            );
-        }
-        Ok(groups)
+        self.list(SYSTEM_GROUP_COLLECTION_ID).await
     }
 
     pub async fn get_replica_state(
@@ -404,63 +821,45 @@ impl Schema {
     }
 
     pub async fn list_replica_state(&self) -> Result<Vec<ReplicaState>> {
-        let vals = self.list(SYSTEM_REPLICA_STATE_COLLECTION_ID).await?;
-        let mut states = Vec::with_capacity(vals.len());
-        for val in vals {
-            let state = ReplicaState::decode(&*val)
-                .map_err(|_| Error::InvalidData("replica state desc".into()))?;
-            states.push(state.to_owned());
-        }
-        Ok(states)
+        self.list(SYSTEM_REPLICA_STATE_COLLECTION_ID).await
     }
 
     pub async fn group_replica_states(&self, group_id: u64) -> Result<Vec<ReplicaState>> {
-        let vals = self
-            .list_prefix(
-                SYSTEM_REPLICA_STATE_COLLECTION_ID,
-                group_id.to_le_bytes().as_slice(),
-            )
-            .await?;
-        let mut states = Vec::with_capacity(vals.len());
-        for val in vals {
-            let state = ReplicaState::decode(&*val)
-                .map_err(|_| Error::InvalidData("replica state desc".into()))?;
-            states.push(state);
-        }
-        Ok(states)
+        self.list_prefix(
+            SYSTEM_REPLICA_STATE_COLLECTION_ID,
+            group_id.to_le_bytes().as_slice(),
+        )
+        .await
     }
 
     pub async fn list_group_state(&self) -> Result<Vec<GroupState>> {
-        let mut states: HashMap<u64, GroupState> = HashMap::new();
+        let mut replicas: HashMap<u64, Vec<ReplicaState>> = HashMap::new();
         for state in self.list_replica_state().await? {
-            match states.entry(state.group_id) {
-                Entry::Occupied(mut ent) => {
-                    let group = ent.get_mut();
-                    if state.role == RaftRole::Leader as i32 {
-                        group.leader_id = Some(state.replica_id);
-                    } else if group.leader_id == Some(state.replica_id) {
-                        group.leader_id = None;
-                    }
-                    group
-                        .replicas
-                        .retain(|desc| desc.replica_id != state.replica_id);
-                    group.replicas.push(state);
-                }
-                Entry::Vacant(ent) => {
-                    let leader_id = if state.role == RaftRole::Leader as i32 {
-                        Some(state.replica_id)
-                    } else {
-                        None
-                    };
-                    ent.insert(GroupState {
-                        group_id: state.group_id,
-                        leader_id,
-                        replicas: vec![state],
-                    });
-                }
-            }
+            let states = replicas.entry(state.group_id).or_default();
+            states.retain(|desc| desc.replica_id != state.replica_id);
+            states.push(state);
         }
-        Ok(states.into_iter().map(|(_, v)| v).collect())
+        Ok(replicas
+            .into_iter()
+            .map(|(group_id, replicas)| build_group_state(group_id, replicas))
+            .collect())
+    }
+
+    /// Delete the replica states of a group, which make up the group state.
+    pub async fn delete_group_state(&self, group_id: u64) -> Result<()> {
+        for state in self.group_replica_states(group_id).await? {
+            self.remove_replica_state(group_id, state.replica_id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_group_state(&self, group_id: u64) -> Result<Option<GroupState>> {
+        let replicas = self.group_replica_states(group_id).await?;
+        if replicas.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(build_group_state(group_id, replicas)))
     }
 
     pub async fn get_root_desc(&self) -> Result<RootDesc> {
@@ -486,12 +885,25 @@ impl Schema {
         })
     }
 
+    /// List the events which make up the snapshot of metadata. The listing is aborted with
+    /// `WatchSnapshotTooLarge` as soon as there are more than `max_events` events, so the rest
+    /// of metadata isn't loaded.
     pub async fn list_all_events(
         &self,
         cur_groups: HashMap<u64, u64>,
+        max_events: Option<usize>,
     ) -> Result<(Vec<UpdateEvent>, Vec<DeleteEvent>)> {
         let mut updates = Vec::new();
         let mut deletes = Vec::new();
+        let check_limit = |updates: &Vec<UpdateEvent>, deletes: &Vec<DeleteEvent>| {
+            let num_events = updates.len() + deletes.len();
+            match max_events {
+                Some(max_events) if num_events > max_events => {
+                    Err(Error::WatchSnapshotTooLarge(num_events, max_events))
+                }
+                _ => Ok(()),
+            }
+        };
 
         // list nodes.
         let nodes = self
@@ -503,6 +915,7 @@ impl Schema {
             })
             .collect::<Vec<UpdateEvent>>();
         updates.extend_from_slice(&nodes);
+        check_limit(&updates, &deletes)?;
 
         // list databases.
         let dbs = self
@@ -514,6 +927,7 @@ impl Schema {
             })
             .collect::<Vec<UpdateEvent>>();
         updates.extend_from_slice(&dbs);
+        check_limit(&updates, &deletes)?;
 
         // list collections.
         let collections = self
@@ -525,6 +939,19 @@ impl Schema {
             })
             .collect::<Vec<UpdateEvent>>();
         updates.extend_from_slice(&collections);
+        check_limit(&updates, &deletes)?;
+
+        // list aliases.
+        let aliases = self
+            .list_alias()
+            .await?
+            .into_iter()
+            .map(|alias| UpdateEvent {
+                event: Some(update_event::Event::Alias(alias)),
+            })
+            .collect::<Vec<UpdateEvent>>();
+        updates.extend_from_slice(&aliases);
+        check_limit(&updates, &deletes)?;
 
         // list groups.
         let groups = self
@@ -577,6 +1004,7 @@ impl Schema {
             deletes.extend_from_slice(&delete_desc);
             deletes.extend_from_slice(&delete_state);
         }
+        check_limit(&updates, &deletes)?;
 
         // list group_state.
         let group_states = self
@@ -589,6 +1017,7 @@ impl Schema {
             })
             .collect::<Vec<UpdateEvent>>();
         updates.extend_from_slice(&group_states);
+        check_limit(&updates, &deletes)?;
 
         Ok((updates, deletes))
     }
@@ -628,25 +1057,11 @@ impl Schema {
     }
 
     pub async fn list_job(&self) -> Result<Vec<BackgroundJob>> {
-        let vals = self.list(SYSTEM_JOB_COLLECTION_ID).await?;
-        let mut jobs = Vec::with_capacity(vals.len());
-        for val in vals {
-            let job = BackgroundJob::decode(&*val)
-                .map_err(|_| Error::InvalidData("backgroud job".into()))?;
-            jobs.push(job.to_owned());
-        }
-        Ok(jobs)
+        self.list(SYSTEM_JOB_COLLECTION_ID).await
     }
 
     pub async fn list_history_job(&self) -> Result<Vec<BackgroundJob>> {
-        let vals = self.list(SYSTEM_JOB_HISTORY_COLLECTION_ID).await?;
-        let mut jobs = Vec::with_capacity(vals.len());
-        for val in vals {
-            let job = BackgroundJob::decode(&*val)
-                .map_err(|_| Error::InvalidData("backgroud job".into()))?;
-            jobs.push(job.to_owned());
-        }
-        Ok(jobs)
+        self.list(SYSTEM_JOB_HISTORY_COLLECTION_ID).await
     }
 
     pub async fn get_job_history(&self, id: &u64) -> Result<Option<BackgroundJob>> {
@@ -691,10 +1106,11 @@ impl Schema {
         debug_assert_ne!(cfg_cpu_nums, 0);
         let _timer = super::metrics::BOOTSTRAP_DURATION_SECONDS.start_timer();
 
-        if let Some(exist_cluster_id) = self.cluster_id().await? {
+        if let Some(exist_cluster_id) = self.load_cluster_id().await? {
             if exist_cluster_id != cluster_id {
                 return Err(Error::ClusterNotMatch);
             }
+            self.set_cluster_id(exist_cluster_id);
             return Ok(());
         }
 
@@ -711,6 +1127,7 @@ impl Schema {
         batch.put_database(DatabaseDesc {
             id: SYSTEM_DATABASE_ID.to_owned(),
             name: SYSTEM_DATABASE_NAME.to_owned(),
+            ..Default::default()
         });
 
         batch.put_node(NodeDesc {
@@ -722,6 +1139,7 @@ impl Schema {
                 leader_count: 0,
             }),
             status: NodeStatus::Active as i32,
+            ..Default::default()
         });
 
         batch.put_group(GroupDesc {
@@ -753,6 +1171,7 @@ impl Schema {
             voted_for: FIRST_REPLICA_ID,
             role: RaftRole::Leader.into(),
             node_id: FIRST_NODE_ID,
+            applied_index: 0,
         });
 
         batch.put_replica_state(ReplicaState {
@@ -762,11 +1181,17 @@ impl Schema {
             voted_for: INIT_USER_REPLICA_ID,
             role: RaftRole::Leader.into(),
             node_id: FIRST_NODE_ID,
+            applied_index: 0,
         });
 
+        let timer = super::metrics::BOOTSTRAP_STEP_DURATION_SECONDS
+            .init_root_meta
+            .start_timer();
         self.batch_write(batch.build()).await?;
+        let elapsed = timer.stop_and_record();
 
-        info!(cluster = ?String::from_utf8_lossy(&cluster_id), "boostrap root successfully");
+        info!(cluster = ?String::from_utf8_lossy(&cluster_id), "boostrap root successfully, init root meta takes {elapsed:.3}s");
+        self.set_cluster_id(cluster_id);
 
         Ok(())
     }
@@ -814,6 +1239,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(self_collection);
 
@@ -824,6 +1250,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(db_collection);
 
@@ -834,6 +1261,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(meta_collection);
 
@@ -844,6 +1272,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(node_collection);
 
@@ -854,6 +1283,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(group_collection);
 
@@ -864,6 +1294,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(replica_state_collection);
 
@@ -874,6 +1305,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(job_collection);
 
@@ -884,6 +1316,7 @@ impl Schema {
             partition: Some(collection_desc::Partition::Range(
                 collection_desc::RangePartition {},
             )),
+            ..Default::default()
         };
         batch.put_collection(job_history_collection);
     }
@@ -923,10 +1356,34 @@ impl Schema {
 
 // internal methods.
 impl Schema {
+    async fn check_alias_target(&self, alias: &CollectionAlias) -> Result<()> {
+        if self
+            .get_collection_by_id(alias.db, alias.collection_id)
+            .await?
+            .is_none()
+        {
+            return Err(Error::InvalidArgument(format!(
+                "alias {} target collection {} not found",
+                alias.name, alias.collection_id
+            )));
+        }
+        Ok(())
+    }
+
     async fn get_meta(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
         self.get(SYSTEM_MATE_COLLECTION_ID, key).await
     }
 
+    async fn load_cluster_id(&self) -> Result<Option<Vec<u8>>> {
+        self.get_meta(META_CLUSTER_ID_KEY.as_bytes()).await
+    }
+
+    fn set_cluster_id(&self, cluster_id: Vec<u8>) {
+        let mut cached = self.cluster_id.write().unwrap();
+        debug_assert!(cached.as_ref().map(|id| id == &cluster_id).unwrap_or(true));
+        *cached = Some(cluster_id);
+    }
+
     async fn batch_write(&self, batch: BatchWriteRequest) -> Result<()> {
         self.store.batch_write(batch).await
     }
@@ -941,13 +1398,36 @@ impl Schema {
         self.store.delete(shard_id, key).await
     }
 
-    async fn list(&self, collection_id: u64) -> Result<Vec<Vec<u8>>> {
+    async fn list<T: Message + Default>(&self, collection_id: u64) -> Result<Vec<T>> {
         self.list_prefix(collection_id, &[]).await
     }
 
-    async fn list_prefix(&self, collection_id: u64, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+    /// List and decode the records of the specified key prefix. A record which could not be
+    /// decoded fails the listing with `Error::CorruptMetadata`, or is skipped if `skip_corrupt`
+    /// is set.
+    async fn list_prefix<T: Message + Default>(
+        &self,
+        collection_id: u64,
+        prefix: &[u8],
+    ) -> Result<Vec<T>> {
         let shard_id = Self::system_shard_id(collection_id); // System collection only have one shard.
-        self.store.list(shard_id, prefix).await
+        let entries = self.store.list(shard_id, prefix).await?;
+        let mut records = Vec::with_capacity(entries.len());
+        for (key, val) in entries {
+            match T::decode(&*val) {
+                Ok(record) => records.push(record),
+                Err(err) => {
+                    let key = corrupt_record_key(collection_id, &key);
+                    error!(key = %key, err = ?err, skip = self.skip_corrupt, "corrupt metadata record");
+                    if !self.skip_corrupt {
+                        return Err(Error::CorruptMetadata(key));
+                    }
+                    metrics::CORRUPT_METADATA_TOTAL.inc();
+                    self.corrupted.store(true, Ordering::Release);
+                }
+            }
+        }
+        Ok(records)
     }
 
     async fn next_id(&self, id_type: &str) -> Result<u64> {
@@ -1095,6 +1575,15 @@ impl PutBatchBuilder {
         self
     }
 
+    fn put_alias(&mut self, alias: CollectionAlias) -> &mut Self {
+        self.put(
+            SYSTEM_MATE_COLLECTION_ID,
+            alias_key(alias.db, &alias.name),
+            alias.encode_to_vec(),
+        );
+        self
+    }
+
     fn put_job(&mut self, desc: BackgroundJob) -> &mut Self {
         self.put(
             SYSTEM_JOB_COLLECTION_ID,
@@ -1118,7 +1607,24 @@ impl PutBatchBuilder {
     }
 }
 
+/// Format the key of a corrupt record as `<system collection>/<hex key>`.
 #[inline]
+fn corrupt_record_key(collection_id: u64, key: &[u8]) -> String {
+    let collection = match collection_id {
+        SYSTEM_COLLECTION_COLLECTION_ID => SYSTEM_COLLECTION_COLLECTION,
+        SYSTEM_DATABASE_COLLECTION_ID => SYSTEM_DATABASE_COLLECTION,
+        SYSTEM_MATE_COLLECTION_ID => SYSTEM_MATE_COLLECTION,
+        SYSTEM_NODE_COLLECTION_ID => SYSTEM_NODE_COLLECTION,
+        SYSTEM_GROUP_COLLECTION_ID => SYSTEM_GROUP_COLLECTION,
+        SYSTEM_REPLICA_STATE_COLLECTION_ID => SYSTEM_REPLICA_STATE_COLLECTION,
+        SYSTEM_JOB_COLLECTION_ID => SYSTEM_JOB_COLLECTION,
+        SYSTEM_JOB_HISTORY_COLLECTION_ID => SYSTEM_JOB_HISTORY_COLLECTION,
+        _ => "unknown",
+    };
+    let key = key.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!("{collection}/{key}")
+}
+
 fn collection_key(database_id: u64, collection_name: &str) -> Vec<u8> {
     let mut buf = Vec::with_capacity(core::mem::size_of::<u64>() + collection_name.len());
     buf.extend_from_slice(database_id.to_le_bytes().as_slice());
@@ -1126,6 +1632,21 @@ fn collection_key(database_id: u64, collection_name: &str) -> Vec<u8> {
     buf
 }
 
+/// Whether `current` hosts any shard which is not hosted by the `previous` desc of the group.
+#[inline]
+fn has_new_shards(previous: &GroupDesc, current: &GroupDesc) -> bool {
+    current
+        .shards
+        .iter()
+        .any(|s| !previous.shards.iter().any(|p| p.id == s.id))
+}
+
+fn alias_key(database_id: u64, alias: &str) -> Vec<u8> {
+    let mut buf = META_ALIAS_KEY_PREFIX.as_bytes().to_vec();
+    buf.extend_from_slice(&collection_key(database_id, alias));
+    buf
+}
+
 #[inline]
 fn group_key(group_id: u64) -> Vec<u8> {
     let mut buf = Vec::with_capacity(core::mem::size_of::<u64>());
@@ -1140,3 +1661,18 @@ fn replica_key(group_id: u64, replica_id: u64) -> Vec<u8> {
     buf.extend_from_slice(replica_id.to_le_bytes().as_slice());
     buf
 }
+
+/// Build the group state from the replica states. The state of a deposed leader might not be
+/// updated yet, so the leader with the largest term wins and its term is the leader epoch.
+fn build_group_state(group_id: u64, replicas: Vec<ReplicaState>) -> GroupState {
+    let leader = replicas
+        .iter()
+        .filter(|r| r.role == RaftRole::Leader as i32)
+        .max_by_key(|r| r.term);
+    GroupState {
+        group_id,
+        leader_id: leader.map(|r| r.replica_id),
+        leader_epoch: leader.map(|r| r.term).unwrap_or_default(),
+        replicas,
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use engula_api::{server::v1::GroupStats, v1::ShardDesc};
+use serde::Serialize;
+
+#[derive(Clone, Copy)]
+struct GroupSize {
+    shard_count: u64,
+    approximate_keys: u64,
+    approximate_bytes: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct CollectionStats {
+    pub approximate_keys: u64,
+    pub approximate_bytes: u64,
+    /// The number of shards whose group has reported stats.
+    pub reported_shards: u64,
+    pub total_shards: u64,
+}
+
+/// Caches the size estimates of groups reported by leaders via heartbeat. The stats of a
+/// collection is estimated by splitting the size of a group evenly across its shards, so the
+/// result is only approximate.
+#[derive(Clone, Default)]
+pub struct GroupStatsCache {
+    groups: Arc<Mutex<HashMap<u64, GroupSize>>>,
+}
+
+impl GroupStatsCache {
+    pub fn update(&self, stats: &GroupStats) {
+        let mut groups = self.groups.lock().unwrap();
+        groups.insert(
+            stats.group_id,
+            GroupSize {
+                shard_count: stats.shard_count,
+                approximate_keys: stats.approximate_keys,
+                approximate_bytes: stats.approximate_bytes,
+            },
+        );
+    }
+
+    pub fn collection_stats(&self, shards: &[(u64 /* group */, ShardDesc)]) -> CollectionStats {
+        let groups = self.groups.lock().unwrap();
+        let mut stats = CollectionStats {
+            total_shards: shards.len() as u64,
+            ..Default::default()
+        };
+        for (group_id, _) in shards {
+            if let Some(size) = groups.get(group_id) {
+                let shard_count = size.shard_count.max(1);
+                stats.approximate_keys += size.approximate_keys / shard_count;
+                stats.approximate_bytes += size.approximate_bytes / shard_count;
+                stats.reported_shards += 1;
+            }
+        }
+        stats
+    }
+
+    pub fn reset(&self) {
+        self.groups.lock().unwrap().clear();
+    }
+}
+
+/// The interval before advising the same group to compact again, it leaves the time for the
+/// compaction to finish and be reflected in the reported stats.
+const COMPACTION_HINT_COOLDOWN: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Copy)]
+struct CompactionCandidate {
+    leader_node: u64,
+    garbage_ratio: f64,
+}
+
+/// Picks the groups whose garbage ratio exceeds the threshold from the stats reported by leaders,
+/// and advises their leaders to compact. The advice is throttled so that only a few groups are
+/// compacted at once.
+#[derive(Default)]
+pub struct CompactionAdvisor {
+    core: Mutex<CompactionAdvisorCore>,
+}
+
+#[derive(Default)]
+struct CompactionAdvisorCore {
+    candidates: HashMap<u64, CompactionCandidate>,
+    last_advised: HashMap<u64, Instant>,
+}
+
+impl CompactionAdvisor {
+    pub fn update(&self, leader_node: u64, stats: &GroupStats, threshold: f64) {
+        let total_bytes = stats.approximate_bytes + stats.approximate_garbage_bytes;
+        let mut core = self.core.lock().unwrap();
+        if total_bytes == 0 {
+            core.candidates.remove(&stats.group_id);
+            return;
+        }
+        let garbage_ratio = stats.approximate_garbage_bytes as f64 / total_bytes as f64;
+        if garbage_ratio < threshold {
+            core.candidates.remove(&stats.group_id);
+            return;
+        }
+        core.candidates.insert(
+            stats.group_id,
+            CompactionCandidate {
+                leader_node,
+                garbage_ratio,
+            },
+        );
+    }
+
+    /// Return the groups to compact of the nodes, grouped by the leader node. At most
+    /// `max_groups` groups are advised, the one with a higher garbage ratio goes first.
+    pub fn advise(
+        &self,
+        nodes: &[u64],
+        max_groups: usize,
+        now: Instant,
+    ) -> HashMap<u64 /* node */, Vec<u64>> {
+        let mut core = self.core.lock().unwrap();
+        core.last_advised.retain(|_, advised_at| {
+            now.saturating_duration_since(*advised_at) < COMPACTION_HINT_COOLDOWN
+        });
+
+        let mut candidates = core
+            .candidates
+            .iter()
+            .filter(|(group_id, c)| {
+                nodes.contains(&c.leader_node) && !core.last_advised.contains_key(group_id)
+            })
+            .map(|(group_id, c)| (*group_id, *c))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.1.garbage_ratio.total_cmp(&a.1.garbage_ratio));
+
+        let mut advice: HashMap<u64, Vec<u64>> = HashMap::default();
+        for (group_id, c) in candidates.into_iter().take(max_groups) {
+            core.candidates.remove(&group_id);
+            core.last_advised.insert(group_id, now);
+            advice.entry(c.leader_node).or_default().push(group_id);
+        }
+        advice
+    }
+
+    pub fn reset(&self) {
+        let mut core = self.core.lock().unwrap();
+        core.candidates.clear();
+        core.last_advised.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group_stats(group_id: u64, shard_count: u64, keys: u64, bytes: u64) -> GroupStats {
+        GroupStats {
+            group_id,
+            shard_count,
+            approximate_keys: keys,
+            approximate_bytes: bytes,
+            ..Default::default()
+        }
+    }
+
+    fn shard(group_id: u64, shard_id: u64) -> (u64, ShardDesc) {
+        (
+            group_id,
+            ShardDesc {
+                id: shard_id,
+                collection_id: 1,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn sum_collection_stats() {
+        let cache = GroupStatsCache::default();
+        cache.update(&group_stats(1, 2, 100, 1000));
+        cache.update(&group_stats(2, 1, 30, 300));
+
+        // one shard of group 1 and the only shard of group 2.
+        let shards = vec![shard(1, 1), shard(2, 2)];
+        assert_eq!(
+            cache.collection_stats(&shards),
+            CollectionStats {
+                approximate_keys: 80,
+                approximate_bytes: 800,
+                reported_shards: 2,
+                total_shards: 2,
+            }
+        );
+
+        // group 3 has not reported yet.
+        let shards = vec![shard(1, 1), shard(3, 3)];
+        assert_eq!(
+            cache.collection_stats(&shards),
+            CollectionStats {
+                approximate_keys: 50,
+                approximate_bytes: 500,
+                reported_shards: 1,
+                total_shards: 2,
+            }
+        );
+
+        cache.reset();
+        assert_eq!(cache.collection_stats(&shards).reported_shards, 0);
+    }
+
+    fn garbage_stats(group_id: u64, live_bytes: u64, garbage_bytes: u64) -> GroupStats {
+        GroupStats {
+            group_id,
+            approximate_bytes: live_bytes,
+            approximate_garbage_bytes: garbage_bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn advise_compaction_above_threshold() {
+        let advisor = CompactionAdvisor::default();
+        advisor.update(1, &garbage_stats(1, 100, 300), 0.5);
+        advisor.update(1, &garbage_stats(2, 300, 100), 0.5);
+        advisor.update(2, &garbage_stats(3, 100, 100), 0.5);
+        advisor.update(2, &garbage_stats(4, 0, 0), 0.5);
+
+        let now = Instant::now();
+        let mut advice = advisor.advise(&[1, 2], 8, now);
+        advice
+            .values_mut()
+            .for_each(|groups| groups.sort_unstable());
+        assert_eq!(advice, HashMap::from([(1, vec![1]), (2, vec![3])]));
+
+        // The advised groups are not advised again within the cooldown.
+        advisor.update(1, &garbage_stats(1, 100, 300), 0.5);
+        assert!(advisor.advise(&[1, 2], 8, now).is_empty());
+        let advice = advisor.advise(&[1, 2], 8, now + COMPACTION_HINT_COOLDOWN);
+        assert_eq!(advice, HashMap::from([(1, vec![1])]));
+
+        // The group below threshold is no longer a candidate.
+        advisor.update(2, &garbage_stats(5, 100, 900), 0.5);
+        advisor.update(2, &garbage_stats(5, 900, 100), 0.5);
+        assert!(advisor.advise(&[1, 2], 8, now).is_empty());
+    }
+
+    #[test]
+    fn throttle_compaction_advice() {
+        let advisor = CompactionAdvisor::default();
+        for group_id in 1..=4 {
+            advisor.update(1, &garbage_stats(group_id, 100, 100 * group_id), 0.5);
+        }
+
+        // The groups of nodes which is not in heartbeat are skipped.
+        let now = Instant::now();
+        assert!(advisor.advise(&[2], 8, now).is_empty());
+
+        // The groups with higher garbage ratio go first.
+        assert_eq!(
+            advisor.advise(&[1], 2, now),
+            HashMap::from([(1, vec![4, 3])])
+        );
+        assert_eq!(
+            advisor.advise(&[1], 2, now),
+            HashMap::from([(1, vec![2, 1])])
+        );
+        assert!(advisor.advise(&[1], 2, now).is_empty());
+    }
+}
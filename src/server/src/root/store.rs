@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use engula_api::{
     server::v1::{
@@ -22,8 +22,12 @@ use engula_api::{
     v1::{DeleteRequest, GetRequest, PutRequest},
 };
 
+use super::metrics::*;
 use crate::{bootstrap::ROOT_GROUP_ID, node::replica::Replica, Error, Result};
 
+/// The max number of retries when the root replica lost leadership and no new leader is elected.
+const MAX_NOT_LEADER_RETRIES: u64 = 3;
+
 pub struct RootStore {
     replica: Arc<Replica>,
 }
@@ -33,28 +37,40 @@ impl RootStore {
         Self { replica }
     }
 
+    #[inline]
+    pub fn replica(&self) -> Arc<Replica> {
+        self.replica.clone()
+    }
+
     pub async fn batch_write(&self, batch: BatchWriteRequest) -> Result<()> {
-        self.submit_request(BatchWrite(batch)).await?;
+        self.submit_request(StoreOp::BatchWrite, BatchWrite(batch))
+            .await?;
         Ok(())
     }
 
     pub async fn put(&self, shard_id: u64, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
-        self.submit_request(Put(ShardPutRequest {
-            shard_id,
-            put: Some(PutRequest { key, value }),
-        }))
+        self.submit_request(
+            StoreOp::Put,
+            Put(ShardPutRequest {
+                shard_id,
+                put: Some(PutRequest { key, value }),
+            }),
+        )
         .await?;
         Ok(())
     }
 
     pub async fn get(&self, shard_id: u64, key: &[u8]) -> Result<Option<Vec<u8>>> {
         let resp = self
-            .submit_request(Get(ShardGetRequest {
-                shard_id,
-                get: Some(GetRequest {
-                    key: key.to_owned(),
+            .submit_request(
+                StoreOp::Get,
+                Get(ShardGetRequest {
+                    shard_id,
+                    get: Some(GetRequest {
+                        key: key.to_owned(),
+                    }),
                 }),
-            }))
+            )
             .await?;
         let resp = resp
             .response
@@ -69,22 +85,29 @@ impl RootStore {
     }
 
     pub async fn delete(&self, shard_id: u64, key: &[u8]) -> Result<()> {
-        self.submit_request(Delete(ShardDeleteRequest {
-            shard_id,
-            delete: Some(DeleteRequest {
-                key: key.to_owned(),
+        self.submit_request(
+            StoreOp::Delete,
+            Delete(ShardDeleteRequest {
+                shard_id,
+                delete: Some(DeleteRequest {
+                    key: key.to_owned(),
+                }),
             }),
-        }))
+        )
         .await?;
         Ok(())
     }
 
-    pub async fn list(&self, shard_id: u64, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+    /// List the key-value pairs of the specified key prefix.
+    pub async fn list(&self, shard_id: u64, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
         let resp = self
-            .submit_request(PrefixList(ShardPrefixListRequest {
-                shard_id,
-                prefix: prefix.to_owned(),
-            }))
+            .submit_request(
+                StoreOp::List,
+                PrefixList(ShardPrefixListRequest {
+                    shard_id,
+                    prefix: prefix.to_owned(),
+                }),
+            )
             .await?;
         let resp = resp
             .response
@@ -93,22 +116,114 @@ impl RootStore {
             .ok_or_else(|| Error::InvalidArgument("PrefixListUnionResponse".into()))?;
 
         if let group_response_union::Response::PrefixList(resp) = resp {
-            Ok(resp.values)
+            Ok(resp.keys.into_iter().zip(resp.values).collect())
         } else {
             Err(Error::InvalidArgument("PrefixListResponse".into()))
         }
     }
 
-    async fn submit_request(&self, req: Request) -> Result<GroupResponse> {
+    async fn submit_request(&self, op: StoreOp, req: Request) -> Result<GroupResponse> {
         use crate::node::replica::{retry::execute, ExecCtx};
 
-        let epoch = self.replica.epoch();
-        let request = GroupRequest {
-            group_id: ROOT_GROUP_ID,
-            epoch,
-            request: Some(GroupRequestUnion { request: Some(req) }),
-        };
+        retry_not_leader(op, || {
+            let request = GroupRequest {
+                group_id: ROOT_GROUP_ID,
+                epoch: self.replica.epoch(),
+                request: Some(GroupRequestUnion {
+                    request: Some(req.clone()),
+                }),
+            };
+            async move { execute(&self.replica, ExecCtx::default(), request).await }
+        })
+        .await
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum StoreOp {
+    Get,
+    Put,
+    Delete,
+    List,
+    BatchWrite,
+}
+
+impl StoreOp {
+    fn counter(self, metric: &RootStoreOperationTotal) -> &prometheus::IntCounter {
+        match self {
+            StoreOp::Get => &metric.get,
+            StoreOp::Put => &metric.put,
+            StoreOp::Delete => &metric.delete,
+            StoreOp::List => &metric.list,
+            StoreOp::BatchWrite => &metric.batch_write,
+        }
+    }
+}
+
+/// Retry the operation if the leadership of root replica is lost but no new leader is elected,
+/// since the leadership might come back after election.
+async fn retry_not_leader<F, Fut, T>(op: StoreOp, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        op.counter(&ROOT_STORE_ATTEMPT_TOTAL).inc();
+        match f().await {
+            Err(Error::NotLeader(group_id, term, None)) => {
+                if retries >= MAX_NOT_LEADER_RETRIES {
+                    op.counter(&ROOT_STORE_RETRY_EXHAUSTED_TOTAL).inc();
+                    return Err(Error::NotLeader(group_id, term, None));
+                }
+                retries += 1;
+                op.counter(&ROOT_STORE_RETRY_TOTAL).inc();
+                crate::runtime::time::sleep(Duration::from_millis(10 * retries)).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::runtime::ExecutorOwner;
+
+    fn flap(times: u64) -> impl FnMut() -> std::future::Ready<Result<()>> {
+        let flaps = AtomicU64::new(0);
+        move || {
+            if flaps.fetch_add(1, Ordering::SeqCst) < times {
+                std::future::ready(Err(Error::NotLeader(ROOT_GROUP_ID, 1, None)))
+            } else {
+                std::future::ready(Ok(()))
+            }
+        }
+    }
+
+    #[test]
+    fn retry_leadership_flaps() {
+        let owner = ExecutorOwner::new(1);
+        owner.executor().block_on(async {
+            let attempts = ROOT_STORE_ATTEMPT_TOTAL.get.get();
+            let retries = ROOT_STORE_RETRY_TOTAL.get.get();
+            let exhausted = ROOT_STORE_RETRY_EXHAUSTED_TOTAL.get.get();
+            retry_not_leader(StoreOp::Get, flap(2)).await.unwrap();
+            // Other tests might access root store concurrently.
+            assert!(ROOT_STORE_ATTEMPT_TOTAL.get.get() - attempts >= 3);
+            assert!(ROOT_STORE_RETRY_TOTAL.get.get() - retries >= 2);
+            assert!(ROOT_STORE_RETRY_EXHAUSTED_TOTAL.get.get() >= exhausted);
 
-        execute(&self.replica, ExecCtx::default(), request).await
+            let attempts = ROOT_STORE_ATTEMPT_TOTAL.put.get();
+            let retries = ROOT_STORE_RETRY_TOTAL.put.get();
+            let exhausted = ROOT_STORE_RETRY_EXHAUSTED_TOTAL.put.get();
+            let res = retry_not_leader(StoreOp::Put, flap(MAX_NOT_LEADER_RETRIES + 1)).await;
+            assert!(matches!(res, Err(Error::NotLeader(..))));
+            assert!(ROOT_STORE_ATTEMPT_TOTAL.put.get() - attempts > MAX_NOT_LEADER_RETRIES);
+            assert!(ROOT_STORE_RETRY_TOTAL.put.get() - retries >= MAX_NOT_LEADER_RETRIES);
+            assert!(ROOT_STORE_RETRY_EXHAUSTED_TOTAL.put.get() - exhausted >= 1);
+        });
     }
 }
@@ -13,61 +13,420 @@
 // limitations under the License.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     sync::{Arc, Mutex},
     task::{Poll, Waker},
+    time::{Duration, Instant},
     vec,
 };
 
 use engula_api::server::v1::{
-    watch_response::{DeleteEvent, UpdateEvent},
-    WatchResponse,
+    watch_response::{delete_event, update_event, DeleteEvent, UpdateEvent},
+    GroupDesc, WatchResponse,
 };
 use futures::Stream;
+use serde::{Deserialize, Serialize};
 use tokio::sync::{RwLock, RwLockWriteGuard};
+use tracing::{error, warn};
 
-use crate::Error;
+use super::Clock;
+use crate::{
+    runtime::{Executor, TaskPriority},
+    Error,
+};
 
-#[derive(Default)]
 pub struct WatchHub {
     inner: Arc<RwLock<WatchHubInner>>,
+    /// The sequence of the last emitted events, the lock serializes the emitting of events to
+    /// preserve the ordering of each watcher.
+    sequence: tokio::sync::Mutex<u64>,
+    executor: Option<Executor>,
+    notify_concurrency: usize,
+    /// The watchers which don't consume the delivered events within it are handled by their
+    /// backpressure policy, zero means no deadline.
+    delivery_deadline: Duration,
+    retention: Mutex<RetentionBuffer>,
+    clock: Arc<dyn Clock>,
+    /// Delay each delivery to a watcher, see `RootTestingKnobs::inject_watch_delivery_delay`.
+    injected_delivery_delay: Duration,
 }
 
 #[derive(Default)]
 pub struct WatchHubInner {
     next_watcher_id: u64,
-    watchers: HashMap<u64, Watcher>,
+    watchers: HashMap<u64, WatcherHandle>,
+}
+
+/// The key of the object an event applies to.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum EventKey {
+    Node(u64),
+    Group(u64),
+    GroupState(u64),
+    Database(u64),
+    Collection(u64),
+    CollectionOptions(u64),
+    Alias(u64, String),
+    ReplicaRole(u64, u64),
+}
+
+impl EventKey {
+    fn event_type(&self) -> EventType {
+        match self {
+            EventKey::Node(_) => EventType::Node,
+            EventKey::Group(_) => EventType::Group,
+            EventKey::GroupState(_) => EventType::GroupState,
+            EventKey::Database(_) => EventType::Database,
+            EventKey::Collection(_) => EventType::Collection,
+            EventKey::CollectionOptions(_) => EventType::CollectionOptions,
+            EventKey::Alias(..) => EventType::Alias,
+            EventKey::ReplicaRole(..) => EventType::ReplicaRole,
+        }
+    }
+}
+
+/// The kind of the object an event applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventType {
+    Node,
+    Group,
+    GroupState,
+    Database,
+    Collection,
+    CollectionOptions,
+    Alias,
+    ReplicaRole,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::Node => "node",
+            EventType::Group => "group",
+            EventType::GroupState => "group_state",
+            EventType::Database => "database",
+            EventType::Collection => "collection",
+            EventType::CollectionOptions => "collection_options",
+            EventType::Alias => "alias",
+            EventType::ReplicaRole => "replica_role",
+        }
+    }
+}
+
+impl std::str::FromStr for EventType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "node" => Ok(EventType::Node),
+            "group" => Ok(EventType::Group),
+            "group_state" => Ok(EventType::GroupState),
+            "database" => Ok(EventType::Database),
+            "collection" => Ok(EventType::Collection),
+            "collection_options" => Ok(EventType::CollectionOptions),
+            "alias" => Ok(EventType::Alias),
+            "replica_role" => Ok(EventType::ReplicaRole),
+            _ => Err(Error::InvalidArgument(format!("unknown event type {s}"))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum RetainedEvent {
+    Update(UpdateEvent),
+    Delete(DeleteEvent),
+}
+
+impl RetainedEvent {
+    pub fn event_type(&self) -> Option<EventType> {
+        self.key().map(|key| key.event_type())
+    }
+
+    fn key(&self) -> Option<EventKey> {
+        match self {
+            RetainedEvent::Update(update) => update_key(update),
+            RetainedEvent::Delete(delete) => delete_key(delete),
+        }
+    }
+}
+
+fn update_key(update: &UpdateEvent) -> Option<EventKey> {
+    match update.event.as_ref()? {
+        update_event::Event::Node(desc) => Some(EventKey::Node(desc.id)),
+        update_event::Event::Group(desc) => Some(EventKey::Group(desc.id)),
+        update_event::Event::GroupState(state) => Some(EventKey::GroupState(state.group_id)),
+        update_event::Event::Database(desc) => Some(EventKey::Database(desc.id)),
+        update_event::Event::Collection(desc) => Some(EventKey::Collection(desc.id)),
+        update_event::Event::CollectionOptions(change) => {
+            Some(EventKey::CollectionOptions(change.collection_id))
+        }
+        update_event::Event::Alias(alias) => Some(EventKey::Alias(alias.db, alias.name.to_owned())),
+        update_event::Event::ReplicaRole(change) => {
+            Some(EventKey::ReplicaRole(change.group_id, change.replica_id))
+        }
+    }
+}
+
+fn delete_key(delete: &DeleteEvent) -> Option<EventKey> {
+    match delete.event.as_ref()? {
+        delete_event::Event::Node(id) => Some(EventKey::Node(*id)),
+        delete_event::Event::Group(id) => Some(EventKey::Group(*id)),
+        delete_event::Event::GroupState(id) => Some(EventKey::GroupState(*id)),
+        delete_event::Event::Database(id) => Some(EventKey::Database(*id)),
+        delete_event::Event::Collection(id) => Some(EventKey::Collection(*id)),
+        delete_event::Event::Alias(alias) => Some(EventKey::Alias(alias.db, alias.name.to_owned())),
+    }
+}
+
+/// Keep the events of the subscribed types, all types are subscribed if `types` is empty.
+fn filter_event_types(
+    types: &[EventType],
+    updates: &[UpdateEvent],
+    deletes: &[DeleteEvent],
+) -> (Vec<UpdateEvent>, Vec<DeleteEvent>) {
+    let subscribed = |key: Option<EventKey>| {
+        types.is_empty() || matches!(key, Some(key) if types.contains(&key.event_type()))
+    };
+    let updates = updates
+        .iter()
+        .filter(|update| subscribed(update_key(update)))
+        .cloned()
+        .collect();
+    let deletes = deletes
+        .iter()
+        .filter(|delete| subscribed(delete_key(delete)))
+        .cloned()
+        .collect();
+    (updates, deletes)
+}
+
+/// Retains the emitted events for resuming watchers. The events older than the low-water mark
+/// are compacted periodically, only the latest event of each object is kept, so the memory is
+/// bounded by the number of objects.
+#[derive(Default)]
+struct RetentionBuffer {
+    events: VecDeque<(u64 /* seq */, RetainedEvent)>,
+    /// The number of latest sequences whose events are retained as they are.
+    window: u64,
+    /// The events whose sequence is not larger than it might be dropped by compaction.
+    compacted_seq: u64,
+    last_seq: u64,
+    /// The number of non-contiguous sequences recorded, which means some events are lost from
+    /// the view of watchers.
+    gaps: u64,
+    /// The number of events when last compaction finished.
+    compacted_len: usize,
+}
+
+impl RetentionBuffer {
+    fn new(window: u64) -> Self {
+        RetentionBuffer {
+            window,
+            ..Default::default()
+        }
+    }
+
+    /// Record the events emitted with `seq`, which is expected to be contiguous to the last one.
+    fn record(&mut self, seq: u64, updates: &[UpdateEvent], deletes: &[DeleteEvent]) {
+        let expect = self.last_seq + 1;
+        if seq != expect {
+            self.gaps += 1;
+            super::metrics::WATCH_SEQUENCE_GAP_TOTAL.inc();
+            error!("watch event sequence gap detected, expect {expect} but emit {seq}");
+        }
+        self.last_seq = seq;
+        self.events.extend(
+            updates
+                .iter()
+                .cloned()
+                .map(|u| (seq, RetainedEvent::Update(u)))
+                .chain(
+                    deletes
+                        .iter()
+                        .cloned()
+                        .map(|d| (seq, RetainedEvent::Delete(d))),
+                ),
+        );
+        // Compact once the events grow twice since last compaction.
+        if self.events.len() > (self.compacted_len.max(self.window as usize)) * 2 {
+            self.compact(self.last_seq.saturating_sub(self.window));
+        }
+    }
+
+    /// Keep only the latest event of each object among the events not newer than `low_water`,
+    /// the events newer than it are retained as they are.
+    fn compact(&mut self, low_water: u64) {
+        if low_water <= self.compacted_seq {
+            return;
+        }
+        let split = self.events.partition_point(|(seq, _)| *seq <= low_water);
+        let mut seen = HashSet::new();
+        let mut compacted = VecDeque::with_capacity(split);
+        for (seq, event) in self.events.drain(..split).rev() {
+            match event.key() {
+                Some(key) if seen.insert(key) => compacted.push_front((seq, event)),
+                _ => {}
+            }
+        }
+        compacted.extend(self.events.drain(..));
+        self.events = compacted;
+        self.compacted_seq = low_water;
+        self.compacted_len = self.events.len();
+    }
+
+    /// Return the events emitted after `seq`, or `None` if some of them were compacted, then the
+    /// watcher must be initialized with a full snapshot.
+    fn events_since(&self, seq: u64) -> Option<(Vec<UpdateEvent>, Vec<DeleteEvent>)> {
+        if seq < self.compacted_seq || seq > self.last_seq {
+            return None;
+        }
+        let mut updates = Vec::new();
+        let mut deletes = Vec::new();
+        for (_, event) in self.events.iter().filter(|(s, _)| *s > seq) {
+            match event {
+                RetainedEvent::Update(update) => updates.push(update.to_owned()),
+                RetainedEvent::Delete(delete) => deletes.push(delete.to_owned()),
+            }
+        }
+        Some((updates, deletes))
+    }
+
+    /// Return the latest `limit` retained events of `types` in emitting order, all types are
+    /// included if `types` is empty.
+    fn tail(&self, limit: usize, types: &[EventType]) -> Vec<(u64, RetainedEvent)> {
+        let mut events = self
+            .events
+            .iter()
+            .rev()
+            .filter(|(_, event)| {
+                types.is_empty()
+                    || matches!(event.event_type(), Some(event_type) if types.contains(&event_type))
+            })
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+        events.reverse();
+        events
+    }
 }
 
 pub struct WatcherInitializer<'a> {
     _guard: RwLockWriteGuard<'a, WatchHubInner>,
     watcher_inner: Arc<Mutex<WatcherInner>>,
+    created_at: Instant,
 }
 
 impl<'a> WatcherInitializer<'a> {
     pub fn set_init_resp(&mut self, updates: Vec<UpdateEvent>, deletes: Vec<DeleteEvent>) {
         let mut inner = self.watcher_inner.lock().unwrap();
+        let (updates, deletes) = match inner.group_scope.as_mut() {
+            Some(scope) => scope.filter(&updates, &deletes),
+            None => (updates, deletes),
+        };
+        let (updates, deletes) = if inner.event_types.is_empty() {
+            (updates, deletes)
+        } else {
+            filter_event_types(&inner.event_types, &updates, &deletes)
+        };
         inner.updates.extend_from_slice(&updates);
         inner.deletes.extend_from_slice(&deletes);
+        inner.pending_since.get_or_insert(self.created_at);
+    }
+
+    /// Limit the events delivered to the watcher to the group and the nodes hosting its replicas.
+    pub fn set_group_scope(&mut self, group: &GroupDesc) {
+        let mut inner = self.watcher_inner.lock().unwrap();
+        inner.group_scope = Some(GroupScope {
+            group_id: group.id,
+            nodes: group.replicas.iter().map(|r| r.node_id).collect(),
+        });
+    }
+
+    pub fn set_cluster_id(&mut self, cluster_id: Vec<u8>) {
+        let mut inner = self.watcher_inner.lock().unwrap();
+        inner.cluster_id = Some(cluster_id);
+    }
+
+    pub fn set_backpressure(&mut self, backpressure: Backpressure) {
+        let mut inner = self.watcher_inner.lock().unwrap();
+        inner.backpressure = backpressure;
     }
 }
 
 impl WatchHub {
-    pub async fn create_watcher(&self) -> (Watcher, WatcherInitializer) {
+    /// Create a `WatchHub` which delivers events to watchers in `notify_concurrency` tasks, and
+    /// retains the events of latest `retention_window` sequences for resuming. A watcher which
+    /// doesn't consume the delivered events within `delivery_deadline` is handled by its
+    /// [`Backpressure`] policy.
+    pub fn new(
+        executor: Executor,
+        notify_concurrency: usize,
+        retention_window: u64,
+        delivery_deadline: Duration,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            inner: Default::default(),
+            sequence: Default::default(),
+            executor: Some(executor),
+            notify_concurrency,
+            delivery_deadline,
+            retention: Mutex::new(RetentionBuffer::new(retention_window)),
+            clock,
+            injected_delivery_delay: Duration::ZERO,
+        }
+    }
+
+    /// Delay each delivery to a watcher, to simulate slow watchers in tests.
+    pub fn with_injected_delivery_delay(mut self, delay: Duration) -> Self {
+        self.injected_delivery_delay = delay;
+        self
+    }
+
+    /// The sequence of the last emitted events.
+    pub fn last_sequence(&self) -> u64 {
+        self.retention.lock().unwrap().last_seq
+    }
+
+    /// Return the events emitted after `seq` to resume a watcher, `None` means the events have
+    /// been compacted and a full snapshot is required.
+    pub fn events_since(&self, seq: u64) -> Option<(Vec<UpdateEvent>, Vec<DeleteEvent>)> {
+        self.retention.lock().unwrap().events_since(seq)
+    }
+
+    /// Return the latest `limit` retained events of `types`, the superseded events older than the
+    /// retention window might have been compacted.
+    pub fn tail(&self, limit: usize, types: &[EventType]) -> Vec<(u64, RetainedEvent)> {
+        self.retention.lock().unwrap().tail(limit, types)
+    }
+
+    /// Create a watcher which subscribes to the events of `event_types`, or all events if it is
+    /// empty.
+    pub async fn create_watcher(&self, event_types: &[EventType]) -> (Watcher, WatcherInitializer) {
         let mut inner = self.inner.write().await;
         inner.next_watcher_id += 1;
-        let watcher_inner = Arc::new(Mutex::new(WatcherInner::default()));
+        let watcher_inner = Arc::new(Mutex::new(WatcherInner {
+            event_types: event_types.to_owned(),
+            ..Default::default()
+        }));
         let watcher = Watcher {
             id: inner.next_watcher_id,
             inner: watcher_inner.to_owned(),
         };
-        inner.watchers.insert(watcher.id, watcher.to_owned());
+        inner.watchers.insert(
+            watcher.id,
+            WatcherHandle {
+                id: watcher.id,
+                inner: watcher_inner.to_owned(),
+            },
+        );
         super::metrics::WATCH_TABLE_SIZE.set(inner.watchers.len() as i64);
         (
             watcher,
             WatcherInitializer {
                 _guard: inner,
                 watcher_inner,
+                created_at: self.clock.now().into_std(),
             },
         )
     }
@@ -96,10 +455,69 @@ impl WatchHub {
         deletes: Vec<DeleteEvent>,
         _err: Option<Error>,
     ) {
-        let inner = self.inner.read().await;
-        for w in inner.watchers.values() {
-            w.notify(&updates, &deletes, None) // TODO: clonable error
+        let mut sequence = self.sequence.lock().await;
+        *sequence += 1;
+        let seq = *sequence;
+        let watchers = {
+            // Take a snapshot to avoid holding lock during delivery.
+            let inner = self.inner.read().await;
+            inner.watchers.values().cloned().collect::<Vec<_>>()
+        };
+        self.retention
+            .lock()
+            .unwrap()
+            .record(seq, &updates, &deletes);
+        self.deliver(watchers, updates, deletes).await;
+    }
+
+    async fn deliver(
+        &self,
+        watchers: Vec<WatcherHandle>,
+        updates: Vec<UpdateEvent>,
+        deletes: Vec<DeleteEvent>,
+    ) {
+        let deadline = self.delivery_deadline;
+        let delay = self.injected_delivery_delay;
+        let now = self.clock.now().into_std();
+        let concurrency = self.notify_concurrency.min(watchers.len());
+        let executor = match &self.executor {
+            Some(executor) if concurrency > 1 => executor,
+            _ => {
+                for w in &watchers {
+                    inject_delay(delay);
+                    w.notify(&updates, &deletes, None, deadline, now) // TODO: clonable error
+                }
+                return;
+            }
+        };
+
+        let events = Arc::new((updates, deletes));
+        let chunk_size = (watchers.len() + concurrency - 1) / concurrency;
+        let mut handles = Vec::with_capacity(concurrency);
+        for chunk in watchers.chunks(chunk_size) {
+            let chunk = chunk.to_vec();
+            let events = events.clone();
+            handles.push(executor.spawn(None, TaskPriority::Middle, async move {
+                for w in &chunk {
+                    inject_delay(delay);
+                    w.notify(&events.0, &events.1, None, deadline, now)
+                }
+            }));
+        }
+        // Wait all deliveries finished, so the next events will be delivered after these.
+        futures::future::join_all(handles).await;
+    }
+
+    /// Terminate all watchers with the error, the clients are expected to resume watching.
+    pub async fn disconnect_all(&self, err: impl Fn() -> Error) {
+        let watchers = {
+            let inner = self.inner.read().await;
+            inner.watchers.values().cloned().collect::<Vec<_>>()
+        };
+        for w in &watchers {
+            w.disconnect(err());
         }
+        self.cleanup().await;
     }
 
     pub async fn cleanup(&self) {
@@ -111,9 +529,80 @@ impl WatchHub {
     }
 }
 
-#[derive(Clone)]
+fn inject_delay(delay: Duration) {
+    if !delay.is_zero() {
+        std::thread::sleep(delay);
+    }
+}
+
+/// How to handle a watcher which doesn't consume the delivered events before the deadline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    /// Terminate the watch stream with an error, the client is expected to resume watching.
+    #[default]
+    Disconnect,
+    /// Drop the events delivered after the deadline but keep the watch stream, the next response
+    /// is marked `resync` so the client knows that it misses events.
+    DropEvents,
+}
+
+/// The scope of a watcher subscribed to a single group.
+struct GroupScope {
+    group_id: u64,
+    /// The nodes hosting the replicas of the group, it follows the updates of the group.
+    nodes: HashSet<u64>,
+}
+
+impl GroupScope {
+    fn filter(
+        &mut self,
+        updates: &[UpdateEvent],
+        deletes: &[DeleteEvent],
+    ) -> (Vec<UpdateEvent>, Vec<DeleteEvent>) {
+        for update in updates {
+            if let Some(update_event::Event::Group(desc)) = &update.event {
+                if desc.id == self.group_id {
+                    self.nodes = desc.replicas.iter().map(|r| r.node_id).collect();
+                }
+            }
+        }
+        let updates = updates
+            .iter()
+            .filter(|update| match &update.event {
+                Some(update_event::Event::Node(desc)) => self.nodes.contains(&desc.id),
+                Some(update_event::Event::Group(desc)) => desc.id == self.group_id,
+                Some(update_event::Event::GroupState(state)) => state.group_id == self.group_id,
+                Some(update_event::Event::ReplicaRole(change)) => change.group_id == self.group_id,
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        let deletes = deletes
+            .iter()
+            .filter(|delete| match &delete.event {
+                Some(delete_event::Event::Node(id)) => self.nodes.contains(id),
+                Some(delete_event::Event::Group(id) | delete_event::Event::GroupState(id)) => {
+                    *id == self.group_id
+                }
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        (updates, deletes)
+    }
+}
+
+/// The watch stream returned to the client, the watcher is dropped once it is dropped.
 pub struct Watcher {
-    #[allow(dead_code)]
+    id: u64,
+    inner: Arc<std::sync::Mutex<WatcherInner>>,
+}
+
+/// The handle of a watcher kept by the hub to deliver events, dropping it doesn't affect the
+/// watcher.
+#[derive(Clone)]
+struct WatcherHandle {
     id: u64,
     inner: Arc<std::sync::Mutex<WatcherInner>>,
 }
@@ -123,17 +612,78 @@ struct WatcherInner {
     waker: Option<Waker>,
     updates: Vec<UpdateEvent>,
     deletes: Vec<DeleteEvent>,
+    /// The cluster id which isn't sent yet.
+    cluster_id: Option<Vec<u8>>,
     err: Option<Error>,
     dropped: bool,
+    backpressure: Backpressure,
+    /// Whether some events are dropped and the client isn't told yet.
+    resync: bool,
+    /// Since when the delivered events are pending to be consumed.
+    pending_since: Option<Instant>,
+    group_scope: Option<GroupScope>,
+    /// The subscribed event types, empty means all types.
+    event_types: Vec<EventType>,
 }
 
-impl Watcher {
-    fn notify(&self, updates: &[UpdateEvent], deletes: &[DeleteEvent], err: Option<Error>) {
+impl WatcherHandle {
+    fn notify(
+        &self,
+        updates: &[UpdateEvent],
+        deletes: &[DeleteEvent],
+        err: Option<Error>,
+        deadline: Duration,
+        now: Instant,
+    ) {
         let _timer = super::metrics::WATCH_NOTIFY_DURATION_SECONDS.start_timer();
         let mut inner = self.inner.lock().unwrap();
         if inner.dropped {
             return;
         }
+        let scoped;
+        let (updates, deletes) = match inner.group_scope.as_mut() {
+            Some(scope) => {
+                scoped = scope.filter(updates, deletes);
+                if scoped.0.is_empty() && scoped.1.is_empty() && err.is_none() {
+                    return;
+                }
+                (&scoped.0[..], &scoped.1[..])
+            }
+            None => (updates, deletes),
+        };
+        let subscribed;
+        let (updates, deletes) = if inner.event_types.is_empty() {
+            (updates, deletes)
+        } else {
+            subscribed = filter_event_types(&inner.event_types, updates, deletes);
+            if subscribed.0.is_empty() && subscribed.1.is_empty() && err.is_none() {
+                return;
+            }
+            (&subscribed.0[..], &subscribed.1[..])
+        };
+        match inner.pending_since {
+            Some(since) if !deadline.is_zero() && now.duration_since(since) > deadline => {
+                super::metrics::WATCH_DELIVERY_DEADLINE_EXCEEDED_TOTAL.inc();
+                if inner.backpressure == Backpressure::Disconnect {
+                    warn!(
+                        "watcher {} stalls beyond the delivery deadline, disconnect it",
+                        self.id
+                    );
+                    inner.updates.clear();
+                    inner.deletes.clear();
+                    inner.err = Some(Error::DeadlineExceeded("watch delivery".into()));
+                    inner.dropped = true;
+                } else {
+                    inner.resync = true;
+                }
+                if let Some(w) = inner.waker.take() {
+                    w.wake();
+                }
+                return;
+            }
+            Some(_) => {}
+            None => inner.pending_since = Some(now),
+        }
         inner.updates.extend_from_slice(updates); // TODO: set capcity limit
         inner.deletes.extend_from_slice(deletes);
         if err.is_some() && inner.err.is_none() {
@@ -143,6 +693,20 @@ impl Watcher {
             w.wake();
         }
     }
+
+    fn disconnect(&self, err: Error) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.dropped {
+            return;
+        }
+        inner.updates.clear();
+        inner.deletes.clear();
+        inner.err = Some(err);
+        inner.dropped = true;
+        if let Some(w) = inner.waker.take() {
+            w.wake();
+        }
+    }
 }
 
 impl Stream for Watcher {
@@ -153,16 +717,23 @@ impl Stream for Watcher {
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         let mut inner = self.inner.lock().unwrap();
-        if inner.dropped {
-            return Poll::Ready(None);
-        }
         if let Some(err) = inner.err.take() {
             return Poll::Ready(Some(Err(err.into())));
         }
-        if !inner.updates.is_empty() || !inner.deletes.is_empty() {
+        if inner.dropped {
+            return Poll::Ready(None);
+        }
+        if !inner.updates.is_empty()
+            || !inner.deletes.is_empty()
+            || inner.cluster_id.is_some()
+            || inner.resync
+        {
+            inner.pending_since = None;
             let resp = WatchResponse {
                 updates: std::mem::take(&mut inner.updates),
                 deletes: std::mem::take(&mut inner.deletes),
+                cluster_id: inner.cluster_id.take().unwrap_or_default(),
+                resync: std::mem::take(&mut inner.resync),
             };
             return Poll::Ready(Some(Ok(resp)));
         }
@@ -177,3 +748,434 @@ impl Drop for Watcher {
         inner.dropped = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use engula_api::{
+        server::v1::{watch_response::update_event, NodeDesc},
+        v1::{CollectionDesc, DatabaseDesc},
+    };
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{
+        root::clock::{ManualClock, SystemClock},
+        runtime::ExecutorOwner,
+    };
+
+    #[test]
+    fn notify_many_watchers_concurrently() {
+        let owner = ExecutorOwner::new(4);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            4,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            let mut watchers = Vec::new();
+            for _ in 0..100 {
+                let (w, _) = hub.create_watcher(&[]).await;
+                watchers.push(w);
+            }
+            for id in 1..=3 {
+                hub.notify_updates(vec![UpdateEvent {
+                    event: Some(update_event::Event::Database(DatabaseDesc {
+                        id,
+                        name: format!("db{id}"),
+                        ..Default::default()
+                    })),
+                }])
+                .await;
+            }
+            for w in &mut watchers {
+                let mut received = Vec::new();
+                while received.len() < 3 {
+                    let resp = w.next().await.unwrap().unwrap();
+                    for update in resp.updates {
+                        if let Some(update_event::Event::Database(db)) = update.event {
+                            received.push(db.id);
+                        }
+                    }
+                }
+                // The ordering of each watcher is preserved.
+                assert_eq!(received, vec![1, 2, 3]);
+            }
+        });
+    }
+
+    #[test]
+    fn notify_duration_scales_with_concurrency() {
+        const WATCHERS: u32 = 8;
+        const DELAY: Duration = Duration::from_millis(50);
+
+        // Return the time to notify the slow watchers.
+        let notify_duration = |concurrency: usize| {
+            let owner = ExecutorOwner::new(concurrency);
+            let executor = owner.executor();
+            let hub = WatchHub::new(
+                executor.to_owned(),
+                concurrency,
+                16,
+                Duration::ZERO,
+                Arc::new(SystemClock),
+            )
+            .with_injected_delivery_delay(DELAY);
+            executor.block_on(async {
+                let mut watchers = Vec::new();
+                for _ in 0..WATCHERS {
+                    let (w, _) = hub.create_watcher(&[]).await;
+                    watchers.push(w);
+                }
+                let start = Instant::now();
+                hub.notify_updates(vec![database_event(1, "a")]).await;
+                let elapsed = start.elapsed();
+                for w in &mut watchers {
+                    assert_eq!(w.next().await.unwrap().unwrap().updates.len(), 1);
+                }
+                elapsed
+            })
+        };
+
+        // The watchers are notified in serial.
+        let serial = DELAY * WATCHERS;
+        let elapsed = notify_duration(1);
+        assert!(elapsed >= serial, "{elapsed:?}");
+
+        // Each task notifies ceil(watchers / concurrency) watchers in serial.
+        for concurrency in [2, 4] {
+            let expect = DELAY * ((WATCHERS + concurrency - 1) / concurrency);
+            let elapsed = notify_duration(concurrency as usize);
+            assert!(elapsed >= expect, "concurrency {concurrency}: {elapsed:?}");
+            assert!(
+                elapsed < expect + DELAY * 2 && elapsed < serial,
+                "concurrency {concurrency}: {elapsed:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn watcher_outlives_delivery() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            let (mut w, _) = hub.create_watcher(&[]).await;
+            for id in 1..=3 {
+                hub.notify_updates(vec![database_event(id, &format!("db{id}"))])
+                    .await;
+                // The hub's handles dropped after delivery don't terminate the stream.
+                let resp = w.next().await.unwrap().unwrap();
+                assert_eq!(database_names(&resp.updates), vec![format!("db{id}")]);
+            }
+            hub.disconnect_all(|| Error::NotRootLeader(Default::default(), 1, None))
+                .await;
+            assert!(w.next().await.unwrap().is_err());
+            assert!(w.next().await.is_none());
+
+            // The watcher is removed from the hub once the stream is dropped.
+            let (w, _) = hub.create_watcher(&[]).await;
+            drop(w);
+            hub.cleanup().await;
+            assert!(hub.inner.read().await.watchers.is_empty());
+        });
+    }
+
+    #[test]
+    fn sequence_gap_detect() {
+        let mut buf = RetentionBuffer::new(1024);
+        for seq in 1..=3 {
+            buf.record(seq, &[database_event(1, &format!("a{seq}"))], &[]);
+        }
+        assert_eq!(buf.gaps, 0);
+
+        // Skip a sequence artificially.
+        let before = super::super::metrics::WATCH_SEQUENCE_GAP_TOTAL.get();
+        buf.record(5, &[database_event(1, "a5")], &[]);
+        assert_eq!(buf.gaps, 1);
+        assert!(super::super::metrics::WATCH_SEQUENCE_GAP_TOTAL.get() > before);
+
+        // Continue from the skipped one.
+        buf.record(6, &[database_event(1, "a6")], &[]);
+        assert_eq!(buf.gaps, 1);
+    }
+
+    #[test]
+    fn stalled_watcher_not_delay_others() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let deadline = Duration::from_millis(100);
+        let clock = Arc::new(ManualClock::new());
+        let hub = WatchHub::new(executor.to_owned(), 1, 16, deadline, clock.clone());
+        executor.block_on(async {
+            let (mut fast, _) = hub.create_watcher(&[]).await;
+            let (mut stalled, _) = hub.create_watcher(&[]).await;
+            let (mut lossy, mut initializer) = hub.create_watcher(&[]).await;
+            initializer.set_backpressure(Backpressure::DropEvents);
+            drop(initializer);
+
+            hub.notify_updates(vec![database_event(1, "a")]).await;
+            let resp = fast.next().await.unwrap().unwrap();
+            assert_eq!(database_names(&resp.updates), vec!["a".to_owned()]);
+
+            // The stalled watchers don't consume events beyond the deadline.
+            clock.advance(deadline * 2);
+            let before = super::super::metrics::WATCH_DELIVERY_DEADLINE_EXCEEDED_TOTAL.get();
+            let start = Instant::now();
+            hub.notify_updates(vec![database_event(2, "b")]).await;
+            assert!(start.elapsed() < deadline);
+            assert!(
+                super::super::metrics::WATCH_DELIVERY_DEADLINE_EXCEEDED_TOTAL.get() >= before + 2
+            );
+            let resp = fast.next().await.unwrap().unwrap();
+            assert_eq!(database_names(&resp.updates), vec!["b".to_owned()]);
+
+            // The stalled watcher is disconnected with an error.
+            assert!(stalled.next().await.unwrap().is_err());
+            assert!(stalled.next().await.is_none());
+
+            // The lossy watcher drops the events delivered after deadline but keeps the stream, and
+            // it is told to resync.
+            let resp = lossy.next().await.unwrap().unwrap();
+            assert_eq!(database_names(&resp.updates), vec!["a".to_owned()]);
+            assert!(resp.resync);
+            hub.notify_updates(vec![database_event(3, "c")]).await;
+            let resp = lossy.next().await.unwrap().unwrap();
+            assert_eq!(database_names(&resp.updates), vec!["c".to_owned()]);
+            assert!(!resp.resync);
+        });
+    }
+
+    fn database_event(id: u64, name: &str) -> UpdateEvent {
+        UpdateEvent {
+            event: Some(update_event::Event::Database(DatabaseDesc {
+                id,
+                name: name.to_owned(),
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn database_names(updates: &[UpdateEvent]) -> Vec<String> {
+        updates
+            .iter()
+            .filter_map(|u| match &u.event {
+                Some(update_event::Event::Database(db)) => Some(db.name.to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn disconnect_all_watchers() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            let (mut w1, _) = hub.create_watcher(&[]).await;
+            let (mut w2, _) = hub.create_watcher(&[]).await;
+            hub.notify_updates(vec![database_event(1, "a")]).await;
+
+            hub.disconnect_all(|| Error::NotRootLeader(Default::default(), 1, None))
+                .await;
+            for w in [&mut w1, &mut w2] {
+                // The pending events are discarded, the client resumes from what it received.
+                let status = w.next().await.unwrap().unwrap_err();
+                assert_eq!(status.code(), tonic::Code::Unknown);
+                assert!(w.next().await.is_none());
+            }
+        });
+    }
+
+    #[test]
+    fn retention_compaction() {
+        let mut buf = RetentionBuffer::new(1024);
+        // seq 1..=4 update database 1 repeatedly, seq 5 deletes database 2.
+        buf.record(1, &[database_event(2, "b")], &[]);
+        for seq in 2..=4 {
+            buf.record(seq, &[database_event(1, &format!("a{seq}"))], &[]);
+        }
+        buf.record(
+            5,
+            &[],
+            &[DeleteEvent {
+                event: Some(delete_event::Event::Database(2)),
+            }],
+        );
+        buf.record(6, &[database_event(1, "a6")], &[]);
+
+        buf.compact(5);
+        // The superseded updates of database 1 and 2 are dropped, the events newer than the
+        // low-water mark are retained as they are.
+        let seqs = buf.events.iter().map(|(seq, _)| *seq).collect::<Vec<_>>();
+        assert_eq!(seqs, vec![4, 5, 6]);
+
+        let (updates, deletes) = buf.events_since(5).unwrap();
+        assert_eq!(database_names(&updates), vec!["a6".to_owned()]);
+        assert!(deletes.is_empty());
+
+        // A compaction with smaller low-water mark is ignored.
+        buf.compact(3);
+        assert_eq!(buf.events.len(), 3);
+    }
+
+    #[test]
+    fn tail_with_type_filter() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        executor.block_on(async {
+            for id in 1..=3 {
+                hub.notify_updates(vec![
+                    database_event(id, &format!("db{id}")),
+                    UpdateEvent {
+                        event: Some(update_event::Event::Node(NodeDesc {
+                            id,
+                            ..Default::default()
+                        })),
+                    },
+                ])
+                .await;
+            }
+            hub.notify_deletes(vec![DeleteEvent {
+                event: Some(delete_event::Event::Group(1)),
+            }])
+            .await;
+        });
+
+        let types = "node,group"
+            .split(',')
+            .map(|t| t.parse::<EventType>().unwrap())
+            .collect::<Vec<_>>();
+        let events = hub.tail(16, &types);
+        let kinds = events
+            .iter()
+            .map(|(_, e)| e.event_type().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                EventType::Node,
+                EventType::Node,
+                EventType::Node,
+                EventType::Group
+            ]
+        );
+
+        // The limit applies to the matched events, and the latest ones are returned.
+        let events = hub.tail(2, &[EventType::Database]);
+        assert_eq!(
+            events.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(hub.tail(16, &[]).len(), 7);
+        assert!("shard".parse::<EventType>().is_err());
+    }
+
+    #[test]
+    fn subscribe_event_types() {
+        let owner = ExecutorOwner::new(1);
+        let executor = owner.executor();
+        let hub = WatchHub::new(
+            executor.to_owned(),
+            1,
+            16,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        let node_event = |id| UpdateEvent {
+            event: Some(update_event::Event::Node(NodeDesc {
+                id,
+                ..Default::default()
+            })),
+        };
+        let collection_event = |id| UpdateEvent {
+            event: Some(update_event::Event::Collection(CollectionDesc {
+                id,
+                ..Default::default()
+            })),
+        };
+        let event_types = |resp: &WatchResponse| {
+            let updates = resp.updates.iter().cloned().map(RetainedEvent::Update);
+            let deletes = resp.deletes.iter().cloned().map(RetainedEvent::Delete);
+            updates
+                .chain(deletes)
+                .map(|e| e.event_type().unwrap())
+                .collect::<Vec<_>>()
+        };
+        executor.block_on(async {
+            let (mut nodes, mut initializer) = hub.create_watcher(&[EventType::Node]).await;
+            initializer.set_init_resp(vec![node_event(1), collection_event(1)], vec![]);
+            drop(initializer);
+            let (mut collections, mut initializer) =
+                hub.create_watcher(&[EventType::Collection]).await;
+            initializer.set_init_resp(vec![node_event(1), collection_event(1)], vec![]);
+            drop(initializer);
+
+            // The snapshot contains the subscribed types only.
+            let resp = nodes.next().await.unwrap().unwrap();
+            assert_eq!(event_types(&resp), vec![EventType::Node]);
+            let resp = collections.next().await.unwrap().unwrap();
+            assert_eq!(event_types(&resp), vec![EventType::Collection]);
+
+            // The unsubscribed events are not delivered at all.
+            hub.notify_updates(vec![collection_event(2)]).await;
+            hub.notify_deletes(vec![DeleteEvent {
+                event: Some(delete_event::Event::Node(1)),
+            }])
+            .await;
+            let resp = nodes.next().await.unwrap().unwrap();
+            assert_eq!(event_types(&resp), vec![EventType::Node]);
+            assert!(resp.updates.is_empty());
+            let resp = collections.next().await.unwrap().unwrap();
+            assert_eq!(event_types(&resp), vec![EventType::Collection]);
+            assert_eq!(resp.updates.len(), 1);
+        });
+    }
+
+    #[test]
+    fn resume_after_compaction() {
+        let mut buf = RetentionBuffer::new(2);
+        for seq in 1..=8 {
+            buf.record(seq, &[database_event(1, &format!("a{seq}"))], &[]);
+        }
+        // The compaction is triggered automatically.
+        assert!(buf.compacted_seq > 0);
+        assert!(buf.events.len() < 8);
+
+        // Resuming from the retained region gets all following events in order.
+        let (updates, _) = buf.events_since(buf.compacted_seq).unwrap();
+        let expect = (buf.compacted_seq + 1..=8)
+            .map(|seq| format!("a{seq}"))
+            .collect::<Vec<_>>();
+        assert_eq!(database_names(&updates), expect);
+
+        // Resuming from the compacted region requires a full snapshot.
+        assert!(buf.events_since(buf.compacted_seq - 1).is_none());
+        // So does an unknown sequence.
+        assert!(buf.events_since(9).is_none());
+        // Nothing is missed if resuming from the last sequence.
+        let (updates, deletes) = buf.events_since(8).unwrap();
+        assert!(updates.is_empty() && deletes.is_empty());
+    }
+}
@@ -128,6 +128,10 @@ impl Task for PromoteGroup {
     }
 
     async fn poll(&mut self, ctx: &mut ScheduleContext<'_>) -> TaskState {
+        if ctx.cfg.testing_knobs.disable_scheduler_promote_group_task {
+            return TaskState::Pending(None);
+        }
+
         if ctx.group_lock_table.has_config_change() {
             return TaskState::Pending(Some(Duration::from_secs(1)));
         }
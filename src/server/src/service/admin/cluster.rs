@@ -14,10 +14,14 @@
 
 use std::collections::HashMap;
 
+use engula_api::server::v1::{GroupDesc, ReplicaDesc};
 use serde_json::json;
 use tonic::{async_trait, codegen::http};
 
-use crate::{Result, Server};
+use crate::{
+    root::{parse_permissions, permission_name},
+    Result, Server,
+};
 
 pub(super) struct CordonHandle {
     server: Server,
@@ -47,6 +51,10 @@ impl super::service::HttpHandle for CordonHandle {
             .body("".to_owned())
             .unwrap())
     }
+
+    fn is_write(&self) -> bool {
+        true
+    }
 }
 
 pub(super) struct UncordonHandle {
@@ -77,6 +85,45 @@ impl super::service::HttpHandle for UncordonHandle {
             .body("".to_owned())
             .unwrap())
     }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct FreezeHandle {
+    server: Server,
+}
+
+impl FreezeHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for FreezeHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let frozen = params
+            .get("frozen")
+            .map(|v| v.parse::<bool>())
+            .transpose()
+            .map_err(|_| crate::Error::InvalidArgument("illegal frozen".into()))?
+            .unwrap_or(true);
+        self.server.root.set_frozen(frozen).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(json!({ "frozen": frozen }).to_string())
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
 }
 
 pub(super) struct DrainHandle {
@@ -101,12 +148,36 @@ impl super::service::HttpHandle for DrainHandle {
             .ok_or_else(|| crate::Error::InvalidArgument("node_id is required".into()))?
             .parse::<u64>()
             .map_err(|_| crate::Error::InvalidArgument("illegal node_id".into()))?;
-        self.server.root.begin_drain(node_id).await?;
+        let max_concurrent_moves = params
+            .get("max_concurrent_moves")
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| crate::Error::InvalidArgument("illegal max_concurrent_moves".into()))?;
+        // Only query the progress of the drain if `status` is specified.
+        if !params.contains_key("status") {
+            self.server
+                .root
+                .begin_drain(node_id, max_concurrent_moves)
+                .await?;
+        }
+        let status = self.server.root.node_status(node_id).await?;
+        let replicas = self.server.root.drain_status(node_id).await?;
         Ok(http::Response::builder()
             .status(http::StatusCode::OK)
-            .body("".to_owned())
+            .body(
+                json!({
+                    "node_id": node_id,
+                    "node_status": format!("{:?}", status).to_uppercase(),
+                    "replicas": replicas,
+                })
+                .to_string(),
+            )
             .unwrap())
     }
+
+    fn is_write(&self) -> bool {
+        true
+    }
 }
 
 pub(super) struct StatusHandle {
@@ -131,10 +202,682 @@ impl super::service::HttpHandle for StatusHandle {
             .ok_or_else(|| crate::Error::InvalidArgument("node_id is required".into()))?
             .parse::<u64>()
             .map_err(|_| crate::Error::InvalidArgument("illegal node_id".into()))?;
+        let cluster_id = self.server.root.cluster_id().await?;
         let status = self.server.root.node_status(node_id).await?;
+        let body = json!({
+            "cluster_id": String::from_utf8_lossy(&cluster_id),
+            "node_id": node_id,
+            "node_status": format!("{:?}", status).to_uppercase(),
+        });
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+
+    fn is_probe(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct MarkAliveHandle {
+    server: Server,
+}
+
+impl MarkAliveHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for MarkAliveHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let node_id = params
+            .get("id")
+            .ok_or_else(|| crate::Error::InvalidArgument("id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal id".into()))?;
+        self.server.root.mark_node_alive(node_id).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body("".to_owned())
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct NodeLagHandle {
+    server: Server,
+}
+
+impl NodeLagHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for NodeLagHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let node_id = params
+            .get("node")
+            .ok_or_else(|| crate::Error::InvalidArgument("node is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal node".into()))?;
+        let lag = self.server.root.node_lag(node_id).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(json!({ "node_id": node_id, "max_lag": lag.max, "avg_lag": lag.avg }).to_string())
+            .unwrap())
+    }
+}
+
+pub(super) struct CollectionStatsHandle {
+    server: Server,
+}
+
+impl CollectionStatsHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for CollectionStatsHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let name = params
+            .get("name")
+            .ok_or_else(|| crate::Error::InvalidArgument("name is required".into()))?;
+        let database = params
+            .get("database")
+            .ok_or_else(|| crate::Error::InvalidArgument("database is required".into()))?;
+        let stats = self.server.root.collection_stats(name, database).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "database": database,
+                    "collection": name,
+                    "approximate": true,
+                    "approximate_keys": stats.approximate_keys,
+                    "approximate_bytes": stats.approximate_bytes,
+                    "reported_shards": stats.reported_shards,
+                    "total_shards": stats.total_shards,
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+}
+
+pub(super) struct CollectionHealthHandle {
+    server: Server,
+}
+
+impl CollectionHealthHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for CollectionHealthHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let name = params
+            .get("name")
+            .ok_or_else(|| crate::Error::InvalidArgument("name is required".into()))?;
+        let database = params
+            .get("database")
+            .ok_or_else(|| crate::Error::InvalidArgument("database is required".into()))?;
+        let health = self.server.root.collection_health(name, database).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "database": database,
+                    "collection": name,
+                    "health": health,
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+}
+
+pub(super) struct CloneCollectionHandle {
+    server: Server,
+}
+
+impl CloneCollectionHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for CloneCollectionHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let name = params
+            .get("name")
+            .ok_or_else(|| crate::Error::InvalidArgument("name is required".into()))?;
+        let database = params
+            .get("database")
+            .ok_or_else(|| crate::Error::InvalidArgument("database is required".into()))?;
+        let target = params
+            .get("target")
+            .ok_or_else(|| crate::Error::InvalidArgument("target is required".into()))?;
+        let desc = self
+            .server
+            .root
+            .clone_collection(database.to_owned(), name.to_owned(), target.to_owned())
+            .await?;
         Ok(http::Response::builder()
             .status(http::StatusCode::OK)
-            .body(json!({ "node_id": node_id, "node_status": format!("{:?}", status).to_uppercase() }).to_string())
+            .body(
+                json!({
+                    "id": desc.id,
+                    "name": desc.name,
+                    "database": database,
+                })
+                .to_string(),
+            )
             .unwrap())
     }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) enum AclOp {
+    Get,
+    Set,
+    Clear,
+}
+
+pub(super) struct CollectionAclHandle {
+    server: Server,
+    op: AclOp,
+}
+
+impl CollectionAclHandle {
+    pub(crate) fn new(server: Server, op: AclOp) -> Self {
+        Self { server, op }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for CollectionAclHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let name = params
+            .get("name")
+            .ok_or_else(|| crate::Error::InvalidArgument("name is required".into()))?;
+        let database = params
+            .get("database")
+            .ok_or_else(|| crate::Error::InvalidArgument("database is required".into()))?;
+        let root = &self.server.root;
+        match self.op {
+            AclOp::Get => {}
+            AclOp::Set => {
+                let principal = params
+                    .get("principal")
+                    .ok_or_else(|| crate::Error::InvalidArgument("principal is required".into()))?;
+                let permissions = params.get("permissions").ok_or_else(|| {
+                    crate::Error::InvalidArgument("permissions is required".into())
+                })?;
+                let permissions = parse_permissions(permissions)?;
+                root.set_collection_acl(name, database, principal.to_owned(), permissions)
+                    .await?;
+            }
+            AclOp::Clear => {
+                let principal = params.get("principal").map(String::as_str);
+                root.clear_collection_acl(name, database, principal).await?;
+            }
+        }
+        let acl = root
+            .get_collection_acl(name, database)
+            .await?
+            .into_iter()
+            .map(|(principal, permissions)| {
+                let permissions = permissions
+                    .into_iter()
+                    .map(permission_name)
+                    .collect::<Vec<_>>();
+                (principal, json!(permissions))
+            })
+            .collect::<serde_json::Map<_, _>>();
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "database": database,
+                    "collection": name,
+                    "acl": acl,
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        !matches!(self.op, AclOp::Get)
+    }
+}
+
+/// Report the inconsistencies of the cluster metadata, or repair them if `repair` is set.
+pub(super) struct ConsistencyHandle {
+    server: Server,
+    repair: bool,
+}
+
+impl ConsistencyHandle {
+    pub(crate) fn new(server: Server, repair: bool) -> Self {
+        Self { server, repair }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for ConsistencyHandle {
+    async fn call(&self, _: &str, _: &HashMap<String, String>) -> Result<http::Response<String>> {
+        let root = &self.server.root;
+        let removed = if self.repair {
+            root.repair_duplicate_node_addrs().await?
+        } else {
+            vec![]
+        };
+        let duplicates = root
+            .duplicate_node_addrs()
+            .await?
+            .into_iter()
+            .map(|d| {
+                json!({
+                    "addr": d.addr,
+                    "keep": d.keep,
+                    "stale": d.stale.iter().map(|(id, replicas)| json!({
+                        "node_id": id,
+                        "replicas": replicas,
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut body = json!({
+            "consistent": duplicates.is_empty(),
+            "duplicate_node_addrs": duplicates,
+        });
+        if self.repair {
+            body["removed_nodes"] = json!(removed);
+        }
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        self.repair
+    }
+}
+
+pub(super) struct RootSnapshotHandle {
+    server: Server,
+}
+
+impl RootSnapshotHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for RootSnapshotHandle {
+    async fn call(&self, _: &str, _: &HashMap<String, String>) -> Result<http::Response<String>> {
+        let stat = self.server.root.snapshot_root().await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "index": stat.index,
+                    "size": stat.size,
+                    "created": stat.created,
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct RouteHandle {
+    server: Server,
+}
+
+impl RouteHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for RouteHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let group_id = params
+            .get("id")
+            .ok_or_else(|| crate::Error::InvalidArgument("id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal id".into()))?;
+        let (group, state) = self.server.root.group_route(group_id).await?;
+        let state = state.unwrap_or_default();
+        let leader = state
+            .leader_id
+            .and_then(|id| group.replicas.iter().find(|r| r.id == id));
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "group": group_json(&group),
+                    "leader": leader.map(replica_json),
+                    "leader_epoch": state.leader_epoch,
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+}
+
+/// Export the raft metrics of the local replica of a group, it is served by any node hosting a
+/// replica of the group.
+pub(super) struct GroupRaftHandle {
+    server: Server,
+}
+
+impl GroupRaftHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for GroupRaftHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let group_id = params
+            .get("id")
+            .ok_or_else(|| crate::Error::InvalidArgument("id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal id".into()))?;
+        let state = match self.server.node.replica_table().find(group_id) {
+            Some(replica) => replica.raft_node().raft_group_state().await,
+            None => None,
+        };
+        let state = match state {
+            Some(state) => state,
+            None => {
+                return Ok(http::Response::builder()
+                    .status(http::StatusCode::NOT_FOUND)
+                    .body(format!("group {group_id} has no replica on this node"))
+                    .unwrap())
+            }
+        };
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "group_id": group_id,
+                    "role": format!("{:?}", state.ss.raft_state),
+                    "leader_id": state.ss.leader_id,
+                    "term": state.hs.term,
+                    "commit_index": state.committed,
+                    "applied_index": state.applied,
+                    "first_index": state.first_index,
+                    "last_index": state.last_index,
+                    "last_snapshot": state.last_snapshot.map(|stat| json!({
+                        "index": stat.index,
+                        "size": stat.size,
+                    })),
+                    "pending_conf_index": state.pending_conf_index,
+                    "pending_conf_change": state.pending_conf_index > state.applied,
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+}
+
+pub(super) struct AddReplicaHandle {
+    server: Server,
+}
+
+impl AddReplicaHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for AddReplicaHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let group_id = params
+            .get("id")
+            .ok_or_else(|| crate::Error::InvalidArgument("id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal id".into()))?;
+        let node_id = params
+            .get("node_id")
+            .ok_or_else(|| crate::Error::InvalidArgument("node_id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal node_id".into()))?;
+        let (group, incoming) = self
+            .server
+            .root
+            .add_group_replica(group_id, node_id)
+            .await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "group": group_json(&group),
+                    "incoming_replica": replica_json(&incoming),
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct RemoveReplicaHandle {
+    server: Server,
+}
+
+impl RemoveReplicaHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for RemoveReplicaHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let group_id = params
+            .get("id")
+            .ok_or_else(|| crate::Error::InvalidArgument("id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal id".into()))?;
+        let replica_id = params
+            .get("replica_id")
+            .ok_or_else(|| crate::Error::InvalidArgument("replica_id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal replica_id".into()))?;
+        let force = match params.get("force") {
+            Some(force) => force
+                .parse::<bool>()
+                .map_err(|_| crate::Error::InvalidArgument("illegal force".into()))?,
+            None => false,
+        };
+        let (group, outgoing) = self
+            .server
+            .root
+            .remove_group_replica(group_id, replica_id, force)
+            .await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "group": group_json(&group),
+                    "outgoing_replica": replica_json(&outgoing),
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct RootAddReplicaHandle {
+    server: Server,
+}
+
+impl RootAddReplicaHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for RootAddReplicaHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let node_id = params
+            .get("node_id")
+            .ok_or_else(|| crate::Error::InvalidArgument("node_id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal node_id".into()))?;
+        let (group, incoming) = self.server.root.add_root_replica(node_id).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "root": group_json(&group),
+                    "incoming_replica": replica_json(&incoming),
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+pub(super) struct RootRemoveReplicaHandle {
+    server: Server,
+}
+
+impl RootRemoveReplicaHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for RootRemoveReplicaHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let replica_id = params
+            .get("replica_id")
+            .ok_or_else(|| crate::Error::InvalidArgument("replica_id is required".into()))?
+            .parse::<u64>()
+            .map_err(|_| crate::Error::InvalidArgument("illegal replica_id".into()))?;
+        let (group, outgoing) = self.server.root.remove_root_replica(replica_id).await?;
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(
+                json!({
+                    "root": group_json(&group),
+                    "outgoing_replica": replica_json(&outgoing),
+                })
+                .to_string(),
+            )
+            .unwrap())
+    }
+
+    fn is_write(&self) -> bool {
+        true
+    }
+}
+
+fn group_json(group: &GroupDesc) -> serde_json::Value {
+    json!({
+        "id": group.id,
+        "epoch": group.epoch,
+        "replicas": group.replicas.iter().map(replica_json).collect::<Vec<_>>(),
+        "shards": group.shards.iter().map(|s| s.id).collect::<Vec<_>>(),
+    })
+}
+
+fn replica_json(replica: &ReplicaDesc) -> serde_json::Value {
+    json!({
+        "id": replica.id,
+        "node_id": replica.node_id,
+        "role": replica.role,
+    })
 }
@@ -0,0 +1,90 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+use tonic::{async_trait, codegen::http};
+
+use crate::{Config, Result, Server};
+
+pub(super) struct ConfigHandle {
+    server: Server,
+}
+
+impl ConfigHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for ConfigHandle {
+    async fn call(&self, _: &str, _: &HashMap<String, String>) -> Result<http::Response<String>> {
+        let config = serde_json::to_value(self.server.config.as_ref()).unwrap();
+        let defaults = serde_json::to_value(Config::default()).unwrap();
+        let mut body = annotate(config, &defaults);
+
+        // The runtime states are only known by the root leader.
+        if let Ok(frozen) = self.server.root.is_frozen().await {
+            body["runtime"] = json!({ "frozen": annotate(json!(frozen), &json!(false)) });
+        }
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+}
+
+/// Replace each leaf of the config with its current and default values, and whether the
+/// default value is overridden.
+fn annotate(value: Value, default: &Value) -> Value {
+    match value {
+        Value::Object(fields) => fields
+            .into_iter()
+            .map(|(name, value)| {
+                let default = default.get(&name).unwrap_or(&Value::Null);
+                let annotated = annotate(value, default);
+                (name, annotated)
+            })
+            .collect(),
+        value => json!({
+            "value": value,
+            "default": default,
+            "overridden": &value != default,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotate_overridden_values() {
+        let value = json!({"a": 1, "nested": {"b": "x", "c": [1, 2]}});
+        let default = json!({"a": 1, "nested": {"b": "y", "c": [1, 2]}});
+        let annotated = annotate(value, &default);
+        assert_eq!(
+            annotated,
+            json!({
+                "a": {"value": 1, "default": 1, "overridden": false},
+                "nested": {
+                    "b": {"value": "x", "default": "y", "overridden": true},
+                    "c": {"value": [1, 2], "default": [1, 2], "overridden": false},
+                },
+            })
+        );
+    }
+}
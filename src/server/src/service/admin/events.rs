@@ -0,0 +1,189 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use engula_api::server::v1::WatchResponse;
+use futures::{future, StreamExt};
+use serde_json::json;
+use tonic::{async_trait, body::BoxBody, codegen::http};
+
+use crate::{
+    root::{EventType, RetainedEvent},
+    Result, Server,
+};
+
+const DEFAULT_TAIL_LIMIT: usize = 100;
+
+pub(super) struct EventsTailHandle {
+    server: Server,
+}
+
+impl EventsTailHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for EventsTailHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let limit = params
+            .get("limit")
+            .map(|v| v.parse::<usize>())
+            .transpose()
+            .map_err(|_| crate::Error::InvalidArgument("illegal limit".into()))?
+            .unwrap_or(DEFAULT_TAIL_LIMIT);
+        let types = parse_event_types(params)?;
+        let events = self
+            .server
+            .root
+            .tail_events(limit, &types)?
+            .into_iter()
+            .map(|(seq, event)| {
+                let mut value = event_to_json(&event);
+                value["seq"] = json!(seq);
+                value
+            })
+            .collect::<Vec<_>>();
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(json!({ "events": events }).to_string())
+            .unwrap())
+    }
+}
+
+/// Watch the changes of metadata over plain HTTP, the events are streamed as newline-delimited
+/// JSON. It accepts the same params as the grpc watch: `group_id` to watch a single group, and
+/// `groups` of `group_id:epoch` pairs separated by comma to resume watching. The `types` param
+/// subscribes to the events of the types separated by comma only. A client which
+/// doesn't consume the events in time is handled by the backpressure policy of root.
+pub(super) struct WatchHandle {
+    server: Server,
+}
+
+impl WatchHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for WatchHandle {
+    async fn call(&self, _: &str, _: &HashMap<String, String>) -> Result<http::Response<String>> {
+        Err(crate::Error::InvalidArgument(
+            "the events of watch could only be streamed".into(),
+        ))
+    }
+
+    async fn call_streaming(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+        _: Vec<u8>,
+    ) -> Result<http::Response<BoxBody>> {
+        let group_id = params
+            .get("group_id")
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| crate::Error::InvalidArgument("illegal group_id".into()))?;
+        let cur_groups = params
+            .get("groups")
+            .map(|v| parse_group_epochs(v))
+            .transpose()?
+            .unwrap_or_default();
+        let types = parse_event_types(params)?;
+        let watcher = self.server.root.watch(cur_groups, group_id, &types).await?;
+        let lines = watcher
+            .map(|resp| resp.map(to_ndjson))
+            .filter(|lines| future::ready(!matches!(lines, Ok(lines) if lines.is_empty())));
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(super::service::streaming(lines))
+            .unwrap())
+    }
+}
+
+fn event_to_json(event: &RetainedEvent) -> serde_json::Value {
+    let event_type = event.event_type().map(|t| t.as_str()).unwrap_or_default();
+    match event {
+        RetainedEvent::Update(update) => json!({
+            "type": event_type,
+            "op": "update",
+            "event": format!("{:?}", update.event),
+        }),
+        RetainedEvent::Delete(delete) => json!({
+            "type": event_type,
+            "op": "delete",
+            "event": format!("{:?}", delete.event),
+        }),
+    }
+}
+
+/// Encode the events of a watch response, one JSON object per line.
+fn to_ndjson(resp: WatchResponse) -> String {
+    let updates = resp.updates.into_iter().map(RetainedEvent::Update);
+    let deletes = resp.deletes.into_iter().map(RetainedEvent::Delete);
+    updates
+        .chain(deletes)
+        .map(|event| format!("{}\n", event_to_json(&event)))
+        .collect()
+}
+
+/// Parse the event types separated by comma, all types are accepted if it is not specified.
+fn parse_event_types(params: &HashMap<String, String>) -> Result<Vec<EventType>> {
+    let types = params
+        .get("types")
+        .map(|v| {
+            v.split(',')
+                .filter(|t| !t.is_empty())
+                .map(|t| t.parse::<EventType>())
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+    Ok(types)
+}
+
+/// Parse the `group_id:epoch` pairs separated by comma.
+fn parse_group_epochs(value: &str) -> Result<HashMap<u64, u64>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            pair.split_once(':')
+                .and_then(|(group_id, epoch)| Some((group_id.parse().ok()?, epoch.parse().ok()?)))
+                .ok_or_else(|| crate::Error::InvalidArgument(format!("illegal group epoch {pair}")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_epochs() {
+        let epochs = parse_group_epochs("1:3,2:5").unwrap();
+        assert_eq!(epochs, HashMap::from([(1, 3), (2, 5)]));
+        assert!(parse_group_epochs("").unwrap().is_empty());
+        assert!(parse_group_epochs("1").is_err());
+        assert!(parse_group_epochs("1:x").is_err());
+    }
+}
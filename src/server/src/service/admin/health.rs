@@ -14,9 +14,21 @@
 
 use std::collections::HashMap;
 
+use engula_api::server::v1::{node_server::NodeServer, root_server::RootServer};
 use tonic::codegen::*;
+use tonic_health::server::{health_reporter, Health, HealthServer};
 
-pub(super) struct HealthHandle;
+use crate::{serverpb::v1::raft_server::RaftServer, Server};
+
+pub(super) struct HealthHandle {
+    server: Server,
+}
+
+impl HealthHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
 
 #[crate::async_trait]
 impl super::service::HttpHandle for HealthHandle {
@@ -25,9 +37,29 @@ impl super::service::HttpHandle for HealthHandle {
         _: &str,
         _: &HashMap<String, String>,
     ) -> crate::Result<http::Response<String>> {
+        // Only the root leader knows the members of cluster, a degraded cluster still serves
+        // requests so the status is not changed. The cached state is served, so that the probes
+        // do not read the schema.
+        let degraded = self.server.root.is_degraded();
+        let body = if degraded { "Degraded\n" } else { "Ok\n" };
         Ok(http::Response::builder()
             .status(http::StatusCode::OK)
-            .body("Ok\n".to_owned())
+            .body(body.to_owned())
             .unwrap())
     }
+
+    fn is_probe(&self) -> bool {
+        true
+    }
+}
+
+/// Build the standard gRPC health checking service. It reports the same readiness as
+/// [`HealthHandle`]: every service is serving once the server accepts requests, even if the
+/// cluster is degraded.
+pub async fn make_grpc_health_service() -> HealthServer<impl Health> {
+    let (mut reporter, service) = health_reporter();
+    reporter.set_serving::<NodeServer<Server>>().await;
+    reporter.set_serving::<RaftServer<Server>>().await;
+    reporter.set_serving::<RootServer<Server>>().await;
+    service
 }
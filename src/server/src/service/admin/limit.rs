@@ -0,0 +1,127 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use super::AdminConfig;
+
+/// A bucket holds at most `rate` tokens, which are refilled at `rate` per second.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        TokenBucket {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Take a token, or return the time to wait until a token is available.
+    fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+/// Limit the requests per second of each admin route, the requests of a route share a bucket
+/// no matter what its path params are.
+pub(super) struct RateLimiter {
+    read_requests_per_sec: u64,
+    write_requests_per_sec: u64,
+    route_requests_per_sec: HashMap<String, u64>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &AdminConfig) -> Self {
+        RateLimiter {
+            read_requests_per_sec: cfg.read_requests_per_sec,
+            write_requests_per_sec: cfg.write_requests_per_sec,
+            route_requests_per_sec: cfg.route_requests_per_sec.clone(),
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Take a token of the route, or return the time to wait before retrying.
+    pub fn try_acquire(&self, route: &str, is_write: bool, now: Instant) -> Result<(), Duration> {
+        let rate = match self.route_requests_per_sec.get(route) {
+            Some(rate) => *rate,
+            None if is_write => self.write_requests_per_sec,
+            None => self.read_requests_per_sec,
+        };
+        if rate == 0 {
+            return Ok(());
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets
+            .entry(route.to_owned())
+            .or_insert_with(|| TokenBucket::new(rate, now))
+            .try_acquire(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refill() {
+        let cfg = AdminConfig {
+            read_requests_per_sec: 2,
+            write_requests_per_sec: 0,
+            route_requests_per_sec: HashMap::from([("/admin/metrics".to_owned(), 1)]),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(&cfg);
+        let now = Instant::now();
+
+        limiter.try_acquire("/admin/metrics", false, now).unwrap();
+        let wait = limiter
+            .try_acquire("/admin/metrics", false, now)
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        limiter
+            .try_acquire("/admin/metrics", false, now + Duration::from_millis(500))
+            .unwrap_err();
+        limiter
+            .try_acquire("/admin/metrics", false, now + Duration::from_secs(1))
+            .unwrap();
+
+        // The other routes have their own buckets.
+        limiter.try_acquire("/admin/job", false, now).unwrap();
+        limiter.try_acquire("/admin/job", false, now).unwrap();
+        limiter.try_acquire("/admin/job", false, now).unwrap_err();
+
+        // The writes are unlimited.
+        for _ in 0..10 {
+            limiter.try_acquire("/admin/cordon", true, now).unwrap();
+        }
+    }
+}
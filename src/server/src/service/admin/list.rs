@@ -0,0 +1,145 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use serde_json::json;
+use tonic::{async_trait, codegen::http};
+
+use crate::{Result, Server};
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+
+fn page_params(params: &HashMap<String, String>) -> Result<(Option<&str>, usize)> {
+    let limit = params
+        .get("limit")
+        .map(|v| v.parse::<usize>())
+        .transpose()
+        .map_err(|_| crate::Error::InvalidArgument("illegal limit".into()))?
+        .unwrap_or(DEFAULT_PAGE_LIMIT);
+    Ok((params.get("page_token").map(String::as_str), limit))
+}
+
+pub(super) struct DatabasesHandle {
+    server: Server,
+}
+
+impl DatabasesHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for DatabasesHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let (page_token, limit) = page_params(params)?;
+        let (databases, next) = self
+            .server
+            .root
+            .list_database_page(page_token, limit)
+            .await?;
+        let databases = databases
+            .into_iter()
+            .map(|db| json!({"id": db.id, "name": db.name}))
+            .collect::<Vec<_>>();
+        let body = json!({
+            "databases": databases,
+            "next_page_token": next,
+        });
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+}
+
+pub(super) struct GroupsHandle {
+    server: Server,
+}
+
+impl GroupsHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for GroupsHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let (page_token, limit) = page_params(params)?;
+        let (groups, next) = self.server.root.list_group_page(page_token, limit).await?;
+        let groups = groups
+            .into_iter()
+            .map(|g| {
+                let replicas = g
+                    .replicas
+                    .iter()
+                    .map(|r| json!({"id": r.id, "node_id": r.node_id}))
+                    .collect::<Vec<_>>();
+                json!({"id": g.id, "epoch": g.epoch, "replicas": replicas})
+            })
+            .collect::<Vec<_>>();
+        let body = json!({
+            "groups": groups,
+            "next_page_token": next,
+        });
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+}
+
+pub(super) struct MoveHistoryHandle {
+    server: Server,
+}
+
+impl MoveHistoryHandle {
+    pub(crate) fn new(server: Server) -> Self {
+        Self { server }
+    }
+}
+
+#[async_trait]
+impl super::service::HttpHandle for MoveHistoryHandle {
+    async fn call(
+        &self,
+        _: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<http::Response<String>> {
+        let (page_token, limit) = page_params(params)?;
+        let (moves, next) = self
+            .server
+            .root
+            .list_move_history_page(page_token, limit)
+            .await?;
+        let body = json!({
+            "moves": moves,
+            "next_page_token": next,
+        });
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+}
@@ -13,17 +13,56 @@
 // limitations under the License.
 
 mod cluster;
+mod config;
+mod events;
 mod health;
 mod job;
+mod limit;
+mod list;
 mod metadata;
 mod metrics;
 mod service;
+mod validate;
 
-pub use self::service::AdminService;
-use self::service::Router;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tonic_reflection::server::{ServerReflection, ServerReflectionServer};
+
+use self::{cluster::AclOp, service::Router};
+pub use self::{health::make_grpc_health_service, service::AdminService};
 use crate::Server;
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// The requests per second allowed for each read route, eg. `/admin/metrics`. Zero means
+    /// unlimited.
+    pub read_requests_per_sec: u64,
+    /// The requests per second allowed for each write route, eg. `/admin/cordon`. Zero means
+    /// unlimited.
+    pub write_requests_per_sec: u64,
+    /// The requests per second of the specified routes, eg. `"/admin/metadata" = 1`, which
+    /// override the read and write limits.
+    pub route_requests_per_sec: HashMap<String, u64>,
+    /// The max bytes of a request body, the larger requests are rejected with `413 Payload Too
+    /// Large` before they are buffered.
+    pub max_request_body_bytes: usize,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        AdminConfig {
+            read_requests_per_sec: 0,
+            write_requests_per_sec: 0,
+            route_requests_per_sec: HashMap::default(),
+            max_request_body_bytes: 64 << 20,
+        }
+    }
+}
+
 pub fn make_admin_service(server: Server) -> AdminService {
+    let cfg = server.config.admin.to_owned();
     let router = Router::empty()
         .route(
             "/metrics",
@@ -34,7 +73,27 @@ pub fn make_admin_service(server: Server) -> AdminService {
             "/metadata",
             self::metadata::MetadataHandle::new(server.to_owned()),
         )
-        .route("/health", self::health::HealthHandle)
+        .route(
+            "/metadata/validate",
+            self::validate::ValidateMetadataHandle::new(),
+        )
+        .route(
+            "/health",
+            self::health::HealthHandle::new(server.to_owned()),
+        )
+        .route(
+            "/config",
+            self::config::ConfigHandle::new(server.to_owned()),
+        )
+        .route(
+            "/databases",
+            self::list::DatabasesHandle::new(server.to_owned()),
+        )
+        .route("/groups", self::list::GroupsHandle::new(server.to_owned()))
+        .route(
+            "/moves/history",
+            self::list::MoveHistoryHandle::new(server.to_owned()),
+        )
         .route(
             "/cordon",
             self::cluster::CordonHandle::new(server.to_owned()),
@@ -43,8 +102,98 @@ pub fn make_admin_service(server: Server) -> AdminService {
             "/uncordon",
             self::cluster::UncordonHandle::new(server.to_owned()),
         )
+        .route(
+            "/freeze",
+            self::cluster::FreezeHandle::new(server.to_owned()),
+        )
         .route("/drain", self::cluster::DrainHandle::new(server.to_owned()))
-        .route("/node_status", self::cluster::StatusHandle::new(server));
-    let api = Router::nest("/admin", router);
+        .route(
+            "/node_status",
+            self::cluster::StatusHandle::new(server.to_owned()),
+        )
+        .route(
+            "/node/{id}/mark_alive",
+            self::cluster::MarkAliveHandle::new(server.to_owned()),
+        )
+        .route(
+            "/root/add_replica",
+            self::cluster::RootAddReplicaHandle::new(server.to_owned()),
+        )
+        .route(
+            "/root/remove_replica",
+            self::cluster::RootRemoveReplicaHandle::new(server.to_owned()),
+        )
+        .route(
+            "/root/snapshot",
+            self::cluster::RootSnapshotHandle::new(server.to_owned()),
+        )
+        .route(
+            "/groups/{id}/route",
+            self::cluster::RouteHandle::new(server.to_owned()),
+        )
+        .route(
+            "/groups/{id}/raft",
+            self::cluster::GroupRaftHandle::new(server.to_owned()),
+        )
+        .route(
+            "/groups/{id}/add_replica",
+            self::cluster::AddReplicaHandle::new(server.to_owned()),
+        )
+        .route(
+            "/groups/{id}/remove_replica",
+            self::cluster::RemoveReplicaHandle::new(server.to_owned()),
+        )
+        .route(
+            "/events/tail",
+            self::events::EventsTailHandle::new(server.to_owned()),
+        )
+        .route("/watch", self::events::WatchHandle::new(server.to_owned()))
+        .route(
+            "/consistency",
+            self::cluster::ConsistencyHandle::new(server.to_owned(), false),
+        )
+        .route(
+            "/consistency/repair",
+            self::cluster::ConsistencyHandle::new(server.to_owned(), true),
+        )
+        .route(
+            "/node_lag",
+            self::cluster::NodeLagHandle::new(server.to_owned()),
+        )
+        .route(
+            "/collections/{name}/stats",
+            self::cluster::CollectionStatsHandle::new(server.to_owned()),
+        )
+        .route(
+            "/collections/{name}/health",
+            self::cluster::CollectionHealthHandle::new(server.to_owned()),
+        )
+        .route(
+            "/collections/{name}/acl",
+            self::cluster::CollectionAclHandle::new(server.to_owned(), AclOp::Get),
+        )
+        .route(
+            "/collections/{name}/acl/set",
+            self::cluster::CollectionAclHandle::new(server.to_owned(), AclOp::Set),
+        )
+        .route(
+            "/collections/{name}/acl/clear",
+            self::cluster::CollectionAclHandle::new(server.to_owned(), AclOp::Clear),
+        )
+        .route(
+            "/collections/{name}/clone",
+            self::cluster::CloneCollectionHandle::new(server),
+        );
+    let api = Router::nest("/admin", router)
+        .with_rate_limit(&cfg)
+        .with_max_body_len(cfg.max_request_body_bytes);
     AdminService::new(api)
 }
+
+/// Build the gRPC server reflection service for the Engula gRPC services.
+pub fn make_reflection_service() -> ServerReflectionServer<impl ServerReflection> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(engula_api::FILE_DESCRIPTOR_SET)
+        .build()
+        .expect("build server reflection service")
+}
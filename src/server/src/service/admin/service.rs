@@ -14,16 +14,22 @@
 
 use std::{
     collections::HashMap,
+    pin::Pin,
     sync::Arc,
     task::{Context, Poll},
 };
 
+use futures::{Stream, StreamExt};
+use tokio::time::Instant;
 use tonic::{
     body::BoxBody,
-    codegen::{empty_body, http, BoxFuture, Service},
+    codegen::{empty_body, http, BoxFuture, Bytes, Service},
     transport::NamedService,
+    Status,
 };
 
+use super::{limit::RateLimiter, AdminConfig};
+
 #[crate::async_trait]
 pub(super) trait HttpHandle: Send + Sync {
     async fn call(
@@ -31,10 +37,45 @@ pub(super) trait HttpHandle: Send + Sync {
         path: &str,
         params: &HashMap<String, String>,
     ) -> crate::Result<http::Response<String>>;
+
+    /// Handle a request with body, eg. `POST`. The body is ignored by default.
+    async fn call_with_body(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        _body: Vec<u8>,
+    ) -> crate::Result<http::Response<String>> {
+        self.call(path, params).await
+    }
+
+    /// Handle a request whose response body is streamed, eg. `/admin/watch`. The response of
+    /// `call_with_body` is sent as a whole by default.
+    async fn call_streaming(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        body: Vec<u8>,
+    ) -> crate::Result<http::Response<BoxBody>> {
+        let resp = self.call_with_body(path, params, body).await?;
+        Ok(resp.map(boxed))
+    }
+
+    /// Whether the handle changes the cluster, the writes are rate limited separately.
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    /// Whether the handle serves the health probes, eg. `/admin/health`. The probes are never
+    /// rate limited, so that they keep working under heavy admin load.
+    fn is_probe(&self) -> bool {
+        false
+    }
 }
 
 pub(super) struct Router {
     handles: HashMap<String, Box<dyn HttpHandle>>,
+    limiter: Option<RateLimiter>,
+    max_body_len: Option<usize>,
 }
 
 pub struct AdminService
@@ -54,7 +95,9 @@ impl AdminService {
 
 impl<B> Service<http::Request<B>> for AdminService
 where
-    B: Send,
+    B: http_body::Body + Send + Unpin + 'static,
+    B::Data: AsRef<[u8]> + Send,
+    B::Error: Send,
 {
     type Response = http::Response<tonic::body::BoxBody>;
     type Error = std::convert::Infallible;
@@ -76,10 +119,47 @@ where
             })
             .unwrap_or_else(HashMap::new);
         let path = req.uri().path().to_owned();
-        Box::pin(async move { inner.call(&path, query_params).await })
+        let content_len = req
+            .headers()
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        let mut body = req.into_body();
+        Box::pin(async move {
+            use http_body::Body;
+
+            let max_body_len = inner.max_body_len.unwrap_or(usize::MAX);
+            if matches!(content_len, Some(len) if len > max_body_len) {
+                return Ok(payload_too_large(max_body_len));
+            }
+            let mut content = Vec::new();
+            while let Some(chunk) = body.data().await {
+                match chunk {
+                    // The declared length might be absent or mismatch, so cap while streaming.
+                    Ok(chunk) if content.len() + chunk.as_ref().len() > max_body_len => {
+                        return Ok(payload_too_large(max_body_len));
+                    }
+                    Ok(chunk) => content.extend_from_slice(chunk.as_ref()),
+                    Err(_) => {
+                        return Ok(http::Response::builder()
+                            .status(http::StatusCode::BAD_REQUEST)
+                            .body(boxed("read request body".into()))
+                            .unwrap())
+                    }
+                }
+            }
+            inner.call(&path, query_params, content).await
+        })
     }
 }
 
+fn payload_too_large(max_body_len: usize) -> http::Response<BoxBody> {
+    http::Response::builder()
+        .status(http::StatusCode::PAYLOAD_TOO_LARGE)
+        .body(boxed(format!("request body exceeds {max_body_len} bytes")))
+        .unwrap()
+}
+
 impl NamedService for AdminService {
     const NAME: &'static str = "admin";
 }
@@ -96,6 +176,8 @@ impl Router {
     pub fn empty() -> Self {
         Router {
             handles: HashMap::default(),
+            limiter: None,
+            max_body_len: None,
         }
     }
 
@@ -109,7 +191,25 @@ impl Router {
             .into_iter()
             .map(|(url, handle)| (format!("{path}{url}"), handle))
             .collect();
-        Router { handles }
+        Router {
+            handles,
+            limiter: r.limiter,
+            max_body_len: r.max_body_len,
+        }
+    }
+
+    /// Limit the requests per second of each route, the exceeded requests are rejected with
+    /// `429 Too Many Requests`.
+    pub fn with_rate_limit(mut self, cfg: &AdminConfig) -> Self {
+        self.limiter = Some(RateLimiter::new(cfg));
+        self
+    }
+
+    /// Reject the requests whose body is larger than `max_body_len` bytes with `413 Payload Too
+    /// Large`. Zero means unlimited.
+    pub fn with_max_body_len(mut self, max_body_len: usize) -> Self {
+        self.max_body_len = Some(max_body_len).filter(|len| *len != 0);
+        self
     }
 
     pub fn route(mut self, path: &str, handle: impl HttpHandle + 'static) -> Self {
@@ -125,10 +225,14 @@ impl Router {
     pub async fn call(
         &self,
         path: &str,
-        params: HashMap<String, String>,
+        mut params: HashMap<String, String>,
+        body: Vec<u8>,
     ) -> Result<http::Response<BoxBody>, std::convert::Infallible> {
-        let handle = match self.handles.get(path) {
-            Some(handle) => handle,
+        let (route, handle) = match self.lookup(path) {
+            Some((route, handle, path_params)) => {
+                params.extend(path_params);
+                (route, handle)
+            }
             None => {
                 return Ok(http::Response::builder()
                     .status(http::StatusCode::NOT_FOUND)
@@ -137,8 +241,18 @@ impl Router {
             }
         };
 
-        let resp = match handle.call(path, &params).await {
-            Ok(resp) => resp.map(boxed),
+        if let Some(limiter) = self.limiter.as_ref().filter(|_| !handle.is_probe()) {
+            if let Err(wait) = limiter.try_acquire(route, handle.is_write(), Instant::now()) {
+                return Ok(http::Response::builder()
+                    .status(http::StatusCode::TOO_MANY_REQUESTS)
+                    .header(http::header::RETRY_AFTER, wait.as_secs_f64().ceil() as u64)
+                    .body(empty_body())
+                    .unwrap());
+            }
+        }
+
+        let resp = match handle.call_streaming(path, &params, body).await {
+            Ok(resp) => resp,
             Err(e) => http::Response::builder()
                 .status(http::StatusCode::INTERNAL_SERVER_ERROR)
                 .body(boxed(e.to_string()))
@@ -147,6 +261,40 @@ impl Router {
 
         Ok(resp)
     }
+
+    /// Find the route pattern and the handle of path, the `{name}` segments of a route pattern
+    /// match any segment and are returned as params.
+    #[allow(clippy::type_complexity)]
+    fn lookup(&self, path: &str) -> Option<(&str, &dyn HttpHandle, HashMap<String, String>)> {
+        if let Some((pattern, handle)) = self.handles.get_key_value(path) {
+            return Some((pattern, handle.as_ref(), HashMap::default()));
+        }
+        self.handles.iter().find_map(|(pattern, handle)| {
+            match_path(pattern, path).map(|params| (pattern.as_str(), handle.as_ref(), params))
+        })
+    }
+}
+
+fn match_path(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+    if !pattern.contains('{') {
+        return None;
+    }
+    let patterns = pattern.split('/').collect::<Vec<_>>();
+    let segments = path.split('/').collect::<Vec<_>>();
+    if patterns.len() != segments.len() {
+        return None;
+    }
+    let mut params = HashMap::default();
+    for (pattern, segment) in patterns.into_iter().zip(segments) {
+        match pattern.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(name) if !segment.is_empty() => {
+                params.insert(name.to_owned(), segment.to_owned());
+            }
+            None if pattern == segment => {}
+            _ => return None,
+        }
+    }
+    Some(params)
 }
 
 fn boxed(body: String) -> BoxBody {
@@ -154,3 +302,169 @@ fn boxed(body: String) -> BoxBody {
 
     body.map_err(|_| panic!("")).boxed_unsync()
 }
+
+/// The body which sends the chunks of stream as they are produced, it is terminated with the
+/// error of stream if any.
+pub(super) fn streaming<S>(chunks: S) -> BoxBody
+where
+    S: Stream<Item = Result<String, Status>> + Send + 'static,
+{
+    use http_body::Body;
+
+    StreamBody {
+        chunks: chunks.boxed(),
+    }
+    .boxed_unsync()
+}
+
+struct StreamBody {
+    chunks: Pin<Box<dyn Stream<Item = Result<String, Status>> + Send>>,
+}
+
+impl http_body::Body for StreamBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.chunks
+            .poll_next_unpin(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Bytes::from)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_path_params() {
+        let pattern = "/admin/collections/{name}/stats";
+        let params = match_path(pattern, "/admin/collections/c1/stats").unwrap();
+        assert_eq!(params.get("name").map(String::as_str), Some("c1"));
+
+        assert!(match_path(pattern, "/admin/collections//stats").is_none());
+        assert!(match_path(pattern, "/admin/collections/c1").is_none());
+        assert!(match_path(pattern, "/admin/groups/c1/stats").is_none());
+        assert!(match_path("/admin/metrics", "/admin/metrics").is_none());
+    }
+
+    struct NoopHandle;
+
+    #[crate::async_trait]
+    impl HttpHandle for NoopHandle {
+        async fn call(
+            &self,
+            _: &str,
+            _: &HashMap<String, String>,
+        ) -> crate::Result<http::Response<String>> {
+            Ok(http::Response::builder()
+                .status(http::StatusCode::OK)
+                .body(String::default())
+                .unwrap())
+        }
+    }
+
+    #[test]
+    fn rate_limit_exceeded() {
+        let cfg = AdminConfig {
+            route_requests_per_sec: HashMap::from([("/admin/metrics".to_owned(), 1)]),
+            ..Default::default()
+        };
+        let router = Router::nest("/admin", Router::empty().route("/metrics", NoopHandle))
+            .with_rate_limit(&cfg);
+        futures::executor::block_on(async {
+            let call = || router.call("/admin/metrics", HashMap::default(), vec![]);
+            assert_eq!(call().await.unwrap().status(), http::StatusCode::OK);
+            let resp = call().await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(resp.headers()[http::header::RETRY_AFTER], "1");
+        });
+    }
+
+    struct ProbeHandle;
+
+    #[crate::async_trait]
+    impl HttpHandle for ProbeHandle {
+        async fn call(
+            &self,
+            path: &str,
+            params: &HashMap<String, String>,
+        ) -> crate::Result<http::Response<String>> {
+            NoopHandle.call(path, params).await
+        }
+
+        fn is_probe(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn probes_not_rate_limited() {
+        let cfg = AdminConfig {
+            read_requests_per_sec: 1,
+            route_requests_per_sec: HashMap::from([("/admin/health".to_owned(), 1)]),
+            ..Default::default()
+        };
+        let router = Router::nest(
+            "/admin",
+            Router::empty()
+                .route("/metrics", NoopHandle)
+                .route("/health", ProbeHandle),
+        )
+        .with_rate_limit(&cfg);
+        futures::executor::block_on(async {
+            let call = |path| router.call(path, HashMap::default(), vec![]);
+            assert_eq!(
+                call("/admin/metrics").await.unwrap().status(),
+                http::StatusCode::OK
+            );
+            let resp = call("/admin/metrics").await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+            for _ in 0..10 {
+                let resp = call("/admin/health").await.unwrap();
+                assert_eq!(resp.status(), http::StatusCode::OK);
+            }
+        });
+    }
+
+    #[test]
+    fn request_body_too_large() {
+        let router = Router::nest("/admin", Router::empty().route("/metrics", NoopHandle))
+            .with_max_body_len(4);
+        let mut service = AdminService::new(router);
+        futures::executor::block_on(async {
+            let request = |body: &'static str, content_len: Option<usize>| {
+                let mut builder = http::Request::builder().uri("/admin/metrics");
+                if let Some(len) = content_len {
+                    builder = builder.header(http::header::CONTENT_LENGTH, len);
+                }
+                builder
+                    .body(http_body::Full::new(Bytes::from(body)))
+                    .unwrap()
+            };
+
+            let resp = service.call(request("1234", Some(4))).await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::OK);
+
+            // Rejected by the declared length.
+            let resp = service.call(request("1", Some(1 << 30))).await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+            // Rejected while streaming, without or with a wrong declared length.
+            let resp = service.call(request("12345", None)).await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+            let resp = service.call(request("12345", Some(1))).await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+        });
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tonic::codegen::http;
+
+use crate::{
+    root::diagnosis::{self, METADATA_VERSION},
+    Error, Result,
+};
+
+/// Validate a metadata dump, which is in the format of `/admin/metadata`, without applying it.
+pub(super) struct ValidateMetadataHandle;
+
+impl ValidateMetadataHandle {
+    pub(crate) fn new() -> Self {
+        ValidateMetadataHandle
+    }
+}
+
+#[crate::async_trait]
+impl super::service::HttpHandle for ValidateMetadataHandle {
+    async fn call(&self, _: &str, _: &HashMap<String, String>) -> Result<http::Response<String>> {
+        Err(Error::InvalidArgument(
+            "the metadata dump is required in request body".into(),
+        ))
+    }
+
+    async fn call_with_body(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        body: Vec<u8>,
+    ) -> Result<http::Response<String>> {
+        if body.is_empty() {
+            return self.call(path, params).await;
+        }
+        let issues = validate(&body);
+        let body = json!({
+            "valid": issues.is_empty(),
+            "issues": issues,
+        });
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(body.to_string())
+            .unwrap())
+    }
+}
+
+/// Collect the issues of a metadata dump, each record is parsed on its own so that all malformed
+/// records are reported.
+fn validate(dump: &[u8]) -> Vec<Value> {
+    let mut issues = Vec::new();
+    let dump: Value = match serde_json::from_slice(dump) {
+        Ok(dump @ Value::Object(_)) => dump,
+        Ok(_) => return vec![issue("malformed", "the dump is not an object".into())],
+        Err(err) => return vec![issue("malformed", format!("parse dump: {err}"))],
+    };
+
+    match dump.get("version").map(Value::as_u64) {
+        Some(Some(version)) if version == METADATA_VERSION as u64 => {}
+        Some(Some(version)) => issues.push(issue("unknown_version", format!("version {version}"))),
+        Some(None) => issues.push(issue("malformed", "version is not a number".into())),
+        None => issues.push(issue("unknown_version", "version is missing".into())),
+    }
+
+    let databases = parse_records::<diagnosis::Database>(&dump, "databases", &mut issues);
+    let nodes = parse_records::<diagnosis::Node>(&dump, "nodes", &mut issues);
+    let groups = parse_records::<diagnosis::Group>(&dump, "groups", &mut issues);
+
+    check_collisions("database", databases.iter().map(|d| d.id), &mut issues);
+    check_collisions(
+        "database name",
+        databases.iter().map(|d| d.name.as_str()),
+        &mut issues,
+    );
+    let collections = databases.iter().flat_map(|d| &d.collections);
+    check_collisions("collection", collections.map(|c| c.id), &mut issues);
+    for db in &databases {
+        check_collisions(
+            &format!("collection name of database {}", db.id),
+            db.collections.iter().map(|c| c.name.as_str()),
+            &mut issues,
+        );
+    }
+    check_collisions("node", nodes.iter().map(|n| n.id), &mut issues);
+    check_collisions(
+        "node address",
+        nodes.iter().map(|n| n.addr.as_str()),
+        &mut issues,
+    );
+    check_collisions("group", groups.iter().map(|g| g.id), &mut issues);
+    let replicas = groups.iter().flat_map(|g| &g.replicas);
+    check_collisions("replica", replicas.map(|r| r.id), &mut issues);
+    let shards = groups.iter().flat_map(|g| &g.shards);
+    check_collisions("shard", shards.map(|s| s.id), &mut issues);
+    issues
+}
+
+fn parse_records<T: DeserializeOwned>(
+    dump: &Value,
+    field: &str,
+    issues: &mut Vec<Value>,
+) -> Vec<T> {
+    let records = match dump.get(field) {
+        Some(Value::Array(records)) => records,
+        _ => {
+            issues.push(issue("malformed", format!("{field} is not an array")));
+            return vec![];
+        }
+    };
+    records
+        .iter()
+        .enumerate()
+        .filter_map(|(index, record)| match T::deserialize(record) {
+            Ok(record) => Some(record),
+            Err(err) => {
+                issues.push(issue("malformed", format!("{field}[{index}]: {err}")));
+                None
+            }
+        })
+        .collect()
+}
+
+fn check_collisions<T: std::fmt::Display>(
+    kind: &str,
+    ids: impl Iterator<Item = T>,
+    issues: &mut Vec<Value>,
+) {
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+    for id in ids {
+        if !seen.insert(id.to_string()) && reported.insert(id.to_string()) {
+            issues.push(issue("collision", format!("{kind} {id} is duplicated")));
+        }
+    }
+}
+
+fn issue(kind: &str, detail: String) -> Value {
+    json!({ "kind": kind, "detail": detail })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(issues: &[Value]) -> Vec<&str> {
+        issues.iter().map(|i| i["kind"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn validate_dump_issues() {
+        let dump = json!({
+            "version": METADATA_VERSION,
+            "databases": [
+                {"id": 1, "name": "db", "collections": [{"id": 10, "mode": "hash(8)", "name": "c"}]},
+            ],
+            "nodes": [{"id": 1, "addr": "a", "replicas": [], "leaders": [], "status": 0}],
+            "groups": [],
+            "balanced": true,
+        });
+        assert!(validate(dump.to_string().as_bytes()).is_empty());
+
+        let dump = json!({
+            "version": 100,
+            "databases": [{"id": 1, "name": "db", "collections": []}, {"id": 1}],
+            "nodes": [],
+            "groups": "none",
+        });
+        let issues = validate(dump.to_string().as_bytes());
+        assert_eq!(
+            kinds(&issues),
+            vec!["unknown_version", "malformed", "malformed"]
+        );
+
+        assert_eq!(kinds(&validate(b"{")), vec!["malformed"]);
+
+        let node = json!({"id": 1, "addr": "a", "replicas": [], "leaders": [], "status": 0});
+        let dump = json!({
+            "version": METADATA_VERSION,
+            "databases": [],
+            "nodes": [node, node, node],
+            "groups": [],
+        });
+        let issues = validate(dump.to_string().as_bytes());
+        assert_eq!(kinds(&issues), vec!["collision", "collision"]);
+        assert_eq!(issues[0]["detail"], "node 1 is duplicated");
+    }
+}
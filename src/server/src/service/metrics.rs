@@ -207,6 +207,7 @@ simple_root_method!(report);
 simple_root_method!(watch);
 simple_root_method!(admin);
 simple_root_method!(join);
+simple_root_method!(join_nodes);
 simple_root_method!(alloc_replica);
 
 lazy_static! {
@@ -25,7 +25,7 @@ use engula_client::{ClientOptions, EngulaClient};
 use crate::{
     node::{resolver::AddressResolver, Node},
     root::Root,
-    Provider,
+    Config, Provider,
 };
 
 #[derive(Clone)]
@@ -33,6 +33,8 @@ pub struct Server {
     pub node: Arc<Node>,
     pub root: Root,
     pub address_resolver: Arc<AddressResolver>,
+    /// The config the server starts with.
+    pub config: Arc<Config>,
 }
 
 #[derive(Clone)]
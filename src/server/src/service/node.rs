@@ -135,6 +135,9 @@ impl node_server::Node for Server {
                         self.node.collect_schedule_state(&req).await,
                     )
                 }
+                piggyback_request::Info::CompactGroups(req) => {
+                    piggyback_response::Info::CompactGroups(self.node.compact_groups(&req).await)
+                }
             };
             piggybacks_resps.push(PiggybackResponse { info: Some(info) });
         }
@@ -132,8 +132,10 @@ impl ProxyServer {
         req: CreateDatabaseRequest,
     ) -> Result<CreateDatabaseResponse, Status> {
         let database = self.client.create_database(req.name).await?;
+        // No system group is allocated for a database yet.
         Ok(CreateDatabaseResponse {
             database: Some(database.desc()),
+            system_group_ids: vec![],
         })
     }
 
@@ -16,7 +16,11 @@ use engula_api::{server::v1::*, v1::*};
 use tonic::{Request, Response, Status};
 
 use super::metrics::*;
-use crate::{record_latency, root::Watcher, Error, Result, Server};
+use crate::{
+    record_latency,
+    root::{CreateCollectionOptions, Watcher},
+    Error, Result, Server,
+};
 
 #[tonic::async_trait]
 impl root_server::Root for Server {
@@ -39,7 +43,11 @@ impl root_server::Root for Server {
         record_latency!(take_watch_request_metrics());
         let req = req.into_inner();
         let watcher = self
-            .wrap(self.root.watch(req.cur_group_epochs).await)
+            .wrap(
+                self.root
+                    .watch(req.cur_group_epochs, req.group_id, &[])
+                    .await,
+            )
             .await?;
         Ok(Response::new(watcher))
     }
@@ -63,14 +71,55 @@ impl root_server::Root for Server {
         }))
     }
 
+    async fn join_nodes(
+        &self,
+        request: Request<JoinNodesRequest>,
+    ) -> std::result::Result<Response<JoinNodesResponse>, Status> {
+        record_latency!(take_join_nodes_request_metrics());
+        let request = request.into_inner();
+        let nodes = request
+            .nodes
+            .into_iter()
+            .map(|node| {
+                let capacity = node.capacity.ok_or_else(|| {
+                    Error::InvalidArgument(format!("capacity of node {} is required", node.addr))
+                })?;
+                Ok((node.addr, capacity))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let summary = self.wrap(self.root.join_nodes(nodes).await).await?;
+        let failures = summary
+            .failed
+            .into_iter()
+            .map(|(addr, err)| join_nodes_response::Failure {
+                addr,
+                reason: err.to_string(),
+            })
+            .collect();
+        Ok(Response::new(JoinNodesResponse {
+            joined: summary.joined,
+            existing: summary.existing,
+            failures,
+        }))
+    }
+
     async fn report(
         &self,
         request: Request<ReportRequest>,
     ) -> std::result::Result<Response<ReportResponse>, Status> {
         record_latency!(take_report_request_metrics());
         let request = request.into_inner();
-        self.wrap(self.root.report(request.updates).await).await?;
-        Ok(Response::new(ReportResponse {}))
+        let summary = self.wrap(self.root.report(request.updates).await).await?;
+        let failures = summary
+            .failed
+            .into_iter()
+            .map(|(index, err)| report_response::Failure {
+                index: index as u64,
+                reason: err.to_string(),
+                permanent: matches!(err, Error::StaleReport(_) | Error::InvalidArgument(_)),
+            })
+            .collect();
+        Ok(Response::new(ReportResponse { failures }))
     }
 
     async fn alloc_replica(
@@ -109,8 +158,9 @@ impl Server {
                 let res = self.handle_create_database(req).await?;
                 admin_response_union::Response::CreateDatabase(res)
             }
-            admin_request_union::Request::UpdateDatabase(_req) => {
-                todo!()
+            admin_request_union::Request::UpdateDatabase(req) => {
+                let res = self.handle_update_database(req).await?;
+                admin_response_union::Response::UpdateDatabase(res)
             }
             admin_request_union::Request::DeleteDatabase(req) => {
                 let res = self.handle_delete_database(req).await?;
@@ -128,8 +178,9 @@ impl Server {
                 let res = self.handle_create_collection(req).await?;
                 admin_response_union::Response::CreateCollection(res)
             }
-            admin_request_union::Request::UpdateCollection(_req) => {
-                todo!()
+            admin_request_union::Request::UpdateCollection(req) => {
+                let res = self.handle_update_collection(req).await?;
+                admin_response_union::Response::UpdateCollection(res)
             }
             admin_request_union::Request::DeleteCollection(req) => {
                 let res = self.handle_delete_collection(req).await?;
@@ -153,9 +204,23 @@ impl Server {
         &self,
         req: CreateDatabaseRequest,
     ) -> Result<CreateDatabaseResponse> {
-        let desc = self.root.create_database(req.name).await?;
+        let (desc, system_group_ids) = self.root.create_database(req.name).await?;
         Ok(CreateDatabaseResponse {
             database: Some(desc),
+            system_group_ids,
+        })
+    }
+
+    async fn handle_update_database(
+        &self,
+        req: UpdateDatabaseRequest,
+    ) -> Result<UpdateDatabaseResponse> {
+        let desc = self
+            .root
+            .update_database_defaults(&req.name, req.default_collection_options)
+            .await?;
+        Ok(UpdateDatabaseResponse {
+            database: Some(desc),
         })
     }
 
@@ -187,15 +252,46 @@ impl Server {
         let database = req.database.ok_or_else(|| {
             Error::InvalidArgument("CreateCollectionRequest::database".to_owned())
         })?;
+        let options = CreateCollectionOptions::builder()
+            .partition(req.partition)
+            .acl(req.acl)
+            .read_only(req.read_only)
+            .build()?;
         let desc = self
             .root
-            .create_collection(req.name, database.name, req.partition)
+            .create_collection(req.name, database.name, options)
             .await?;
         Ok(CreateCollectionResponse {
             collection: Some(desc),
         })
     }
 
+    async fn handle_update_collection(
+        &self,
+        req: UpdateCollectionRequest,
+    ) -> Result<UpdateCollectionResponse> {
+        let database = req.database.ok_or_else(|| {
+            Error::InvalidArgument("UpdateCollectionRequest::database is required".to_owned())
+        })?;
+        let collection = match req.read_only {
+            Some(read_only) => {
+                self.root
+                    .set_collection_read_only(&req.name, &database.name, read_only)
+                    .await?
+            }
+            None => self
+                .root
+                .get_collection(&req.name, &database)
+                .await?
+                .ok_or_else(|| {
+                    Error::InvalidArgument(format!("collection {} not found", req.name))
+                })?,
+        };
+        Ok(UpdateCollectionResponse {
+            collection: Some(collection),
+        })
+    }
+
     async fn handle_delete_collection(
         &self,
         req: DeleteCollectionRequest,
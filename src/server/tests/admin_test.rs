@@ -20,7 +20,7 @@ use engula_client::{ClientOptions, EngulaClient, NodeClient, Partition};
 use engula_server::diagnosis;
 use tracing::info;
 
-use crate::helper::{context::*, init::setup_panic_hook, runtime::block_on_current};
+use crate::helper::{client::*, context::*, init::setup_panic_hook, runtime::block_on_current};
 
 #[ctor::ctor]
 fn init() {
@@ -200,6 +200,548 @@ fn admin_basic() {
     })
 }
 
+#[test]
+fn admin_add_replica() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin-add-replica");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(4).await;
+        let addrs = nodes.values().cloned().collect::<Vec<_>>();
+        let c = ClusterClient::new(nodes.clone()).await;
+
+        let group_id = 1;
+        c.assert_num_group_voters(group_id, 3).await;
+        let state = c.get_router_group_state(group_id).await.unwrap();
+        let target = *nodes
+            .keys()
+            .find(|id| !state.replicas.values().any(|r| r.node_id == **id))
+            .unwrap();
+
+        let root_addr = find_root(addrs).await;
+        let url = format!("http://{root_addr}/admin/groups/{group_id}/add_replica");
+        let resp = reqwest::Client::new()
+            .post(format!("{url}?node_id={target}"))
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["group"]["id"], group_id);
+        assert_eq!(body["incoming_replica"]["node_id"], target);
+        c.assert_num_group_voters(group_id, 4).await;
+
+        // The target is a member already.
+        let resp = reqwest::Client::new()
+            .post(format!("{url}?node_id={target}"))
+            .send()
+            .await
+            .unwrap();
+        assert!(!resp.status().is_success());
+    })
+}
+
+#[test]
+fn admin_group_raft_metrics() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin-group-raft-metrics");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(1).await;
+        let c = ClusterClient::new(nodes.clone()).await;
+        let addr = nodes.values().next().unwrap().to_owned();
+        let client = reqwest::Client::new();
+
+        let group_id = 1;
+        c.assert_group_leader(group_id).await;
+        let body = loop {
+            let resp = client
+                .get(format!("http://{addr}/admin/groups/{group_id}/raft"))
+                .send()
+                .await
+                .unwrap();
+            assert!(resp.status().is_success());
+            let body: serde_json::Value = resp.json().await.unwrap();
+            // The leader treats the entries before its election as a pending config change.
+            if body["role"] == "Leader" && body["pending_conf_change"] == false {
+                break body;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(body["group_id"], group_id);
+        assert!(body["term"].as_u64().unwrap() > 0);
+        let commit_index = body["commit_index"].as_u64().unwrap();
+        let applied_index = body["applied_index"].as_u64().unwrap();
+        assert!(commit_index > 0 && commit_index >= applied_index);
+        assert!(body["last_index"].as_u64().unwrap() >= commit_index);
+
+        let resp = client
+            .get(format!("http://{addr}/admin/groups/12345/raft"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+    })
+}
+
+#[test]
+fn admin_remove_replica() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin-remove-replica");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(4).await;
+        let addrs = nodes.values().cloned().collect::<Vec<_>>();
+        let c = ClusterClient::new(nodes.clone()).await;
+        let client = reqwest::Client::new();
+
+        let group_id = 1;
+        c.assert_num_group_voters(group_id, 3).await;
+        let state = c.get_router_group_state(group_id).await.unwrap();
+        let target = *nodes
+            .keys()
+            .find(|id| !state.replicas.values().any(|r| r.node_id == **id))
+            .unwrap();
+        let root_addr = find_root(addrs).await;
+        let url = format!("http://{root_addr}/admin/groups/{group_id}");
+        let resp = client
+            .post(format!("{url}/add_replica?node_id={target}"))
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        c.assert_num_group_voters(group_id, 4).await;
+
+        // Remove the leader, the leadership is transferred before removing.
+        let leader = c.assert_group_leader(group_id).await;
+        let resp = loop {
+            // Retry until the previous membership change is finished.
+            let resp = client
+                .post(format!("{url}/remove_replica?replica_id={leader}"))
+                .send()
+                .await
+                .unwrap();
+            if resp.status().is_success() {
+                break resp;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        };
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["outgoing_replica"]["id"], leader);
+        c.assert_group_not_contains_member(group_id, leader).await;
+        c.assert_num_group_voters(group_id, 3).await;
+        assert_ne!(c.assert_group_leader(group_id).await, leader);
+
+        // Removing one more voter leaves the group below the minimum.
+        let follower = c.must_group_any_follower(group_id).await;
+        let resp = client
+            .post(format!("{url}/remove_replica?replica_id={}", follower.id))
+            .send()
+            .await
+            .unwrap();
+        assert!(!resp.status().is_success());
+        c.assert_num_group_voters(group_id, 3).await;
+    })
+}
+
+#[test]
+fn admin_grow_root_replicas() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin-grow-root-replicas");
+        ctx.disable_all_balance();
+        ctx.disable_group_promoting();
+        ctx.disable_all_node_scheduler();
+        let nodes = ctx.bootstrap_servers(3).await;
+        let c = ClusterClient::new(nodes.clone()).await;
+        c.assert_num_group_voters(0, 1).await;
+
+        let root_addr = nodes.get(&0).unwrap().to_owned();
+        let client = reqwest::Client::new();
+        for (node_id, num_voters) in [(1, 2), (2, 3)] {
+            let resp = client
+                .post(format!(
+                    "http://{root_addr}/admin/root/add_replica?node_id={node_id}"
+                ))
+                .send()
+                .await
+                .unwrap();
+            assert!(resp.status().is_success());
+            let body: serde_json::Value = resp.json().await.unwrap();
+            assert_eq!(body["incoming_replica"]["node_id"], node_id);
+            c.assert_num_group_voters(0, num_voters).await;
+        }
+
+        // The root nodes returned to the nodes reflect the current membership.
+        let root_addr = find_root(nodes.values().cloned().collect()).await;
+        let n_cli = NodeClient::connect(root_addr.clone()).await.unwrap();
+        loop {
+            let root = n_cli.get_root().await.unwrap();
+            if root.root_nodes.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // Removing an unknown replica is rejected.
+        let resp = client
+            .post(format!(
+                "http://{root_addr}/admin/root/remove_replica?replica_id=12345"
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert!(!resp.status().is_success());
+        c.assert_num_group_voters(0, 3).await;
+    })
+}
+
+#[test]
+fn admin_clone_collection() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin_test__admin_clone_collection");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(3).await;
+        let addrs = nodes.values().cloned().collect::<Vec<_>>();
+        let c = ClusterClient::new(nodes).await;
+        let app = c.app_client().await;
+
+        let db = app.create_database("db".into()).await.unwrap();
+        let source = db
+            .create_collection("source".into(), Some(Partition::Hash { slots: 3 }))
+            .await
+            .unwrap();
+        source
+            .put(b"key".to_vec(), b"value".to_vec())
+            .await
+            .unwrap();
+
+        let root_addr = find_root(addrs).await;
+        let url = format!("http://{root_addr}/admin/collections/source/clone?database=db");
+        let resp = reqwest::Client::new()
+            .post(format!("{url}&target=target"))
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+
+        // The clone inherits the partition, but owns its shards and contains no data.
+        let target = db.open_collection("target".into()).await.unwrap();
+        let (source_desc, target_desc) = (source.desc(), target.desc());
+        assert_ne!(source_desc.id, target_desc.id);
+        assert_eq!(source_desc.partition, target_desc.partition);
+        assert!(target.get(b"key".to_vec()).await.unwrap().is_none());
+        target
+            .put(b"key".to_vec(), b"other".to_vec())
+            .await
+            .unwrap();
+        let source_shard = c.get_shard_desc(&source_desc, b"key").await.unwrap();
+        let target_shard = c.get_shard_desc(&target_desc, b"key").await.unwrap();
+        assert_ne!(source_shard.id, target_shard.id);
+        assert_eq!(
+            source.get(b"key".to_vec()).await.unwrap(),
+            Some(b"value".to_vec())
+        );
+
+        // The target exists already.
+        let resp = reqwest::Client::new()
+            .post(format!("{url}&target=target"))
+            .send()
+            .await
+            .unwrap();
+        assert!(!resp.status().is_success());
+    });
+}
+
+#[test]
+fn delete_collection_removes_empty_groups() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin_test__delete_collection_removes_empty_groups");
+        ctx.disable_replica_balance();
+        ctx.disable_leader_balance();
+        ctx.disable_shard_balance();
+        let nodes = ctx.bootstrap_servers(3).await;
+        let addrs = nodes.values().cloned().collect::<Vec<_>>();
+        let c = ClusterClient::new(nodes).await;
+        let app = c.app_client().await;
+
+        // Wait until the group balancing creates more user groups.
+        while list_group_ids(addrs.clone()).await.len() < 3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let db = app.create_database("db".into()).await.unwrap();
+        let co = db
+            .create_collection("co".into(), Some(Partition::Hash { slots: 1 }))
+            .await
+            .unwrap();
+        c.assert_collection_ready(&co.desc()).await;
+        let group_id = c
+            .find_router_group_state_by_key(&co.desc(), b"key")
+            .await
+            .unwrap()
+            .id;
+
+        db.delete_collection("co".into()).await.unwrap();
+        for _ in 0..300 {
+            if !list_group_ids(addrs.clone()).await.contains(&group_id) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("group {group_id} of the deleted collection is not removed");
+    });
+}
+
+#[test]
+fn admin_moves_history() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin_test__admin_moves_history");
+        ctx.disable_leader_balance();
+        ctx.disable_shard_balance();
+        let nodes = ctx.bootstrap_servers(3).await;
+        let addrs = nodes.values().cloned().collect::<Vec<_>>();
+        let c = ClusterClient::new(nodes).await;
+        c.assert_root_group_has_promoted().await;
+        while list_group_ids(addrs.clone()).await.len() < 3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // The replicas are moved to the new node to balance the replica count.
+        let root_addr = find_root(addrs.clone()).await;
+        ctx.add_server(vec![root_addr], 3).await;
+        for _ in 0..600 {
+            let moves = list_moves(addrs.clone()).await;
+            let succeeded = moves
+                .iter()
+                .filter(|m| m["outcome"] == "succeeded")
+                .collect::<Vec<_>>();
+            if succeeded.len() < 2 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+            let ids = moves
+                .iter()
+                .map(|m| m["id"].as_u64().unwrap())
+                .collect::<Vec<_>>();
+            assert!(ids.windows(2).all(|w| w[0] < w[1]), "{ids:?}");
+            for m in succeeded {
+                assert_eq!(m["reason"], "rebalance");
+                assert_ne!(m["from_node"], m["to_node"]);
+                assert!(m["group_id"].as_u64().unwrap() > 0);
+                assert!(m["error"].is_null());
+            }
+            return;
+        }
+        panic!("the replicas are not moved to the new node");
+    });
+}
+
+#[test]
+fn admin_effective_config() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin_test__admin_effective_config");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(1).await;
+        let addr = nodes.values().next().unwrap().to_owned();
+        let get_config = || async {
+            let resp = reqwest::get(format!("http://{addr}/admin/config"))
+                .await
+                .unwrap();
+            assert!(resp.status().is_success());
+            resp.json::<serde_json::Value>().await.unwrap()
+        };
+
+        let config = get_config().await;
+        let replicas_per_group = &config["root"]["replicas_per_group"];
+        assert_eq!(replicas_per_group["value"], 3);
+        assert_eq!(replicas_per_group["overridden"], false);
+        let group_balance = &config["root"]["enable_group_balance"];
+        assert_eq!(group_balance["value"], false);
+        assert_eq!(group_balance["default"], true);
+        assert_eq!(group_balance["overridden"], true);
+        assert_eq!(config["runtime"]["frozen"]["overridden"], false);
+
+        let resp = reqwest::Client::new()
+            .post(format!("http://{addr}/admin/freeze"))
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        let config = get_config().await;
+        assert_eq!(config["runtime"]["frozen"]["value"], true);
+        assert_eq!(config["runtime"]["frozen"]["overridden"], true);
+    });
+}
+
+#[test]
+fn admin_validate_metadata() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin_test__admin_validate_metadata");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(1).await;
+        let addr = nodes.values().next().unwrap().to_owned();
+        let validate = |dump: String| {
+            let addr = addr.to_owned();
+            async move {
+                let resp = reqwest::Client::new()
+                    .post(format!("http://{addr}/admin/metadata/validate"))
+                    .body(dump)
+                    .send()
+                    .await
+                    .unwrap();
+                assert!(resp.status().is_success());
+                resp.json::<serde_json::Value>().await.unwrap()
+            }
+        };
+
+        // The dump of a running cluster is valid.
+        let dump = reqwest::get(format!("http://{addr}/admin/metadata"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let report = validate(dump.to_owned()).await;
+        assert_eq!(report["valid"], true, "{report}");
+        assert!(report["issues"].as_array().unwrap().is_empty());
+
+        // The groups are duplicated, so are their replicas and shards.
+        let mut dump: serde_json::Value = serde_json::from_str(&dump).unwrap();
+        let groups = dump["groups"].as_array().unwrap().to_owned();
+        dump["groups"]
+            .as_array_mut()
+            .unwrap()
+            .extend(groups.iter().cloned());
+        let report = validate(dump.to_string()).await;
+        assert_eq!(report["valid"], false);
+        let issues = report["issues"].as_array().unwrap();
+        assert!(!issues.is_empty());
+        assert!(issues.iter().all(|i| i["kind"] == "collision"), "{report}");
+        for group in &groups {
+            let detail = format!("group {} is duplicated", group["id"]);
+            assert!(
+                issues.iter().any(|i| i["detail"] == detail.as_str()),
+                "{report}"
+            );
+        }
+
+        // Nothing is changed.
+        let after = curr_metadata(nodes.values().cloned().collect()).await;
+        assert_eq!(after.groups.len(), groups.len());
+    });
+}
+
+#[test]
+fn root_leader_moves_to_preferred_node() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin_test__root_leader_moves_to_preferred_node");
+        ctx.disable_all_balance();
+        // Shorten the heartbeat interval, the preference is checked once per heartbeat.
+        ctx.mut_root_config().liveness_threshold_sec = 6;
+        ctx.mut_root_config().root_leader_preferred_nodes = vec![2];
+        let nodes = ctx.bootstrap_servers(3).await;
+        let c = ClusterClient::new(nodes).await;
+        c.assert_root_group_has_promoted().await;
+
+        for _ in 0..300 {
+            if c.get_group_leader_node_id(0).await == Some(2) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("root leadership is not moved to the preferred node");
+    });
+}
+
+#[test]
+fn grpc_health_mirrors_http_health() {
+    use tonic_health::proto::{
+        health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+    };
+
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin-grpc-health");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(1).await;
+        let addr = nodes.values().next().unwrap().to_owned();
+
+        let resp = reqwest::get(format!("http://{addr}/admin/health"))
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+
+        let mut client = HealthClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap();
+        for service in [
+            "",
+            "engula.server.v1.Node",
+            "engula.server.v1.Root",
+            "serverpb.v1.Raft",
+        ] {
+            let resp = client
+                .check(HealthCheckRequest {
+                    service: service.to_owned(),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(resp.status, ServingStatus::Serving as i32, "{service}");
+        }
+    })
+}
+
+#[test]
+fn admin_watch_ndjson() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("admin-watch-ndjson");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(1).await;
+        let addr = nodes.values().next().unwrap().to_owned();
+        let c = EngulaClient::new(ClientOptions::default(), vec![addr.to_owned()])
+            .await
+            .unwrap();
+
+        let mut resp = reqwest::get(format!("http://{addr}/admin/watch"))
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers()[reqwest::header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+
+        // The snapshot is followed by the events of the new databases.
+        c.create_database("watch_db1".into()).await.unwrap();
+        c.create_database("watch_db2".into()).await.unwrap();
+        let mut buf = String::new();
+        let mut events = Vec::new();
+        while !events
+            .iter()
+            .any(|e: &serde_json::Value| e["event"].as_str().unwrap().contains("watch_db2"))
+        {
+            let chunk = resp.chunk().await.unwrap().expect("watch stream is closed");
+            buf.push_str(std::str::from_utf8(&chunk).unwrap());
+            while let Some((line, rest)) = buf.split_once('\n') {
+                events.push(serde_json::from_str(line).unwrap());
+                buf = rest.to_owned();
+            }
+        }
+        let databases = events
+            .iter()
+            .filter(|e| e["type"] == "database" && e["op"] == "update")
+            .map(|e| e["event"].as_str().unwrap())
+            .filter(|e| e.contains("watch_db"))
+            .collect::<Vec<_>>();
+        assert_eq!(databases.len(), 2);
+        assert!(databases[0].contains("watch_db1"));
+
+        // Disconnect and the server still serves.
+        drop(resp);
+        let resp = reqwest::get(format!("http://{addr}/admin/watch?groups=x"))
+            .await
+            .unwrap();
+        assert!(!resp.status().is_success());
+    })
+}
+
 fn collection_key(database_id: u64, collection_name: &str) -> Vec<u8> {
     let mut buf = Vec::with_capacity(core::mem::size_of::<u64>() + collection_name.len());
     buf.extend_from_slice(database_id.to_le_bytes().as_slice());
@@ -217,6 +759,38 @@ async fn curr_metadata(nodes: Vec<String>) -> diagnosis::Metadata {
     json_res.unwrap_or_else(|_| panic!("decode json fail: {:?}", content))
 }
 
+async fn list_group_ids(nodes: Vec<String>) -> Vec<u64> {
+    let root_addr = find_root(nodes).await;
+    let resp = reqwest::get(format!("http://{root_addr}/admin/groups"))
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    body["groups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| g["id"].as_u64().unwrap())
+        .collect()
+}
+
+/// List all moves of the history, one page at a time.
+async fn list_moves(nodes: Vec<String>) -> Vec<serde_json::Value> {
+    let root_addr = find_root(nodes).await;
+    let mut moves = Vec::new();
+    let mut url = format!("http://{root_addr}/admin/moves/history?limit=1");
+    loop {
+        let resp = reqwest::get(&url).await.unwrap();
+        let body: serde_json::Value = resp.json().await.unwrap();
+        moves.extend(body["moves"].as_array().unwrap().iter().cloned());
+        match body["next_page_token"].as_str() {
+            Some(token) => {
+                url = format!("http://{root_addr}/admin/moves/history?limit=1&page_token={token}")
+            }
+            None => return moves,
+        }
+    }
+}
+
 async fn find_root(nodes: Vec<String>) -> String {
     for node in nodes {
         let n_cli = NodeClient::connect(node).await;
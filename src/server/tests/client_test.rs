@@ -109,6 +109,55 @@ fn create_duplicated_database_or_collection() {
     });
 }
 
+#[test]
+fn create_with_name_policy() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("client_test__create_with_name_policy");
+        ctx.disable_all_balance();
+        ctx.mut_root_config().name_prefixes = vec!["tenant_a_".into(), "tenant_b_".into()];
+        let nodes = ctx.bootstrap_servers(1).await;
+        let addr = nodes.values().next().unwrap().to_owned();
+        let c = ClusterClient::new(nodes).await;
+        let client = c.app_client().await;
+
+        assert!(matches!(
+            client.create_database("test_db".to_string()).await,
+            Err(AppError::InvalidArgument(msg)) if msg.contains("invalid name")
+        ));
+        let db = client
+            .create_database("tenant_a_db".to_string())
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.create_collection("test_co".to_string(), Some(Partition::Hash { slots: 1 }))
+                .await,
+            Err(AppError::InvalidArgument(msg)) if msg.contains("invalid name")
+        ));
+        let co = db
+            .create_collection(
+                "tenant_b_co".to_string(),
+                Some(Partition::Hash { slots: 1 }),
+            )
+            .await
+            .unwrap();
+        c.assert_collection_ready(&co.desc()).await;
+
+        // The policy is exposed by the effective config.
+        let config = reqwest::get(format!("http://{addr}/admin/config"))
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap();
+        let name_prefixes = &config["root"]["name_prefixes"];
+        assert_eq!(
+            name_prefixes["value"],
+            serde_json::json!(["tenant_a_", "tenant_b_"])
+        );
+        assert_eq!(name_prefixes["overridden"], true);
+    });
+}
+
 #[test]
 fn access_not_exists_database_or_collection() {
     block_on_current(async {
@@ -17,7 +17,7 @@ use engula_server::{
     node::replica::{ReplicaConfig, ReplicaTestingKnobs},
     raftgroup::RaftTestingKnobs,
     runtime::{ExecutorConfig, ExecutorOwner, ShutdownNotifier},
-    Config, NodeConfig, RaftConfig, RootConfig,
+    AdminConfig, Config, NodeConfig, RaftConfig, RootConfig,
 };
 use tempdir::TempDir;
 use tracing::info;
@@ -85,6 +85,10 @@ impl TestContext {
         &mut self.raft_knobs
     }
 
+    pub fn mut_root_config(&mut self) -> &mut RootConfig {
+        &mut self.root_cfg
+    }
+
     pub fn disable_replica_balance(&mut self) {
         self.root_cfg.enable_replica_balance = false;
     }
@@ -108,6 +112,11 @@ impl TestContext {
         self.disable_group_balance();
     }
 
+    pub fn disable_group_promoting(&mut self) {
+        self.disable_group_promoting = true;
+        self.replica_knobs.disable_scheduler_promote_group_task = true;
+    }
+
     pub fn disable_all_node_scheduler(&mut self) {
         self.replica_knobs.disable_scheduler_durable_task = true;
         self.replica_knobs
@@ -153,6 +162,7 @@ impl TestContext {
             },
             root,
             executor: ExecutorConfig::default(),
+            admin: AdminConfig::default(),
         };
         let notifier = ShutdownNotifier::new();
         let shutdown = notifier.subscribe();
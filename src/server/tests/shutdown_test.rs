@@ -0,0 +1,94 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+mod helper;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use engula_client::{ConnManager, Partition, RootClient, StaticServiceDiscovery};
+use futures::StreamExt;
+use tokio::time::{timeout, Instant};
+use tracing::info;
+
+use crate::helper::{client::*, context::*, init::setup_panic_hook, runtime::block_on_current};
+
+#[ctor::ctor]
+fn init() {
+    setup_panic_hook();
+    tracing_subscriber::fmt::init();
+}
+
+const PHASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[test]
+fn graceful_shutdown_sequence() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("shutdown_test__graceful_shutdown_sequence");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(1).await;
+        let addr = nodes.get(&0).unwrap().to_owned();
+        let c = ClusterClient::new(nodes.clone()).await;
+        let app = c.app_client().await;
+        let db = app.create_database("db".into()).await.unwrap();
+        let co = db
+            .create_collection("co".into(), Some(Partition::Hash { slots: 1 }))
+            .await
+            .unwrap();
+        c.assert_collection_ready(&co.desc()).await;
+        co.put(b"key".to_vec(), b"value".to_vec()).await.unwrap();
+
+        let http = reqwest::Client::new();
+        let metrics_url = format!("http://{addr}/admin/metrics");
+        let resp = http.get(&metrics_url).send().await.unwrap();
+        assert!(resp.status().is_success());
+
+        let discovery = Arc::new(StaticServiceDiscovery::new(vec![addr.clone()]));
+        let root_client = RootClient::new(discovery, ConnManager::new());
+        let mut events = root_client.watch(HashMap::default()).await.unwrap();
+        events.next().await.unwrap().unwrap();
+
+        info!("phase 1: shutdown the server");
+        let start = Instant::now();
+        ctx.stop_server(0).await;
+        assert!(start.elapsed() < PHASE_TIMEOUT, "shutdown takes too long");
+
+        info!("phase 2: the admin service doesn't accept new requests");
+        let resp = timeout(PHASE_TIMEOUT, http.get(&metrics_url).send())
+            .await
+            .expect("admin request hangs after shutdown");
+        assert!(resp.is_err());
+
+        info!("phase 3: the watcher is closed");
+        let event = timeout(PHASE_TIMEOUT, events.next())
+            .await
+            .expect("watcher hangs after shutdown");
+        assert!(!matches!(event, Some(Ok(_))));
+
+        info!("phase 4: the root serves again after restart");
+        let nodes = ctx.start_servers(nodes).await;
+        let c = ClusterClient::new(nodes).await;
+        timeout(PHASE_TIMEOUT, c.assert_group_leader(0))
+            .await
+            .expect("root doesn't step leader after restart");
+
+        info!("phase 5: the flushed state is recovered");
+        let app = c.app_client().await;
+        let db = app.open_database("db".into()).await.unwrap();
+        let co = db.open_collection("co".into()).await.unwrap();
+        let value = timeout(PHASE_TIMEOUT, co.get(b"key".to_vec()))
+            .await
+            .expect("read hangs after restart")
+            .unwrap();
+        assert_eq!(value, Some(b"value".to_vec()));
+    });
+}
@@ -0,0 +1,171 @@
+// Copyright 2022 The Engula Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod helper;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use engula_api::server::v1::{watch_response::update_event, WatchResponse};
+use engula_client::{ConnManager, ResumableWatch, RootClient, StaticServiceDiscovery};
+use futures::StreamExt;
+use tonic::Streaming;
+use tracing::info;
+
+use crate::helper::{client::*, context::*, init::setup_panic_hook, runtime::block_on_current};
+
+#[ctor::ctor]
+fn init() {
+    setup_panic_hook();
+    tracing_subscriber::fmt::init();
+}
+
+/// The events received by a watcher, the group epochs are used to resume watching.
+#[derive(Default)]
+struct WatchState {
+    group_epochs: HashMap<u64, u64>,
+    databases: Vec<String>,
+}
+
+impl WatchState {
+    fn apply(&mut self, resp: WatchResponse) {
+        for update in resp.updates {
+            match update.event {
+                Some(update_event::Event::Group(group)) => {
+                    let epoch = self.group_epochs.entry(group.id).or_default();
+                    assert!(
+                        group.epoch > *epoch,
+                        "group {} with epoch {} is delivered twice",
+                        group.id,
+                        group.epoch
+                    );
+                    *epoch = group.epoch;
+                }
+                Some(update_event::Event::Database(db)) => {
+                    self.databases.push(db.name);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn wait_database(&mut self, events: &mut Streaming<WatchResponse>, name: &str) {
+        while !self.databases.iter().any(|db| db == name) {
+            let resp = events.next().await.unwrap().unwrap();
+            self.apply(resp);
+        }
+    }
+}
+
+#[test]
+fn watch_resume_after_root_leader_change() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("watch_test__watch_resume_after_root_leader_change");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(3).await;
+        let c = ClusterClient::new(nodes.clone()).await;
+        c.assert_root_group_has_promoted().await;
+        let app = c.app_client().await;
+
+        let discovery = Arc::new(StaticServiceDiscovery::new(
+            nodes.values().cloned().collect(),
+        ));
+        let root_client = RootClient::new(discovery, ConnManager::new());
+
+        let mut state = WatchState::default();
+        let mut events = root_client.watch(HashMap::default()).await.unwrap();
+        app.create_database("db1".into()).await.unwrap();
+        state.wait_database(&mut events, "db1").await;
+
+        // Move the leadership of root group.
+        let prev_leader = c.assert_group_leader(0).await;
+        let follower = c.must_group_any_follower(0).await;
+        info!(
+            "transfer root leadership from {prev_leader} to {}",
+            follower.id
+        );
+        c.group(0).transfer_leader(follower.id).await.unwrap();
+        while c.get_group_leader(0).await == Some(prev_leader) {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // Resume watching against the new leader with the received group epochs, the groups
+        // which are already known must not be delivered again.
+        let mut events = root_client.watch(state.group_epochs.clone()).await.unwrap();
+        state.databases.clear();
+        app.create_database("db2".into()).await.unwrap();
+        state.wait_database(&mut events, "db2").await;
+
+        // The snapshot of the new leader contains db1, and the following events contain db2,
+        // each database is delivered exactly once and in order.
+        let user_databases = state
+            .databases
+            .iter()
+            .filter(|name| name.starts_with("db"))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(user_databases, vec!["db1".to_owned(), "db2".to_owned()]);
+    });
+}
+
+#[test]
+fn watch_follow_root_leader_hint() {
+    block_on_current(async {
+        let mut ctx = TestContext::new("watch_test__watch_follow_root_leader_hint");
+        ctx.disable_all_balance();
+        let nodes = ctx.bootstrap_servers(3).await;
+        let c = ClusterClient::new(nodes.clone()).await;
+        c.assert_root_group_has_promoted().await;
+        let app = c.app_client().await;
+
+        let discovery = Arc::new(StaticServiceDiscovery::new(
+            nodes.values().cloned().collect(),
+        ));
+        let root_client = RootClient::new(discovery, ConnManager::new());
+
+        let mut databases = vec![];
+        let mut events = root_client
+            .watch_resumable(HashMap::default())
+            .await
+            .unwrap();
+        app.create_database("db1".into()).await.unwrap();
+        wait_database(&mut events, &mut databases, "db1").await;
+
+        // The old leader terminates the watch stream with the leader hint.
+        let prev_leader = c.assert_group_leader(0).await;
+        let follower = c.must_group_any_follower(0).await;
+        info!(
+            "transfer root leadership from {prev_leader} to {}",
+            follower.id
+        );
+        c.group(0).transfer_leader(follower.id).await.unwrap();
+        while c.get_group_leader(0).await == Some(prev_leader) {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // The same stream resumes on the new leader without surfacing the error.
+        app.create_database("db2".into()).await.unwrap();
+        wait_database(&mut events, &mut databases, "db2").await;
+    });
+}
+
+async fn wait_database(events: &mut ResumableWatch, databases: &mut Vec<String>, name: &str) {
+    while !databases.iter().any(|db| db == name) {
+        let resp = events.next().await.unwrap().unwrap();
+        for update in resp.updates {
+            if let Some(update_event::Event::Database(db)) = update.event {
+                databases.push(db.name);
+            }
+        }
+    }
+}